@@ -0,0 +1,12 @@
+CREATE TABLE events(
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    subscriber_id INT NOT NULL REFERENCES subscribers(id) ON DELETE CASCADE,
+    campaign_id INT,
+    url TEXT,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX events_subscriber_id_idx ON events(subscriber_id);
+CREATE INDEX events_campaign_id_idx ON events(campaign_id);
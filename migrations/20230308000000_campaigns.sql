@@ -0,0 +1,28 @@
+CREATE TABLE campaigns(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at TIMESTAMPTZ
+);
+
+-- Hourly rollups of campaign events, kept up to date as events are recorded
+-- so that reports never need to scan the events table. Rows with an empty
+-- url count every event of a kind, clicks also get one row per link.
+CREATE TABLE campaign_stats(
+    campaign_id INT NOT NULL,
+    kind TEXT NOT NULL,
+    url TEXT NOT NULL DEFAULT '',
+    hour TIMESTAMPTZ NOT NULL,
+    total BIGINT NOT NULL DEFAULT 0,
+    uniques BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (campaign_id, kind, url, hour)
+);
+
+-- Remembers which subscribers were already counted towards uniques.
+CREATE TABLE campaign_stat_uniques(
+    campaign_id INT NOT NULL,
+    kind TEXT NOT NULL,
+    url TEXT NOT NULL DEFAULT '',
+    subscriber_id INT NOT NULL,
+    PRIMARY KEY (campaign_id, kind, url, subscriber_id)
+);
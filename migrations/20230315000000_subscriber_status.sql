@@ -0,0 +1,3 @@
+ALTER TABLE subscribers
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'active',
+    ADD COLUMN soft_bounces INT NOT NULL DEFAULT 0;
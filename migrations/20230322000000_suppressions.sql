@@ -0,0 +1,5 @@
+CREATE TABLE suppressions(
+    email TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
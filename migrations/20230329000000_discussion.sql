@@ -0,0 +1,27 @@
+ALTER TABLE subscribers
+    ADD COLUMN digest BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE posts(
+    id SERIAL PRIMARY KEY,
+    sender TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    raw BYTEA NOT NULL,
+    status TEXT NOT NULL,
+    digested BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE outbox(
+    id BIGSERIAL PRIMARY KEY,
+    sender TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    raw BYTEA NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_due_idx ON outbox(next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,7 @@
+ALTER TABLE posts
+    ADD COLUMN reason TEXT;
+
+CREATE TABLE allowlist(
+    email TEXT PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,41 @@
+ALTER TABLE subscribers
+    ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}';
+
+CREATE TABLE subscriber_tags(
+    subscriber_id INT NOT NULL REFERENCES subscribers(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (subscriber_id, tag)
+);
+
+CREATE TABLE templates(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    subject TEXT NOT NULL,
+    text TEXT NOT NULL,
+    html TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE automations(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    trigger JSONB NOT NULL,
+    steps JSONB NOT NULL,
+    -- Opens and clicks of automation mail are recorded against it.
+    campaign_id INT NOT NULL REFERENCES campaigns(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE automation_runs(
+    id SERIAL PRIMARY KEY,
+    automation_id INT NOT NULL REFERENCES automations(id) ON DELETE CASCADE,
+    subscriber_id INT NOT NULL REFERENCES subscribers(id) ON DELETE CASCADE,
+    step INT NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'active',
+    wake_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (automation_id, subscriber_id)
+);
+
+CREATE INDEX automation_runs_due_idx ON automation_runs(wake_at) WHERE status = 'active';
@@ -0,0 +1,16 @@
+CREATE TABLE api_keys(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- Only a SHA-256 of the key is kept, the key itself is shown once.
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ
+);
+
+ALTER TABLE outbox
+    ADD COLUMN api_key_id INT REFERENCES api_keys(id),
+    ADD COLUMN idempotency_key TEXT;
+
+CREATE UNIQUE INDEX outbox_idempotency_idx ON outbox(api_key_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
@@ -0,0 +1,6 @@
+-- IANA name such as Europe/Berlin, when we know it.
+ALTER TABLE subscribers
+    ADD COLUMN timezone TEXT;
+
+ALTER TABLE campaigns
+    ADD COLUMN template_id INT REFERENCES templates(id);
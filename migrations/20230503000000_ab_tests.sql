@@ -0,0 +1,29 @@
+CREATE TABLE ab_tests(
+    campaign_id INT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    test_percent INT NOT NULL,
+    metric TEXT NOT NULL,
+    decide_at TIMESTAMPTZ NOT NULL,
+    winner_id INT,
+    decided_at TIMESTAMPTZ
+);
+
+CREATE TABLE campaign_variants(
+    id SERIAL PRIMARY KEY,
+    campaign_id INT NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    subject TEXT,
+    from_name TEXT,
+    template_id INT REFERENCES templates(id),
+    UNIQUE (campaign_id, name)
+);
+
+-- Who got which variant, so that nobody gets a campaign twice and opens and
+-- clicks can be told apart per variant.
+CREATE TABLE campaign_recipients(
+    campaign_id INT NOT NULL REFERENCES campaigns(id) ON DELETE CASCADE,
+    subscriber_id INT NOT NULL REFERENCES subscribers(id) ON DELETE CASCADE,
+    variant_id INT NOT NULL REFERENCES campaign_variants(id) ON DELETE CASCADE,
+    -- Whether the subscriber was part of the test rather than the remainder.
+    test BOOLEAN NOT NULL,
+    PRIMARY KEY (campaign_id, subscriber_id)
+);
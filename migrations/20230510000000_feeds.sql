@@ -0,0 +1,20 @@
+CREATE TABLE feeds(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    source TEXT NOT NULL,
+    schedule TEXT NOT NULL,
+    template_id INT NOT NULL REFERENCES templates(id),
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Entries that went out, or were there before the feed was added. Only
+-- entries missing here make it into a digest.
+CREATE TABLE feed_entries(
+    feed_id INT NOT NULL REFERENCES feeds(id) ON DELETE CASCADE,
+    entry_id TEXT NOT NULL,
+    campaign_id INT REFERENCES campaigns(id) ON DELETE SET NULL,
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (feed_id, entry_id)
+);
@@ -0,0 +1,11 @@
+ALTER TABLE campaigns
+    ADD COLUMN exclude_from_archive BOOLEAN NOT NULL DEFAULT false;
+
+-- What a campaign looked like, without tracking or anything personal, for
+-- the public archive.
+CREATE TABLE issues(
+    campaign_id INT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    text TEXT NOT NULL,
+    html TEXT
+);
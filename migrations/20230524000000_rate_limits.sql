@@ -0,0 +1,10 @@
+-- Signup attempts per client address or subscriber address, counted in
+-- fixed windows.
+CREATE TABLE rate_limits(
+    key TEXT NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (key, window_start)
+);
+
+CREATE INDEX rate_limits_window_start ON rate_limits(window_start);
@@ -0,0 +1,16 @@
+-- Campaign mail counts towards the daily caps of a warm-up plan.
+ALTER TABLE outbox
+    ADD COLUMN campaign_id INT;
+
+CREATE INDEX outbox_campaign_sent_idx ON outbox(sent_at) WHERE campaign_id IS NOT NULL;
+
+-- Daily caps on campaign mail while a new sending domain or address builds
+-- its reputation. Only the newest plan applies.
+CREATE TABLE warmup_plans(
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    starts_on DATE NOT NULL,
+    -- The cap on each day of the plan, from the first.
+    daily_caps INT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
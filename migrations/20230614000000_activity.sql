@@ -0,0 +1,8 @@
+-- The latest subscriber and send activity, for live streams to pick up
+-- where they left off. Only the newest rows are kept.
+CREATE TABLE activity(
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,10 @@
+-- Browser sessions of the admin interface, by the hash of their cookie.
+CREATE TABLE admin_sessions(
+    id TEXT PRIMARY KEY,
+    -- Repeated by every form of the session.
+    csrf TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX admin_sessions_expires_idx ON admin_sessions(expires_at);
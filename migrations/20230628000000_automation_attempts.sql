@@ -0,0 +1,4 @@
+-- Claims of a run's current step, so that a step that keeps failing is
+-- given up on.
+ALTER TABLE automation_runs
+    ADD COLUMN attempts INT NOT NULL DEFAULT 0;
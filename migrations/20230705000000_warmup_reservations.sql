@@ -0,0 +1,11 @@
+-- Campaign mail taken against each day's warm-up cap. It is reserved
+-- before it is sent, so that instances sending at once stay under the cap.
+CREATE TABLE warmup_days(
+    day DATE PRIMARY KEY,
+    reserved BIGINT NOT NULL
+);
+
+-- Opens and clicks of the recipient when the mail was queued during a
+-- warm-up, by which campaign mail is claimed most engaged first.
+ALTER TABLE outbox
+    ADD COLUMN engagement BIGINT NOT NULL DEFAULT 0;
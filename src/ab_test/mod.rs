@@ -0,0 +1,191 @@
+mod worker;
+
+pub(crate) use worker::run_ab_tests;
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use log::info;
+
+use crate::{
+    data::ApplicationData,
+    model::{
+        assign, AbTestReport, Campaign, NewAbTest, NewDelivery, Subscriber, SubscriberStatus,
+        Template, Variant,
+    },
+    outbound::{campaign_delivery_from, unsuppressed},
+    store::{
+        AbTestStore, CampaignStore, PsqlAbTestStore, PsqlCampaignStore, PsqlSubscriberStore,
+        PsqlTemplateStore, SubscriberStore, TemplateStore,
+    },
+    warmup,
+};
+
+/// A variant with the template and sender it is sent with.
+struct Prepared {
+    variant: Variant,
+    template: Template,
+    from_name: String,
+}
+
+/// Sends every variant to its test group straight away. The winner goes to
+/// everyone else once the window is over. Returns `None` when the campaign
+/// was already sent. When the test can't be started, the campaign is left
+/// unscheduled again.
+pub(crate) async fn start(
+    data: &ApplicationData,
+    campaign: &Campaign,
+    template: &Template,
+    new_test: &NewAbTest,
+) -> Result<Option<AbTestReport>> {
+    let now = Utc::now();
+    if !PsqlCampaignStore::from(data.pool.clone())
+        .schedule(campaign.id, template.id, now, &[])
+        .await?
+    {
+        return Ok(None);
+    }
+    if let Err(e) = send_test(data, campaign, template, new_test).await {
+        PsqlAbTestStore::from(data.pool.clone())
+            .abandon(campaign.id)
+            .await?;
+        return Err(e);
+    }
+    report(data, campaign.id).await
+}
+
+/// Creates the test and queues its variants for the test group at once, so
+/// that either all of them go out or none.
+async fn send_test(
+    data: &ApplicationData,
+    campaign: &Campaign,
+    template: &Template,
+    new_test: &NewAbTest,
+) -> Result<()> {
+    let mut store = PsqlAbTestStore::from(data.pool.clone());
+    let (test, variants) = store
+        .create(
+            campaign.id,
+            new_test,
+            Utc::now() + Duration::hours(new_test.window_hours),
+        )
+        .await?;
+
+    let mut prepared = Vec::with_capacity(variants.len());
+    for variant in variants {
+        prepared.push(prepare(data, template, variant).await?);
+    }
+
+    let everyone = PsqlSubscriberStore::from(data.pool.clone()).all().await?;
+    let engagement = warmup::engagement(data).await?;
+    let mut deliveries = Vec::new();
+    for subscriber in recipients(data, everyone).await? {
+        if let Some(index) = assign(
+            campaign.id,
+            subscriber.id,
+            test.test_percent,
+            prepared.len(),
+        ) {
+            deliveries.push(
+                delivery(
+                    data,
+                    campaign.id,
+                    &prepared[index],
+                    &subscriber,
+                    engagement.of(&subscriber),
+                )
+                .await?,
+            );
+        }
+    }
+    let tested = store.send(campaign.id, true, &deliveries).await?;
+
+    info!(
+        "Sent {} variants of campaign {} to {tested} subscribers, picking a winner at {}",
+        prepared.len(),
+        campaign.id,
+        test.decide_at
+    );
+    Ok(())
+}
+
+/// The active subscribers that can be sent to.
+async fn recipients(
+    data: &ApplicationData,
+    subscribers: Vec<Subscriber>,
+) -> Result<Vec<Subscriber>> {
+    let active = subscribers
+        .into_iter()
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Active)
+        .collect();
+    unsuppressed(data, active).await
+}
+
+/// The test of a campaign with how each variant did so far.
+pub(crate) async fn report(
+    data: &ApplicationData,
+    campaign_id: i32,
+) -> Result<Option<AbTestReport>> {
+    let store = PsqlAbTestStore::from(data.pool.clone());
+    let test = match store.get(campaign_id).await? {
+        Some(test) => test,
+        None => return Ok(None),
+    };
+    let variants = store.variants(campaign_id).await?;
+    let tallies = store.tallies(campaign_id).await?;
+    let remainder = store.remainder(campaign_id).await?;
+    Ok(Some(AbTestReport::new(test, variants, &tallies, remainder)))
+}
+
+/// Applies what a variant changes to the campaign's template.
+async fn prepare(
+    data: &ApplicationData,
+    template: &Template,
+    variant: Variant,
+) -> Result<Prepared> {
+    let body = match variant.template_id {
+        Some(id) => PsqlTemplateStore::from(data.pool.clone())
+            .get(id)
+            .await?
+            .ok_or_else(|| anyhow!("Template {id} of variant {} disappeared", variant.name))?,
+        None => template.clone(),
+    };
+    Ok(Prepared {
+        template: Template {
+            subject: variant
+                .subject
+                .clone()
+                .unwrap_or_else(|| template.subject.clone()),
+            ..body
+        },
+        from_name: variant
+            .from_name
+            .clone()
+            .unwrap_or_else(|| data.list.name.clone()),
+        variant,
+    })
+}
+
+/// A variant for a subscriber, to be queued with [`AbTestStore::send`].
+async fn delivery(
+    data: &ApplicationData,
+    campaign_id: i32,
+    prepared: &Prepared,
+    subscriber: &Subscriber,
+    engagement: i64,
+) -> Result<(i32, i32, NewDelivery)> {
+    let attributes = PsqlSubscriberStore::from(data.pool.clone())
+        .attributes(subscriber.id)
+        .await?;
+    let delivery = NewDelivery {
+        engagement,
+        ..campaign_delivery_from(
+            data,
+            &prepared.from_name,
+            &prepared.template,
+            subscriber,
+            &attributes,
+            campaign_id,
+        )?
+    };
+    Ok((subscriber.id, prepared.variant.id, delivery))
+}
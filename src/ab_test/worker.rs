@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use serde_json::Map;
+
+use crate::{
+    ab_test::{delivery, prepare, recipients, report},
+    archive,
+    data::ApplicationData,
+    model::AbTest,
+    store::{
+        AbTestStore, CampaignStore, PsqlAbTestStore, PsqlCampaignStore, PsqlSubscriberStore,
+        PsqlTemplateStore, SubscriberStore, TemplateStore,
+    },
+    warmup,
+};
+
+/// Sends the winner of every test whose window is over, forever.
+pub(crate) async fn run_ab_tests(data: ApplicationData) {
+    let mut interval = tokio::time::interval(Duration::from_secs(data.ab_test.interval));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = decide_due(&data).await {
+            error!("Failed to decide A/B tests: {e}");
+        }
+    }
+}
+
+async fn decide_due(data: &ApplicationData) -> Result<()> {
+    for test in PsqlAbTestStore::from(data.pool.clone()).due().await? {
+        // A failed test is retried on the next tick, without sending the
+        // winner twice to anyone.
+        if let Err(e) = decide(data, &test).await {
+            error!(
+                "Failed to decide A/B test of campaign {}: {e}",
+                test.campaign_id
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn decide(data: &ApplicationData, test: &AbTest) -> Result<()> {
+    let mut store = PsqlAbTestStore::from(data.pool.clone());
+    let campaign = PsqlCampaignStore::from(data.pool.clone())
+        .get(test.campaign_id)
+        .await?
+        .ok_or_else(|| anyhow!("Campaign {} disappeared", test.campaign_id))?;
+    let template_id = campaign
+        .template_id
+        .ok_or_else(|| anyhow!("Campaign {} has no template", campaign.id))?;
+    let template = PsqlTemplateStore::from(data.pool.clone())
+        .get(template_id)
+        .await?
+        .ok_or_else(|| anyhow!("Template {template_id} disappeared"))?;
+
+    let report = report(data, campaign.id)
+        .await?
+        .ok_or_else(|| anyhow!("A/B test of campaign {} disappeared", campaign.id))?;
+    let leader = report
+        .leader()
+        .ok_or_else(|| anyhow!("A/B test of campaign {} has no variants", campaign.id))?;
+    let winner_id = store.pick(campaign.id, leader.variant.id).await?;
+    let winner = report
+        .variants
+        .into_iter()
+        .find(|result| result.variant.id == winner_id)
+        .ok_or_else(|| anyhow!("Variant {winner_id} disappeared"))?
+        .variant;
+    info!(
+        "Variant {} won the A/B test of campaign {}",
+        winner.name, campaign.id
+    );
+
+    let prepared = prepare(data, &template, winner).await?;
+    archive::save_issue(data, campaign.id, &prepared.template, &Map::new()).await?;
+    let everyone = PsqlSubscriberStore::from(data.pool.clone()).all().await?;
+    let engagement = warmup::engagement(data).await?;
+    let mut deliveries = Vec::new();
+    for subscriber in recipients(data, everyone).await? {
+        let engagement = engagement.of(&subscriber);
+        deliveries.push(delivery(data, campaign.id, &prepared, &subscriber, engagement).await?);
+    }
+    let sent = store.send(campaign.id, false, &deliveries).await?;
+    store.finish(campaign.id).await?;
+
+    info!(
+        "Sent variant {} of campaign {} to the remaining {sent} subscribers",
+        prepared.variant.name, campaign.id
+    );
+    Ok(())
+}
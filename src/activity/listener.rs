@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{error, info, warn};
+use sqlx::postgres::PgListener;
+
+use crate::data::ApplicationData;
+
+use super::{Notification, CHANNEL};
+
+const RETRY: Duration = Duration::from_secs(5);
+
+/// Passes activity published by other instances to the live streams of
+/// this one.
+pub(crate) async fn listen_for_activity(data: ApplicationData) {
+    while !data.pool.is_closed() {
+        if let Err(e) = forward(&data).await {
+            error!("Stopped listening for activity: {e}");
+        }
+        tokio::time::sleep(RETRY).await;
+    }
+}
+
+async fn forward(data: &ApplicationData) -> Result<()> {
+    let mut listener = PgListener::connect_with(&data.pool).await?;
+    listener.listen(CHANNEL).await?;
+    info!("Listening for activity of other instances");
+
+    loop {
+        // Reconnects by itself when the connection drops.
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<Notification>(notification.payload()) {
+            Ok(notification) if notification.origin == data.activity.origin => {}
+            Ok(notification) => data.activity.send(notification.activity),
+            Err(e) => warn!("Ignoring malformed activity notification: {e}"),
+        }
+    }
+}
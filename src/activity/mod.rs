@@ -0,0 +1,129 @@
+mod listener;
+mod token;
+
+pub(crate) use listener::listen_for_activity;
+pub(crate) use token::StreamToken;
+
+use anyhow::Result;
+use log::error;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+
+use crate::{
+    config::ActivitySettings,
+    data::ApplicationData,
+    model::{Activity, ActivityKind, Subscriber},
+    store::{ActivityStore, PsqlActivityStore},
+};
+
+// The Postgres channel that instances share activity on.
+const CHANNEL: &str = "minimail_activity";
+
+/// Hands activity to the live streams of this instance.
+#[derive(Clone, Debug)]
+pub struct ActivityFeed {
+    pub settings: ActivitySettings,
+    sender: broadcast::Sender<Activity>,
+    // Tells our own notifications apart from those of other instances.
+    origin: String,
+}
+
+/// What instances tell each other about activity.
+#[derive(Debug, Serialize, Deserialize)]
+struct Notification {
+    origin: String,
+    activity: Activity,
+}
+
+impl From<ActivitySettings> for ActivityFeed {
+    fn from(settings: ActivitySettings) -> Self {
+        let (sender, _) = broadcast::channel(settings.buffer.max(1));
+        let origin = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        Self {
+            settings,
+            sender,
+            origin,
+        }
+    }
+}
+
+impl ActivityFeed {
+    /// Activity from now on, on this instance and all others.
+    pub fn subscribe(&self) -> broadcast::Receiver<Activity> {
+        self.sender.subscribe()
+    }
+
+    fn send(&self, activity: Activity) {
+        // Nobody might be watching, which is fine.
+        self.sender.send(activity).ok();
+    }
+}
+
+/// Logs activity and passes it to the live streams of every instance. The
+/// activity has already happened, so failing to publish it is only logged.
+pub(crate) async fn publish(data: &ApplicationData, kind: ActivityKind, fields: Value) {
+    if let Err(e) = record(data, kind, fields).await {
+        error!("Failed to publish {} activity: {e}", kind.as_str());
+    }
+}
+
+async fn record(data: &ApplicationData, kind: ActivityKind, fields: Value) -> Result<()> {
+    let mut store = PsqlActivityStore::from(data.pool.clone());
+    let activity = store
+        .record(kind, &fields, data.activity.settings.log_size)
+        .await?;
+    let notification = serde_json::to_string(&Notification {
+        origin: data.activity.origin.clone(),
+        activity: activity.clone(),
+    })?;
+    data.activity.send(activity);
+    store.notify(CHANNEL, &notification).await
+}
+
+/// Publishes activity of a subscriber.
+pub(crate) async fn publish_subscriber(
+    data: &ApplicationData,
+    kind: ActivityKind,
+    subscriber: &Subscriber,
+) {
+    publish(
+        data,
+        kind,
+        json!({
+            "subscriber_id": subscriber.id,
+            "email": subscriber.email.0,
+            "status": subscriber.status,
+        }),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn feeds_tell_their_notifications_apart() {
+        let feed = ActivityFeed::from(ActivitySettings::default());
+        let other = ActivityFeed::from(ActivitySettings::default());
+        let mut receiver = feed.subscribe();
+
+        feed.send(Activity {
+            id: 1,
+            kind: ActivityKind::Sent,
+            data: json!({}),
+            created_at: Utc::now(),
+        });
+
+        assert_ne!(feed.origin, other.origin);
+        assert_eq!(1, receiver.try_recv().unwrap().id);
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{data::ApplicationData, signing::Signer};
+
+/// Opens the activity stream for `EventSource`, which can't send the admin
+/// token in a header and would leave it in URLs and logs. It opens nothing
+/// else and only lasts `token_minutes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StreamToken {
+    #[serde(rename = "x")]
+    expires: i64,
+}
+
+impl StreamToken {
+    pub fn issue(data: &ApplicationData) -> (String, DateTime<Utc>) {
+        let expires = Utc::now() + Duration::minutes(data.activity.settings.token_minutes);
+        let token = signer(data).sign(&Self {
+            expires: expires.timestamp(),
+        });
+        (token, expires)
+    }
+
+    pub fn verify(data: &ApplicationData, token: &str) -> bool {
+        signer(data)
+            .verify::<Self>(token)
+            .is_some_and(|token| token.expires >= Utc::now().timestamp())
+    }
+}
+
+// Keyed by the admin token, so that changing it revokes every stream token,
+// and apart from every other kind of signed token.
+fn signer(data: &ApplicationData) -> Signer {
+    Signer::new(&format!("activity-stream:{}", data.admin.token))
+}
@@ -0,0 +1,8 @@
+mod render;
+mod session;
+
+pub use render::AdminPage;
+pub(crate) use session::{clear_cookie, current_session, set_cookie, sign_in, sign_out};
+
+/// The stylesheet of the admin interface, served from the binary.
+pub const STYLESHEET: &str = include_str!("assets/admin.css");
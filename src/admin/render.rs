@@ -0,0 +1,110 @@
+use crate::{
+    model::AdminSession,
+    pages::{escape, fill},
+};
+
+/// A page of the admin interface, filled into its template and then into
+/// the layout. Unlike hosted pages, they can't be restyled, so that they
+/// keep working whatever a list does to its own pages.
+pub struct AdminPage {
+    name: &'static str,
+    title: String,
+    // Placeholders and their values, already escaped.
+    variables: Vec<(&'static str, String)>,
+}
+
+impl AdminPage {
+    pub fn new(name: &'static str, title: impl Into<String>) -> Self {
+        Self {
+            name,
+            title: title.into(),
+            variables: Vec::new(),
+        }
+    }
+
+    /// Fills `{{ name }}` with text, escaped.
+    pub fn text(mut self, name: &'static str, value: &str) -> Self {
+        self.variables.push((name, escape(value)));
+        self
+    }
+
+    /// Fills `{{ name }}` with markup as is.
+    pub fn html(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.variables.push((name, value.into()));
+        self
+    }
+
+    /// Renders the page. Signed in, the layout gets the navigation and
+    /// every form can repeat the session with `{{ csrf }}`.
+    pub fn render(self, list: &str, session: Option<&AdminSession>) -> String {
+        let csrf = session.map(csrf_field).unwrap_or_default();
+        let mut variables = self.variables;
+        variables.push(("list", escape(list)));
+        variables.push(("csrf", csrf.clone()));
+        let content = fill(template(self.name), &variables);
+
+        let navigation = match session {
+            Some(_) => fill(template("navigation"), &[("csrf", csrf)]),
+            None => String::new(),
+        };
+        fill(
+            template("layout"),
+            &[
+                ("title", escape(&self.title)),
+                ("list", escape(list)),
+                ("navigation", navigation),
+                ("content", content),
+            ],
+        )
+    }
+}
+
+fn csrf_field(session: &AdminSession) -> String {
+    format!(
+        "<input type=\"hidden\" name=\"csrf\" value=\"{}\">",
+        escape(&session.csrf)
+    )
+}
+
+fn template(name: &str) -> &'static str {
+    match name {
+        "layout" => include_str!("templates/layout.html"),
+        "navigation" => include_str!("templates/navigation.html"),
+        "login" => include_str!("templates/login.html"),
+        "overview" => include_str!("templates/overview.html"),
+        "subscribers" => include_str!("templates/subscribers.html"),
+        "subscriber" => include_str!("templates/subscriber.html"),
+        "list" => include_str!("templates/list.html"),
+        "campaigns" => include_str!("templates/campaigns.html"),
+        "campaign" => include_str!("templates/campaign.html"),
+        "keys" => include_str!("templates/keys.html"),
+        _ => include_str!("templates/message.html"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn navigation_and_csrf_only_when_signed_in() {
+        let session = AdminSession {
+            csrf: "token".to_string(),
+            expires_at: Utc::now(),
+        };
+
+        let signed_in = AdminPage::new("subscriber", "ada@example.org")
+            .text("email", "<ada@example.org>")
+            .render("News & Views", Some(&session));
+        let signed_out = AdminPage::new("login", "Sign in").render("News", None);
+
+        assert!(signed_in.contains("<title>ada@example.org · News &amp; Views admin</title>"));
+        assert!(signed_in.contains("href=\"/admin/subscribers\""));
+        assert!(signed_in.contains("name=\"csrf\" value=\"token\""));
+        assert!(signed_in.contains("&lt;ada@example.org&gt;"));
+        assert!(!signed_out.contains("href=\"/admin/subscribers\""));
+        assert!(!signed_out.contains("name=\"csrf\""));
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use axum::headers::Cookie;
+use chrono::{Duration, Utc};
+use log::info;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    data::ApplicationData,
+    model::AdminSession,
+    store::{AdminSessionStore, PsqlAdminSessionStore},
+};
+
+const COOKIE: &str = "minimail_admin";
+
+/// The session of the visitor's cookie, unless it ran out.
+pub(crate) async fn current_session(
+    data: &ApplicationData,
+    cookie: Option<&Cookie>,
+) -> Result<Option<AdminSession>> {
+    match cookie.and_then(|cookie| cookie.get(COOKIE)) {
+        Some(token) if !token.is_empty() => {
+            PsqlAdminSessionStore::from(data.pool.clone())
+                .find(&AdminSession::hash(token))
+                .await
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Starts a session when the password is right, returning the token for
+/// the cookie. Nobody can sign in until a password is configured.
+pub(crate) async fn sign_in(
+    data: &ApplicationData,
+    password: &str,
+) -> Result<Option<(String, AdminSession)>> {
+    let Some(expected) = &data.admin_ui.password else {
+        return Ok(None);
+    };
+    if expected.is_empty() || !same(expected, password) {
+        info!("Rejecting admin sign in with a wrong password");
+        return Ok(None);
+    }
+
+    let token = AdminSession::generate_token();
+    let session = AdminSession {
+        csrf: AdminSession::generate_token(),
+        expires_at: Utc::now() + Duration::hours(data.admin_ui.session_hours),
+    };
+    PsqlAdminSessionStore::from(data.pool.clone())
+        .create(&AdminSession::hash(&token), &session)
+        .await?;
+    info!("Admin signed in");
+    Ok(Some((token, session)))
+}
+
+/// Compares secrets in constant time, by comparing their hashes, which also
+/// hides their lengths.
+fn same(a: &str, b: &str) -> bool {
+    let (a, b) = (Sha256::digest(a.as_bytes()), Sha256::digest(b.as_bytes()));
+    a.iter()
+        .zip(b.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+/// Ends the session of the visitor's cookie.
+pub(crate) async fn sign_out(data: &ApplicationData, cookie: Option<&Cookie>) -> Result<()> {
+    if let Some(token) = cookie.and_then(|cookie| cookie.get(COOKIE)) {
+        PsqlAdminSessionStore::from(data.pool.clone())
+            .delete(&AdminSession::hash(token))
+            .await?;
+    }
+    Ok(())
+}
+
+/// The `Set-Cookie` header value for a new session. The cookie only goes
+/// to the admin interface, never to the API, and never along with
+/// requests from other sites.
+pub(crate) fn set_cookie(data: &ApplicationData, token: &str) -> String {
+    let secure = if data.pages.url.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{COOKIE}={token}; Path=/admin; HttpOnly; SameSite=Strict; Max-Age={}{secure}",
+        data.admin_ui.session_hours * 60 * 60
+    )
+}
+
+pub(crate) fn clear_cookie() -> String {
+    format!("{COOKIE}=; Path=/admin; HttpOnly; SameSite=Strict; Max-Age=0")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_compares_whole_secrets() {
+        assert!(same("secret", "secret"));
+        assert!(!same("secret", "secreT"));
+        assert!(!same("secret", "secret "));
+        assert!(!same("secret", ""));
+    }
+}
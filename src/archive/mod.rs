@@ -0,0 +1,27 @@
+mod render;
+
+pub use render::{atom, index_page, issue_page, json_feed};
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use crate::{
+    data::ApplicationData,
+    model::{Email, Template},
+    store::{CampaignStore, PsqlCampaignStore},
+};
+
+/// Keeps a copy of a campaign for the archive. It is filled in with what
+/// every subscriber got alike, such as the entries of a feed digest, and
+/// without any subscriber's address or attributes. Links are not tracked.
+pub(crate) async fn save_issue(
+    data: &ApplicationData,
+    campaign_id: i32,
+    template: &Template,
+    variables: &Map<String, Value>,
+) -> Result<()> {
+    let issue = template.render(&Email::from(""), variables);
+    PsqlCampaignStore::from(data.pool.clone())
+        .save_issue(campaign_id, &issue)
+        .await
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::{
+    model::Issue,
+    pages::{escape, page},
+};
+
+// How many issues the feeds carry.
+const FEED_ISSUES: usize = 20;
+
+/// Lists every issue, newest first.
+pub fn index_page(list: &str, issues: &[Issue]) -> String {
+    let items: String = issues
+        .iter()
+        .map(|issue| {
+            format!(
+                "<li><a href=\"/archive/{}\">{}</a> <time datetime=\"{}\">{}</time></li>",
+                issue.campaign_id,
+                escape(&issue.subject),
+                issue.sent_at.to_rfc3339(),
+                issue.sent_at.format("%Y-%m-%d"),
+            )
+        })
+        .collect();
+    page(
+        &format!("{list} archive"),
+        &format!(
+            "<p><a href=\"/archive.atom\">Atom</a> · <a href=\"/archive.json\">JSON Feed</a></p>\
+             <ul>{items}</ul>"
+        ),
+    )
+}
+
+pub fn issue_page(list: &str, issue: &Issue) -> String {
+    page(
+        &issue.subject,
+        &format!(
+            "<p><a href=\"/archive\">{}</a> · <time datetime=\"{}\">{}</time></p>{}",
+            escape(&format!("{list} archive")),
+            issue.sent_at.to_rfc3339(),
+            issue.sent_at.format("%Y-%m-%d"),
+            content_html(issue),
+        ),
+    )
+}
+
+pub fn atom(list: &str, url: &str, issues: &[Issue]) -> String {
+    let url = url.trim_end_matches('/');
+    let entries: String = issues
+        .iter()
+        .take(FEED_ISSUES)
+        .map(|issue| {
+            let link = format!("{url}/archive/{}", issue.campaign_id);
+            let content = match &issue.html {
+                Some(html) => format!("<content type=\"html\">{}</content>", escape(html)),
+                None => format!("<content type=\"text\">{}</content>", escape(&issue.text)),
+            };
+            format!(
+                "<entry><title>{}</title><id>{link}</id><link href=\"{link}\"/>\
+                 <updated>{}</updated>{content}</entry>",
+                escape(&issue.subject),
+                issue.sent_at.to_rfc3339(),
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\"><title>{}</title><id>{url}/archive</id>\
+         <link href=\"{url}/archive\"/><link rel=\"self\" href=\"{url}/archive.atom\"/>\
+         <updated>{}</updated>{entries}</feed>\n",
+        escape(list),
+        updated(issues).to_rfc3339(),
+    )
+}
+
+pub fn json_feed(list: &str, url: &str, issues: &[Issue]) -> Value {
+    let url = url.trim_end_matches('/');
+    let items: Vec<_> = issues
+        .iter()
+        .take(FEED_ISSUES)
+        .map(|issue| {
+            let link = format!("{url}/archive/{}", issue.campaign_id);
+            let mut item = json!({
+                "id": link,
+                "url": link,
+                "title": issue.subject,
+                "date_published": issue.sent_at.to_rfc3339(),
+                "content_text": issue.text,
+            });
+            if let Some(html) = &issue.html {
+                item["content_html"] = json!(html);
+            }
+            item
+        })
+        .collect();
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": format!("{list} archive"),
+        "home_page_url": format!("{url}/archive"),
+        "feed_url": format!("{url}/archive.json"),
+        "items": items,
+    })
+}
+
+fn content_html(issue: &Issue) -> String {
+    match &issue.html {
+        Some(html) => html.clone(),
+        None => format!("<pre>{}</pre>", escape(&issue.text)),
+    }
+}
+
+// The newest issue, so that an unchanged archive keeps the same feed.
+fn updated(issues: &[Issue]) -> DateTime<Utc> {
+    issues
+        .iter()
+        .map(|issue| issue.sent_at)
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn issue(campaign_id: i32, subject: &str, html: Option<&str>) -> Issue {
+        Issue {
+            campaign_id,
+            subject: subject.to_string(),
+            text: "Hello & welcome".to_string(),
+            html: html.map(str::to_string),
+            sent_at: Utc
+                .with_ymd_and_hms(2023, 5, campaign_id as u32, 9, 0, 0)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn index_page_links_every_issue() {
+        let page = index_page(
+            "News",
+            &[issue(2, "May <2>", None), issue(1, "May 1", None)],
+        );
+
+        assert!(page.contains("<title>News archive</title>"));
+        assert!(page.contains("<a href=\"/archive/2\">May &lt;2&gt;</a>"));
+        assert!(page.contains("<time datetime=\"2023-05-01T09:00:00+00:00\">2023-05-01</time>"));
+    }
+
+    #[test]
+    fn issue_page_falls_back_to_text() {
+        let html = issue_page("News", &issue(1, "May", Some("<p>Hi</p>")));
+        let text = issue_page("News", &issue(1, "May", None));
+
+        assert!(html.contains("<p>Hi</p>"));
+        assert!(text.contains("<pre>Hello &amp; welcome</pre>"));
+    }
+
+    #[test]
+    fn atom_escapes_content() {
+        let atom = atom(
+            "News",
+            "https://example.com/",
+            &[issue(2, "May", Some("<p>Hi</p>")), issue(1, "April", None)],
+        );
+
+        assert!(atom.contains("<id>https://example.com/archive/2</id>"));
+        assert!(atom.contains("<content type=\"html\">&lt;p&gt;Hi&lt;/p&gt;</content>"));
+        assert!(atom.contains("<content type=\"text\">Hello &amp; welcome</content>"));
+        assert!(atom.contains("<updated>2023-05-02T09:00:00+00:00</updated><entry>"));
+        assert!(feed_rs::parser::parse(atom.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn json_feed_has_html_when_there_is_some() {
+        let feed = json_feed(
+            "News",
+            "https://example.com",
+            &[issue(2, "May", Some("<p>Hi</p>")), issue(1, "April", None)],
+        );
+
+        assert_eq!(json!("https://jsonfeed.org/version/1.1"), feed["version"]);
+        assert_eq!(json!("<p>Hi</p>"), feed["items"][0]["content_html"]);
+        assert_eq!(Value::Null, feed["items"][1]["content_html"]);
+        assert_eq!(
+            json!("https://example.com/archive/1"),
+            feed["items"][1]["url"]
+        );
+    }
+}
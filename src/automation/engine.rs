@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use chrono::Duration;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::model::{Condition, Step};
+
+/// What we know about a subscriber when deciding on branches.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub opened: bool,
+    pub clicked: bool,
+    pub attributes: Map<String, Value>,
+    pub tags: BTreeSet<String>,
+}
+
+impl Snapshot {
+    fn holds(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Opened => self.opened,
+            Condition::Clicked => self.clicked,
+            Condition::Attribute { name, equals } => self.attributes.get(name) == Some(equals),
+            Condition::Tagged { tag } => self.tags.contains(tag),
+        }
+    }
+}
+
+/// A step that does something to the subscriber.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    Send { template: String },
+    AddTag { tag: String },
+    RemoveTag { tag: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advance {
+    // Every effect with the index of its step.
+    pub effects: Vec<(usize, Effect)>,
+    // The step to continue with and how long to wait for it, or `None` once
+    // the run is over.
+    pub next: Option<(usize, Duration)>,
+}
+
+/// Takes the steps from `from` up to the next wait. Tag changes are applied
+/// to the snapshot, so later branches see them.
+pub fn advance(steps: &[Step], from: usize, snapshot: &mut Snapshot) -> Advance {
+    let mut effects = Vec::new();
+    let mut index = from;
+
+    while let Some(step) = steps.get(index) {
+        let current = index;
+        index += 1;
+        match step {
+            Step::Send { template } => effects.push((
+                current,
+                Effect::Send {
+                    template: template.clone(),
+                },
+            )),
+            Step::Wait { days, hours } => {
+                return Advance {
+                    effects,
+                    next: Some((index, Duration::days(*days) + Duration::hours(*hours))),
+                };
+            }
+            Step::Branch { when, goto } => {
+                if snapshot.holds(when) {
+                    // Branches only jump forward, so this always ends.
+                    index = (*goto).max(index);
+                }
+            }
+            Step::AddTag { tag } => {
+                snapshot.tags.insert(tag.clone());
+                effects.push((current, Effect::AddTag { tag: tag.clone() }));
+            }
+            Step::RemoveTag { tag } => {
+                snapshot.tags.remove(tag);
+                effects.push((current, Effect::RemoveTag { tag: tag.clone() }));
+            }
+        }
+    }
+
+    Advance {
+        effects,
+        next: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn send(template: &str) -> Step {
+        Step::Send {
+            template: template.to_string(),
+        }
+    }
+
+    fn steps() -> Vec<Step> {
+        vec![
+            send("welcome"),
+            Step::Wait { days: 2, hours: 0 },
+            Step::Branch {
+                when: Condition::Opened,
+                goto: 5,
+            },
+            send("reminder"),
+            Step::AddTag {
+                tag: "cold".to_string(),
+            },
+            send("tips"),
+        ]
+    }
+
+    #[test]
+    fn advance_stops_at_waits() {
+        let advance = advance(&steps(), 0, &mut Snapshot::default());
+
+        assert_eq!(
+            vec![(
+                0,
+                Effect::Send {
+                    template: "welcome".to_string()
+                }
+            )],
+            advance.effects
+        );
+        assert_eq!(Some((2, Duration::days(2))), advance.next);
+    }
+
+    #[test]
+    fn advance_follows_branch_when_condition_holds() {
+        let mut snapshot = Snapshot {
+            opened: true,
+            ..Snapshot::default()
+        };
+
+        let advance = advance(&steps(), 2, &mut snapshot);
+
+        assert_eq!(
+            vec![(
+                5,
+                Effect::Send {
+                    template: "tips".to_string()
+                }
+            )],
+            advance.effects
+        );
+        assert_eq!(None, advance.next);
+    }
+
+    #[test]
+    fn advance_continues_when_condition_fails() {
+        let mut snapshot = Snapshot::default();
+
+        let advance = advance(&steps(), 2, &mut snapshot);
+
+        assert_eq!(3, advance.effects.len());
+        assert!(snapshot.tags.contains("cold"));
+    }
+
+    #[test]
+    fn branches_see_attributes_and_earlier_tags() {
+        let steps = vec![
+            Step::AddTag {
+                tag: "vip".to_string(),
+            },
+            Step::Branch {
+                when: Condition::Tagged {
+                    tag: "vip".to_string(),
+                },
+                goto: 3,
+            },
+            send("regular"),
+            Step::Branch {
+                when: Condition::Attribute {
+                    name: "plan".to_string(),
+                    equals: json!("pro"),
+                },
+                goto: 5,
+            },
+            send("upgrade"),
+        ];
+        let mut snapshot = Snapshot {
+            attributes: json!({"plan": "pro"}).as_object().unwrap().clone(),
+            ..Snapshot::default()
+        };
+
+        let advance = advance(&steps, 0, &mut snapshot);
+
+        assert_eq!(
+            vec![(
+                0,
+                Effect::AddTag {
+                    tag: "vip".to_string()
+                }
+            )],
+            advance.effects
+        );
+        assert_eq!(None, advance.next);
+    }
+}
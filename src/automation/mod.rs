@@ -0,0 +1,55 @@
+mod engine;
+mod simulator;
+mod worker;
+
+pub use simulator::{simulate, Scenario, Simulation};
+pub(crate) use worker::run_automations;
+
+use anyhow::Result;
+use log::info;
+
+use crate::{
+    data::ApplicationData,
+    model::{Automation, NewAutomation, NewCampaign, Subscriber, SubscriberStatus, Trigger},
+    store::{AutomationStore, CampaignStore, PsqlAutomationStore, PsqlCampaignStore},
+};
+
+/// Stores an automation together with the campaign its mail is tracked as.
+pub(crate) async fn create(
+    data: &ApplicationData,
+    new_automation: NewAutomation,
+) -> Result<Automation> {
+    let campaign = PsqlCampaignStore::from(data.pool.clone())
+        .create(NewCampaign {
+            name: format!("Automation: {}", new_automation.name),
+        })
+        .await?;
+    PsqlAutomationStore::from(data.pool.clone())
+        .create(new_automation, campaign.id)
+        .await
+}
+
+/// Starts every automation with this trigger for an active subscriber, who
+/// goes through each automation at most once.
+pub(crate) async fn enroll(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    trigger: &Trigger,
+) -> Result<usize> {
+    if subscriber.status != SubscriberStatus::Active {
+        return Ok(0);
+    }
+
+    let mut automations = PsqlAutomationStore::from(data.pool.clone());
+    let mut enrolled = 0;
+    for automation in automations.triggered_by(trigger).await? {
+        if automations.enroll(automation.id, subscriber.id).await? {
+            info!(
+                "Enrolled subscriber {} in automation {}",
+                subscriber.id, automation.id
+            );
+            enrolled += 1;
+        }
+    }
+    Ok(enrolled)
+}
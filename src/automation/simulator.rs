@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    automation::engine::{advance, Effect, Snapshot},
+    model::Automation,
+};
+
+/// How the imaginary subscriber of a dry run behaves.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Scenario {
+    // Opens the mail they are sent.
+    #[serde(default)]
+    pub opened: bool,
+    // Clicks a link in the mail they are sent.
+    #[serde(default)]
+    pub clicked: bool,
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulated {
+    pub at: DateTime<Utc>,
+    pub step: usize,
+    #[serde(flatten)]
+    pub effect: Effect,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub timeline: Vec<Simulated>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Runs an automation for an imaginary subscriber who enrolls at `start`,
+/// skipping over the waits instead of sleeping through them. Nothing is
+/// sent or stored.
+pub fn simulate(automation: &Automation, scenario: Scenario, start: DateTime<Utc>) -> Simulation {
+    let mut snapshot = Snapshot {
+        opened: false,
+        clicked: false,
+        attributes: scenario.attributes,
+        tags: scenario.tags,
+    };
+    let mut timeline = Vec::new();
+    let mut at = start;
+    let mut step = 0;
+
+    loop {
+        let advanced = advance(&automation.steps, step, &mut snapshot);
+        for (index, effect) in advanced.effects {
+            if matches!(effect, Effect::Send { .. }) {
+                // Opens and clicks can only happen once there is mail.
+                snapshot.opened |= scenario.opened;
+                snapshot.clicked |= scenario.clicked;
+            }
+            timeline.push(Simulated {
+                at,
+                step: index,
+                effect,
+            });
+        }
+        match advanced.next {
+            Some((next, wait)) => {
+                step = next;
+                at += wait;
+            }
+            None => {
+                return Simulation {
+                    timeline,
+                    completed_at: at,
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::model::{Condition, Step, Trigger};
+
+    use super::*;
+
+    fn automation() -> Automation {
+        Automation {
+            id: 1,
+            name: "Welcome".to_string(),
+            trigger: Trigger::Subscribed,
+            steps: vec![
+                Step::Send {
+                    template: "welcome".to_string(),
+                },
+                Step::Wait { days: 3, hours: 0 },
+                Step::Branch {
+                    when: Condition::Clicked,
+                    goto: 5,
+                },
+                Step::Send {
+                    template: "reminder".to_string(),
+                },
+                Step::Wait { days: 0, hours: 12 },
+                Step::AddTag {
+                    tag: "onboarded".to_string(),
+                },
+            ],
+            campaign_id: 1,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn simulate_fast_forwards_through_waits() {
+        let start = Utc::now();
+
+        let simulation = simulate(&automation(), Scenario::default(), start);
+
+        let steps: Vec<_> = simulation.timeline.iter().map(|s| s.step).collect();
+        assert_eq!(vec![0, 3, 5], steps);
+        assert_eq!(start + Duration::days(3), simulation.timeline[1].at);
+        assert_eq!(
+            start + Duration::days(3) + Duration::hours(12),
+            simulation.completed_at
+        );
+    }
+
+    #[test]
+    fn simulate_takes_branch_for_engaged_subscribers() {
+        let start = Utc::now();
+        let scenario = Scenario {
+            clicked: true,
+            ..Scenario::default()
+        };
+
+        let simulation = simulate(&automation(), scenario, start);
+
+        assert_eq!(2, simulation.timeline.len());
+        assert_eq!(
+            Effect::AddTag {
+                tag: "onboarded".to_string()
+            },
+            simulation.timeline[1].effect
+        );
+        assert_eq!(start + Duration::days(3), simulation.completed_at);
+    }
+}
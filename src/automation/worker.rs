@@ -0,0 +1,170 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{error, info};
+
+use crate::{
+    automation::{
+        engine::{advance, Effect, Snapshot},
+        enroll,
+    },
+    data::ApplicationData,
+    model::{Automation, AutomationRun, EventKind, NewDelivery, RunStatus, Subscriber},
+    model::{SubscriberStatus, Trigger},
+    outbound::campaign_delivery,
+    store::{
+        AutomationStore, EventStore, PsqlAutomationStore, PsqlEventStore, PsqlSubscriberStore,
+        PsqlSuppressionStore, PsqlTemplateStore, SubscriberStore, SuppressionStore, TemplateStore,
+    },
+};
+
+const BATCH: i64 = 100;
+// Claims of a step before the run is given up on.
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Takes the steps of every run that is due, forever.
+pub(crate) async fn run_automations(data: ApplicationData) {
+    let mut interval = tokio::time::interval(Duration::from_secs(data.automation.interval));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_due(&data).await {
+            error!("Failed to run automations: {e}");
+        }
+    }
+}
+
+async fn run_due(data: &ApplicationData) -> Result<()> {
+    let mut automations = PsqlAutomationStore::from(data.pool.clone());
+
+    loop {
+        let runs = automations.claim(BATCH).await?;
+        if runs.is_empty() {
+            return Ok(());
+        }
+
+        for run in runs {
+            // A failed run is retried once its claim runs out, from the step
+            // after the last one taken.
+            if let Err(e) = take_steps(data, &run).await {
+                error!("Failed to run automation run {}: {e}", run.id);
+                if run.attempts >= MAX_ATTEMPTS {
+                    error!("Giving up on automation run {}", run.id);
+                    automations.finish(run.id, RunStatus::Failed).await?;
+                }
+            }
+        }
+    }
+}
+
+async fn take_steps(data: &ApplicationData, run: &AutomationRun) -> Result<()> {
+    let mut automations = PsqlAutomationStore::from(data.pool.clone());
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+
+    let automation = automations
+        .get(run.automation_id)
+        .await?
+        .ok_or_else(|| anyhow!("Automation {} disappeared", run.automation_id))?;
+    let subscriber = match subscribers.get(run.subscriber_id).await? {
+        Some(subscriber) if subscriber.status == SubscriberStatus::Active => subscriber,
+        _ => {
+            info!(
+                "Subscriber {} left automation {}",
+                run.subscriber_id, automation.id
+            );
+            return automations.finish(run.id, RunStatus::Exited).await;
+        }
+    };
+
+    let suppressions = PsqlSuppressionStore::from(data.pool.clone());
+    let events = PsqlEventStore::from(data.pool.clone());
+    let mut snapshot = Snapshot {
+        opened: events
+            .occurred(subscriber.id, automation.campaign_id, EventKind::Open)
+            .await?,
+        clicked: events
+            .occurred(subscriber.id, automation.campaign_id, EventKind::Click)
+            .await?,
+        attributes: subscribers.attributes(subscriber.id).await?,
+        tags: subscribers
+            .tags(subscriber.id)
+            .await?
+            .into_iter()
+            .collect::<BTreeSet<_>>(),
+    };
+
+    let advanced = advance(&automation.steps, run.step as usize, &mut snapshot);
+    for (index, effect) in advanced.effects {
+        // Progress is recorded after every effect, so that a retry after a
+        // later one fails does not take it again.
+        let next = index as i32 + 1;
+        match effect {
+            Effect::Send { template } => {
+                // The address may have been suppressed since the last step.
+                if suppressions.contains(&subscriber.email).await? {
+                    info!(
+                        "Skipped {template} of automation {} for suppressed subscriber {}",
+                        automation.id, subscriber.id
+                    );
+                    automations.progress(run.id, next).await?;
+                    continue;
+                }
+                let delivery =
+                    delivery(data, &automation, &subscriber, &template, &snapshot).await?;
+                automations.send(run.id, next, delivery).await?;
+                info!(
+                    "Queued {template} of automation {} for subscriber {}",
+                    automation.id, subscriber.id
+                );
+            }
+            Effect::AddTag { tag } => {
+                if subscribers.add_tag(subscriber.id, &tag).await? {
+                    enroll(data, &subscriber, &Trigger::TagAdded { tag }).await?;
+                }
+                automations.progress(run.id, next).await?;
+            }
+            Effect::RemoveTag { tag } => {
+                subscribers.remove_tag(subscriber.id, &tag).await?;
+                automations.progress(run.id, next).await?;
+            }
+        }
+    }
+
+    match advanced.next {
+        Some((step, wait)) => {
+            automations
+                .advance(run.id, step as i32, Utc::now() + wait)
+                .await
+        }
+        None => {
+            info!(
+                "Subscriber {} completed automation {}",
+                subscriber.id, automation.id
+            );
+            automations.finish(run.id, RunStatus::Completed).await
+        }
+    }
+}
+
+/// Builds the mail of a template for the subscriber, with tracking that
+/// counts towards the automation's campaign.
+async fn delivery(
+    data: &ApplicationData,
+    automation: &Automation,
+    subscriber: &Subscriber,
+    name: &str,
+    snapshot: &Snapshot,
+) -> Result<NewDelivery> {
+    let template = PsqlTemplateStore::from(data.pool.clone())
+        .find(name)
+        .await?
+        .ok_or_else(|| anyhow!("No template named {name}"))?;
+    campaign_delivery(
+        data,
+        &template,
+        subscriber,
+        &snapshot.attributes,
+        automation.campaign_id,
+    )
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AbTestSettings {
+    // Seconds between looks for A/B tests whose window is over.
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    60
+}
@@ -0,0 +1,57 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActivitySettings {
+    // Events kept for streams resuming with `Last-Event-ID`.
+    #[serde(
+        default = "default_log_size",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub log_size: i64,
+    // Events a slow stream may fall behind before it reloads from the log.
+    #[serde(
+        default = "default_buffer",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub buffer: usize,
+    // Seconds between comments that keep idle streams open.
+    #[serde(
+        default = "default_keep_alive",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub keep_alive: u64,
+    // Minutes a stream token lasts.
+    #[serde(
+        default = "default_token_minutes",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub token_minutes: i64,
+}
+
+impl Default for ActivitySettings {
+    fn default() -> Self {
+        Self {
+            log_size: default_log_size(),
+            buffer: default_buffer(),
+            keep_alive: default_keep_alive(),
+            token_minutes: default_token_minutes(),
+        }
+    }
+}
+
+fn default_log_size() -> i64 {
+    1000
+}
+
+fn default_buffer() -> usize {
+    256
+}
+
+fn default_keep_alive() -> u64 {
+    15
+}
+
+fn default_token_minutes() -> i64 {
+    60
+}
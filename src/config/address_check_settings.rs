@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+/// What happens to a signup whose address fails a check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckPolicy {
+    // Turns the signup away.
+    Reject,
+    // Subscribes, but tags the subscriber and warns the visitor.
+    Flag,
+    // Skips the check.
+    Allow,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AddressCheckSettings {
+    // Addresses at throwaway domains like mailinator.com.
+    #[serde(default = "reject")]
+    pub disposable: CheckPolicy,
+    // Addresses of roles like info@ or admin@.
+    #[serde(default = "flag")]
+    pub role: CheckPolicy,
+    // Addresses at a likely misspelling of a common domain.
+    #[serde(default = "flag")]
+    pub typo: CheckPolicy,
+    // A file of further disposable domains, one per line, read at startup
+    // in addition to the bundled list.
+    pub disposable_domains: Option<String>,
+    // Domains never treated as disposable, even when a list has them.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for AddressCheckSettings {
+    fn default() -> Self {
+        Self {
+            disposable: reject(),
+            role: flag(),
+            typo: flag(),
+            disposable_domains: None,
+            allowed_domains: Vec::new(),
+        }
+    }
+}
+
+fn reject() -> CheckPolicy {
+    CheckPolicy::Reject
+}
+
+fn flag() -> CheckPolicy {
+    CheckPolicy::Flag
+}
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminUiSettings {
+    // What signs in to the admin interface. It stays closed without one.
+    #[serde(default)]
+    pub password: Option<String>,
+    // How long a session lasts.
+    #[serde(
+        default = "default_session_hours",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub session_hours: i64,
+    // Sign in attempts allowed from one client address per rate limit
+    // window of `protection`. 0 for no limit.
+    #[serde(
+        default = "default_login_attempts",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub login_attempts: i64,
+}
+
+impl Default for AdminUiSettings {
+    fn default() -> Self {
+        Self {
+            password: None,
+            session_hours: default_session_hours(),
+            login_attempts: default_login_attempts(),
+        }
+    }
+}
+
+fn default_session_hours() -> i64 {
+    12
+}
+
+fn default_login_attempts() -> i64 {
+    10
+}
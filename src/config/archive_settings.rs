@@ -0,0 +1,21 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ArchiveSettings {
+    // Serves sent campaigns publicly under `/archive`.
+    #[serde(default)]
+    pub enabled: bool,
+    // Public address of the app, for the links in the feeds.
+    pub url: String,
+    // Seconds that browsers and proxies may cache archive pages.
+    #[serde(
+        default = "default_max_age",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub max_age: u64,
+}
+
+fn default_max_age() -> u64 {
+    300
+}
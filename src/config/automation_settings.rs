@@ -0,0 +1,16 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AutomationSettings {
+    // Seconds between looks for automation runs that are due.
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    60
+}
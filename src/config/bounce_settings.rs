@@ -0,0 +1,24 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BounceSettings {
+    // Domain of the VERP return paths, e.g. `bounces+user=example.com@domain`.
+    pub domain: String,
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    // Number of soft bounces after which a subscriber is marked as bounced.
+    #[serde(
+        default = "default_threshold",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub threshold: i32,
+}
+
+fn default_prefix() -> String {
+    "bounces".to_string()
+}
+
+fn default_threshold() -> i32 {
+    3
+}
@@ -0,0 +1,45 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+/// What happens to a signup whose domain doesn't receive mail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliverabilityPolicy {
+    // Turns the signup away.
+    #[default]
+    Reject,
+    // Subscribes, but holds the subscriber for moderation.
+    Pending,
+    // Subscribes, tagging the subscriber.
+    Accept,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeliverabilitySettings {
+    #[serde(default)]
+    pub policy: DeliverabilityPolicy,
+    // Name servers to ask, like `9.9.9.9:53`. Without any, the system's
+    // are used.
+    #[serde(default)]
+    pub nameservers: Vec<String>,
+    // Milliseconds to wait for an answer before letting the signup through.
+    #[serde(
+        default = "default_timeout_ms",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub timeout_ms: u64,
+    // Seconds that the answer for a domain is reused.
+    #[serde(
+        default = "default_cache_seconds",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub cache_seconds: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_cache_seconds() -> u64 {
+    3600
+}
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FeedSettings {
+    // Seconds between looks for feeds that are due.
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+    // Seconds a feed has to answer.
+    #[serde(
+        default = "default_timeout",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub timeout: u64,
+    // Bytes a feed may be at most.
+    #[serde(
+        default = "default_max_size",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub max_size: usize,
+    // Directory that feeds may also be read from as files, by a path below
+    // it. Without one, feeds are read over http(s) only.
+    pub directory: Option<String>,
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_max_size() -> usize {
+    5 * 1024 * 1024
+}
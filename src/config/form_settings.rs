@@ -0,0 +1,11 @@
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct FormSettings {
+    // Attributes asked for by the signup form, besides the address.
+    #[serde(default)]
+    pub fields: Vec<String>,
+    // Label of a checkbox that has to be ticked to subscribe, e.g. agreeing
+    // to the privacy policy. Without one, the form has no checkbox.
+    pub consent: Option<String>,
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GovernorSettings {
+    // Messages sent per second, across all domains. 0 for no limit.
+    #[serde(
+        default = "default_per_second",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub per_second: f64,
+    // Limits for recipient domains without limits of their own.
+    #[serde(default)]
+    pub default: DomainLimits,
+    // Limits by recipient domain, like `gmail.com`.
+    #[serde(default)]
+    pub domains: HashMap<String, DomainLimits>,
+    // Seconds that a domain is left alone after it defers a message. The
+    // pause doubles with every further deferral, up to `max_backoff`.
+    #[serde(
+        default = "default_backoff",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub backoff: u64,
+    #[serde(
+        default = "default_max_backoff",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub max_backoff: u64,
+}
+
+impl Default for GovernorSettings {
+    fn default() -> Self {
+        Self {
+            per_second: default_per_second(),
+            default: DomainLimits::default(),
+            domains: HashMap::new(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct DomainLimits {
+    // Messages being sent to the domain at once. 0 for no limit.
+    #[serde(
+        default = "default_concurrency",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub concurrency: usize,
+    // Messages sent to the domain per hour. 0 for no limit.
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    pub hourly: usize,
+}
+
+impl Default for DomainLimits {
+    fn default() -> Self {
+        Self {
+            concurrency: default_concurrency(),
+            hourly: 0,
+        }
+    }
+}
+
+fn default_per_second() -> f64 {
+    20.0
+}
+
+fn default_concurrency() -> usize {
+    5
+}
+
+fn default_backoff() -> u64 {
+    60
+}
+
+fn default_max_backoff() -> u64 {
+    3600
+}
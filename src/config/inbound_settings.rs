@@ -0,0 +1,26 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use super::SmtpSettings;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct InboundSettings {
+    // Has no default, so that nobody can post mail with a well-known one.
+    #[serde(default)]
+    pub token: String,
+    // A Maildir that is checked for bounces every `interval` seconds.
+    pub mailbox: Option<String>,
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+    // Runs our own SMTP receiver when set.
+    pub smtp: Option<SmtpSettings>,
+    // Where replies and other mail we do not handle ourselves is sent.
+    pub forward: Option<String>,
+}
+
+fn default_interval() -> u64 {
+    60
+}
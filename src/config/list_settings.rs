@@ -0,0 +1,105 @@
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMode {
+    // Only we send to the list, replies go to `inbound.forward`.
+    #[default]
+    Newsletter,
+    // Members post to the list address and every post is sent to all of them.
+    Discussion,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ListSettings {
+    pub name: String,
+    // Posting address of the list. Commands go to `<local part>-request@`
+    // the same domain.
+    pub address: String,
+    // Signs confirmation and moderation tokens. Has no default, so that they
+    // can't be forged with a well-known one.
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default)]
+    pub mode: ListMode,
+    // Put in front of the subject of posts, e.g. `[news]`.
+    pub prefix: Option<String>,
+    // Appended to every post.
+    pub footer: Option<String>,
+    // Sends posts from the list address, with the author in `Reply-To`, so
+    // that they pass DMARC checks of the author's domain.
+    #[serde(default = "rewrite_by_default")]
+    pub rewrite: bool,
+    // Hours between digests.
+    #[serde(
+        default = "default_digest",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub digest: u64,
+    // IANA timezone that campaign times are in, and that subscribers whose
+    // timezone we do not know are assumed to be in.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    // Sites whose pages may call `/api/subscriptions` from the browser.
+    #[serde(default, deserialize_with = "named_origins")]
+    pub origins: Vec<String>,
+}
+
+impl ListSettings {
+    pub fn request_address(&self) -> String {
+        match self.address.rsplit_once('@') {
+            Some((local_part, domain)) => format!("{local_part}-request@{domain}"),
+            None => format!("{}-request", self.address),
+        }
+    }
+}
+
+fn rewrite_by_default() -> bool {
+    true
+}
+
+fn default_digest() -> u64 {
+    24
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+// Any site could read what happens to signups with `*`, so the sites have
+// to be named.
+fn named_origins<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let origins = Vec::<String>::deserialize(deserializer)?;
+    if origins.iter().any(|origin| origin.trim() == "*") {
+        return Err(D::Error::custom(
+            "list.origins has to name the sites, `*` is not allowed",
+        ));
+    }
+    Ok(origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn origins_have_to_be_named() {
+        let list = |origins: &[&str]| {
+            serde_json::from_value::<ListSettings>(json!({
+                "name": "News",
+                "address": "news@example.com",
+                "secret": "secret",
+                "origins": origins,
+            }))
+        };
+
+        assert_eq!(
+            vec!["https://www.example.com"],
+            list(&["https://www.example.com"]).unwrap().origins
+        );
+        assert!(list(&["https://www.example.com", "*"]).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MailerSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Requires STARTTLS, should only be turned off for a local relay.
+    #[serde(default = "tls_by_default")]
+    pub tls: bool,
+    // Address our own mail is sent from.
+    pub from: String,
+    // Seconds between looks at the outbox.
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+    // Delivery attempts before a message is given up on.
+    #[serde(
+        default = "default_attempts",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub attempts: i32,
+}
+
+fn tls_by_default() -> bool {
+    true
+}
+
+fn default_interval() -> u64 {
+    10
+}
+
+fn default_attempts() -> i32 {
+    5
+}
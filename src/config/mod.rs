@@ -1,18 +1,60 @@
+mod ab_test_settings;
+mod activity_settings;
+mod address_check_settings;
 mod admin_settings;
+mod admin_ui_settings;
 mod application_settings;
+mod archive_settings;
+mod automation_settings;
+mod bounce_settings;
 mod database_settings;
+mod deliverability_settings;
 mod environment;
+mod feed_settings;
+mod form_settings;
+mod governor_settings;
+mod inbound_settings;
+mod list_settings;
+mod mailer_settings;
+mod moderation_settings;
+mod pages_settings;
+mod protection_settings;
 mod settings;
+mod smtp_settings;
 mod subscribed_settings;
+mod tracking_settings;
+mod webhook_settings;
 
 use config::ConfigError;
 
+pub use ab_test_settings::AbTestSettings;
+pub use activity_settings::ActivitySettings;
+pub use address_check_settings::{AddressCheckSettings, CheckPolicy};
 pub use admin_settings::AdminSettings;
+pub use admin_ui_settings::AdminUiSettings;
 pub use application_settings::ApplicationSettings;
+pub use archive_settings::ArchiveSettings;
+pub use automation_settings::AutomationSettings;
+pub use bounce_settings::BounceSettings;
 pub use database_settings::DatabaseSettings;
+pub use deliverability_settings::{DeliverabilityPolicy, DeliverabilitySettings};
 use environment::Environment;
+pub use feed_settings::FeedSettings;
+pub use form_settings::FormSettings;
+pub use governor_settings::{DomainLimits, GovernorSettings};
+pub use inbound_settings::InboundSettings;
+pub use list_settings::{ListMode, ListSettings};
+pub use mailer_settings::MailerSettings;
+pub use moderation_settings::ModerationSettings;
+pub use pages_settings::PagesSettings;
+pub use protection_settings::{
+    ChallengeKind, ChallengeSettings, ProtectionSettings, RateLimitBackend, RateLimitSettings,
+};
 pub use settings::Settings;
+pub use smtp_settings::SmtpSettings;
 pub use subscribed_settings::SubscribedSettings;
+pub use tracking_settings::TrackingSettings;
+pub use webhook_settings::WebhookSettings;
 
 pub fn get_configuration() -> Result<Settings, ConfigError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
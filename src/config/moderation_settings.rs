@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ModerationSettings {
+    // Addresses notified of every held post and pending signup.
+    #[serde(default)]
+    pub moderators: Vec<String>,
+    // Holds new subscribers until a moderator approves them.
+    #[serde(default)]
+    pub signups: bool,
+    // Public address of the app, for the links in notifications.
+    pub url: String,
+}
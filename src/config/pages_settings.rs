@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PagesSettings {
+    // Public address of the app, for the links in confirmation mails and
+    // campaigns.
+    pub url: String,
+    // Directory with templates that replace the built-in pages, named like
+    // them, e.g. `subscribe.html` or `layout.html`.
+    pub templates: Option<String>,
+    // Address of a stylesheet linked from every page.
+    pub stylesheet: Option<String>,
+    // Tags that subscribers may pick in the preference center.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    // Attributes that subscribers may edit in the preference center.
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
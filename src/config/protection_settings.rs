@@ -0,0 +1,115 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ProtectionSettings {
+    // Seconds that have to pass between loading a signup form and sending
+    // it, since bots fill in forms at once. 0 turns the check off.
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    pub min_fill_seconds: i64,
+    // Reads the client address from `X-Forwarded-For`, when the app is
+    // behind a proxy that sets it.
+    #[serde(default)]
+    pub trust_forwarded: bool,
+    // Proxies in front of the app that each add to `X-Forwarded-For`. The
+    // client is the entry this many places from the right, since entries
+    // further left come from the client itself.
+    #[serde(
+        default = "default_forwarded_hops",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub forwarded_hops: usize,
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+    // Asks signups to solve a challenge, such as a CAPTCHA.
+    pub challenge: Option<ChallengeSettings>,
+}
+
+fn default_forwarded_hops() -> usize {
+    1
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    // Counts in each instance of the app on its own.
+    #[default]
+    Memory,
+    // Counts in the database, shared by every instance.
+    Postgres,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub store: RateLimitBackend,
+    // Signups allowed from one client address per window. 0 for no limit.
+    #[serde(
+        default = "default_per_ip",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub per_ip: i64,
+    // Signups allowed for one email address per window. 0 for no limit.
+    #[serde(
+        default = "default_per_address",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub per_address: i64,
+    // Seconds in a window.
+    #[serde(
+        default = "default_window",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub window: i64,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            store: RateLimitBackend::default(),
+            per_ip: default_per_ip(),
+            per_address: default_per_address(),
+            window: default_window(),
+        }
+    }
+}
+
+fn default_per_ip() -> i64 {
+    20
+}
+
+fn default_per_address() -> i64 {
+    5
+}
+
+fn default_window() -> i64 {
+    3600
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChallengeKind {
+    // A provider with a `siteverify` API, like Turnstile, hCaptcha or
+    // reCAPTCHA.
+    Http,
+    // Passes when the answer is the secret, for tests.
+    Stub,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChallengeSettings {
+    pub kind: ChallengeKind,
+    // Where answers are verified, for `http`.
+    pub verify_url: Option<String>,
+    pub secret: String,
+    // The form field the provider's widget puts its answer in, e.g.
+    // `cf-turnstile-response`.
+    #[serde(default = "default_field")]
+    pub field: String,
+    // Markup that shows the provider's widget in forms.
+    pub widget: Option<String>,
+}
+
+fn default_field() -> String {
+    "challenge".to_string()
+}
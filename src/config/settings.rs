@@ -1,8 +1,41 @@
-use super::{AdminSettings, ApplicationSettings, DatabaseSettings};
+use super::{
+    AbTestSettings, ActivitySettings, AddressCheckSettings, AdminSettings, AdminUiSettings,
+    ApplicationSettings, ArchiveSettings, AutomationSettings, BounceSettings, DatabaseSettings,
+    DeliverabilitySettings, FeedSettings, FormSettings, GovernorSettings, InboundSettings,
+    ListSettings, MailerSettings, ModerationSettings, PagesSettings, ProtectionSettings,
+    TrackingSettings, WebhookSettings,
+};
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub admin: AdminSettings,
+    #[serde(default)]
+    pub admin_ui: AdminUiSettings,
+    pub tracking: TrackingSettings,
+    pub inbound: InboundSettings,
+    pub bounce: BounceSettings,
+    pub mailer: MailerSettings,
+    #[serde(default)]
+    pub governor: GovernorSettings,
+    pub list: ListSettings,
+    pub moderation: ModerationSettings,
+    pub automation: AutomationSettings,
+    pub ab_test: AbTestSettings,
+    pub feed: FeedSettings,
+    pub archive: ArchiveSettings,
+    pub pages: PagesSettings,
+    #[serde(default)]
+    pub form: FormSettings,
+    #[serde(default)]
+    pub protection: ProtectionSettings,
+    #[serde(default)]
+    pub address_checks: AddressCheckSettings,
+    // Checks that the domains of new addresses receive mail.
+    pub deliverability: Option<DeliverabilitySettings>,
+    #[serde(default)]
+    pub webhooks: WebhookSettings,
+    #[serde(default)]
+    pub activity: ActivitySettings,
 }
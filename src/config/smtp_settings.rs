@@ -0,0 +1,28 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    // Name we greet clients with.
+    #[serde(default = "default_hostname")]
+    pub hostname: String,
+    // Mail is only accepted for recipients at these domains.
+    pub domains: Vec<String>,
+    // Largest message accepted, in bytes.
+    #[serde(
+        default = "default_size",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub size: usize,
+}
+
+fn default_hostname() -> String {
+    "localhost".to_string()
+}
+
+fn default_size() -> usize {
+    10 * 1024 * 1024
+}
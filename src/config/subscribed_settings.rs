@@ -2,5 +2,15 @@ use serde::Deserialize;
 
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct SubscribedSettings {
+    // Where visitors go after subscribing with `/api/subscribe`.
     pub redirect: Option<String>,
+    // Where visitors go when their signup waits for a moderator, instead of
+    // `redirect`.
+    pub pending: Option<String>,
+    // Where visitors go when the form had problems.
+    pub error: Option<String>,
+    // Origins, like `https://www.example.com`, that visitors may be sent
+    // back to with the form's `redirect` field or the page's `Origin`.
+    #[serde(default)]
+    pub origins: Vec<String>,
 }
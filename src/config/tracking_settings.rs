@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TrackingSettings {
+    // Turning tracking off leaves already sent links redirecting, but stops
+    // rewriting new mail and recording events.
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub url: String,
+    // Has no default, so that links can't be forged with a well-known one.
+    #[serde(default)]
+    pub secret: String,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
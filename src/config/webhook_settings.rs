@@ -0,0 +1,57 @@
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookSettings {
+    // Seconds between looks for webhook calls that are due.
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub interval: u64,
+    // Calls before giving up on an event.
+    #[serde(
+        default = "default_attempts",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub attempts: i32,
+    // Seconds before the first retry, doubling with every further one.
+    #[serde(
+        default = "default_backoff",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub backoff: u64,
+    // Seconds an endpoint has to answer.
+    #[serde(
+        default = "default_timeout",
+        deserialize_with = "deserialize_number_from_string"
+    )]
+    pub timeout: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            attempts: default_attempts(),
+            backoff: default_backoff(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+fn default_attempts() -> i32 {
+    8
+}
+
+fn default_backoff() -> u64 {
+    30
+}
+
+fn default_timeout() -> u64 {
+    10
+}
@@ -1,10 +1,41 @@
 use sqlx::{Pool, Postgres};
 
-use crate::config::{AdminSettings, SubscribedSettings};
+use crate::{
+    activity::ActivityFeed,
+    config::{
+        AbTestSettings, AdminSettings, AdminUiSettings, ArchiveSettings, AutomationSettings,
+        BounceSettings, FeedSettings, FormSettings, InboundSettings, ListSettings,
+        ModerationSettings, PagesSettings, SubscribedSettings, WebhookSettings,
+    },
+    deliverability::DomainCheck,
+    forms::AddressChecks,
+    outbound::{Governor, Mailer},
+    protection::Protection,
+    tracking::Tracker,
+};
 
 #[derive(Clone, Debug)]
 pub struct ApplicationData {
     pub admin: AdminSettings,
+    pub admin_ui: AdminUiSettings,
     pub pool: Pool<Postgres>,
     pub subscribed: SubscribedSettings,
+    pub tracker: Tracker,
+    pub inbound: InboundSettings,
+    pub bounce: BounceSettings,
+    pub mailer: Mailer,
+    pub governor: Governor,
+    pub list: ListSettings,
+    pub moderation: ModerationSettings,
+    pub automation: AutomationSettings,
+    pub ab_test: AbTestSettings,
+    pub feed: FeedSettings,
+    pub archive: ArchiveSettings,
+    pub pages: PagesSettings,
+    pub form: FormSettings,
+    pub protection: Protection,
+    pub address_checks: AddressChecks,
+    pub deliverability: Option<DomainCheck>,
+    pub webhooks: WebhookSettings,
+    pub activity: ActivityFeed,
 }
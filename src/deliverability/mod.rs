@@ -0,0 +1,215 @@
+mod resolver;
+
+pub use resolver::{DnsResolver, MailDomain, MailResolver};
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::warn;
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{DeliverabilityPolicy, DeliverabilitySettings},
+    model::Email,
+};
+
+// Domains cached at most. When full, expired answers are swept out first,
+// then the oldest answers make room.
+const MAX_CACHED: usize = 10_000;
+
+/// Checks that the domains of addresses receive mail, remembering answers.
+#[derive(Clone, Debug)]
+pub struct DomainCheck<R = DnsResolver> {
+    pub policy: DeliverabilityPolicy,
+    resolver: R,
+    timeout: Duration,
+    ttl: Duration,
+    capacity: usize,
+    cache: Arc<Mutex<HashMap<String, (MailDomain, Instant)>>>,
+}
+
+impl DomainCheck {
+    pub fn new(settings: DeliverabilitySettings) -> Result<Self> {
+        let nameservers = settings
+            .nameservers
+            .iter()
+            .map(|nameserver| {
+                nameserver
+                    .parse::<SocketAddr>()
+                    .with_context(|| format!("Invalid name server {nameserver}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        let resolver = DnsResolver::new(&nameservers, timeout)?;
+        Ok(Self::with_resolver(resolver, &settings))
+    }
+}
+
+impl<R: MailResolver> DomainCheck<R> {
+    pub fn with_resolver(resolver: R, settings: &DeliverabilitySettings) -> Self {
+        Self {
+            policy: settings.policy,
+            resolver,
+            timeout: Duration::from_millis(settings.timeout_ms),
+            ttl: Duration::from_secs(settings.cache_seconds),
+            capacity: MAX_CACHED,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Whether the domain of `email` receives mail, or `None` when the
+    /// lookup failed or took too long, which isn't held against anyone.
+    pub async fn receives_mail(&self, email: &Email) -> Option<bool> {
+        let domain = email.domain().to_lowercase();
+        if let Some((answer, at)) = self.cache.lock().await.get(&domain) {
+            if at.elapsed() < self.ttl {
+                return Some(answer.receives_mail());
+            }
+        }
+
+        let answer =
+            match tokio::time::timeout(self.timeout, self.resolver.mail_domain(&domain)).await {
+                Ok(Ok(answer)) => answer,
+                Ok(Err(e)) => {
+                    warn!("Failed to look up mail servers of {domain}: {e}");
+                    return None;
+                }
+                Err(_) => {
+                    warn!("Timed out looking up mail servers of {domain}");
+                    return None;
+                }
+            };
+
+        let mut cache = self.cache.lock().await;
+        if cache.len() >= self.capacity {
+            let ttl = self.ttl;
+            cache.retain(|_, (_, at)| at.elapsed() < ttl);
+        }
+        if cache.len() >= self.capacity {
+            // Frees a quarter at once, so that not every lookup has to.
+            let mut ages: Vec<Instant> = cache.values().map(|(_, at)| *at).collect();
+            ages.sort_unstable();
+            let cutoff = ages[cache.len() - self.capacity * 3 / 4 - 1];
+            cache.retain(|_, (_, at)| *at > cutoff);
+        }
+        cache.insert(domain, (answer, Instant::now()));
+        Some(answer.receives_mail())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Answers from a fixed table, counting lookups.
+    #[derive(Default)]
+    struct FakeResolver {
+        lookups: AtomicUsize,
+    }
+
+    impl MailResolver for FakeResolver {
+        async fn mail_domain(&self, domain: &str) -> Result<MailDomain> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            match domain {
+                "example.org" => Ok(MailDomain::Exchangers),
+                "web.example.org" => Ok(MailDomain::Addresses),
+                "slow.example.org" => {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok(MailDomain::Exchangers)
+                }
+                "broken.example.org" => Err(anyhow::anyhow!("SERVFAIL")),
+                _ => Ok(MailDomain::NoMail),
+            }
+        }
+    }
+
+    fn check(cache_seconds: u64) -> DomainCheck<FakeResolver> {
+        DomainCheck::with_resolver(
+            FakeResolver::default(),
+            &DeliverabilitySettings {
+                policy: DeliverabilityPolicy::Reject,
+                nameservers: Vec::new(),
+                timeout_ms: 100,
+                cache_seconds,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn answers_by_records() {
+        let check = check(60);
+
+        assert_eq!(
+            Some(true),
+            check.receives_mail(&Email::from("ada@example.org")).await
+        );
+        assert_eq!(
+            Some(true),
+            check
+                .receives_mail(&Email::from("ada@web.example.org"))
+                .await
+        );
+        assert_eq!(
+            Some(false),
+            check.receives_mail(&Email::from("ada@gmial.com")).await
+        );
+    }
+
+    #[tokio::test]
+    async fn caches_answers_by_domain() {
+        let cached = check(60);
+        let uncached = check(0);
+
+        for email in ["ada@example.org", "grace@EXAMPLE.org"] {
+            cached.receives_mail(&Email::from(email)).await;
+            uncached.receives_mail(&Email::from(email)).await;
+        }
+
+        assert_eq!(1, cached.resolver.lookups.load(Ordering::SeqCst));
+        assert_eq!(2, uncached.resolver.lookups.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn full_cache_forgets_oldest_answers() {
+        let mut check = check(60);
+        check.capacity = 4;
+
+        for domain in ["a", "b", "c", "d", "e", "a"] {
+            check
+                .receives_mail(&Email(format!("ada@{domain}.example.org")))
+                .await;
+        }
+
+        assert!(check.cache.lock().await.len() <= 4);
+        assert_eq!(6, check.resolver.lookups.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn failures_are_unknown_and_not_cached() {
+        let check = check(60);
+
+        assert_eq!(
+            None,
+            check
+                .receives_mail(&Email::from("ada@slow.example.org"))
+                .await
+        );
+        assert_eq!(
+            None,
+            check
+                .receives_mail(&Email::from("ada@broken.example.org"))
+                .await
+        );
+        check
+            .receives_mail(&Email::from("ada@broken.example.org"))
+            .await;
+        assert_eq!(3, check.resolver.lookups.load(Ordering::SeqCst));
+    }
+}
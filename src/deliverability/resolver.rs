@@ -0,0 +1,93 @@
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::Result;
+use hickory_resolver::{
+    config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts},
+    error::{ResolveError, ResolveErrorKind},
+    TokioAsyncResolver,
+};
+
+/// Whether and how a domain receives mail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailDomain {
+    // It has MX records.
+    Exchangers,
+    // It has no MX records, but A or AAAA records that mail falls back to.
+    Addresses,
+    // It has neither, or a null MX record saying that it takes no mail.
+    NoMail,
+}
+
+impl MailDomain {
+    pub fn receives_mail(&self) -> bool {
+        *self != MailDomain::NoMail
+    }
+}
+
+// Only implemented and awaited within the crate, so its futures needn't be
+// `Send` for others.
+#[allow(async_fn_in_trait)]
+pub trait MailResolver {
+    async fn mail_domain(&self, domain: &str) -> Result<MailDomain>;
+}
+
+/// Looks domains up over DNS.
+#[derive(Clone)]
+pub struct DnsResolver {
+    resolver: TokioAsyncResolver,
+}
+
+impl std::fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DnsResolver").finish_non_exhaustive()
+    }
+}
+
+impl DnsResolver {
+    /// Asks `nameservers`, or the system's when there are none.
+    pub fn new(nameservers: &[SocketAddr], timeout: Duration) -> Result<Self> {
+        let (mut config, mut options) = if nameservers.is_empty() {
+            hickory_resolver::system_conf::read_system_conf()?
+        } else {
+            (ResolverConfig::new(), ResolverOpts::default())
+        };
+        for nameserver in nameservers {
+            config.add_name_server(NameServerConfig::new(*nameserver, Protocol::Udp));
+        }
+        options.timeout = timeout;
+        options.attempts = 1;
+        // Answers are cached by the caller, for as long as it likes.
+        options.cache_size = 0;
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio(config, options),
+        })
+    }
+}
+
+impl MailResolver for DnsResolver {
+    async fn mail_domain(&self, domain: &str) -> Result<MailDomain> {
+        // A trailing dot keeps search domains from being tried.
+        let name = format!("{}.", domain.trim_end_matches('.'));
+        match self.resolver.mx_lookup(name.as_str()).await {
+            Ok(exchangers) => {
+                let null = exchangers.iter().all(|mx| mx.exchange().is_root());
+                Ok(if null {
+                    MailDomain::NoMail
+                } else {
+                    MailDomain::Exchangers
+                })
+            }
+            Err(e) if no_records(&e) => match self.resolver.lookup_ip(name.as_str()).await {
+                Ok(addresses) if addresses.iter().next().is_some() => Ok(MailDomain::Addresses),
+                Ok(_) => Ok(MailDomain::NoMail),
+                Err(e) if no_records(&e) => Ok(MailDomain::NoMail),
+                Err(e) => Err(e.into()),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn no_records(error: &ResolveError) -> bool {
+    matches!(error.kind(), ResolveErrorKind::NoRecordsFound { .. })
+}
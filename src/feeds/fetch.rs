@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::Client;
+use tokio::io::AsyncReadExt;
+
+use crate::{config::FeedSettings, model::FeedItem};
+
+/// Reads the entries of an Atom or RSS feed from an http(s) URL or a file in
+/// `directory`, in the order the feed lists them. Feeds that take longer
+/// than `timeout` or are bigger than `max_size` are given up on.
+pub(crate) async fn fetch(settings: &FeedSettings, source: &str) -> Result<Vec<FeedItem>> {
+    let body = if source.starts_with("http://") || source.starts_with("https://") {
+        download(settings, source).await?
+    } else {
+        read_file(settings, source).await?
+    };
+    parse(&body)
+}
+
+async fn download(settings: &FeedSettings, source: &str) -> Result<Vec<u8>> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(settings.timeout))
+        .build()?;
+    let mut response = client.get(source).send().await?.error_for_status()?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > settings.max_size as u64)
+    {
+        bail!("The feed is bigger than {} bytes", settings.max_size);
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > settings.max_size {
+            bail!("The feed is bigger than {} bytes", settings.max_size);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Reads a feed file, as long as it is inside `directory` once symbolic
+/// links and `..` are resolved.
+async fn read_file(settings: &FeedSettings, source: &str) -> Result<Vec<u8>> {
+    let Some(directory) = &settings.directory else {
+        bail!("Feeds are read from http(s) URLs only, unless feed.directory is set");
+    };
+    let directory = tokio::fs::canonicalize(directory).await?;
+    let path = tokio::fs::canonicalize(directory.join(source)).await?;
+    if !path.starts_with(&directory) {
+        bail!("Feed files are read from {} only", directory.display());
+    }
+
+    let mut body = Vec::new();
+    tokio::fs::File::open(&path)
+        .await?
+        .take(settings.max_size as u64 + 1)
+        .read_to_end(&mut body)
+        .await?;
+    if body.len() > settings.max_size {
+        bail!("The feed is bigger than {} bytes", settings.max_size);
+    }
+    Ok(body)
+}
+
+pub(crate) fn parse(body: &[u8]) -> Result<Vec<FeedItem>> {
+    let feed = feed_rs::parser::parse(body).map_err(|e| anyhow!("Not a feed: {e}"))?;
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| FeedItem {
+            title: entry.title.map(|title| title.content).unwrap_or_default(),
+            link: entry
+                .links
+                .into_iter()
+                .next()
+                .map(|link| link.href)
+                .unwrap_or_default(),
+            summary: entry
+                .summary
+                .map(|summary| summary.content)
+                .unwrap_or_default(),
+            published: entry.published.or(entry.updated),
+            id: entry.id,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn parse_reads_atom_entries() {
+        let atom = r#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Blog</title>
+              <id>urn:blog</id>
+              <updated>2023-05-02T10:00:00Z</updated>
+              <entry>
+                <title>Second post</title>
+                <id>urn:blog:2</id>
+                <link href="https://example.com/2"/>
+                <updated>2023-05-02T10:00:00Z</updated>
+                <summary>More news</summary>
+              </entry>
+              <entry>
+                <title>First post</title>
+                <id>urn:blog:1</id>
+                <link href="https://example.com/1"/>
+                <published>2023-05-01T10:00:00Z</published>
+                <updated>2023-05-01T12:00:00Z</updated>
+              </entry>
+            </feed>"#;
+
+        let items = parse(atom.as_bytes()).unwrap();
+
+        assert_eq!(
+            vec![
+                FeedItem {
+                    id: "urn:blog:2".to_string(),
+                    title: "Second post".to_string(),
+                    link: "https://example.com/2".to_string(),
+                    summary: "More news".to_string(),
+                    published: Some(Utc.with_ymd_and_hms(2023, 5, 2, 10, 0, 0).unwrap()),
+                },
+                FeedItem {
+                    id: "urn:blog:1".to_string(),
+                    title: "First post".to_string(),
+                    link: "https://example.com/1".to_string(),
+                    summary: String::new(),
+                    published: Some(Utc.with_ymd_and_hms(2023, 5, 1, 10, 0, 0).unwrap()),
+                },
+            ],
+            items
+        );
+    }
+
+    #[test]
+    fn parse_reads_rss_items() {
+        let rss = r#"<?xml version="1.0"?>
+            <rss version="2.0"><channel>
+              <title>Blog</title>
+              <item>
+                <title>Hello</title>
+                <link>https://example.com/hello</link>
+                <guid>hello</guid>
+                <description>Hi there</description>
+              </item>
+            </channel></rss>"#;
+
+        let items = parse(rss.as_bytes()).unwrap();
+
+        assert_eq!(1, items.len());
+        assert_eq!("hello", items[0].id);
+        assert_eq!("https://example.com/hello", items[0].link);
+        assert_eq!("Hi there", items[0].summary);
+    }
+
+    #[test]
+    fn parse_rejects_other_documents() {
+        assert!(parse(b"<html><body>Not found</body></html>").is_err());
+    }
+}
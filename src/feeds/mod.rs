@@ -0,0 +1,147 @@
+mod fetch;
+mod worker;
+
+pub(crate) use fetch::fetch;
+pub(crate) use worker::run_feeds;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use chrono_tz::Tz;
+use log::info;
+use serde::Serialize;
+use serde_json::{json, Map};
+
+use crate::{
+    archive,
+    data::ApplicationData,
+    model::{next_run, Feed, FeedItem, NewCampaign, NewDelivery, NewFeed, SubscriberStatus},
+    outbound::{campaign_delivery, unsuppressed},
+    store::{
+        CampaignStore, FeedStore, PsqlCampaignStore, PsqlFeedStore, PsqlSubscriberStore,
+        PsqlTemplateStore, SubscriberStore, TemplateStore,
+    },
+    warmup,
+};
+
+/// What a look at a feed came up with.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedRun {
+    pub feed_id: i32,
+    // New entries, which all went out in one digest.
+    pub items: usize,
+    // The digest's campaign, when there was something new.
+    pub campaign_id: Option<i32>,
+    pub deliveries: usize,
+}
+
+impl FeedRun {
+    fn empty(feed_id: i32) -> Self {
+        Self {
+            feed_id,
+            items: 0,
+            campaign_id: None,
+            deliveries: 0,
+        }
+    }
+}
+
+/// The list's timezone, which feed schedules run in.
+pub(crate) fn timezone(data: &ApplicationData) -> Result<Tz> {
+    data.list
+        .timezone
+        .parse()
+        .map_err(|e| anyhow!("Bad list timezone: {e}"))
+}
+
+/// Stores a feed whose `items` were just read from it. Unless the feed asks
+/// to send them, these entries are taken as sent already.
+pub(crate) async fn create(
+    data: &ApplicationData,
+    new_feed: NewFeed,
+    items: &[FeedItem],
+) -> Result<Feed> {
+    let next_run_at =
+        next_run(&new_feed.schedule, &timezone(data)?, Utc::now()).map_err(|e| anyhow!(e))?;
+    let send_existing = new_feed.send_existing;
+    let mut store = PsqlFeedStore::from(data.pool.clone());
+    let feed = store.create(new_feed, next_run_at).await?;
+    if !send_existing {
+        let ids: Vec<_> = items.iter().map(|item| item.id.clone()).collect();
+        store.mark_sent(feed.id, &ids, None).await?;
+    }
+    info!("Added feed {} from {}", feed.id, feed.source);
+    Ok(feed)
+}
+
+/// Mails a digest of the entries that were not sent yet to every active
+/// subscriber, and nothing when there are none.
+pub(crate) async fn run(data: &ApplicationData, feed: &Feed) -> Result<FeedRun> {
+    let mut store = PsqlFeedStore::from(data.pool.clone());
+    let items = fetch(&data.feed, &feed.source).await?;
+    let ids: Vec<_> = items.iter().map(|item| item.id.clone()).collect();
+    let unsent = store.unsent(feed.id, &ids).await?;
+    let items: Vec<_> = items
+        .into_iter()
+        .filter(|item| unsent.contains(&item.id))
+        .collect();
+    if items.is_empty() {
+        info!("Nothing new in feed {}", feed.id);
+        return Ok(FeedRun::empty(feed.id));
+    }
+
+    let template = PsqlTemplateStore::from(data.pool.clone())
+        .get(feed.template_id)
+        .await?
+        .ok_or_else(|| anyhow!("Template {} disappeared", feed.template_id))?;
+    let now = Utc::now();
+    let ids: Vec<_> = items.iter().map(|item| item.id.clone()).collect();
+    let name = format!("{} {}", feed.name, now.format("%Y-%m-%d"));
+    // Another run that got to the entries first sends them instead.
+    let Some((campaign, claimed)) = store.claim(feed.id, &ids, NewCampaign { name }).await? else {
+        info!("Entries of feed {} are sent by another run", feed.id);
+        return Ok(FeedRun::empty(feed.id));
+    };
+    let items: Vec<_> = items
+        .into_iter()
+        .filter(|item| claimed.contains(&item.id))
+        .collect();
+
+    let variables = Map::from_iter([
+        ("feed".to_string(), json!(feed.name)),
+        ("count".to_string(), json!(items.len())),
+        ("items".to_string(), serde_json::to_value(&items)?),
+    ]);
+    let subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let everyone = unsuppressed(data, subscribers.all().await?).await?;
+    let engagement = warmup::engagement(data).await?;
+    let mut deliveries = Vec::new();
+    for subscriber in everyone {
+        if subscriber.status != SubscriberStatus::Active {
+            continue;
+        }
+        let mut attributes = subscribers.attributes(subscriber.id).await?;
+        attributes.extend(variables.clone());
+        let delivery = NewDelivery {
+            engagement: engagement.of(&subscriber),
+            ..campaign_delivery(data, &template, &subscriber, &attributes, campaign.id)?
+        };
+        deliveries.push((delivery, now));
+    }
+    PsqlCampaignStore::from(data.pool.clone())
+        .schedule(campaign.id, template.id, now, &deliveries)
+        .await?;
+    archive::save_issue(data, campaign.id, &template, &variables).await?;
+    let deliveries = deliveries.len();
+
+    info!(
+        "Sent {} new entries of feed {} to {deliveries} subscribers",
+        items.len(),
+        feed.id
+    );
+    Ok(FeedRun {
+        feed_id: feed.id,
+        items: items.len(),
+        campaign_id: Some(campaign.id),
+        deliveries,
+    })
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::error;
+
+use crate::{
+    data::ApplicationData,
+    feeds::{run, timezone},
+    model::next_run,
+    store::{FeedStore, PsqlFeedStore},
+};
+
+/// Looks at every feed whose schedule fired, forever.
+pub(crate) async fn run_feeds(data: ApplicationData) {
+    let mut interval = tokio::time::interval(Duration::from_secs(data.feed.interval));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_due(&data).await {
+            error!("Failed to run feeds: {e}");
+        }
+    }
+}
+
+async fn run_due(data: &ApplicationData) -> Result<()> {
+    let mut store = PsqlFeedStore::from(data.pool.clone());
+    let timezone = timezone(data)?;
+
+    for feed in store.due().await? {
+        // Moves on to the next run first, so that a feed that cannot be read
+        // is tried again on schedule rather than on every tick. Its entries
+        // stay unsent until then.
+        let next_run_at =
+            next_run(&feed.schedule, &timezone, Utc::now()).map_err(|e| anyhow!(e))?;
+        store.reschedule(feed.id, next_run_at).await?;
+        if let Err(e) = run(data, &feed).await {
+            error!("Failed to run feed {}: {e}", feed.id);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::{AddressCheckSettings, CheckPolicy},
+    model::Email,
+};
+
+const DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+
+/// Something about an address that suggests it won't reach a person who
+/// reads the list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressProblem {
+    Disposable,
+    Role,
+    // The address with the domain it probably meant.
+    Typo(Email),
+    // The domain has no mail servers.
+    Undeliverable,
+}
+
+impl AddressProblem {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddressProblem::Disposable => "disposable",
+            AddressProblem::Role => "role",
+            AddressProblem::Typo(_) => "typo",
+            AddressProblem::Undeliverable => "undeliverable",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            AddressProblem::Disposable => "Use an address that doesn't expire.".to_string(),
+            AddressProblem::Role => "Use a personal address instead of a shared one.".to_string(),
+            AddressProblem::Typo(suggestion) => format!("Did you mean {}?", suggestion.0),
+            AddressProblem::Undeliverable => {
+                "This address doesn't seem to receive mail.".to_string()
+            }
+        }
+    }
+
+    pub fn suggestion(&self) -> Option<&Email> {
+        match self {
+            AddressProblem::Typo(suggestion) => Some(suggestion),
+            _ => None,
+        }
+    }
+}
+
+/// The problems of an address, split by what the settings do about them.
+#[derive(Debug, Default, PartialEq)]
+pub struct Checked {
+    pub rejected: Vec<AddressProblem>,
+    pub flagged: Vec<AddressProblem>,
+}
+
+/// Looks for disposable domains, role accounts and mistyped domains.
+#[derive(Clone, Debug)]
+pub struct AddressChecks {
+    settings: AddressCheckSettings,
+    disposable: Arc<HashSet<String>>,
+}
+
+impl AddressChecks {
+    /// Reads the bundled disposable domains and the configured file.
+    pub fn new(settings: AddressCheckSettings) -> Result<Self> {
+        let mut disposable = domains(DISPOSABLE_DOMAINS);
+        if let Some(path) = &settings.disposable_domains {
+            let extra = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read disposable domains from {path}"))?;
+            disposable.extend(domains(&extra));
+        }
+        for allowed in &settings.allowed_domains {
+            disposable.remove(&allowed.to_lowercase());
+        }
+        Ok(Self {
+            settings,
+            disposable: Arc::new(disposable),
+        })
+    }
+
+    /// Whether the address is at a disposable domain or one of its
+    /// subdomains.
+    pub fn is_disposable(&self, email: &Email) -> bool {
+        let domain = email.domain().to_lowercase();
+        let mut candidate = domain.as_str();
+        loop {
+            if self.disposable.contains(candidate) {
+                return true;
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => candidate = parent,
+                _ => return false,
+            }
+        }
+    }
+
+    // Whether the address is at a domain that the settings vouch for.
+    fn is_allowed(&self, email: &Email) -> bool {
+        let domain = email.domain();
+        self.settings
+            .allowed_domains
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+    }
+
+    pub fn check(&self, email: &Email) -> Checked {
+        let mut checked = Checked::default();
+        let mut sort = |policy: CheckPolicy, problem: AddressProblem| match policy {
+            CheckPolicy::Reject => checked.rejected.push(problem),
+            CheckPolicy::Flag => checked.flagged.push(problem),
+            CheckPolicy::Allow => {}
+        };
+        if self.settings.disposable != CheckPolicy::Allow && self.is_disposable(email) {
+            sort(self.settings.disposable, AddressProblem::Disposable);
+        }
+        if email.is_role_account() {
+            sort(self.settings.role, AddressProblem::Role);
+        }
+        if self.settings.typo != CheckPolicy::Allow && !self.is_allowed(email) {
+            if let Some(suggestion) = email.suggestion() {
+                sort(self.settings.typo, AddressProblem::Typo(suggestion));
+            }
+        }
+        checked
+    }
+}
+
+impl Default for AddressChecks {
+    fn default() -> Self {
+        Self::new(AddressCheckSettings::default()).expect("The bundled list needs no file")
+    }
+}
+
+fn domains(list: &str) -> HashSet<String> {
+    list.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_list_catches_subdomains() {
+        let checks = AddressChecks::default();
+
+        assert!(checks.is_disposable(&Email::from("ada@mailinator.com")));
+        assert!(checks.is_disposable(&Email::from("ada@Eu.MAILINATOR.com")));
+        assert!(!checks.is_disposable(&Email::from("ada@example.org")));
+        assert!(!checks.is_disposable(&Email::from("ada@notmailinator.com")));
+    }
+
+    #[test]
+    fn list_is_extended_by_file_and_allowed_domains() {
+        let path = std::env::temp_dir().join(format!(
+            "minimail-disposable-domains-{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "# ours\nthrowaway.example\n").unwrap();
+
+        let checks = AddressChecks::new(AddressCheckSettings {
+            disposable_domains: Some(path.to_string_lossy().to_string()),
+            allowed_domains: vec!["Yopmail.com".to_string()],
+            ..AddressCheckSettings::default()
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(checks.is_disposable(&Email::from("ada@throwaway.example")));
+        assert!(checks.is_disposable(&Email::from("ada@mailinator.com")));
+        assert!(!checks.is_disposable(&Email::from("ada@yopmail.com")));
+    }
+
+    #[test]
+    fn allowed_domains_are_not_typos() {
+        let checks = AddressChecks::new(AddressCheckSettings {
+            typo: CheckPolicy::Flag,
+            allowed_domains: vec!["Gmail.co".to_string()],
+            ..AddressCheckSettings::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            Checked::default(),
+            checks.check(&Email::from("ada@gmail.co"))
+        );
+        assert_eq!(1, checks.check(&Email::from("ada@gmial.com")).flagged.len());
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let checks = AddressChecks::new(AddressCheckSettings {
+            disposable_domains: Some("/nonexistent/domains.txt".to_string()),
+            ..AddressCheckSettings::default()
+        });
+
+        assert!(checks.is_err());
+    }
+
+    #[test]
+    fn check_sorts_problems_by_policy() {
+        let checks = AddressChecks::default();
+
+        assert_eq!(
+            Checked {
+                rejected: vec![AddressProblem::Disposable],
+                flagged: vec![AddressProblem::Role],
+            },
+            checks.check(&Email::from("info@mailinator.com"))
+        );
+        assert_eq!(
+            Checked {
+                rejected: Vec::new(),
+                flagged: vec![AddressProblem::Typo(Email::from("ada@gmail.com"))],
+            },
+            checks.check(&Email::from("ada@gmial.com"))
+        );
+        assert_eq!(
+            Checked::default(),
+            checks.check(&Email::from("ada@example.org"))
+        );
+    }
+
+    #[test]
+    fn allowed_checks_are_skipped() {
+        let checks = AddressChecks::new(AddressCheckSettings {
+            disposable: CheckPolicy::Allow,
+            role: CheckPolicy::Reject,
+            typo: CheckPolicy::Allow,
+            ..AddressCheckSettings::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            vec![AddressProblem::Role],
+            checks.check(&Email::from("admin@mailinator.com")).rejected
+        );
+        assert_eq!(
+            Checked::default(),
+            checks.check(&Email::from("ada@gmial.com"))
+        );
+    }
+}
@@ -0,0 +1,334 @@
+mod checks;
+mod redirect;
+mod snippet;
+
+pub use checks::{AddressChecks, AddressProblem};
+pub use redirect::{redirect_target, Outcome};
+pub use snippet::snippet;
+
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{DeliverabilityPolicy, FormSettings},
+    data::ApplicationData,
+    model::{Email, Subscriber},
+    moderation::signup,
+    store::{PsqlSubscriberStore, PsqlSuppressionStore, SubscriberStore, SuppressionStore},
+};
+
+/// A field the form hides from people, so that whoever fills it in is a bot.
+pub const HONEYPOT: &str = "website";
+const MAX_FIELD_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+    // A corrected value to offer, like an address with a mistyped domain
+    // fixed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+            suggestion: None,
+        }
+    }
+
+    fn address(problem: &AddressProblem) -> Self {
+        Self {
+            suggestion: problem.suggestion().map(|email| email.0.clone()),
+            ..Self::new("email", &problem.message())
+        }
+    }
+}
+
+/// A signup form as filled in, with the configured fields as attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submission {
+    pub email: Email,
+    pub timezone: Option<String>,
+    pub attributes: Map<String, Value>,
+    // Problems of the address that are let through, but noted.
+    pub flags: Vec<AddressProblem>,
+    // Holds the signup for a moderator.
+    pub hold: bool,
+}
+
+impl Submission {
+    /// Checks the fields of a form, collecting every problem so that they
+    /// can be shown at once. Fields that are not configured are ignored.
+    pub fn parse(
+        settings: &FormSettings,
+        checks: &AddressChecks,
+        fields: &Map<String, Value>,
+    ) -> Result<Self, Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let mut flags = Vec::new();
+
+        let email = text(fields, "email").unwrap_or_default();
+        if email.is_empty() {
+            errors.push(FieldError::new("email", "Enter your email address."));
+        } else if email.parse::<lettre::Address>().is_err() {
+            errors.push(FieldError::new("email", "Enter a valid email address."));
+        } else {
+            let checked = checks.check(&Email::from(email.as_str()));
+            errors.extend(checked.rejected.iter().map(FieldError::address));
+            flags = checked.flagged;
+        }
+
+        if settings.consent.is_some() && !consented(fields.get("consent")) {
+            errors.push(FieldError::new("consent", "Tick the box to subscribe."));
+        }
+
+        let mut attributes = Map::new();
+        for name in &settings.fields {
+            match text(fields, name) {
+                Some(value) if value.len() > MAX_FIELD_LENGTH => {
+                    errors.push(FieldError::new(name, "This is too long."));
+                }
+                Some(value) if !value.is_empty() => {
+                    attributes.insert(name.clone(), Value::String(value));
+                }
+                _ => {}
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Self {
+            email: Email(email),
+            timezone: text(fields, "timezone")
+                .filter(|timezone| timezone.parse::<chrono_tz::Tz>().is_ok()),
+            attributes,
+            flags,
+            hold: false,
+        })
+    }
+
+    /// Checks that the domain of the address receives mail, when that is
+    /// configured, and applies the policy for domains that don't.
+    pub(crate) async fn check_deliverable(
+        &mut self,
+        data: &ApplicationData,
+    ) -> Result<(), Vec<FieldError>> {
+        let Some(check) = &data.deliverability else {
+            return Ok(());
+        };
+        if check.receives_mail(&self.email).await != Some(false) {
+            return Ok(());
+        }
+        match check.policy {
+            DeliverabilityPolicy::Reject => {
+                return Err(vec![FieldError::address(&AddressProblem::Undeliverable)]);
+            }
+            DeliverabilityPolicy::Pending => self.hold = true,
+            DeliverabilityPolicy::Accept => {}
+        }
+        self.flags.push(AddressProblem::Undeliverable);
+        Ok(())
+    }
+
+    /// What to tell the visitor about the flagged problems of the address.
+    pub fn warnings(&self) -> Vec<FieldError> {
+        self.flags.iter().map(FieldError::address).collect()
+    }
+}
+
+/// Whether a bot filled in the form.
+pub fn is_bot(fields: &Map<String, Value>) -> bool {
+    text(fields, HONEYPOT).is_some_and(|value| !value.is_empty())
+}
+
+/// Parses a signup form and checks its address, including the lookups
+/// that `Submission::parse` can't make.
+pub(crate) async fn validate(
+    data: &ApplicationData,
+    fields: &Map<String, Value>,
+) -> Result<Submission, Vec<FieldError>> {
+    let mut submission = Submission::parse(&data.form, &data.address_checks, fields)?;
+    submission.check_deliverable(data).await?;
+    Ok(submission)
+}
+
+/// Subscribes the address of a form, unless it is suppressed. The attributes
+/// of existing subscribers are left as they are, since anyone can fill in
+/// the form with their address.
+pub(crate) async fn subscribe(
+    data: &ApplicationData,
+    submission: Submission,
+) -> Result<Option<Subscriber>> {
+    let suppressions = PsqlSuppressionStore::from(data.pool.clone());
+    if suppressions.contains(&submission.email).await? {
+        info!("Not subscribing suppressed address: {:?}", submission.email);
+        return Ok(None);
+    }
+
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let existing = subscribers.find(&submission.email).await?;
+    let subscriber = signup(data, submission.email, submission.hold).await?;
+    if subscriber.timezone.is_none() && submission.timezone.is_some() {
+        subscribers
+            .set_timezone(&subscriber.email, submission.timezone.as_deref())
+            .await?;
+    }
+    if existing.is_none() && !submission.attributes.is_empty() {
+        subscribers
+            .set_attributes(subscriber.id, submission.attributes)
+            .await?;
+    }
+    flag(data, &subscriber, &submission.flags).await?;
+    Ok(Some(subscriber))
+}
+
+/// Tags a subscriber with the problems of their address, like
+/// `flagged:role`, so that they can be looked at or left out of sends.
+pub(crate) async fn flag(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    flags: &[AddressProblem],
+) -> Result<()> {
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    for problem in flags {
+        info!("Flagging {:?} as {}", subscriber.email, problem.as_str());
+        subscribers
+            .add_tag(subscriber.id, &format!("flagged:{}", problem.as_str()))
+            .await?;
+    }
+    Ok(())
+}
+
+pub(crate) fn text(fields: &Map<String, Value>, name: &str) -> Option<String> {
+    match fields.get(name)? {
+        Value::String(value) => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn consented(value: Option<&Value>) -> bool {
+    match value {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => ["true", "on", "yes", "1"].contains(&value.as_str()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn settings(consent: bool) -> FormSettings {
+        FormSettings {
+            fields: vec!["name".to_string()],
+            consent: consent.then(|| "I agree".to_string()),
+        }
+    }
+
+    fn fields(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(fields) => fields,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn parse_keeps_configured_fields() {
+        let submission = Submission::parse(
+            &settings(false),
+            &AddressChecks::default(),
+            &fields(json!({
+                "email": " ada@example.org ",
+                "name": "Ada",
+                "role": "admin",
+                "timezone": "Europe/Berlin"
+            })),
+        )
+        .unwrap();
+
+        assert_eq!(Email::from("ada@example.org"), submission.email);
+        assert_eq!(Some("Europe/Berlin".to_string()), submission.timezone);
+        assert_eq!(fields(json!({"name": "Ada"})), submission.attributes);
+    }
+
+    #[test]
+    fn parse_drops_unknown_timezone() {
+        let submission = Submission::parse(
+            &settings(false),
+            &AddressChecks::default(),
+            &fields(json!({"email": "ada@example.org", "timezone": "Mars/Olympus"})),
+        )
+        .unwrap();
+
+        assert_eq!(None, submission.timezone);
+    }
+
+    #[test]
+    fn parse_reports_every_error() {
+        let errors = Submission::parse(
+            &settings(true),
+            &AddressChecks::default(),
+            &fields(json!({"email": "ada", "name": "a".repeat(501)})),
+        )
+        .unwrap_err();
+
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(vec!["email", "consent", "name"], fields);
+    }
+
+    #[test]
+    fn parse_accepts_checkbox_and_json_consent() {
+        for consent in [json!("on"), json!("true"), json!(true)] {
+            let fields = fields(json!({"email": "ada@example.org", "consent": consent}));
+
+            assert!(Submission::parse(&settings(true), &AddressChecks::default(), &fields).is_ok());
+        }
+    }
+
+    #[test]
+    fn parse_rejects_and_flags_addresses() {
+        let parse = |email: &str| {
+            Submission::parse(
+                &settings(false),
+                &AddressChecks::default(),
+                &fields(json!({ "email": email })),
+            )
+        };
+
+        let disposable = parse("ada@mailinator.com").unwrap_err();
+        let typo = parse("ada@gmial.com").unwrap();
+
+        assert_eq!(
+            vec![FieldError::new(
+                "email",
+                "Use an address that doesn't expire."
+            )],
+            disposable
+        );
+        assert_eq!(
+            vec![FieldError {
+                suggestion: Some("ada@gmail.com".to_string()),
+                ..FieldError::new("email", "Did you mean ada@gmail.com?")
+            }],
+            typo.warnings()
+        );
+    }
+
+    #[test]
+    fn recognizes_bots_by_honeypot() {
+        assert!(is_bot(&fields(json!({"website": "https://spam.example"}))));
+        assert!(!is_bot(&fields(json!({"website": ""}))));
+        assert!(!is_bot(&fields(json!({"email": "ada@example.org"}))));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::config::SubscribedSettings;
+
+/// How a signup form turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Subscribed,
+    Pending,
+    Invalid,
+}
+
+/// Where to send a visitor after a signup form. Configured addresses are
+/// trusted, while the form's `redirect` field and the page's `Origin` are
+/// only followed to allowed origins, so that nobody can use the form to
+/// send visitors elsewhere. Errors go back to the form rather than on to
+/// `redirect`. `None` when there is nowhere safe to go.
+pub fn redirect_target(
+    settings: &SubscribedSettings,
+    own_url: &str,
+    outcome: Outcome,
+    field: Option<&str>,
+    origin: Option<&str>,
+) -> Option<String> {
+    let allowed = |url: &&str| {
+        origin_of(url).is_some_and(|target| {
+            origin_of(own_url).as_ref() == Some(&target)
+                || settings
+                    .origins
+                    .iter()
+                    .any(|allowed| origin_of(allowed).as_ref() == Some(&target))
+        })
+    };
+    let field = field.filter(allowed).map(str::to_string);
+    let origin = origin.filter(allowed).map(str::to_string);
+
+    match outcome {
+        Outcome::Invalid => settings.error.clone().or(origin),
+        Outcome::Pending => settings
+            .pending
+            .clone()
+            .or(field)
+            .or_else(|| settings.redirect.clone())
+            .or(origin),
+        Outcome::Subscribed => field.or_else(|| settings.redirect.clone()).or(origin),
+    }
+}
+
+/// The `scheme://host:port` of an http(s) address, lowercased. Addresses
+/// with credentials or backslashes, which browsers read differently than
+/// one might expect, have none.
+fn origin_of(url: &str) -> Option<String> {
+    let url = url.trim();
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next()?;
+    if authority.is_empty() || authority.contains(['@', '\\']) {
+        return None;
+    }
+    Some(format!("{scheme}://{}", authority.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN: &str = "https://news.example.com";
+
+    fn settings() -> SubscribedSettings {
+        SubscribedSettings {
+            origins: vec!["https://www.example.com".to_string()],
+            ..SubscribedSettings::default()
+        }
+    }
+
+    #[test]
+    fn origin_of_reads_scheme_and_authority() {
+        assert_eq!(
+            Some("https://www.example.com:8443".to_string()),
+            origin_of("HTTPS://WWW.example.com:8443/thanks?x=1")
+        );
+        assert_eq!(None, origin_of("javascript:alert(1)"));
+        assert_eq!(None, origin_of("//evil.example/"));
+        assert_eq!(None, origin_of("https://www.example.com@evil.example/"));
+        assert_eq!(None, origin_of("https://evil.example\\@www.example.com/"));
+    }
+
+    #[test]
+    fn follows_allowed_field_and_origin() {
+        let target =
+            |field, origin| redirect_target(&settings(), OWN, Outcome::Subscribed, field, origin);
+
+        assert_eq!(
+            Some("https://www.example.com/thanks".to_string()),
+            target(
+                Some("https://www.example.com/thanks"),
+                Some("https://www.example.com")
+            )
+        );
+        assert_eq!(
+            Some("https://www.example.com".to_string()),
+            target(
+                Some("https://evil.example/"),
+                Some("https://www.example.com")
+            )
+        );
+        assert_eq!(
+            Some("https://news.example.com/subscribe".to_string()),
+            target(Some("https://news.example.com/subscribe"), None)
+        );
+        assert_eq!(None, target(None, Some("https://evil.example")));
+    }
+
+    #[test]
+    fn configured_redirects_depend_on_outcome() {
+        let settings = SubscribedSettings {
+            redirect: Some("https://www.example.com/thanks".to_string()),
+            pending: Some("https://www.example.com/pending".to_string()),
+            error: None,
+            ..settings()
+        };
+        let target = |outcome| {
+            redirect_target(
+                &settings,
+                OWN,
+                outcome,
+                None,
+                Some("https://www.example.com"),
+            )
+        };
+
+        assert_eq!(
+            Some("https://www.example.com/thanks".to_string()),
+            target(Outcome::Subscribed)
+        );
+        assert_eq!(
+            Some("https://www.example.com/pending".to_string()),
+            target(Outcome::Pending)
+        );
+        assert_eq!(
+            Some("https://www.example.com".to_string()),
+            target(Outcome::Invalid)
+        );
+    }
+}
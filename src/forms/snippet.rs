@@ -0,0 +1,202 @@
+use serde_json::json;
+
+use crate::{
+    config::{FormSettings, ProtectionSettings},
+    forms::HONEYPOT,
+    pages::escape,
+    protection::STAMP,
+};
+
+// Fills in the timezone and the form stamp and, for `ajax`, sends the form with `fetch` and
+// shows the outcome instead of leaving the page. The `CONFIG` placeholder becomes a JSON object.
+const CONFIG: &str = "/*MINIMAIL_CONFIG*/";
+const SCRIPT: &str = r#"<script>
+(function () {
+  var config = /*MINIMAIL_CONFIG*/;
+  var form = document.currentScript.previousElementSibling;
+  form.elements.timezone.value = Intl.DateTimeFormat().resolvedOptions().timeZone;
+  var stamp = form.elements.started;
+  if (stamp) {
+    fetch(config.stamp)
+      .then(function (response) { return response.json(); })
+      .then(function (result) { stamp.value = result.stamp; });
+  }
+  if (!config.ajax) return;
+  var message = form.querySelector(".minimail-message");
+  form.addEventListener("submit", function (event) {
+    event.preventDefault();
+    var fields = {};
+    new FormData(form).forEach(function (value, name) { fields[name] = value; });
+    fetch(config.subscriptions, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(fields)
+    })
+      .then(function (response) { return response.json(); })
+      .then(function (result) {
+        message.textContent = result.errors
+          ? result.errors.map(function (error) { return error.message; }).join(" ")
+          : result.status === "pending"
+          ? "Thanks! Your subscription waits for approval."
+          : "Thanks for subscribing!";
+      })
+      .catch(function () { message.textContent = "Something went wrong, please try again."; });
+  });
+})();
+</script>"#;
+
+/// A signup form to paste into any page. It posts to `/api/subscribe` and
+/// works without JavaScript. With `ajax`, it posts to `/api/subscriptions`
+/// instead and stays on the page, which needs the page's origin allowed.
+pub fn snippet(
+    settings: &FormSettings,
+    protection: &ProtectionSettings,
+    url: &str,
+    list: &str,
+    ajax: bool,
+) -> String {
+    let url = url.trim_end_matches('/');
+    let mut form = format!(
+        "<form class=\"minimail-form\" method=\"post\" action=\"{}/api/subscribe\">\n\
+         <p><label>Email<br><input type=\"email\" name=\"email\" required></label></p>\n",
+        escape(url)
+    );
+    for field in &settings.fields {
+        form.push_str(&format!(
+            "<p><label>{}<br><input type=\"text\" name=\"{}\"></label></p>\n",
+            escape(&label(field)),
+            escape(field)
+        ));
+    }
+    if let Some(consent) = &settings.consent {
+        form.push_str(&format!(
+            "<p><label><input type=\"checkbox\" name=\"consent\" value=\"true\" required> {}</label></p>\n",
+            escape(consent)
+        ));
+    }
+    form.push_str(&format!(
+        "<div style=\"position: absolute; left: -10000px;\" aria-hidden=\"true\">\
+         <input type=\"text\" name=\"{HONEYPOT}\" tabindex=\"-1\" autocomplete=\"off\"></div>\n\
+         <input type=\"hidden\" name=\"timezone\">\n"
+    ));
+    // The stamp is fetched when the page loads, since a pasted snippet
+    // would carry the time it was made.
+    if protection.min_fill_seconds > 0 {
+        form.push_str(&format!("<input type=\"hidden\" name=\"{STAMP}\">\n"));
+    }
+    if let Some(widget) = protection
+        .challenge
+        .as_ref()
+        .and_then(|challenge| challenge.widget.as_ref())
+    {
+        form.push_str(widget);
+        form.push('\n');
+    }
+    form.push_str(&format!(
+        "<p><button type=\"submit\">Subscribe to {}</button></p>\n",
+        escape(list)
+    ));
+    if ajax {
+        form.push_str("<p class=\"minimail-message\" role=\"status\"></p>\n");
+    }
+    form.push_str("</form>\n");
+
+    // Escaping `<` keeps a `</script>` in the URL from ending the script.
+    let config = json!({
+        "ajax": ajax,
+        "stamp": format!("{url}/api/forms/stamp"),
+        "subscriptions": format!("{url}/api/subscriptions"),
+    })
+    .to_string()
+    .replace('<', "\\u003c");
+    form.push_str(&SCRIPT.replace(CONFIG, &config));
+    form.push('\n');
+    form
+}
+
+/// `first_name` reads as `First name`.
+fn label(field: &str) -> String {
+    let label = field.replace(['_', '-'], " ");
+    let mut chars = label.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => label,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ChallengeKind, ChallengeSettings};
+
+    fn settings() -> FormSettings {
+        FormSettings {
+            fields: vec!["first_name".to_string()],
+            consent: Some("I agree to the <privacy policy>".to_string()),
+        }
+    }
+
+    #[test]
+    fn snippet_has_fields_honeypot_and_consent() {
+        let snippet = snippet(
+            &settings(),
+            &ProtectionSettings::default(),
+            "https://news.example.com/",
+            "News",
+            false,
+        );
+
+        assert!(snippet.contains("action=\"https://news.example.com/api/subscribe\""));
+        assert!(snippet.contains("First name<br><input type=\"text\" name=\"first_name\">"));
+        assert!(snippet.contains("required> I agree to the &lt;privacy policy&gt;</label>"));
+        assert!(snippet.contains("name=\"website\" tabindex=\"-1\""));
+        assert!(snippet.contains("\"ajax\":false"));
+        assert!(!snippet.contains("role=\"status\""));
+        assert!(!snippet.contains("name=\"started\""));
+    }
+
+    #[test]
+    fn snippet_has_stamp_and_challenge_widget() {
+        let protection = ProtectionSettings {
+            min_fill_seconds: 3,
+            challenge: Some(ChallengeSettings {
+                kind: ChallengeKind::Http,
+                verify_url: Some("https://challenges.example/siteverify".to_string()),
+                secret: "secret".to_string(),
+                field: "challenge".to_string(),
+                widget: Some("<div class=\"captcha\"></div>".to_string()),
+            }),
+            ..ProtectionSettings::default()
+        };
+
+        let snippet = snippet(
+            &settings(),
+            &protection,
+            "https://news.example.com",
+            "News",
+            false,
+        );
+
+        assert!(snippet.contains("<input type=\"hidden\" name=\"started\">"));
+        assert!(snippet.contains("\"stamp\":\"https://news.example.com/api/forms/stamp\""));
+        assert!(snippet.contains("<div class=\"captcha\"></div>"));
+    }
+
+    #[test]
+    fn ajax_snippet_posts_json() {
+        let snippet = snippet(
+            &FormSettings::default(),
+            &ProtectionSettings::default(),
+            "https://news.example.com",
+            "News",
+            true,
+        );
+
+        assert!(
+            snippet.contains("\"subscriptions\":\"https://news.example.com/api/subscriptions\"")
+        );
+        assert!(snippet.contains("\"ajax\":true"));
+        assert!(snippet.contains("<p class=\"minimail-message\" role=\"status\"></p>"));
+        assert!(!snippet.contains("consent"));
+    }
+}
@@ -0,0 +1,152 @@
+use mailparse::ParsedMail;
+
+use crate::{
+    inbound::{
+        campaign_id,
+        dsn::{blocks, is_report},
+        original_header, Verp,
+    },
+    model::{Complaint, Email},
+};
+
+/// Reads a feedback loop complaint in Abuse Reporting Format (RFC 5965).
+/// Returns `None` when the message is not an ARF report or the recipient
+/// cannot be worked out.
+pub(crate) fn parse_complaint(mail: &ParsedMail, verp: &Verp) -> Option<Complaint> {
+    if !is_report(mail, "feedback-report") {
+        return None;
+    }
+
+    let report = mail.subparts.iter().find(|part| {
+        part.ctype
+            .mimetype
+            .eq_ignore_ascii_case("message/feedback-report")
+    })?;
+    let fields = blocks(&report.get_body().ok()?).into_iter().next()?;
+
+    // ISPs often redact the recipient from the copy of the message, so the
+    // VERP return path is the most reliable way to find them.
+    let email = fields
+        .get("original-rcpt-to")
+        .map(|address| Email::from(unbracket(address)))
+        .or_else(|| {
+            fields
+                .get("original-mail-from")
+                .and_then(|address| verp.recipient(address))
+        })
+        .or_else(|| verp.recipient(&original_header(mail, "Return-Path")?))
+        .or_else(|| Some(Email::from(unbracket(&original_header(mail, "To")?))))
+        .filter(|email| email.0.contains('@'))?;
+
+    Some(Complaint {
+        email,
+        feedback_type: fields.get("feedback-type").unwrap_or("abuse").to_string(),
+        campaign_id: campaign_id(mail),
+    })
+}
+
+fn unbracket(address: &str) -> &str {
+    let address = address.trim();
+    match (address.find('<'), address.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &address[start + 1..end],
+        _ => address,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verp() -> Verp {
+        Verp::from(crate::config::BounceSettings {
+            domain: "mail.example.com".to_string(),
+            prefix: "bounces".to_string(),
+            threshold: 3,
+        })
+    }
+
+    fn arf(report: &str, original: &str) -> String {
+        format!(
+            "From: feedback@isp.example.net\r\n\
+             To: abuse@mail.example.com\r\n\
+             Subject: FW: Newsletter\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/report; report-type=feedback-report; boundary=\"b\"\r\n\
+             \r\n\
+             --b\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             This is an email abuse report.\r\n\
+             --b\r\n\
+             Content-Type: message/feedback-report\r\n\
+             \r\n\
+             Feedback-Type: abuse\r\n\
+             User-Agent: ISP-FBL/1.0\r\n\
+             Version: 1\r\n\
+             {report}\r\n\
+             --b\r\n\
+             Content-Type: message/rfc822\r\n\
+             \r\n\
+             {original}\r\n\
+             Subject: Newsletter\r\n\
+             X-Minimail-Campaign: 4\r\n\
+             \r\n\
+             Hello!\r\n\
+             --b--\r\n"
+        )
+    }
+
+    #[test]
+    fn parse_complaint_reads_original_recipient() {
+        let raw = arf(
+            "Original-Rcpt-To: <user@example.org>\r\n",
+            "To: redacted@example.org",
+        );
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let complaint = parse_complaint(&mail, &verp());
+
+        assert_eq!(
+            Some(Complaint {
+                email: Email::from("user@example.org"),
+                feedback_type: "abuse".to_string(),
+                campaign_id: Some(4),
+            }),
+            complaint
+        );
+    }
+
+    #[test]
+    fn parse_complaint_falls_back_to_verp_return_path() {
+        let raw = arf(
+            "",
+            "Return-Path: <bounces+user=example.org@mail.example.com>\r\n\
+             To: redacted@example.org",
+        );
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let complaint = parse_complaint(&mail, &verp()).unwrap();
+
+        assert_eq!(Email::from("user@example.org"), complaint.email);
+    }
+
+    #[test]
+    fn parse_complaint_falls_back_to_original_to() {
+        let raw = arf("", "To: User <user@example.org>");
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let complaint = parse_complaint(&mail, &verp()).unwrap();
+
+        assert_eq!(Email::from("user@example.org"), complaint.email);
+    }
+
+    #[test]
+    fn parse_complaint_ignores_delivery_reports() {
+        let raw = "Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n\
+                   \r\n\
+                   --b--\r\n";
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        assert_eq!(None, parse_complaint(&mail, &verp()));
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use log::info;
+
+use crate::{
+    data::ApplicationData,
+    model::{Bounce, BounceKind, EventKind, NewEvent, SubscriberStatus, WebhookEvent},
+    store::{EventStore, PsqlEventStore, PsqlSubscriberStore, SubscriberStore},
+    webhooks,
+};
+
+/// Records the bounce and marks the subscriber as bounced after a hard
+/// bounce, or once they have soft bounced `bounce.threshold` times.
+pub(crate) async fn process_bounce(data: &ApplicationData, bounce: &Bounce) -> Result<()> {
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = subscribers.find(&bounce.email).await? else {
+        info!("Ignoring bounce for unknown address: {:?}", bounce.email);
+        return Ok(());
+    };
+
+    let bounced = match bounce.kind {
+        BounceKind::Hard => true,
+        BounceKind::Soft => {
+            subscribers.add_soft_bounce(&bounce.email).await? >= data.bounce.threshold
+        }
+    };
+    if bounced {
+        info!("Marking subscriber as bounced: {:?}", bounce.email);
+        subscribers
+            .set_status(&bounce.email, SubscriberStatus::Bounced)
+            .await?;
+        webhooks::emit_subscriber(data, WebhookEvent::Bounced, &subscriber).await;
+    }
+
+    PsqlEventStore::from(data.pool.clone())
+        .record(NewEvent {
+            kind: EventKind::Bounce,
+            subscriber_id: subscriber.id,
+            campaign_id: bounce.campaign_id,
+            url: None,
+            user_agent: None,
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use log::info;
+
+use crate::{
+    data::ApplicationData,
+    model::{Complaint, EventKind, NewEvent, SubscriberStatus, WebhookEvent},
+    store::{
+        EventStore, PsqlEventStore, PsqlSubscriberStore, PsqlSuppressionStore, SubscriberStore,
+        SuppressionStore,
+    },
+    webhooks,
+};
+
+/// Marks the subscriber as complained and suppresses their address, so they
+/// are never mailed or signed up again.
+pub(crate) async fn process_complaint(data: &ApplicationData, complaint: &Complaint) -> Result<()> {
+    PsqlSuppressionStore::from(data.pool.clone())
+        .add(&complaint.email, "complaint")
+        .await?;
+
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = subscribers.find(&complaint.email).await? else {
+        info!(
+            "Suppressed address of unknown complainant: {:?}",
+            complaint.email
+        );
+        return Ok(());
+    };
+
+    info!("Marking subscriber as complained: {:?}", complaint.email);
+    subscribers
+        .set_status(&complaint.email, SubscriberStatus::Complained)
+        .await?;
+    webhooks::emit_subscriber(data, WebhookEvent::Complained, &subscriber).await;
+
+    PsqlEventStore::from(data.pool.clone())
+        .record(NewEvent {
+            kind: EventKind::Complaint,
+            subscriber_id: subscriber.id,
+            campaign_id: complaint.campaign_id,
+            url: None,
+            user_agent: None,
+        })
+        .await?;
+
+    Ok(())
+}
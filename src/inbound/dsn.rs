@@ -0,0 +1,241 @@
+use mailparse::{MailHeaderMap, ParsedMail};
+
+use crate::{
+    inbound::{campaign_id, Verp},
+    model::{Bounce, BounceKind, Email},
+};
+
+/// Reads the failed recipients out of a delivery status notification (RFC
+/// 3464). Returns `None` when the message is not a DSN at all.
+pub(crate) fn parse_bounces(mail: &ParsedMail, verp: &Verp) -> Option<Vec<Bounce>> {
+    if !is_report(mail, "delivery-status") {
+        return None;
+    }
+
+    let status = mail.subparts.iter().find(|part| {
+        part.ctype
+            .mimetype
+            .eq_ignore_ascii_case("message/delivery-status")
+    })?;
+    let body = status.get_body().ok()?;
+    let campaign_id = campaign_id(mail);
+
+    // The first block holds the per-message fields, every following one
+    // describes a single recipient.
+    let recipients: Vec<Fields> = blocks(&body).into_iter().skip(1).collect();
+    let verp_recipient = ["Delivered-To", "X-Original-To", "Envelope-To", "To"]
+        .iter()
+        .flat_map(|header| mail.headers.get_all_values(header))
+        .find_map(|address| verp.recipient(&address));
+
+    let bounces: Vec<Bounce> = recipients
+        .iter()
+        .filter_map(|fields| {
+            let kind = classify(fields)?;
+            let email = match (&verp_recipient, recipients.len()) {
+                (Some(email), 1) => email.clone(),
+                _ => fields
+                    .get("original-recipient")
+                    .or_else(|| fields.get("final-recipient"))
+                    .and_then(address)?,
+            };
+            Some(Bounce {
+                email,
+                kind,
+                status: fields.get("status").unwrap_or_default().to_string(),
+                diagnostic: fields.get("diagnostic-code").map(str::to_string),
+                campaign_id,
+            })
+        })
+        .collect();
+
+    Some(bounces)
+}
+
+pub(crate) fn is_report(mail: &ParsedMail, report_type: &str) -> bool {
+    mail.ctype.mimetype.eq_ignore_ascii_case("multipart/report")
+        && mail
+            .ctype
+            .params
+            .get("report-type")
+            .map(|value| value.eq_ignore_ascii_case(report_type))
+            .unwrap_or(false)
+}
+
+fn classify(fields: &Fields) -> Option<BounceKind> {
+    let action = fields.get("action").unwrap_or_default();
+    if !action.eq_ignore_ascii_case("failed") {
+        // Delayed, delivered, relayed and expanded messages are not bounces.
+        return None;
+    }
+
+    match fields.get("status").unwrap_or_default().chars().next() {
+        Some('4') => Some(BounceKind::Soft),
+        _ => Some(BounceKind::Hard),
+    }
+}
+
+/// Turns `rfc822; user@example.com` into the address.
+fn address(value: &str) -> Option<Email> {
+    let (address_type, address) = value.split_once(';')?;
+    if !address_type.trim().eq_ignore_ascii_case("rfc822") {
+        return None;
+    }
+    let address = address.trim().trim_start_matches('<').trim_end_matches('>');
+    address.contains('@').then(|| Email::from(address))
+}
+
+pub(crate) struct Fields(Vec<(String, String)>);
+
+impl Fields {
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Splits a body made of header-like `Name: value` lines into its blank line
+/// separated blocks, unfolding continuation lines on the way.
+pub(crate) fn blocks(body: &str) -> Vec<Fields> {
+    let mut blocks = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for line in body.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                blocks.push(Fields(std::mem::take(&mut fields)));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    if !fields.is_empty() {
+        blocks.push(Fields(fields));
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verp() -> Verp {
+        Verp::from(crate::config::BounceSettings {
+            domain: "mail.example.com".to_string(),
+            prefix: "bounces".to_string(),
+            threshold: 3,
+        })
+    }
+
+    fn dsn(to: &str, recipients: &str) -> String {
+        format!(
+            "From: MAILER-DAEMON@mx.example.org\r\n\
+             To: {to}\r\n\
+             Subject: Undelivered Mail Returned to Sender\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n\
+             \r\n\
+             --b\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             Your message could not be delivered.\r\n\
+             --b\r\n\
+             Content-Type: message/delivery-status\r\n\
+             \r\n\
+             Reporting-MTA: dns; mx.example.org\r\n\
+             \r\n\
+             {recipients}\r\n\
+             --b\r\n\
+             Content-Type: text/rfc822-headers\r\n\
+             \r\n\
+             From: news@mail.example.com\r\n\
+             X-Minimail-Campaign: 12\r\n\
+             \r\n\
+             --b--\r\n"
+        )
+    }
+
+    #[test]
+    fn parse_bounces_reads_hard_bounce() {
+        let raw = dsn(
+            "bounces+user=example.org@mail.example.com",
+            "Final-Recipient: rfc822; user@example.org\r\n\
+             Action: failed\r\n\
+             Status: 5.1.1\r\n\
+             Diagnostic-Code: smtp; 550 5.1.1 User unknown\r\n",
+        );
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let bounces = parse_bounces(&mail, &verp()).unwrap();
+
+        assert_eq!(
+            vec![Bounce {
+                email: Email::from("user@example.org"),
+                kind: BounceKind::Hard,
+                status: "5.1.1".to_string(),
+                diagnostic: Some("smtp; 550 5.1.1 User unknown".to_string()),
+                campaign_id: Some(12),
+            }],
+            bounces
+        );
+    }
+
+    #[test]
+    fn parse_bounces_prefers_verp_recipient() {
+        let raw = dsn(
+            "bounces+user=example.org@mail.example.com",
+            "Final-Recipient: rfc822; forwarded@elsewhere.net\r\n\
+             Action: failed\r\n\
+             Status: 4.2.2\r\n",
+        );
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let bounces = parse_bounces(&mail, &verp()).unwrap();
+
+        assert_eq!(Email::from("user@example.org"), bounces[0].email);
+        assert_eq!(BounceKind::Soft, bounces[0].kind);
+    }
+
+    #[test]
+    fn parse_bounces_skips_delays_and_reads_every_recipient() {
+        let raw = dsn(
+            "news@mail.example.com",
+            "Original-Recipient: rfc822; first@example.org\r\n\
+             Final-Recipient: rfc822; first@example.org\r\n\
+             Action: failed\r\n\
+             Status: 5.2.1\r\n\
+             \r\n\
+             Final-Recipient: rfc822; second@example.org\r\n\
+             Action: delayed\r\n\
+             Status: 4.4.1\r\n\
+             \r\n\
+             Final-Recipient: rfc822; third@example.org\r\n\
+             Action: failed\r\n\
+             Status: 4.2.2\r\n",
+        );
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        let bounces = parse_bounces(&mail, &verp()).unwrap();
+
+        let emails: Vec<&str> = bounces
+            .iter()
+            .map(|bounce| bounce.email.0.as_str())
+            .collect();
+        assert_eq!(vec!["first@example.org", "third@example.org"], emails);
+    }
+
+    #[test]
+    fn parse_bounces_ignores_other_messages() {
+        let mail = mailparse::parse_mail(b"Subject: Hello\r\n\r\nJust saying hi.\r\n").unwrap();
+
+        assert_eq!(None, parse_bounces(&mail, &verp()));
+    }
+}
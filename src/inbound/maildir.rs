@@ -0,0 +1,168 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::{error, warn};
+use mailparse::MailParseError;
+
+/// A local Maildir that other software, such as the MTA, delivers into.
+/// Handled messages are moved from `new` to `cur`. Those that can't be read
+/// or parsed are moved to the `.failed` folder, other failed ones are left
+/// in place so that they are retried on the next poll.
+pub struct Maildir {
+    path: PathBuf,
+}
+
+impl From<&str> for Maildir {
+    fn from(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+        }
+    }
+}
+
+impl Maildir {
+    pub async fn deliver<F, Fut>(&self, mut handler: F) -> Result<usize>
+    where
+        F: FnMut(Vec<u8>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let new = self.path.join("new");
+        let cur = self.path.join("cur");
+        let failed = self.path.join(".failed");
+        tokio::fs::create_dir_all(&cur).await?;
+        for folder in ["new", "cur", "tmp"] {
+            tokio::fs::create_dir_all(failed.join(folder)).await?;
+        }
+
+        let mut handled = 0;
+        let mut entries = tokio::fs::read_dir(&new).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let raw = match tokio::fs::read(&path).await {
+                Ok(raw) => raw,
+                Err(e) => {
+                    error!("Moving unreadable {} to .failed: {}", path.display(), e);
+                    if let Err(e) = tokio::fs::rename(&path, seen(&failed.join("cur"), &path)).await
+                    {
+                        error!("Failed to move {}: {}", path.display(), e);
+                    }
+                    continue;
+                }
+            };
+            match handler(raw).await {
+                Ok(()) => {
+                    tokio::fs::rename(&path, seen(&cur, &path)).await?;
+                    handled += 1;
+                }
+                // Trying again won't make it parse.
+                Err(e) if e.is::<MailParseError>() => {
+                    warn!("Moving unparseable {} to .failed: {}", path.display(), e);
+                    tokio::fs::rename(&path, seen(&failed.join("cur"), &path)).await?;
+                }
+                Err(e) => error!("Failed to handle {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(handled)
+    }
+}
+
+fn seen(cur: &Path, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    cur.join(format!("{name}:2,S"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn maildir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("minimail-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(path.join("new")).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn deliver_hands_over_and_moves_messages() -> Result<()> {
+        let path = maildir("deliver");
+        std::fs::write(path.join("new").join("1"), "Subject: First\r\n\r\n")?;
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let handled = Maildir::from(path.to_str().unwrap())
+            .deliver(|raw| {
+                let seen = seen.clone();
+                async move {
+                    seen.lock().unwrap().push(raw);
+                    Ok(())
+                }
+            })
+            .await?;
+
+        assert_eq!(1, handled);
+        assert_eq!(b"Subject: First\r\n\r\n".to_vec(), seen.lock().unwrap()[0]);
+        assert!(path.join("cur").join("1:2,S").exists());
+        assert!(!path.join("new").join("1").exists());
+        std::fs::remove_dir_all(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deliver_leaves_failed_messages() -> Result<()> {
+        let path = maildir("failed");
+        std::fs::write(path.join("new").join("1"), "Subject: First\r\n\r\n")?;
+
+        let handled = Maildir::from(path.to_str().unwrap())
+            .deliver(|_| async { Err(anyhow!("Database is down")) })
+            .await?;
+
+        assert_eq!(0, handled);
+        assert!(path.join("new").join("1").exists());
+        std::fs::remove_dir_all(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deliver_moves_unreadable_messages_aside_and_goes_on() -> Result<()> {
+        let path = maildir("unreadable");
+        // A folder where a message should be can't be read as one.
+        std::fs::create_dir_all(path.join("new").join("1"))?;
+        std::fs::write(path.join("new").join("2"), "Subject: Second\r\n\r\n")?;
+
+        let handled = Maildir::from(path.to_str().unwrap())
+            .deliver(|_| async { Ok(()) })
+            .await?;
+
+        assert_eq!(1, handled);
+        assert!(path.join(".failed").join("cur").join("1:2,S").exists());
+        assert!(path.join("cur").join("2:2,S").exists());
+        std::fs::remove_dir_all(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deliver_moves_unparseable_messages_aside() -> Result<()> {
+        let path = maildir("unparseable");
+        std::fs::write(path.join("new").join("1"), "Subject: First\r\n\r\n")?;
+
+        let handled = Maildir::from(path.to_str().unwrap())
+            .deliver(|_| async { Err(MailParseError::Generic("Bad header").into()) })
+            .await?;
+
+        assert_eq!(0, handled);
+        assert!(!path.join("new").join("1").exists());
+        assert!(path.join(".failed").join("cur").join("1:2,S").exists());
+        std::fs::remove_dir_all(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+mod arf;
+mod bounces;
+mod complaints;
+mod dsn;
+mod maildir;
+mod posts;
+mod requests;
+mod smtp;
+mod verp;
+
+pub(crate) use arf::parse_complaint;
+pub(crate) use bounces::process_bounce;
+pub(crate) use complaints::process_complaint;
+pub(crate) use dsn::parse_bounces;
+pub use maildir::Maildir;
+pub(crate) use posts::process_post;
+pub(crate) use requests::{process_request, Command};
+pub use smtp::{Envelope, SmtpServer};
+pub use verp::Verp;
+
+use anyhow::Result;
+use log::info;
+use mailparse::{MailAddr, MailHeaderMap, ParsedMail};
+
+use crate::{config::ListMode, data::ApplicationData, model::Email};
+
+/// Header added to outgoing campaign mail, which bounce and complaint reports
+/// usually include a copy of.
+pub const CAMPAIGN_HEADER: &str = "X-Minimail-Campaign";
+
+enum Route {
+    // Sent to a VERP return path, so a bounce or an auto-reply to one.
+    Report,
+    // Sent to `list-request@` or `<list>-request@`.
+    ListRequest,
+    // Sent to the address of a discussion list.
+    Post,
+    Reply,
+}
+
+fn route(data: &ApplicationData, recipient: &str) -> Route {
+    let local_part = recipient
+        .rsplit_once('@')
+        .map(|(local_part, _)| local_part)
+        .unwrap_or(recipient);
+
+    if Verp::from(data.bounce.clone())
+        .recipient(recipient)
+        .is_some()
+    {
+        Route::Report
+    } else if local_part.to_ascii_lowercase().ends_with("-request") {
+        Route::ListRequest
+    } else if data.list.mode == ListMode::Discussion
+        && recipient.eq_ignore_ascii_case(&data.list.address)
+    {
+        Route::Post
+    } else {
+        Route::Reply
+    }
+}
+
+/// Processes a message received by our SMTP listener, once for each of its
+/// recipients. A bounce or complaint only counts once, however many of them
+/// it was sent to.
+pub(crate) async fn deliver(
+    data: &ApplicationData,
+    envelope: Envelope,
+    raw: Vec<u8>,
+) -> Result<()> {
+    let mail = mailparse::parse_mail(&raw)?;
+    let mut report = None;
+
+    for recipient in &envelope.recipients {
+        let route = route(data, recipient);
+        if matches!(route, Route::Report | Route::Reply) && report.is_none() {
+            report = Some(process_report(data, &mail).await?);
+        }
+        let is_report = report == Some(true);
+        match route {
+            Route::Report => {
+                if !is_report {
+                    info!("Dropping message to return path {recipient} that is not a report");
+                }
+            }
+            Route::ListRequest => {
+                process_request(data, &mail).await?;
+            }
+            Route::Post => {
+                process_post(data, &mail, &raw).await?;
+            }
+            Route::Reply => {
+                // Feedback loops send complaints to an ordinary address.
+                if !is_report {
+                    forward(data, recipient, &raw).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Processes a raw RFC 822 message delivered to us by the mailbox poller.
+pub(crate) async fn handle(data: &ApplicationData, raw: &[u8]) -> Result<()> {
+    let mail = mailparse::parse_mail(raw)?;
+
+    if !process_report(data, &mail).await? {
+        info!("Ignoring inbound message that is neither a bounce nor a complaint");
+    }
+    Ok(())
+}
+
+/// Handles bounces and complaints, returning whether the message was one.
+async fn process_report(data: &ApplicationData, mail: &ParsedMail<'_>) -> Result<bool> {
+    let verp = Verp::from(data.bounce.clone());
+
+    if let Some(bounces) = parse_bounces(mail, &verp) {
+        for bounce in bounces {
+            process_bounce(data, &bounce).await?;
+        }
+        return Ok(true);
+    }
+
+    if let Some(complaint) = parse_complaint(mail, &verp) {
+        process_complaint(data, &complaint).await?;
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+async fn forward(data: &ApplicationData, recipient: &str, raw: &[u8]) -> Result<()> {
+    match &data.inbound.forward {
+        Some(forward) => {
+            info!("Forwarding message for {recipient} to {forward}");
+            data.mailer.forward(forward, raw).await
+        }
+        None => {
+            info!("Dropping message for {recipient}, no forward address is configured");
+            Ok(())
+        }
+    }
+}
+
+/// Finds the campaign of the original message from the copy of its headers
+/// that reports embed.
+fn campaign_id(mail: &ParsedMail) -> Option<i32> {
+    original_header(mail, CAMPAIGN_HEADER)?.trim().parse().ok()
+}
+
+/// Reads a header of the original message embedded in a bounce or
+/// complaint report.
+fn original_header(mail: &ParsedMail, name: &str) -> Option<String> {
+    mail.subparts
+        .iter()
+        .filter(|part| {
+            let mimetype = part.ctype.mimetype.to_ascii_lowercase();
+            mimetype == "text/rfc822-headers" || mimetype == "message/rfc822"
+        })
+        .find_map(|part| {
+            let raw = part.get_body_raw().ok()?;
+            let (headers, _) = mailparse::parse_headers(&raw).ok()?;
+            headers.get_first_value(name)
+        })
+}
+
+/// Whether a message came from a robot, which must never be answered.
+pub(crate) fn is_automated(mail: &ParsedMail) -> bool {
+    let header = |name| {
+        mail.headers
+            .get_first_value(name)
+            .map(|value| value.trim().to_ascii_lowercase())
+    };
+
+    matches!(header("Auto-Submitted"), Some(value) if value != "no")
+        || matches!(
+            header("Precedence").as_deref(),
+            Some("bulk" | "junk" | "list")
+        )
+}
+
+/// The first address in an address header such as `From`.
+pub(crate) fn address(mail: &ParsedMail, name: &str) -> Option<Email> {
+    let header = mail.headers.get_first_header(name)?;
+    let address = match mailparse::addrparse_header(header)
+        .ok()?
+        .into_inner()
+        .into_iter()
+        .next()?
+    {
+        MailAddr::Single(info) => info.addr,
+        MailAddr::Group(group) => group.addrs.into_iter().next()?.addr,
+    };
+    Some(Email(address))
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use log::info;
+use mailparse::{MailHeaderMap, ParsedMail};
+
+use crate::{
+    data::ApplicationData,
+    inbound::{address, is_automated},
+    model::{NewPost, Post, PostStatus, SubscriberStatus},
+    moderation::held_post,
+    outbound::distribute,
+    store::{
+        AllowlistStore, PostStore, PsqlAllowlistStore, PsqlPostStore, PsqlSubscriberStore,
+        SubscriberStore,
+    },
+};
+
+/// Takes a post to a discussion list. Posts of members and allowed senders
+/// go out to the list straight away, those of anybody else are held for
+/// moderation. Returns
+/// `None` for messages that must not be posted at all.
+pub(crate) async fn process_post(
+    data: &ApplicationData,
+    mail: &ParsedMail<'_>,
+    raw: &[u8],
+) -> Result<Option<Post>> {
+    let list_id = data.list.address.replace('@', ".");
+    let looped = mail
+        .headers
+        .get_all_values("List-Id")
+        .iter()
+        .any(|value| value.contains(&format!("<{list_id}>")));
+    if is_automated(mail) || looped {
+        info!("Not posting automatic message to the list");
+        return Ok(None);
+    }
+    let Some(sender) = address(mail, "From") else {
+        info!("Not posting message without a sender");
+        return Ok(None);
+    };
+
+    let member = PsqlSubscriberStore::from(data.pool.clone())
+        .find(&sender)
+        .await?
+        .is_some_and(|subscriber| subscriber.status == SubscriberStatus::Active);
+    let allowed = PsqlAllowlistStore::from(data.pool.clone())
+        .contains(&sender)
+        .await?;
+    let status = if member || allowed {
+        PostStatus::Accepted
+    } else {
+        PostStatus::Held
+    };
+
+    let post = PsqlPostStore::from(data.pool.clone())
+        .create(NewPost {
+            sender,
+            subject: mail.headers.get_first_value("Subject").unwrap_or_default(),
+            raw: raw.to_vec(),
+            status,
+        })
+        .await?;
+
+    match post.status {
+        PostStatus::Accepted => {
+            distribute(data, &post).await?;
+        }
+        PostStatus::Held => {
+            info!("Holding post {} by non-member {:?}", post.id, post.sender);
+            held_post(data, &post).await?;
+        }
+        PostStatus::Rejected => {}
+    }
+    Ok(Some(post))
+}
@@ -0,0 +1,440 @@
+use anyhow::Result;
+use lettre::{message::Mailbox, Message};
+use log::info;
+use mailparse::{MailHeaderMap, ParsedMail};
+use serde::Serialize;
+
+use crate::{
+    activity,
+    config::ListSettings,
+    data::ApplicationData,
+    inbound::{address, is_automated},
+    model::{ActivityKind, Email, SubscriberStatus, WebhookEvent},
+    moderation::signup,
+    outbound::AutoSubmitted,
+    pages::{Confirmation, Purpose},
+    signing::Signer,
+    store::{PsqlSubscriberStore, PsqlSuppressionStore, SubscriberStore, SuppressionStore},
+    tracking, webhooks,
+};
+
+// Only the first few commands of a message are carried out.
+const MAX_COMMANDS: usize = 10;
+
+/// A listserv style command sent to the request address.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "command", content = "token")]
+pub enum Command {
+    Subscribe,
+    Unsubscribe,
+    Help,
+    Which,
+    // Switches to receiving discussion list posts in a digest, and back.
+    Digest,
+    NoDigest,
+    // Reply to the confirmation of subscribing or leaving.
+    Confirm(String),
+}
+
+impl Command {
+    fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let command = match words.next()?.to_ascii_lowercase().as_str() {
+            "subscribe" | "join" => Self::Subscribe,
+            "unsubscribe" | "leave" => Self::Unsubscribe,
+            "help" => Self::Help,
+            "which" => Self::Which,
+            "digest" => Self::Digest,
+            "nodigest" => Self::NoDigest,
+            "confirm" => Self::Confirm(words.next()?.to_string()),
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+struct Reply {
+    subject: String,
+    body: String,
+}
+
+/// Reads the command in the subject, then one command per line of the body
+/// until the first line that is not one, e.g. a signature or quoted text.
+pub(crate) fn parse_commands(mail: &ParsedMail) -> Vec<Command> {
+    let subject = mail.headers.get_first_value("Subject").unwrap_or_default();
+    let mut lines = vec![strip_reply_prefixes(&subject).to_string()];
+    if let Some(body) = text_body(mail) {
+        lines.extend(
+            body.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .take_while(|line| *line != "--" && !line.eq_ignore_ascii_case("end"))
+                .map(str::to_string),
+        );
+    }
+
+    let mut commands = Vec::new();
+    let mut lines = lines.iter();
+    // A subject that is not a command is fine, the body may still have some.
+    if let Some(command) = lines.next().and_then(|subject| Command::parse(subject)) {
+        commands.push(command);
+    }
+    for command in lines.map_while(|line| Command::parse(line)) {
+        if !commands.contains(&command) {
+            commands.push(command);
+        }
+    }
+    commands.truncate(MAX_COMMANDS);
+    commands
+}
+
+/// Carries out the commands in a message to the request address and mails
+/// the results back to its sender.
+pub(crate) async fn process_request(
+    data: &ApplicationData,
+    mail: &ParsedMail<'_>,
+) -> Result<Vec<Command>> {
+    if is_automated(mail) {
+        info!("Ignoring automatic message to the request address");
+        return Ok(Vec::new());
+    }
+    let Some(sender) = sender(mail) else {
+        info!("Ignoring request without a sender address");
+        return Ok(Vec::new());
+    };
+
+    let commands = parse_commands(mail);
+    let mut replies = Vec::new();
+    if commands.is_empty() {
+        replies.push(help(&data.list));
+    }
+    for command in &commands {
+        info!("Processing {command:?} from {sender:?}");
+        if let Some(reply) = execute(data, &sender, command).await? {
+            replies.push(reply);
+        }
+    }
+
+    for reply in replies {
+        send_reply(data, &sender, mail, reply).await?;
+    }
+    Ok(commands)
+}
+
+async fn execute(
+    data: &ApplicationData,
+    sender: &Email,
+    command: &Command,
+) -> Result<Option<Reply>> {
+    let list = &data.list;
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let suppressions = PsqlSuppressionStore::from(data.pool.clone());
+    let signer = Signer::new(&list.secret);
+
+    let reply = match command {
+        Command::Subscribe | Command::Unsubscribe | Command::Confirm(_)
+            if suppressions.contains(sender).await? =>
+        {
+            info!("Not answering suppressed address: {sender:?}");
+            return Ok(None);
+        }
+        Command::Subscribe if subscribers.find(sender).await?.is_some() => Reply {
+            subject: format!("You are subscribed to {}", list.name),
+            body: format!("{} is already subscribed to {}.\n", sender.0, list.name),
+        },
+        Command::Subscribe => {
+            let token = signer.sign(&Confirmation::new(sender.0.clone(), None));
+            Reply {
+                subject: format!("confirm {token}"),
+                body: format!(
+                    "Someone, hopefully you, asked to subscribe {} to {}.\n\n\
+                     To confirm, reply to this message without changing its subject. \
+                     If you did not ask for this, ignore this message.\n",
+                    sender.0, list.name
+                ),
+            }
+        }
+        Command::Confirm(token) => match signer.verify::<Confirmation>(token) {
+            Some(confirmation)
+                if !confirmation.expired() && confirmation.purpose == Purpose::Unsubscribe =>
+            {
+                leave(data, &Email(confirmation.email)).await?
+            }
+            Some(confirmation) if !confirmation.expired() => {
+                let email = Email(confirmation.email);
+                if signup(data, email.clone(), false).await?.status == SubscriberStatus::Pending {
+                    return Ok(Some(Reply {
+                        subject: format!("Your subscription to {}", list.name),
+                        body: format!(
+                            "{} is confirmed and waits for a moderator of {} to approve it.\n",
+                            email.0, list.name
+                        ),
+                    }));
+                }
+                Reply {
+                    subject: format!("Welcome to {}", list.name),
+                    body: format!(
+                        "{} is now subscribed to {}.\n\n\
+                         To unsubscribe, send \"unsubscribe\" to {}.\n",
+                        email.0,
+                        list.name,
+                        list.request_address()
+                    ),
+                }
+            }
+            _ => Reply {
+                subject: "Confirmation failed".to_string(),
+                body: format!(
+                    "This confirmation is invalid or has expired.\n\n\
+                     To try again, send \"subscribe\" to {}.\n",
+                    list.request_address()
+                ),
+            },
+        },
+        Command::Unsubscribe if subscribers.find(sender).await?.is_none() => {
+            not_subscribed(list, sender)
+        }
+        Command::Unsubscribe => {
+            let token = signer.sign(&Confirmation::unsubscribe(sender.0.clone()));
+            Reply {
+                subject: format!("confirm {token}"),
+                body: format!(
+                    "Someone, hopefully you, asked to unsubscribe {} from {}.\n\n\
+                     To confirm, reply to this message without changing its subject. \
+                     If you did not ask for this, ignore this message and you stay subscribed.\n",
+                    sender.0, list.name
+                ),
+            }
+        }
+        Command::Which => {
+            let (status, reason) = match subscribers.find(sender).await?.map(|s| s.status) {
+                Some(SubscriberStatus::Active) => ("is subscribed", ""),
+                Some(SubscriberStatus::Pending) => ("waits for approval to be subscribed", ""),
+                Some(SubscriberStatus::Bounced) => {
+                    ("is no longer subscribed", ", since mail to it bounced")
+                }
+                Some(SubscriberStatus::Complained) => (
+                    "is no longer subscribed",
+                    ", since it reported our mail as spam",
+                ),
+                None => ("is not subscribed", ""),
+            };
+            Reply {
+                subject: format!("Your subscription to {}", list.name),
+                body: format!("{} {status} to {}{reason}.\n", sender.0, list.name),
+            }
+        }
+        Command::Digest | Command::NoDigest if subscribers.find(sender).await?.is_none() => {
+            not_subscribed(list, sender)
+        }
+        Command::Digest => {
+            subscribers.set_digest(sender, true).await?;
+            Reply {
+                subject: format!("Digest of {}", list.name),
+                body: format!(
+                    "{} now receives a digest of {} every {} hours.\n",
+                    sender.0, list.name, list.digest
+                ),
+            }
+        }
+        Command::NoDigest => {
+            subscribers.set_digest(sender, false).await?;
+            Reply {
+                subject: format!("Posts of {}", list.name),
+                body: format!(
+                    "{} now receives every post to {} as it is sent.\n",
+                    sender.0, list.name
+                ),
+            }
+        }
+        Command::Help => help(list),
+    };
+
+    Ok(Some(reply))
+}
+
+/// Unsubscribes an address once it confirmed that it wants to leave.
+async fn leave(data: &ApplicationData, email: &Email) -> Result<Reply> {
+    let list = &data.list;
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = subscribers.find(email).await? else {
+        return Ok(not_subscribed(list, email));
+    };
+    tracking::unsubscribed(data, &subscriber, None).await;
+    subscribers.delete(email).await?;
+    webhooks::emit_subscriber(data, WebhookEvent::Unsubscribed, &subscriber).await;
+    activity::publish_subscriber(data, ActivityKind::Unsubscribed, &subscriber).await;
+    Ok(Reply {
+        subject: format!("You have left {}", list.name),
+        body: format!("{} has been unsubscribed from {}.\n", email.0, list.name),
+    })
+}
+
+fn not_subscribed(list: &ListSettings, email: &Email) -> Reply {
+    Reply {
+        subject: format!("You are not subscribed to {}", list.name),
+        body: format!("{} is not subscribed to {}.\n", email.0, list.name),
+    }
+}
+
+fn help(list: &ListSettings) -> Reply {
+    Reply {
+        subject: format!("Help for {}", list.name),
+        body: format!(
+            "Send commands to {} in the subject or the body of a message, \
+             one per line:\n\n\
+             subscribe    Subscribe your address, which you then confirm by replying\n\
+             unsubscribe  Unsubscribe your address, which you then confirm by replying\n\
+             which        Tell whether your address is subscribed\n\
+             digest       Receive a digest of discussion posts instead of each one\n\
+             nodigest     Receive each discussion post again\n\
+             help         Send this message\n",
+            list.request_address()
+        ),
+    }
+}
+
+async fn send_reply(
+    data: &ApplicationData,
+    to: &Email,
+    request: &ParsedMail<'_>,
+    reply: Reply,
+) -> Result<()> {
+    let from = Mailbox::new(
+        Some(data.list.name.clone()),
+        data.list.request_address().parse()?,
+    );
+    let mut message = Message::builder()
+        .from(from)
+        .to(to.0.parse()?)
+        .subject(reply.subject)
+        .header(AutoSubmitted::replied());
+    if let Some(id) = request.headers.get_first_value("Message-ID") {
+        message = message.in_reply_to(id.clone()).references(id);
+    }
+
+    data.mailer.send(message.body(reply.body)?).await
+}
+
+/// The address commands act on and replies go to. `Reply-To` is left out,
+/// since commands must only ever change the subscription of the address
+/// that gets the confirmation.
+fn sender(mail: &ParsedMail) -> Option<Email> {
+    address(mail, "From")
+}
+
+fn strip_reply_prefixes(subject: &str) -> &str {
+    let mut subject = subject.trim();
+    while let Some((prefix, rest)) = subject.split_once(':') {
+        let prefix = prefix.trim().to_ascii_lowercase();
+        if !["re", "fw", "fwd", "aw", "sv"].contains(&prefix.as_str()) {
+            break;
+        }
+        subject = rest.trim();
+    }
+    subject
+}
+
+fn text_body(mail: &ParsedMail) -> Option<String> {
+    if mail.subparts.is_empty() {
+        return (mail.ctype.mimetype.eq_ignore_ascii_case("text/plain"))
+            .then(|| mail.get_body().ok())
+            .flatten();
+    }
+    mail.subparts.iter().find_map(text_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ListMode;
+
+    fn commands(raw: &str) -> Vec<Command> {
+        parse_commands(&mailparse::parse_mail(raw.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn reads_command_from_subject() {
+        let raw = "From: user@example.org\r\nSubject: Subscribe\r\n\r\nThanks!\r\n";
+
+        assert_eq!(vec![Command::Subscribe], commands(raw));
+    }
+
+    #[test]
+    fn reads_confirmation_from_reply_subject() {
+        let raw = "From: user@example.org\r\nSubject: Re: RE: confirm abc.def\r\n\r\n> Someone\r\n";
+
+        assert_eq!(vec![Command::Confirm("abc.def".to_string())], commands(raw));
+    }
+
+    #[test]
+    fn reads_body_until_first_other_line() {
+        let raw = "From: user@example.org\r\nSubject: hello\r\n\r\n\
+                   which\r\n\r\nhelp\r\nwhich\r\nThanks\r\nunsubscribe\r\n";
+
+        assert_eq!(vec![Command::Which, Command::Help], commands(raw));
+    }
+
+    #[test]
+    fn stops_at_signature() {
+        let raw = "From: user@example.org\r\n\r\nsubscribe\r\n--\r\nhelp\r\n";
+
+        assert_eq!(vec![Command::Subscribe], commands(raw));
+    }
+
+    #[test]
+    fn reads_text_part_of_multipart_message() {
+        let raw = "From: user@example.org\r\n\
+                   Content-Type: multipart/alternative; boundary=\"b\"\r\n\r\n\
+                   --b\r\nContent-Type: text/plain\r\n\r\nunsubscribe\r\n\
+                   --b\r\nContent-Type: text/html\r\n\r\n<p>unsubscribe</p>\r\n--b--\r\n";
+
+        assert_eq!(vec![Command::Unsubscribe], commands(raw));
+    }
+
+    #[test]
+    fn finds_no_commands_in_ordinary_mail() {
+        let raw = "From: user@example.org\r\nSubject: Question\r\n\r\nHow do I join?\r\n";
+
+        assert!(commands(raw).is_empty());
+    }
+
+    #[test]
+    fn recognizes_automatic_mail() {
+        let automated = |headers: &str| {
+            let raw = format!("From: user@example.org\r\n{headers}\r\n\r\nhelp\r\n");
+            is_automated(&mailparse::parse_mail(raw.as_bytes()).unwrap())
+        };
+
+        assert!(automated("Auto-Submitted: auto-replied"));
+        assert!(automated("Precedence: bulk"));
+        assert!(!automated("Auto-Submitted: no"));
+        assert!(!automated("Subject: help"));
+    }
+
+    #[test]
+    fn ignores_reply_to_address() {
+        let raw = "From: User <user@example.org>\r\nReply-To: other@example.org\r\n\r\n";
+        let mail = mailparse::parse_mail(raw.as_bytes()).unwrap();
+
+        assert_eq!(Some(Email::from("user@example.org")), sender(&mail));
+    }
+
+    #[test]
+    fn derives_request_address_from_list_address() {
+        let list = ListSettings {
+            name: "News".to_string(),
+            address: "news@example.com".to_string(),
+            secret: "secret".to_string(),
+            mode: ListMode::Newsletter,
+            prefix: None,
+            footer: None,
+            rewrite: true,
+            digest: 24,
+            timezone: "UTC".to_string(),
+            origins: Vec::new(),
+        };
+
+        assert_eq!("news-request@example.com", list.request_address());
+    }
+}
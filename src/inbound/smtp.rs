@@ -0,0 +1,455 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use log::{debug, error};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::config::SmtpSettings;
+
+const TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_COMMAND_LENGTH: u64 = 512;
+// Well past the 1000 octets RFC 5321 allows for a line of text, since not
+// every sender keeps to it.
+const MAX_LINE_LENGTH: u64 = 8192;
+const MAX_RECIPIENTS: usize = 100;
+// How long to wait after failing to accept a connection, e.g. while out of
+// file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    // Empty for bounces, which are sent with a null reverse path.
+    pub from: String,
+    pub recipients: Vec<String>,
+}
+
+/// A small SMTP receiver (RFC 5321) that accepts mail for our own domains and
+/// hands every message over to a handler. It never relays.
+#[derive(Debug, Clone)]
+pub struct SmtpServer {
+    hostname: String,
+    domains: Vec<String>,
+    size: usize,
+}
+
+impl From<SmtpSettings> for SmtpServer {
+    fn from(settings: SmtpSettings) -> Self {
+        Self {
+            hostname: settings.hostname,
+            domains: settings.domains,
+            size: settings.size,
+        }
+    }
+}
+
+impl SmtpServer {
+    pub async fn serve<H, Fut>(self, listener: TcpListener, handler: H) -> Result<()>
+    where
+        H: Fn(Envelope, Vec<u8>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let server = Arc::new(self);
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("Failed to accept SMTP connection: {e}");
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let server = server.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.session(stream, handler).await {
+                    debug!("SMTP session with {peer} ended: {e}");
+                }
+            });
+        }
+    }
+
+    async fn session<H, Fut>(&self, stream: TcpStream, handler: H) -> Result<()>
+    where
+        H: Fn(Envelope, Vec<u8>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut from: Option<String> = None;
+        let mut recipients: Vec<String> = Vec::new();
+
+        reply(
+            &mut writer,
+            &format!("220 {} ESMTP Minimail", self.hostname),
+        )
+        .await?;
+
+        loop {
+            let Some(line) = read_command(&mut reader).await? else {
+                return Ok(());
+            };
+            let (verb, argument) = line.split_once(' ').unwrap_or((&line, ""));
+
+            let response = match verb.to_ascii_uppercase().as_str() {
+                "EHLO" => format!(
+                    "250-{}\r\n250-SIZE {}\r\n250-8BITMIME\r\n250 ENHANCEDSTATUSCODES",
+                    self.hostname, self.size
+                ),
+                "HELO" => format!("250 {}", self.hostname),
+                "MAIL" if from.is_some() => "503 5.5.1 Sender already given".to_string(),
+                "MAIL" => match parse_path(argument, "FROM:") {
+                    Some((_, parameters)) if declared_size(parameters) > self.size => {
+                        "552 5.3.4 Message too big".to_string()
+                    }
+                    Some((address, _)) => {
+                        from = Some(address);
+                        "250 2.1.0 OK".to_string()
+                    }
+                    None => "501 5.5.4 Syntax: MAIL FROM:<address>".to_string(),
+                },
+                "RCPT" if from.is_none() => "503 5.5.1 Need MAIL first".to_string(),
+                "RCPT" => match parse_path(argument, "TO:") {
+                    Some(_) if recipients.len() >= MAX_RECIPIENTS => {
+                        "452 4.5.3 Too many recipients".to_string()
+                    }
+                    Some((address, _)) if self.accepts(&address) => {
+                        recipients.push(address);
+                        "250 2.1.5 OK".to_string()
+                    }
+                    Some(_) => "550 5.7.1 Relaying denied".to_string(),
+                    None => "501 5.5.4 Syntax: RCPT TO:<address>".to_string(),
+                },
+                "DATA" if recipients.is_empty() => "503 5.5.1 Need RCPT first".to_string(),
+                "DATA" => {
+                    reply(&mut writer, "354 End data with <CR><LF>.<CR><LF>").await?;
+                    let data = read_data(&mut reader, self.size).await?;
+                    let envelope = Envelope {
+                        from: from.take().unwrap_or_default(),
+                        recipients: std::mem::take(&mut recipients),
+                    };
+                    match data {
+                        Err(refusal) => refusal.to_string(),
+                        Ok(data) => match handler(envelope, data).await {
+                            Ok(()) => "250 2.0.0 OK".to_string(),
+                            Err(e) => {
+                                error!("Failed to handle inbound message: {e}");
+                                "451 4.3.0 Temporary failure, try again later".to_string()
+                            }
+                        },
+                    }
+                }
+                "RSET" => {
+                    from = None;
+                    recipients.clear();
+                    "250 2.0.0 OK".to_string()
+                }
+                "NOOP" => "250 2.0.0 OK".to_string(),
+                "VRFY" => "252 2.5.0 Cannot verify, but will try delivery".to_string(),
+                "QUIT" => {
+                    reply(&mut writer, "221 2.0.0 Bye").await?;
+                    return Ok(());
+                }
+                _ => "502 5.5.2 Command not recognized".to_string(),
+            };
+
+            reply(&mut writer, &response).await?;
+        }
+    }
+
+    fn accepts(&self, address: &str) -> bool {
+        address
+            .rsplit_once('@')
+            .map(|(_, domain)| {
+                self.domains
+                    .iter()
+                    .any(|accepted| accepted.eq_ignore_ascii_case(domain))
+            })
+            .unwrap_or(false)
+    }
+}
+
+async fn reply(writer: &mut (impl AsyncWrite + Unpin), response: &str) -> Result<()> {
+    writer.write_all(response.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn read_command(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = tokio::time::timeout(
+        TIMEOUT,
+        (&mut *reader)
+            .take(MAX_COMMAND_LENGTH)
+            .read_until(b'\n', &mut line),
+    )
+    .await??;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(anyhow!("Command line too long"));
+    }
+    Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()))
+}
+
+/// Reads the message up to the terminating `.` line, undoing dot stuffing.
+/// Lines are read at most `MAX_LINE_LENGTH` at a time and nothing is kept
+/// past `size`, so that a sender can't make us hold more. A message that
+/// grew larger than `size` or had a longer line is read to its end, and
+/// then refused with the reply in the error.
+async fn read_data(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    size: usize,
+) -> Result<std::result::Result<Vec<u8>, &'static str>> {
+    let mut data = Vec::new();
+    let mut refusal = None;
+    // Whether the last read ended inside a line that was too long.
+    let mut in_long_line = false;
+
+    loop {
+        let mut line = Vec::new();
+        let read = tokio::time::timeout(
+            TIMEOUT,
+            (&mut *reader)
+                .take(MAX_LINE_LENGTH)
+                .read_until(b'\n', &mut line),
+        )
+        .await??;
+        if read == 0 {
+            return Err(anyhow!("Connection closed during DATA"));
+        }
+        let continued = std::mem::replace(&mut in_long_line, !line.ends_with(b"\n"));
+        if in_long_line {
+            refusal = Some("500 5.5.6 Line too long");
+        }
+        if !continued && (line == b".\r\n" || line == b".\n") {
+            break;
+        }
+        if continued || refusal.is_some() {
+            continue;
+        }
+
+        let line = line.strip_prefix(b".").unwrap_or(&line);
+        data.extend_from_slice(line);
+        if data.len() > size {
+            refusal = Some("552 5.3.4 Message too big");
+        }
+    }
+
+    Ok(refusal.map_or(Ok(data), Err))
+}
+
+/// Splits `FROM:<address> PARAMETERS` into the address and its parameters.
+fn parse_path<'a>(argument: &'a str, prefix: &str) -> Option<(String, &'a str)> {
+    let argument = argument.trim_start();
+    if !argument
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    {
+        return None;
+    }
+
+    let path = argument[prefix.len()..].trim_start();
+    let path = path.strip_prefix('<')?;
+    let (address, parameters) = path.split_once('>')?;
+    Some((address.to_string(), parameters.trim()))
+}
+
+fn declared_size(parameters: &str) -> usize {
+    parameters
+        .split_whitespace()
+        .find_map(|parameter| {
+            let (name, value) = parameter.split_once('=')?;
+            name.eq_ignore_ascii_case("SIZE")
+                .then(|| value.parse().ok())?
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use lettre::{message::Message, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+    use super::*;
+
+    type Delivered = Arc<Mutex<Vec<(Envelope, Vec<u8>)>>>;
+
+    async fn start(size: usize) -> (u16, Delivered) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let delivered: Delivered = Arc::default();
+        let server = SmtpServer {
+            hostname: "mx.test".to_string(),
+            domains: vec!["example.com".to_string()],
+            size,
+        };
+
+        let sink = delivered.clone();
+        tokio::spawn(server.serve(listener, move |envelope, data| {
+            let sink = sink.clone();
+            async move {
+                sink.lock().unwrap().push((envelope, data));
+                Ok(())
+            }
+        }));
+
+        (port, delivered)
+    }
+
+    /// Sends each line and returns the last reply. Lines between `DATA` and
+    /// the final `.` get no reply of their own.
+    async fn converse(port: u16, lines: &[&str]) -> String {
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut response = read_reply(&mut reader).await;
+        let mut in_data = false;
+
+        for line in lines {
+            writer
+                .write_all(format!("{line}\r\n").as_bytes())
+                .await
+                .unwrap();
+            if in_data && *line != "." {
+                continue;
+            }
+            in_data = *line == "DATA";
+            response = read_reply(&mut reader).await;
+        }
+        response
+    }
+
+    async fn read_reply(reader: &mut (impl AsyncBufRead + Unpin)) -> String {
+        let mut response = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            response.push_str(&line);
+            if line.len() < 4 || line.as_bytes()[3] != b'-' {
+                return response.trim_end().to_string();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn serve_hands_messages_to_handler() {
+        let (port, delivered) = start(1024).await;
+        let message = Message::builder()
+            .from("sender@elsewhere.net".parse().unwrap())
+            .to("list-request@example.com".parse().unwrap())
+            .subject("help")
+            .body("help".to_string())
+            .unwrap();
+
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+            .port(port)
+            .build()
+            .send(message)
+            .await
+            .unwrap();
+
+        let delivered = delivered.lock().unwrap();
+        assert_eq!(
+            Envelope {
+                from: "sender@elsewhere.net".to_string(),
+                recipients: vec!["list-request@example.com".to_string()],
+            },
+            delivered[0].0
+        );
+        assert!(String::from_utf8_lossy(&delivered[0].1).contains("Subject: help"));
+    }
+
+    #[tokio::test]
+    async fn serve_accepts_null_sender() {
+        let (port, _) = start(1024).await;
+
+        let response = converse(port, &["EHLO client", "MAIL FROM:<>"]).await;
+
+        assert_eq!("250 2.1.0 OK", response);
+    }
+
+    #[tokio::test]
+    async fn serve_refuses_to_relay() {
+        let (port, _) = start(1024).await;
+
+        let response = converse(
+            port,
+            &[
+                "EHLO client",
+                "MAIL FROM:<a@b.c>",
+                "RCPT TO:<user@elsewhere.net>",
+            ],
+        )
+        .await;
+
+        assert_eq!("550 5.7.1 Relaying denied", response);
+    }
+
+    #[tokio::test]
+    async fn serve_refuses_declared_oversize_messages() {
+        let (port, _) = start(1024).await;
+
+        let response = converse(port, &["EHLO client", "MAIL FROM:<a@b.c> SIZE=4096"]).await;
+
+        assert_eq!("552 5.3.4 Message too big", response);
+    }
+
+    #[tokio::test]
+    async fn serve_refuses_oversize_data() {
+        let (port, delivered) = start(16).await;
+        let long = "x".repeat(64);
+
+        let response = converse(
+            port,
+            &[
+                "EHLO client",
+                "MAIL FROM:<a@b.c>",
+                "RCPT TO:<user@example.com>",
+                "DATA",
+                &long,
+                ".",
+            ],
+        )
+        .await;
+
+        assert_eq!("552 5.3.4 Message too big", response);
+        assert!(delivered.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn serve_refuses_overlong_lines() {
+        let (port, delivered) = start(64 * 1024).await;
+        let long = "x".repeat(MAX_LINE_LENGTH as usize * 2);
+
+        let response = converse(
+            port,
+            &[
+                "EHLO client",
+                "MAIL FROM:<a@b.c>",
+                "RCPT TO:<user@example.com>",
+                "DATA",
+                &long,
+                ".",
+            ],
+        )
+        .await;
+
+        assert_eq!("500 5.5.6 Line too long", response);
+        assert!(delivered.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_path_ignores_multibyte_prefixes() {
+        assert_eq!(None, parse_path("FRO€:<a@b.c>", "FROM:"));
+        assert_eq!(
+            Some(("a@b.c".to_string(), "")),
+            parse_path("from:<a@b.c>", "FROM:")
+        );
+    }
+}
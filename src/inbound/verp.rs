@@ -0,0 +1,75 @@
+use crate::{config::BounceSettings, model::Email};
+
+/// Encodes the recipient into the return path of every message, so that a
+/// bounce tells us who it was for even when the DSN itself does not.
+#[derive(Clone, Debug)]
+pub struct Verp {
+    prefix: String,
+    domain: String,
+}
+
+impl From<BounceSettings> for Verp {
+    fn from(settings: BounceSettings) -> Self {
+        Self {
+            prefix: settings.prefix,
+            domain: settings.domain,
+        }
+    }
+}
+
+impl Verp {
+    pub fn return_path(&self, email: &Email) -> String {
+        let encoded = email.0.replacen('@', "=", 1);
+        format!("{}+{encoded}@{}", self.prefix, self.domain)
+    }
+
+    pub fn recipient(&self, address: &str) -> Option<Email> {
+        let address = address.trim().trim_start_matches('<').trim_end_matches('>');
+        let (local, domain) = address.rsplit_once('@')?;
+        if !domain.eq_ignore_ascii_case(&self.domain) {
+            return None;
+        }
+
+        let encoded = local.strip_prefix(&self.prefix)?.strip_prefix('+')?;
+        let (user, domain) = encoded.rsplit_once('=')?;
+        if user.is_empty() || domain.is_empty() {
+            return None;
+        }
+        Some(Email(format!("{user}@{domain}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verp() -> Verp {
+        Verp {
+            prefix: "bounces".to_string(),
+            domain: "mail.example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn return_path_encodes_recipient() {
+        let return_path = verp().return_path(&Email::from("first.last+tag@example.org"));
+
+        assert_eq!(
+            "bounces+first.last+tag=example.org@mail.example.com",
+            return_path
+        );
+    }
+
+    #[test]
+    fn recipient_decodes_return_path() {
+        let recipient = verp().recipient("<bounces+first.last+tag=example.org@MAIL.example.com>");
+
+        assert_eq!(Some(Email::from("first.last+tag@example.org")), recipient);
+    }
+
+    #[test]
+    fn recipient_ignores_other_addresses() {
+        assert_eq!(None, verp().recipient("bounces+user=example.org@other.com"));
+        assert_eq!(None, verp().recipient("postmaster@mail.example.com"));
+    }
+}
@@ -1,11 +1,26 @@
-#![allow(incomplete_features)]
-#![feature(async_fn_in_trait)]
-
+mod ab_test;
+mod activity;
+mod admin;
+mod archive;
+mod automation;
 pub mod config;
 pub mod data;
 pub mod db;
+mod deliverability;
+mod feeds;
+mod forms;
+pub mod inbound;
 pub mod logging;
 mod model;
+mod moderation;
+pub mod outbound;
+mod pages;
+mod protection;
 mod routes;
+mod scheduling;
+mod signing;
 pub mod startup;
 mod store;
+pub mod tracking;
+mod warmup;
+mod webhooks;
@@ -23,13 +23,12 @@ async fn main() -> Result<()> {
     ))
     .expect("Failed to setup TCP listener.");
 
-    run(
-        listener,
-        pool,
-        configuration.admin,
-        configuration.application.subscribed,
-    )
-    .await?;
+    let smtp_listener = configuration.inbound.smtp.as_ref().map(|smtp| {
+        TcpListener::bind(format!("{}:{}", smtp.host, smtp.port))
+            .expect("Failed to setup SMTP listener.")
+    });
+
+    run(listener, smtp_listener, pool, configuration).await?;
 
     Ok(())
 }
@@ -0,0 +1,321 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const VARIANT_NAMES: [&str; 4] = ["A", "B", "C", "D"];
+
+/// What decides the winner of an A/B test.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    #[default]
+    Open,
+    Click,
+}
+
+impl Metric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Metric::Open => "open",
+            Metric::Click => "click",
+        }
+    }
+}
+
+impl TryFrom<String> for Metric {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "open" => Ok(Self::Open),
+            "click" => Ok(Self::Click),
+            other => Err(format!("{other} is not a valid metric.")),
+        }
+    }
+}
+
+/// What a variant changes about the campaign. Fields left out are taken
+/// from the campaign's template.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewVariant {
+    pub subject: Option<String>,
+    pub from_name: Option<String>,
+    // A template whose body replaces the campaign's.
+    pub template_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAbTest {
+    pub template_id: i32,
+    pub variants: Vec<NewVariant>,
+    // Share of the audience, split evenly between the variants, that gets
+    // the test. Everyone else gets the winner.
+    #[serde(default = "default_test_percent")]
+    pub test_percent: i32,
+    // Hours between sending the test and picking the winner.
+    #[serde(default = "default_window_hours")]
+    pub window_hours: i64,
+    #[serde(default)]
+    pub metric: Metric,
+}
+
+fn default_test_percent() -> i32 {
+    20
+}
+
+fn default_window_hours() -> i64 {
+    4
+}
+
+impl NewAbTest {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=VARIANT_NAMES.len()).contains(&self.variants.len()) {
+            return Err(format!(
+                "A test needs 2 to {} variants.",
+                VARIANT_NAMES.len()
+            ));
+        }
+        if !(1..=100).contains(&self.test_percent) {
+            return Err("The test percentage must be between 1 and 100.".to_string());
+        }
+        if self.window_hours < 1 {
+            return Err("The test must run for at least an hour.".to_string());
+        }
+        Ok(())
+    }
+
+    /// The name of the variant at `index`, `A` for the first one.
+    pub fn variant_name(index: usize) -> &'static str {
+        VARIANT_NAMES[index]
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbTest {
+    pub campaign_id: i32,
+    pub test_percent: i32,
+    pub metric: Metric,
+    pub decide_at: DateTime<Utc>,
+    pub winner_id: Option<i32>,
+    // When the winner went out to the remainder.
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub id: i32,
+    pub campaign_id: i32,
+    pub name: String,
+    pub subject: Option<String>,
+    pub from_name: Option<String>,
+    pub template_id: Option<i32>,
+}
+
+/// How many test recipients a variant had, and how many of them opened and
+/// clicked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VariantTally {
+    pub variant_id: i32,
+    pub recipients: i64,
+    pub opened: i64,
+    pub clicked: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantResult {
+    #[serde(flatten)]
+    pub variant: Variant,
+    pub recipients: i64,
+    pub opened: i64,
+    pub clicked: i64,
+    pub open_rate: f64,
+    pub click_rate: f64,
+    pub winner: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AbTestReport {
+    #[serde(flatten)]
+    pub test: AbTest,
+    pub variants: Vec<VariantResult>,
+    // Recipients who got the winner after the test.
+    pub remainder: i64,
+}
+
+/// Puts a subscriber into one of `variants` test groups, or `None` for the
+/// remainder. The same subscriber always lands in the same group of a
+/// campaign, while groups are spread independently across campaigns.
+pub fn assign(
+    campaign_id: i32,
+    subscriber_id: i32,
+    test_percent: i32,
+    variants: usize,
+) -> Option<usize> {
+    let digest = Sha256::digest(format!("{campaign_id}:{subscriber_id}"));
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("A digest has 8 bytes"));
+    ((hash % 100) < test_percent as u64).then(|| ((hash / 100) % variants as u64) as usize)
+}
+
+impl AbTestReport {
+    pub fn new(
+        test: AbTest,
+        variants: Vec<Variant>,
+        tallies: &[VariantTally],
+        remainder: i64,
+    ) -> Self {
+        let rate = |count: i64, base: i64| {
+            if base == 0 {
+                0.0
+            } else {
+                count as f64 / base as f64
+            }
+        };
+        let variants = variants
+            .into_iter()
+            .map(|variant| {
+                let tally = tallies
+                    .iter()
+                    .find(|tally| tally.variant_id == variant.id)
+                    .copied()
+                    .unwrap_or_default();
+                VariantResult {
+                    winner: test.winner_id == Some(variant.id),
+                    recipients: tally.recipients,
+                    opened: tally.opened,
+                    clicked: tally.clicked,
+                    open_rate: rate(tally.opened, tally.recipients),
+                    click_rate: rate(tally.clicked, tally.recipients),
+                    variant,
+                }
+            })
+            .collect();
+        Self {
+            test,
+            variants,
+            remainder,
+        }
+    }
+
+    /// The variant with the best rate on the test's metric. Ties go to the
+    /// earlier variant.
+    pub fn leader(&self) -> Option<&VariantResult> {
+        let score = |result: &VariantResult| match self.test.metric {
+            Metric::Open => result.open_rate,
+            Metric::Click => result.click_rate,
+        };
+        self.variants.iter().fold(None, |best, result| match best {
+            Some(best) if score(best) >= score(result) => Some(best),
+            _ => Some(result),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant(id: i32, name: &str) -> Variant {
+        Variant {
+            id,
+            campaign_id: 1,
+            name: name.to_string(),
+            subject: Some(format!("Subject {name}")),
+            from_name: None,
+            template_id: None,
+        }
+    }
+
+    fn report(metric: Metric, tallies: &[VariantTally]) -> AbTestReport {
+        AbTestReport::new(
+            AbTest {
+                campaign_id: 1,
+                test_percent: 20,
+                metric,
+                decide_at: Utc::now(),
+                winner_id: None,
+                decided_at: None,
+            },
+            vec![variant(1, "A"), variant(2, "B"), variant(3, "C")],
+            tallies,
+            0,
+        )
+    }
+
+    fn tally(variant_id: i32, opened: i64, clicked: i64) -> VariantTally {
+        VariantTally {
+            variant_id,
+            recipients: 10,
+            opened,
+            clicked,
+        }
+    }
+
+    #[test]
+    fn assign_is_deterministic_and_proportional() {
+        let groups: Vec<_> = (0..10_000).map(|id| assign(7, id, 30, 3)).collect();
+
+        assert_eq!(
+            groups,
+            (0..10_000)
+                .map(|id| assign(7, id, 30, 3))
+                .collect::<Vec<_>>()
+        );
+        let tested = groups.iter().flatten().count();
+        assert!((2_700..3_300).contains(&tested), "{tested} tested");
+        for variant in 0..3 {
+            let size = groups
+                .iter()
+                .filter(|group| **group == Some(variant))
+                .count();
+            assert!((800..1_200).contains(&size), "{size} in variant {variant}");
+        }
+        assert!((0..100).all(|id| assign(7, id, 100, 2).is_some()));
+    }
+
+    #[test]
+    fn leader_has_best_rate_on_metric() {
+        let tallies = [tally(1, 5, 0), tally(2, 3, 2), tally(3, 4, 1)];
+
+        assert_eq!(
+            "A",
+            report(Metric::Open, &tallies)
+                .leader()
+                .unwrap()
+                .variant
+                .name
+        );
+        assert_eq!(
+            "B",
+            report(Metric::Click, &tallies)
+                .leader()
+                .unwrap()
+                .variant
+                .name
+        );
+    }
+
+    #[test]
+    fn leader_ties_go_to_earlier_variant() {
+        let report = report(Metric::Open, &[tally(2, 3, 0), tally(3, 3, 0)]);
+
+        assert_eq!("B", report.leader().unwrap().variant.name);
+        assert_eq!(0, report.variants[0].recipients);
+        assert_eq!(0.3, report.variants[1].open_rate);
+    }
+
+    #[test]
+    fn validate_limits_variants() {
+        let new_test = |variants: usize| NewAbTest {
+            template_id: 1,
+            variants: vec![NewVariant::default(); variants],
+            test_percent: 20,
+            window_hours: 4,
+            metric: Metric::Open,
+        };
+
+        assert!(new_test(1).validate().is_err());
+        assert!(new_test(2).validate().is_ok());
+        assert!(new_test(4).validate().is_ok());
+        assert!(new_test(5).validate().is_err());
+    }
+}
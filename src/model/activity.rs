@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What shows up in the live activity stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    // A new subscriber, active or held for a moderator.
+    Signup,
+    // A subscriber became active.
+    Confirmed,
+    Unsubscribed,
+    // A message went out.
+    Sent,
+    // A message was given up on.
+    Failed,
+}
+
+impl ActivityKind {
+    pub const ALL: [ActivityKind; 5] = [
+        ActivityKind::Signup,
+        ActivityKind::Confirmed,
+        ActivityKind::Unsubscribed,
+        ActivityKind::Sent,
+        ActivityKind::Failed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityKind::Signup => "signup",
+            ActivityKind::Confirmed => "confirmed",
+            ActivityKind::Unsubscribed => "unsubscribed",
+            ActivityKind::Sent => "sent",
+            ActivityKind::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for ActivityKind {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        ActivityKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("{s} is not a known kind of activity."))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    // Doubles as the id of the server-sent event.
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: ActivityKind,
+    pub data: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn kinds_round_trip_through_names() {
+        for kind in ActivityKind::ALL {
+            assert_eq!(Ok(kind), ActivityKind::try_from(kind.as_str().to_string()));
+        }
+        assert!(ActivityKind::try_from("opened".to_string()).is_err());
+    }
+
+    #[test]
+    fn activity_is_sent_with_its_type() {
+        let activity = Activity {
+            id: 7,
+            kind: ActivityKind::Signup,
+            data: json!({"email": "ada@example.org"}),
+            created_at: Utc::now(),
+        };
+
+        let json = serde_json::to_value(&activity).unwrap();
+
+        assert_eq!("signup", json["type"]);
+        assert_eq!(activity, serde_json::from_value(json).unwrap());
+    }
+}
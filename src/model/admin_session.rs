@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// Someone signed in to the admin interface.
+#[derive(Debug, Clone)]
+pub struct AdminSession {
+    // Forms of the session repeat it, since other sites can't read it.
+    pub csrf: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AdminSession {
+    /// Makes up a token for a session cookie or form.
+    pub fn generate_token() -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(40)
+            .map(char::from)
+            .collect()
+    }
+
+    /// The hash a session is stored and looked up by, so that the cookie
+    /// can't be read back from the database.
+    pub fn hash(token: &str) -> String {
+        Sha256::digest(token.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Whether a submitted form belongs to the session.
+    pub fn verify(&self, csrf: &str) -> bool {
+        !csrf.is_empty() && self.csrf == csrf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_differ_and_hash_to_hex() {
+        let first = AdminSession::generate_token();
+        let second = AdminSession::generate_token();
+
+        assert_ne!(first, second);
+        assert_eq!(64, AdminSession::hash(&first).len());
+        assert_eq!(AdminSession::hash(&first), AdminSession::hash(&first));
+    }
+
+    #[test]
+    fn verify_wants_session_csrf() {
+        let session = AdminSession {
+            csrf: "abc".to_string(),
+            expires_at: Utc::now(),
+        };
+
+        assert!(session.verify("abc"));
+        assert!(!session.verify("xyz"));
+        assert!(!session.verify(""));
+    }
+}
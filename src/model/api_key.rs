@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// What an API key may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    // Sending transactional messages.
+    Send,
+    // Looking up the delivery status of messages sent with the key.
+    Status,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Send => "send",
+            Scope::Status => "status",
+        }
+    }
+}
+
+impl TryFrom<String> for Scope {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "send" => Ok(Self::Send),
+            "status" => Ok(Self::Status),
+            other => Err(format!("{other} is not a known scope.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Makes up a new secret key.
+    pub fn generate() -> String {
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(40)
+            .map(char::from)
+            .collect();
+        format!("mm_{random}")
+    }
+
+    /// The hash a key is stored and looked up by.
+    pub fn hash(key: &str) -> String {
+        Sha256::digest(key.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.revoked_at.is_none() && self.scopes.contains(&scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_differ() {
+        let first = ApiKey::generate();
+        let second = ApiKey::generate();
+
+        assert!(first.starts_with("mm_"));
+        assert_ne!(first, second);
+        assert_ne!(ApiKey::hash(&first), ApiKey::hash(&second));
+        assert_eq!(64, ApiKey::hash(&first).len());
+    }
+
+    #[test]
+    fn allows_only_granted_scopes() {
+        let mut key = ApiKey {
+            id: 1,
+            name: "App".to_string(),
+            scopes: vec![Scope::Send],
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+
+        assert!(key.allows(Scope::Send));
+        assert!(!key.allows(Scope::Status));
+        key.revoked_at = Some(Utc::now());
+        assert!(!key.allows(Scope::Send));
+    }
+}
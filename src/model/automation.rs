@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What enrolls a subscriber in an automation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    // Subscribing to the list, once the subscriber is active.
+    Subscribed,
+    TagAdded { tag: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    // Opened any mail of this automation.
+    Opened,
+    // Clicked a link in any mail of this automation.
+    Clicked,
+    Attribute {
+        name: String,
+        equals: serde_json::Value,
+    },
+    Tagged {
+        tag: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    Send {
+        template: String,
+    },
+    Wait {
+        #[serde(default)]
+        days: i64,
+        #[serde(default)]
+        hours: i64,
+    },
+    // Jumps forward to step `goto` when the condition holds, and carries on
+    // with the next step otherwise. Jumping past the last step ends the run.
+    Branch {
+        when: Condition,
+        goto: usize,
+    },
+    AddTag {
+        tag: String,
+    },
+    RemoveTag {
+        tag: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAutomation {
+    pub name: String,
+    pub trigger: Trigger,
+    pub steps: Vec<Step>,
+}
+
+// The longest a single wait step may take.
+const MAX_WAIT_DAYS: i64 = 365;
+
+impl NewAutomation {
+    /// Checks that every branch jumps forward, so that a run cannot loop.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.steps.is_empty() {
+            return Err("An automation needs at least one step.".to_string());
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            match step {
+                Step::Branch { goto, .. } if *goto <= index || *goto > self.steps.len() => {
+                    return Err(format!(
+                        "Step {index} must jump forward to at most step {}.",
+                        self.steps.len()
+                    ));
+                }
+                Step::Wait { days, hours } if *days < 0 || *hours < 0 => {
+                    return Err(format!("Step {index} cannot wait a negative time."));
+                }
+                Step::Wait { days, hours }
+                    if *days > MAX_WAIT_DAYS
+                        || *hours > MAX_WAIT_DAYS * 24
+                        || days * 24 + hours > MAX_WAIT_DAYS * 24 =>
+                {
+                    return Err(format!(
+                        "Step {index} cannot wait longer than {MAX_WAIT_DAYS} days."
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Automation {
+    pub id: i32,
+    pub name: String,
+    pub trigger: Trigger,
+    pub steps: Vec<Step>,
+    // Campaign that opens and clicks of the automation's mail count towards.
+    pub campaign_id: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Active,
+    Completed,
+    // Left early, because the subscriber is no longer active.
+    Exited,
+    // Given up on, after a step kept failing.
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Active => "active",
+            RunStatus::Completed => "completed",
+            RunStatus::Exited => "exited",
+            RunStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for RunStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "active" => Ok(Self::Active),
+            "completed" => Ok(Self::Completed),
+            "exited" => Ok(Self::Exited),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("{other} is not a known run status.")),
+        }
+    }
+}
+
+/// The progress of one subscriber through an automation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutomationRun {
+    pub id: i32,
+    pub automation_id: i32,
+    pub subscriber_id: i32,
+    // Index of the next step to take.
+    pub step: i32,
+    pub status: RunStatus,
+    pub wake_at: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    // Times the current step was claimed.
+    pub attempts: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn automation(steps: Vec<Step>) -> NewAutomation {
+        NewAutomation {
+            name: "Welcome".to_string(),
+            trigger: Trigger::Subscribed,
+            steps,
+        }
+    }
+
+    #[test]
+    fn steps_parse_from_json() {
+        let steps: Vec<Step> = serde_json::from_str(
+            r#"[
+                {"type": "send", "template": "welcome"},
+                {"type": "wait", "days": 2},
+                {"type": "branch", "when": {"type": "opened"}, "goto": 4},
+                {"type": "add_tag", "tag": "cold"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(Step::Wait { days: 2, hours: 0 }, steps[1]);
+        assert_eq!(
+            Step::Branch {
+                when: Condition::Opened,
+                goto: 4
+            },
+            steps[2]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_backward_branches() {
+        let looping = automation(vec![
+            Step::Wait { days: 1, hours: 0 },
+            Step::Branch {
+                when: Condition::Clicked,
+                goto: 0,
+            },
+        ]);
+        let ending = automation(vec![Step::Branch {
+            when: Condition::Clicked,
+            goto: 1,
+        }]);
+
+        assert!(looping.validate().is_err());
+        assert!(ending.validate().is_ok());
+        assert!(automation(vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn validate_caps_waits() {
+        let wait = |days, hours| automation(vec![Step::Wait { days, hours }]);
+
+        assert!(wait(365, 0).validate().is_ok());
+        assert!(wait(0, 48).validate().is_ok());
+        assert!(wait(366, 0).validate().is_err());
+        assert!(wait(365, 1).validate().is_err());
+        assert!(wait(i64::MAX, 0).validate().is_err());
+        assert!(wait(0, i64::MAX).validate().is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::Email;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BounceKind {
+    // A permanent failure, such as a mailbox that does not exist.
+    Hard,
+    // A temporary failure, such as a full mailbox.
+    Soft,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bounce {
+    pub email: Email,
+    pub kind: BounceKind,
+    pub status: String,
+    pub diagnostic: Option<String>,
+    pub campaign_id: Option<i32>,
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::EventKind;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewCampaign {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Campaign {
+    pub id: i32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    // What was sent, once the campaign is scheduled.
+    pub template_id: Option<i32>,
+    // Keeps the campaign out of the public archive.
+    pub exclude_from_archive: bool,
+}
+
+/// A sent campaign as it appears in the public archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub campaign_id: i32,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// One hourly rollup row. An empty `url` holds the count for every event of
+/// that kind, a non-empty one the count for a single link.
+#[derive(Debug, Clone)]
+pub struct CampaignStat {
+    pub kind: EventKind,
+    pub url: String,
+    pub hour: DateTime<Utc>,
+    pub total: i64,
+    pub uniques: i64,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::Email;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Complaint {
+    pub email: Email,
+    // The ARF Feedback-Type, usually `abuse`.
+    pub feedback_type: String,
+    pub campaign_id: Option<i32>,
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A message waiting in the outbox for one recipient.
+#[derive(Debug, Clone)]
+pub struct NewDelivery {
+    // Envelope sender, usually a VERP return path.
+    pub sender: String,
+    pub recipient: String,
+    pub raw: Vec<u8>,
+    // The campaign it is part of, which counts it towards warm-up caps.
+    pub campaign_id: Option<i32>,
+    // Recent opens and clicks of the recipient while a warm-up plan is in
+    // place, so that the most engaged get campaign mail first.
+    pub engagement: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub id: i64,
+    pub sender: String,
+    pub recipient: String,
+    pub raw: Vec<u8>,
+    // Failed attempts so far.
+    pub attempts: i32,
+    pub campaign_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    // Queued, or waiting for another attempt.
+    Pending,
+    Sent,
+    // Given up on.
+    Failed,
+}
+
+impl TryFrom<String> for DeliveryStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("{other} is not a known delivery status.")),
+        }
+    }
+}
+
+/// Where a message sent through the API is in the outbox.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryReport {
+    pub id: i64,
+    pub recipient: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    // What went wrong with the last attempt.
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
@@ -3,6 +3,162 @@ use serde::{de::Visitor, Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq)]
 pub struct Email(pub String);
 
+// Local parts that reach a team or a system rather than a person.
+const ROLE_ACCOUNTS: [&str; 22] = [
+    "abuse",
+    "admin",
+    "administrator",
+    "billing",
+    "contact",
+    "enquiries",
+    "hello",
+    "help",
+    "hostmaster",
+    "info",
+    "mail",
+    "marketing",
+    "no-reply",
+    "noreply",
+    "office",
+    "postmaster",
+    "root",
+    "sales",
+    "security",
+    "support",
+    "team",
+    "webmaster",
+];
+
+// Domains that many people have addresses at, and so mistype.
+const COMMON_DOMAINS: [&str; 20] = [
+    "gmail.com",
+    "googlemail.com",
+    "yahoo.com",
+    "yahoo.co.uk",
+    "hotmail.com",
+    "hotmail.co.uk",
+    "outlook.com",
+    "live.com",
+    "msn.com",
+    "icloud.com",
+    "me.com",
+    "aol.com",
+    "mail.com",
+    "gmx.com",
+    "gmx.de",
+    "gmx.net",
+    "web.de",
+    "proton.me",
+    "protonmail.com",
+    "comcast.net",
+];
+
+// Real domains of mail providers that are close enough to a common domain
+// to look like a typo of it, such as `ymail.com` and `gmail.com`.
+const KNOWN_DOMAINS: [&str; 32] = [
+    "ymail.com",
+    "rocketmail.com",
+    "yahoo.ca",
+    "yahoo.de",
+    "yahoo.fr",
+    "yahoo.es",
+    "yahoo.it",
+    "yahoo.in",
+    "yahoo.com.au",
+    "hotmail.ca",
+    "hotmail.de",
+    "hotmail.fr",
+    "hotmail.es",
+    "hotmail.it",
+    "live.ca",
+    "live.de",
+    "live.fr",
+    "live.nl",
+    "live.co.uk",
+    "outlook.de",
+    "outlook.fr",
+    "mac.com",
+    "aim.com",
+    "email.com",
+    "mail.de",
+    "gmx.at",
+    "gmx.ch",
+    "gmx.us",
+    "gmx.fr",
+    "pm.me",
+    "att.net",
+    "cox.net",
+];
+
+// Domains shorter than this are too short to tell a typo from another
+// domain.
+const MIN_TYPO_DOMAIN_LENGTH: usize = 8;
+// How many edits away from a common domain still looks like a typo.
+const MAX_TYPO_DISTANCE: usize = 1;
+
+impl Email {
+    /// The part before the `@`.
+    pub fn local_part(&self) -> &str {
+        self.0.rsplit_once('@').map_or(&self.0, |(local, _)| local)
+    }
+
+    /// The part after the `@`, as written.
+    pub fn domain(&self) -> &str {
+        self.0.rsplit_once('@').map_or("", |(_, domain)| domain)
+    }
+
+    /// Whether the address belongs to a role, like `info@` or `admin@`,
+    /// rather than a person. A `+tag` is ignored.
+    pub fn is_role_account(&self) -> bool {
+        let local = self.local_part().to_lowercase();
+        let local = local.split('+').next().unwrap_or_default();
+        ROLE_ACCOUNTS.contains(&local)
+    }
+
+    /// The address with a mistyped common domain corrected, such as
+    /// `gmail.com` for `gmial.com`. Known domains of other providers and
+    /// short domains are taken as they are.
+    pub fn suggestion(&self) -> Option<Email> {
+        let domain = self.domain().to_lowercase();
+        if domain.chars().count() < MIN_TYPO_DOMAIN_LENGTH
+            || COMMON_DOMAINS.contains(&domain.as_str())
+            || KNOWN_DOMAINS.contains(&domain.as_str())
+        {
+            return None;
+        }
+        COMMON_DOMAINS
+            .iter()
+            .map(|common| (edit_distance(&domain, common), common))
+            .filter(|(distance, _)| *distance <= MAX_TYPO_DISTANCE)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, common)| Email(format!("{}@{common}", self.local_part())))
+    }
+}
+
+/// The Levenshtein distance, counting a swap of neighbours as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
 impl From<String> for Email {
     fn from(email: String) -> Self {
         Email(email)
@@ -56,3 +212,70 @@ impl<'de> Visitor<'de> for StringVisitor {
         Ok(value.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_address() {
+        let email = Email::from("ada+news@Example.org");
+
+        assert_eq!("ada+news", email.local_part());
+        assert_eq!("Example.org", email.domain());
+    }
+
+    #[test]
+    fn recognises_role_accounts() {
+        assert!(Email::from("info@example.org").is_role_account());
+        assert!(Email::from("Admin+lists@example.org").is_role_account());
+        assert!(!Email::from("ada@example.org").is_role_account());
+        assert!(!Email::from("information@example.org").is_role_account());
+    }
+
+    #[test]
+    fn suggests_common_domains() {
+        let suggestion = |email: &str| Email::from(email).suggestion().map(|email| email.0);
+
+        assert_eq!(
+            Some("ada@gmail.com".to_string()),
+            suggestion("ada@gmial.com")
+        );
+        assert_eq!(
+            Some("ada@gmail.com".to_string()),
+            suggestion("ada@gmail.con")
+        );
+        assert_eq!(
+            Some("Ada@hotmail.com".to_string()),
+            suggestion("Ada@HOTMAL.COM")
+        );
+        assert_eq!(None, suggestion("ada@gmx.net"));
+        assert_eq!(None, suggestion("ada@example.org"));
+    }
+
+    #[test]
+    fn leaves_real_domains_alone() {
+        let suggestion = |email: &str| Email::from(email).suggestion().map(|email| email.0);
+
+        for domain in [
+            "ymail.com",
+            "email.com",
+            "mac.com",
+            "yahoo.ca",
+            "live.ca",
+            "gmx.at",
+            "gmx.ch",
+        ] {
+            assert_eq!(None, suggestion(&format!("ada@{domain}")), "{domain}");
+        }
+        // Two edits away is too far to be sure.
+        assert_eq!(None, suggestion("ada@gmal.co"));
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        assert_eq!(0, edit_distance("gmail.com", "gmail.com"));
+        assert_eq!(1, edit_distance("gmial.com", "gmail.com"));
+        assert_eq!(2, edit_distance("gmal.co", "gmail.com"));
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Delivered,
+    Bounce,
+    Open,
+    Click,
+    Unsubscribe,
+    Complaint,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Delivered => "delivered",
+            EventKind::Bounce => "bounce",
+            EventKind::Open => "open",
+            EventKind::Click => "click",
+            EventKind::Unsubscribe => "unsubscribe",
+            EventKind::Complaint => "complaint",
+        }
+    }
+}
+
+impl TryFrom<String> for EventKind {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "delivered" => Ok(Self::Delivered),
+            "bounce" => Ok(Self::Bounce),
+            "open" => Ok(Self::Open),
+            "click" => Ok(Self::Click),
+            "unsubscribe" => Ok(Self::Unsubscribe),
+            "complaint" => Ok(Self::Complaint),
+            other => Err(format!("{other} is not a known event kind.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewEvent {
+    pub kind: EventKind,
+    pub subscriber_id: i32,
+    pub campaign_id: Option<i32>,
+    pub url: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: i64,
+    pub kind: EventKind,
+    pub subscriber_id: i32,
+    pub campaign_id: Option<i32>,
+    pub url: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewFeed {
+    pub name: String,
+    // An http(s) URL or a path on the server.
+    pub source: String,
+    // When to look for new entries, in cron syntax.
+    pub schedule: String,
+    // The digest template, which lists the new entries as `items`.
+    pub template_id: i32,
+    // Whether entries published before the feed was added go out in the
+    // first digest, rather than only the ones published after.
+    #[serde(default)]
+    pub send_existing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feed {
+    pub id: i32,
+    pub name: String,
+    pub source: String,
+    pub schedule: String,
+    pub template_id: i32,
+    pub next_run_at: DateTime<Utc>,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One entry of a feed, as it is handed to the digest template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedItem {
+    // The entry's id in the feed, which tells entries that were sent apart.
+    #[serde(skip)]
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub summary: String,
+    pub published: Option<DateTime<Utc>>,
+}
+
+/// The first time after `after` that a cron schedule fires, in `timezone`.
+/// Schedules have five fields (minute, hour, day of month, month, day of
+/// week) or six, with seconds first.
+pub fn next_run<Tz: TimeZone>(
+    schedule: &str,
+    timezone: &Tz,
+    after: DateTime<Utc>,
+) -> Result<DateTime<Utc>, String> {
+    let schedule = match schedule.split_whitespace().count() {
+        5 => format!("0 {schedule}"),
+        _ => schedule.to_string(),
+    };
+    Schedule::from_str(&schedule)
+        .map_err(|e| format!("{schedule} is not a valid schedule: {e}"))?
+        .after(&after.with_timezone(timezone))
+        .next()
+        .map(|next| next.with_timezone(&Utc))
+        .ok_or_else(|| format!("{schedule} never fires."))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+
+    #[test]
+    fn next_run_takes_five_fields_in_timezone() {
+        let after = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+
+        let next = next_run("0 9 * * Mon", &Berlin, after).unwrap();
+
+        assert_eq!(Utc.with_ymd_and_hms(2023, 5, 8, 7, 0, 0).unwrap(), next);
+    }
+
+    #[test]
+    fn next_run_takes_seconds() {
+        let after = Utc.with_ymd_and_hms(2023, 5, 1, 12, 0, 0).unwrap();
+
+        let next = next_run("30 0 13 * * *", &Utc, after).unwrap();
+
+        assert_eq!(Utc.with_ymd_and_hms(2023, 5, 1, 13, 0, 30).unwrap(), next);
+    }
+
+    #[test]
+    fn next_run_rejects_nonsense() {
+        assert!(next_run("every monday", &Utc, Utc::now()).is_err());
+    }
+}
@@ -1,6 +1,52 @@
+mod ab_test;
+mod activity;
+mod admin_session;
+mod api_key;
+mod automation;
+mod bounce;
+mod campaign;
+mod complaint;
+mod delivery;
 mod email;
+mod event;
+mod feed;
+mod post;
+mod report;
 mod subscriber;
+mod suppression;
+mod template;
+mod warmup;
+mod webhook;
 
+pub use ab_test::{assign, AbTest, AbTestReport, NewAbTest, Variant, VariantTally};
+// Only named by tests for now.
+#[allow(unused_imports)]
+pub use ab_test::{Metric, NewVariant};
+pub use activity::{Activity, ActivityKind};
+pub use admin_session::AdminSession;
+pub use api_key::{ApiKey, NewApiKey, Scope};
+pub use automation::{
+    Automation, AutomationRun, Condition, NewAutomation, RunStatus, Step, Trigger,
+};
+pub use bounce::{Bounce, BounceKind};
+pub use campaign::{Campaign, CampaignStat, Issue, NewCampaign};
+pub use complaint::Complaint;
+pub use delivery::{Delivery, DeliveryReport, NewDelivery};
+// Only named by tests for now.
+#[allow(unused_imports)]
+pub use delivery::DeliveryStatus;
 pub use email::Email;
+pub use event::{Event, EventKind, NewEvent};
+pub use feed::{next_run, Feed, FeedItem, NewFeed};
+pub use post::{NewPost, Post, PostStatus};
+pub use report::{CampaignReport, CampaignSummary, Tally};
 pub use subscriber::NewSubscriber;
 pub use subscriber::Subscriber;
+pub use subscriber::SubscriberStatus;
+pub use suppression::Suppression;
+pub use template::{NewTemplate, Rendered, Template};
+pub use warmup::{NewWarmupPlan, WarmupPlan, WarmupReport};
+pub use webhook::{NewWebhook, Webhook, WebhookCall, WebhookDelivery, WebhookEvent};
+// Only named by tests for now.
+#[allow(unused_imports)]
+pub use webhook::WebhookStatus;
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Email;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    Accepted,
+    // Sent by someone who is not a member, waiting for a moderator.
+    Held,
+    Rejected,
+}
+
+impl PostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Accepted => "accepted",
+            PostStatus::Held => "held",
+            PostStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl TryFrom<String> for PostStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "accepted" => Ok(Self::Accepted),
+            "held" => Ok(Self::Held),
+            "rejected" => Ok(Self::Rejected),
+            other => Err(format!("{other} is not a known post status.")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewPost {
+    pub sender: Email,
+    pub subject: String,
+    pub raw: Vec<u8>,
+    pub status: PostStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Post {
+    pub id: i32,
+    pub sender: Email,
+    pub subject: String,
+    #[serde(skip)]
+    pub raw: Vec<u8>,
+    pub status: PostStatus,
+    // Why a moderator rejected the post.
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
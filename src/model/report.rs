@@ -0,0 +1,361 @@
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Serialize;
+
+use crate::model::{AbTestReport, Campaign, CampaignStat, EventKind};
+
+const TIMELINE_HOURS: i64 = 48;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub total: i64,
+    pub unique: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Rates {
+    pub bounce: f64,
+    pub open: f64,
+    pub click: f64,
+    pub unsubscribe: f64,
+    pub complaint: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignSummary {
+    pub campaign: Campaign,
+    pub delivered: Tally,
+    pub bounced: Tally,
+    pub opened: Tally,
+    pub clicked: Tally,
+    pub unsubscribed: Tally,
+    pub complained: Tally,
+    pub rates: Rates,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkReport {
+    pub url: String,
+    pub clicks: Tally,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineBucket {
+    pub hour: DateTime<Utc>,
+    pub opened: i64,
+    pub clicked: i64,
+    pub bounced: i64,
+    pub unsubscribed: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CampaignReport {
+    #[serde(flatten)]
+    pub summary: CampaignSummary,
+    pub links: Vec<LinkReport>,
+    pub timeline: Vec<TimelineBucket>,
+    // How the variants did, when the campaign was sent as an A/B test.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ab_test: Option<AbTestReport>,
+}
+
+impl CampaignSummary {
+    pub fn new(campaign: Campaign, stats: &[CampaignStat]) -> Self {
+        let tally = |kind: EventKind| {
+            stats
+                .iter()
+                .filter(|stat| stat.kind == kind && stat.url.is_empty())
+                .fold(Tally::default(), |tally, stat| Tally {
+                    total: tally.total + stat.total,
+                    unique: tally.unique + stat.uniques,
+                })
+        };
+
+        let delivered = tally(EventKind::Delivered);
+        let bounced = tally(EventKind::Bounce);
+        let opened = tally(EventKind::Open);
+        let clicked = tally(EventKind::Click);
+        let unsubscribed = tally(EventKind::Unsubscribe);
+        let complained = tally(EventKind::Complaint);
+
+        let rate = |count: i64, base: i64| {
+            if base == 0 {
+                0.0
+            } else {
+                count as f64 / base as f64
+            }
+        };
+        let rates = Rates {
+            bounce: rate(bounced.unique, delivered.unique + bounced.unique),
+            open: rate(opened.unique, delivered.unique),
+            click: rate(clicked.unique, delivered.unique),
+            unsubscribe: rate(unsubscribed.unique, delivered.unique),
+            complaint: rate(complained.unique, delivered.unique),
+        };
+
+        Self {
+            campaign,
+            delivered,
+            bounced,
+            opened,
+            clicked,
+            unsubscribed,
+            complained,
+            rates,
+        }
+    }
+}
+
+impl CampaignReport {
+    pub fn new(campaign: Campaign, stats: &[CampaignStat]) -> Self {
+        let mut links: Vec<LinkReport> = Vec::new();
+        for stat in stats
+            .iter()
+            .filter(|stat| stat.kind == EventKind::Click && !stat.url.is_empty())
+        {
+            match links.iter_mut().find(|link| link.url == stat.url) {
+                Some(link) => {
+                    link.clicks.total += stat.total;
+                    link.clicks.unique += stat.uniques;
+                }
+                None => links.push(LinkReport {
+                    url: stat.url.clone(),
+                    clicks: Tally {
+                        total: stat.total,
+                        unique: stat.uniques,
+                    },
+                }),
+            }
+        }
+        links.sort_by_key(|link| std::cmp::Reverse(link.clicks.total));
+
+        let start = campaign
+            .sent_at
+            .unwrap_or(campaign.created_at)
+            .duration_trunc(Duration::hours(1))
+            .expect("An hour always fits in a timestamp");
+        let timeline = (0..TIMELINE_HOURS)
+            .map(|offset| {
+                let hour = start + Duration::hours(offset);
+                let count = |kind: EventKind| {
+                    stats
+                        .iter()
+                        .filter(|stat| {
+                            stat.kind == kind && stat.url.is_empty() && stat.hour == hour
+                        })
+                        .map(|stat| stat.total)
+                        .sum()
+                };
+                TimelineBucket {
+                    hour,
+                    opened: count(EventKind::Open),
+                    clicked: count(EventKind::Click),
+                    bounced: count(EventKind::Bounce),
+                    unsubscribed: count(EventKind::Unsubscribe),
+                }
+            })
+            .collect();
+
+        Self {
+            summary: CampaignSummary::new(campaign, stats),
+            links,
+            timeline,
+            ab_test: None,
+        }
+    }
+}
+
+impl CampaignSummary {
+    pub fn to_csv(summaries: &[CampaignSummary]) -> String {
+        let mut csv = String::from(
+            "id,name,sent_at,delivered,bounced,opened,unique_opened,clicked,unique_clicked,\
+             unsubscribed,complained,bounce_rate,open_rate,click_rate,unsubscribe_rate,complaint_rate\n",
+        );
+        for summary in summaries {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                summary.campaign.id,
+                escape(&summary.campaign.name),
+                summary
+                    .campaign
+                    .sent_at
+                    .map(|sent_at| sent_at.to_rfc3339())
+                    .unwrap_or_default(),
+                summary.delivered.unique,
+                summary.bounced.unique,
+                summary.opened.total,
+                summary.opened.unique,
+                summary.clicked.total,
+                summary.clicked.unique,
+                summary.unsubscribed.unique,
+                summary.complained.unique,
+                summary.rates.bounce,
+                summary.rates.open,
+                summary.rates.click,
+                summary.rates.unsubscribe,
+                summary.rates.complaint,
+            ));
+        }
+        csv
+    }
+}
+
+impl CampaignReport {
+    /// Renders the summary row, the clicks per link and the hourly timeline
+    /// as three CSV tables separated by blank lines.
+    pub fn to_csv(&self) -> String {
+        let mut csv = CampaignSummary::to_csv(std::slice::from_ref(&self.summary));
+
+        csv.push_str("\nurl,clicked,unique_clicked\n");
+        for link in &self.links {
+            csv.push_str(&format!(
+                "{},{},{}\n",
+                escape(&link.url),
+                link.clicks.total,
+                link.clicks.unique
+            ));
+        }
+
+        csv.push_str("\nhour,opened,clicked,bounced,unsubscribed\n");
+        for bucket in &self.timeline {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                bucket.hour.to_rfc3339(),
+                bucket.opened,
+                bucket.clicked,
+                bucket.bounced,
+                bucket.unsubscribed
+            ));
+        }
+
+        if let Some(ab_test) = &self.ab_test {
+            csv.push_str(
+                "\nvariant,subject,from_name,template_id,recipients,opened,clicked,\
+                 open_rate,click_rate,winner\n",
+            );
+            for result in &ab_test.variants {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
+                    result.variant.name,
+                    escape(result.variant.subject.as_deref().unwrap_or_default()),
+                    escape(result.variant.from_name.as_deref().unwrap_or_default()),
+                    result
+                        .variant
+                        .template_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_default(),
+                    result.recipients,
+                    result.opened,
+                    result.clicked,
+                    result.open_rate,
+                    result.click_rate,
+                    result.winner,
+                ));
+            }
+        }
+        csv
+    }
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn campaign() -> Campaign {
+        Campaign {
+            id: 1,
+            name: "Launch".to_string(),
+            created_at: Utc.with_ymd_and_hms(2023, 3, 1, 8, 0, 0).unwrap(),
+            sent_at: Some(Utc.with_ymd_and_hms(2023, 3, 1, 9, 30, 0).unwrap()),
+            template_id: None,
+            exclude_from_archive: false,
+        }
+    }
+
+    fn stat(kind: EventKind, url: &str, hour: u32, total: i64, uniques: i64) -> CampaignStat {
+        CampaignStat {
+            kind,
+            url: url.to_string(),
+            hour: Utc.with_ymd_and_hms(2023, 3, 1, hour, 0, 0).unwrap(),
+            total,
+            uniques,
+        }
+    }
+
+    #[test]
+    fn summary_adds_up_hours_and_computes_rates() {
+        let stats = [
+            stat(EventKind::Delivered, "", 9, 10, 10),
+            stat(EventKind::Open, "", 9, 6, 4),
+            stat(EventKind::Open, "", 10, 3, 1),
+            stat(EventKind::Click, "", 10, 2, 2),
+            stat(EventKind::Click, "https://example.com", 10, 2, 2),
+        ];
+
+        let summary = CampaignSummary::new(campaign(), &stats);
+
+        assert_eq!(
+            Tally {
+                total: 9,
+                unique: 5
+            },
+            summary.opened
+        );
+        assert_eq!(
+            Tally {
+                total: 2,
+                unique: 2
+            },
+            summary.clicked
+        );
+        assert_eq!(0.5, summary.rates.open);
+        assert_eq!(0.2, summary.rates.click);
+        assert_eq!(0.0, summary.rates.bounce);
+    }
+
+    #[test]
+    fn report_groups_links_and_buckets_first_two_days() {
+        let stats = [
+            stat(EventKind::Open, "", 9, 6, 4),
+            stat(EventKind::Click, "", 11, 3, 2),
+            stat(EventKind::Click, "https://a.example.com", 11, 1, 1),
+            stat(EventKind::Click, "https://b.example.com", 11, 2, 1),
+        ];
+
+        let report = CampaignReport::new(campaign(), &stats);
+
+        assert_eq!("https://b.example.com", report.links[0].url);
+        assert_eq!(2, report.links.len());
+        assert_eq!(48, report.timeline.len());
+        assert_eq!(6, report.timeline[0].opened);
+        assert_eq!(3, report.timeline[2].clicked);
+        assert_eq!(0, report.timeline[1].clicked);
+    }
+
+    #[test]
+    fn rates_are_zero_without_deliveries() {
+        let summary = CampaignSummary::new(campaign(), &[stat(EventKind::Open, "", 9, 1, 1)]);
+
+        assert_eq!(Rates::default(), summary.rates);
+    }
+
+    #[test]
+    fn summary_csv_quotes_names() {
+        let mut campaign = campaign();
+        campaign.name = "Hello, \"world\"".to_string();
+
+        let csv = CampaignSummary::to_csv(&[CampaignSummary::new(campaign, &[])]);
+
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.starts_with("1,\"Hello, \"\"world\"\"\",2023-03-01T09:30:00+00:00,"));
+    }
+}
@@ -2,6 +2,41 @@ use serde::{Deserialize, Serialize};
 
 use crate::model::Email;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriberStatus {
+    // Waiting for a moderator to approve the signup.
+    Pending,
+    Active,
+    Bounced,
+    Complained,
+}
+
+impl SubscriberStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriberStatus::Pending => "pending",
+            SubscriberStatus::Active => "active",
+            SubscriberStatus::Bounced => "bounced",
+            SubscriberStatus::Complained => "complained",
+        }
+    }
+}
+
+impl TryFrom<String> for SubscriberStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "pending" => Ok(Self::Pending),
+            "active" => Ok(Self::Active),
+            "bounced" => Ok(Self::Bounced),
+            "complained" => Ok(Self::Complained),
+            other => Err(format!("{other} is not a known subscriber status.")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewSubscriber {
     pub email: Email,
@@ -11,4 +46,9 @@ pub struct NewSubscriber {
 pub struct Subscriber {
     pub id: i32,
     pub email: Email,
+    pub status: SubscriberStatus,
+    // Receives discussion list posts in a daily digest instead of one by one.
+    pub digest: bool,
+    // IANA timezone, for sending at a local time.
+    pub timezone: Option<String>,
 }
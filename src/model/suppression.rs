@@ -0,0 +1,11 @@
+use crate::model::Email;
+use chrono::{DateTime, Utc};
+
+/// An address that gets no mail from the list, whatever it asks for.
+#[derive(Debug, Clone)]
+pub struct Suppression {
+    pub email: Email,
+    // Like `complaint` or `admin`.
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
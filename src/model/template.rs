@@ -0,0 +1,198 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::model::Email;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewTemplate {
+    pub name: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub id: i32,
+    pub name: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A template filled in for one subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+impl Template {
+    /// Replaces `{{ email }}` and `{{ <attribute> }}` placeholders with the
+    /// subscriber's address and attributes. Unknown placeholders are left
+    /// empty, and values are escaped in the HTML part. A section such as
+    /// `{{#items}}...{{/items}}` is repeated for every object in the `items`
+    /// list, with the object's fields as placeholders.
+    pub fn render(&self, email: &Email, attributes: &Map<String, Value>) -> Rendered {
+        Rendered {
+            subject: render(&self.subject, email, attributes, false),
+            text: render(&self.text, email, attributes, false),
+            html: self
+                .html
+                .as_ref()
+                .map(|html| render(html, email, attributes, true)),
+        }
+    }
+}
+
+fn render(template: &str, email: &Email, attributes: &Map<String, Value>, html: bool) -> String {
+    let lookup = |name: &str| -> String {
+        let value = if name == "email" {
+            email.0.clone()
+        } else {
+            match attributes.get(name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            }
+        };
+        if html {
+            escape(&value)
+        } else {
+            value
+        }
+    };
+
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{#") {
+        let Some(close) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 3..start + close].trim();
+        let end_tag = format!("{{{{/{name}}}}}");
+        let body_start = start + close + 2;
+        let Some(body_length) = rest[body_start..].find(&end_tag) else {
+            break;
+        };
+
+        output.push_str(&fill(&rest[..start], lookup));
+        if let Some(Value::Array(items)) = attributes.get(name) {
+            let body = &rest[body_start..body_start + body_length];
+            for item in items {
+                let mut scope = attributes.clone();
+                if let Value::Object(fields) = item {
+                    scope.extend(fields.clone());
+                }
+                output.push_str(&render(body, email, &scope, html));
+            }
+        }
+        rest = &rest[body_start + body_length + end_tag.len()..];
+    }
+    output.push_str(&fill(rest, lookup));
+    output
+}
+
+fn fill(template: &str, value: impl Fn(&str) -> String) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        output.push_str(&value(rest[start + 2..start + end].trim()));
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn template(html: Option<&str>) -> Template {
+        Template {
+            id: 1,
+            name: "welcome".to_string(),
+            subject: "Welcome, {{ name }}".to_string(),
+            text: "Hi {{name}}, you signed up as {{ email }}.{{ missing }}".to_string(),
+            html: html.map(str::to_string),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn render_fills_in_placeholders() {
+        let attributes = json!({"name": "Ada"}).as_object().unwrap().clone();
+
+        let rendered = template(None).render(&Email::from("ada@example.com"), &attributes);
+
+        assert_eq!("Welcome, Ada", rendered.subject);
+        assert_eq!("Hi Ada, you signed up as ada@example.com.", rendered.text);
+        assert_eq!(None, rendered.html);
+    }
+
+    #[test]
+    fn render_escapes_html() {
+        let attributes = json!({"name": "<b>Ada</b>", "plan": 3})
+            .as_object()
+            .unwrap()
+            .clone();
+        let template = template(Some("<p>{{ name }} ({{ plan }})</p>"));
+
+        let rendered = template.render(&Email::from("ada@example.com"), &attributes);
+
+        assert_eq!("Welcome, <b>Ada</b>", rendered.subject);
+        assert_eq!(
+            Some("<p>&lt;b&gt;Ada&lt;/b&gt; (3)</p>".to_string()),
+            rendered.html
+        );
+    }
+
+    #[test]
+    fn render_keeps_unclosed_braces() {
+        let mut template = template(None);
+        template.subject = "Hello {{ name".to_string();
+
+        let rendered = template.render(&Email::from("ada@example.com"), &Map::new());
+
+        assert_eq!("Hello {{ name", rendered.subject);
+    }
+
+    #[test]
+    fn render_repeats_sections_for_each_item() {
+        let attributes = json!({
+            "name": "Ada",
+            "items": [{"title": "First & best"}, {"title": "Second"}]
+        })
+        .as_object()
+        .unwrap()
+        .clone();
+        let mut template = template(Some("<ul>{{#items}}<li>{{ title }}</li>{{/items}}</ul>"));
+        template.text =
+            "{{#items}}- {{ title }} for {{ name }}\n{{/items}}{{#none}}x{{/none}}".to_string();
+
+        let rendered = template.render(&Email::from("ada@example.com"), &attributes);
+
+        assert_eq!("- First & best for Ada\n- Second for Ada\n", rendered.text);
+        assert_eq!(
+            Some("<ul><li>First &amp; best</li><li>Second</li></ul>".to_string()),
+            rendered.html
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+// Longer plans are more likely a typo than a warm-up.
+const MAX_DAYS: usize = 365;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewWarmupPlan {
+    pub name: String,
+    // The first day of the plan, in the list's timezone.
+    pub starts_on: NaiveDate,
+    // How many campaign messages may go out on each day of the plan.
+    pub daily_caps: Vec<i32>,
+}
+
+impl NewWarmupPlan {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_DAYS).contains(&self.daily_caps.len()) {
+            return Err(format!("A plan needs 1 to {MAX_DAYS} daily caps."));
+        }
+        if self.daily_caps.iter().any(|cap| *cap < 1) {
+            return Err("Daily caps must be at least 1.".to_string());
+        }
+        if self.daily_caps.windows(2).any(|caps| caps[1] < caps[0]) {
+            return Err("Daily caps must not decrease from one day to the next.".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmupPlan {
+    pub id: i32,
+    pub name: String,
+    pub starts_on: NaiveDate,
+    pub daily_caps: Vec<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WarmupPlan {
+    /// The day of the plan `date` falls on, counting from 1, or `None`
+    /// before the plan starts or after it is over.
+    pub fn day(&self, date: NaiveDate) -> Option<usize> {
+        let day = usize::try_from((date - self.starts_on).num_days()).ok()?;
+        (day < self.daily_caps.len()).then_some(day + 1)
+    }
+
+    /// The cap on campaign mail on `date`, or `None` when the plan does not
+    /// limit that day.
+    pub fn cap(&self, date: NaiveDate) -> Option<i32> {
+        self.day(date).map(|day| self.daily_caps[day - 1])
+    }
+}
+
+/// How far a warm-up plan got today.
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupReport {
+    pub plan: WarmupPlan,
+    pub today: NaiveDate,
+    pub day: Option<usize>,
+    pub cap: Option<i32>,
+    // Campaign messages sent today.
+    pub sent: i64,
+    // Campaign messages waiting in the outbox, including those carried over
+    // to a later day.
+    pub waiting: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 6, day).unwrap()
+    }
+
+    fn new_plan(daily_caps: Vec<i32>) -> NewWarmupPlan {
+        NewWarmupPlan {
+            name: "New domain".to_string(),
+            starts_on: date(1),
+            daily_caps,
+        }
+    }
+
+    #[test]
+    fn validate_wants_growing_caps() {
+        assert!(new_plan(vec![50, 100, 100, 200]).validate().is_ok());
+        assert!(new_plan(vec![]).validate().is_err());
+        assert!(new_plan(vec![0, 100]).validate().is_err());
+        assert!(new_plan(vec![100, 50]).validate().is_err());
+    }
+
+    #[test]
+    fn cap_only_applies_during_plan() {
+        let plan = WarmupPlan {
+            id: 1,
+            name: "New domain".to_string(),
+            starts_on: date(10),
+            daily_caps: vec![50, 100, 200],
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(None, plan.cap(date(9)));
+        assert_eq!(Some(50), plan.cap(date(10)));
+        assert_eq!(Some(3), plan.day(date(12)));
+        assert_eq!(Some(200), plan.cap(date(12)));
+        assert_eq!(None, plan.cap(date(13)));
+    }
+}
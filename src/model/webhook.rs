@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What a webhook endpoint can be told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "subscriber.subscribed")]
+    Subscribed,
+    #[serde(rename = "subscriber.unsubscribed")]
+    Unsubscribed,
+    #[serde(rename = "subscriber.bounced")]
+    Bounced,
+    #[serde(rename = "subscriber.complained")]
+    Complained,
+    #[serde(rename = "delivery.sent")]
+    Sent,
+    #[serde(rename = "delivery.failed")]
+    Failed,
+    #[serde(rename = "delivery.opened")]
+    Opened,
+    #[serde(rename = "delivery.clicked")]
+    Clicked,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 8] = [
+        WebhookEvent::Subscribed,
+        WebhookEvent::Unsubscribed,
+        WebhookEvent::Bounced,
+        WebhookEvent::Complained,
+        WebhookEvent::Sent,
+        WebhookEvent::Failed,
+        WebhookEvent::Opened,
+        WebhookEvent::Clicked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Subscribed => "subscriber.subscribed",
+            WebhookEvent::Unsubscribed => "subscriber.unsubscribed",
+            WebhookEvent::Bounced => "subscriber.bounced",
+            WebhookEvent::Complained => "subscriber.complained",
+            WebhookEvent::Sent => "delivery.sent",
+            WebhookEvent::Failed => "delivery.failed",
+            WebhookEvent::Opened => "delivery.opened",
+            WebhookEvent::Clicked => "delivery.clicked",
+        }
+    }
+}
+
+impl TryFrom<String> for WebhookEvent {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        WebhookEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == s)
+            .ok_or_else(|| format!("{s} is not a known webhook event."))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    // Made up when left out.
+    pub secret: Option<String>,
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), String> {
+        match Url::parse(&self.url) {
+            Ok(url) if ["http", "https"].contains(&url.scheme()) => {}
+            _ => return Err(format!("{} is not an http(s) URL.", self.url)),
+        }
+        if self.events.is_empty() {
+            return Err("A webhook needs at least one event.".to_string());
+        }
+        if self.secret.as_ref().is_some_and(|secret| secret.len() < 16) {
+            return Err("The secret must have at least 16 characters.".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Makes up a new signing secret.
+    pub fn generate_secret() -> String {
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        format!("whsec_{random}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookStatus {
+    // Queued, or waiting for another call.
+    Pending,
+    Delivered,
+    // Given up on.
+    Failed,
+}
+
+impl TryFrom<String> for WebhookStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("{other} is not a known webhook status.")),
+        }
+    }
+}
+
+/// An event on its way to an endpoint, as it appears in the endpoint's log.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event: WebhookEvent,
+    pub payload: Value,
+    pub status: WebhookStatus,
+    pub attempts: i32,
+    // What the endpoint answered the last call with.
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A delivery that is due, with where it goes and how to sign it.
+#[derive(Debug, Clone)]
+pub struct WebhookCall {
+    pub id: i64,
+    pub url: String,
+    pub secret: String,
+    pub payload: Value,
+    // Failed calls so far.
+    pub attempts: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_webhook(url: &str, secret: Option<&str>) -> NewWebhook {
+        NewWebhook {
+            url: url.to_string(),
+            events: vec![WebhookEvent::Subscribed],
+            secret: secret.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn events_round_trip_through_names() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(
+                Ok(event),
+                WebhookEvent::try_from(event.as_str().to_string())
+            );
+            assert_eq!(
+                format!("\"{}\"", event.as_str()),
+                serde_json::to_string(&event).unwrap()
+            );
+        }
+        assert!(WebhookEvent::try_from("subscriber.deleted".to_string()).is_err());
+    }
+
+    #[test]
+    fn validate_wants_http_url_and_long_secret() {
+        assert!(new_webhook("https://crm.example.com/hooks", None)
+            .validate()
+            .is_ok());
+        assert!(new_webhook("ftp://crm.example.com/hooks", None)
+            .validate()
+            .is_err());
+        assert!(new_webhook("https://crm.example.com/hooks", Some("short"))
+            .validate()
+            .is_err());
+        assert!(NewWebhook {
+            events: vec![],
+            ..new_webhook("https://crm.example.com/hooks", None)
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,7 @@
+mod notify;
+mod queue;
+mod token;
+
+pub(crate) use notify::held_post;
+pub(crate) use queue::{approve_post, approve_signup, reject_post, reject_signup, signup};
+pub use token::{Action, Item, ModerationToken};
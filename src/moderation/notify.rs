@@ -0,0 +1,99 @@
+use anyhow::Result;
+use lettre::{message::Mailbox, Message};
+
+use crate::{
+    data::ApplicationData,
+    model::{Email, Post, Subscriber},
+    moderation::{Action, Item, ModerationToken},
+    outbound::{self, AutoSubmitted},
+    signing::Signer,
+};
+
+/// Asks the moderators to approve or reject a held post.
+pub(crate) async fn held_post(data: &ApplicationData, post: &Post) -> Result<()> {
+    let body = format!(
+        "{} sent a post to {} that needs approval.\n\n\
+         Subject: {}\n\n\
+         Approve: {}\n\
+         Reject: {}\n",
+        post.sender.0,
+        data.list.name,
+        post.subject,
+        link(data, Item::Post, post.id, Action::Approve),
+        link(data, Item::Post, post.id, Action::Reject),
+    );
+    to_moderators(
+        data,
+        format!(
+            "Post to {} awaits approval: {}",
+            data.list.name, post.subject
+        ),
+        body,
+    )
+    .await
+}
+
+/// Asks the moderators to approve or reject a new subscriber.
+pub(crate) async fn pending_signup(data: &ApplicationData, subscriber: &Subscriber) -> Result<()> {
+    let body = format!(
+        "{} wants to subscribe to {}.\n\n\
+         Approve: {}\n\
+         Reject: {}\n",
+        subscriber.email.0,
+        data.list.name,
+        link(data, Item::Signup, subscriber.id, Action::Approve),
+        link(data, Item::Signup, subscriber.id, Action::Reject),
+    );
+    to_moderators(
+        data,
+        format!("Signup to {} awaits approval", data.list.name),
+        body,
+    )
+    .await
+}
+
+/// Tells the sender of a post or signup why a moderator rejected it.
+pub(crate) async fn rejected(
+    data: &ApplicationData,
+    to: &Email,
+    subject: String,
+    reason: &str,
+) -> Result<()> {
+    let body = format!(
+        "A moderator of {} gave this reason:\n\n{reason}\n",
+        data.list.name
+    );
+    outbound::queue(data, message(data, &to.0, subject, body)?).await
+}
+
+async fn to_moderators(data: &ApplicationData, subject: String, body: String) -> Result<()> {
+    for moderator in &data.moderation.moderators {
+        outbound::queue(
+            data,
+            message(data, moderator, subject.clone(), body.clone())?,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+fn message(data: &ApplicationData, to: &str, subject: String, body: String) -> Result<Message> {
+    let from = Mailbox::new(
+        Some(data.list.name.clone()),
+        data.list.request_address().parse()?,
+    );
+    Ok(Message::builder()
+        .from(from)
+        .to(to.parse()?)
+        .subject(subject)
+        .header(AutoSubmitted::generated())
+        .body(body)?)
+}
+
+fn link(data: &ApplicationData, item: Item, id: i32, action: Action) -> String {
+    let token = Signer::new(&data.list.secret).sign(&ModerationToken::new(item, id, action));
+    format!(
+        "{}/moderate/{token}",
+        data.moderation.url.trim_end_matches('/')
+    )
+}
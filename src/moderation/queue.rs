@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use log::info;
+
+use crate::{
+    activity, automation,
+    data::ApplicationData,
+    model::{
+        ActivityKind, Email, NewSubscriber, Post, PostStatus, Subscriber, SubscriberStatus,
+        Trigger, WebhookEvent,
+    },
+    moderation::notify,
+    outbound::distribute,
+    store::{
+        AllowlistStore, PostStore, PsqlAllowlistStore, PsqlPostStore, PsqlSubscriberStore,
+        SubscriberStore,
+    },
+    webhooks,
+};
+
+/// Subscribes an address, holding the signup for a moderator when signups
+/// are moderated or `hold` asks for it. Existing subscribers are left as
+/// they are.
+pub(crate) async fn signup(data: &ApplicationData, email: Email, hold: bool) -> Result<Subscriber> {
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    if let Some(subscriber) = subscribers.find(&email).await? {
+        return Ok(subscriber);
+    }
+
+    if data.moderation.signups || hold {
+        let subscriber = subscribers
+            .create_with_status(NewSubscriber { email }, SubscriberStatus::Pending)
+            .await?;
+        info!("Holding signup of {:?} for moderation", subscriber.email);
+        notify::pending_signup(data, &subscriber).await?;
+        activity::publish_subscriber(data, ActivityKind::Signup, &subscriber).await;
+        return Ok(subscriber);
+    }
+
+    let subscriber = subscribers.create(NewSubscriber { email }).await?;
+    automation::enroll(data, &subscriber, &Trigger::Subscribed).await?;
+    webhooks::emit_subscriber(data, WebhookEvent::Subscribed, &subscriber).await;
+    activity::publish_subscriber(data, ActivityKind::Signup, &subscriber).await;
+    activity::publish_subscriber(data, ActivityKind::Confirmed, &subscriber).await;
+    Ok(subscriber)
+}
+
+/// Sends a held post to the list, and with `allow` lets its sender post
+/// without moderation from now on. Returns `None` unless the post was held.
+pub(crate) async fn approve_post(
+    data: &ApplicationData,
+    id: i32,
+    allow: bool,
+) -> Result<Option<Post>> {
+    let mut posts = PsqlPostStore::from(data.pool.clone());
+    if !posts.moderate(id, PostStatus::Accepted, None).await? {
+        return Ok(None);
+    }
+    let post = posts
+        .get(id)
+        .await?
+        .ok_or_else(|| anyhow!("Post {id} disappeared"))?;
+
+    if allow {
+        PsqlAllowlistStore::from(data.pool.clone())
+            .add(&post.sender)
+            .await?;
+    }
+    info!("Approved post {id} by {:?}", post.sender);
+    distribute(data, &post).await?;
+    Ok(Some(post))
+}
+
+/// Rejects a held post. The sender only hears about it when there is a
+/// reason, so that spam is dropped silently.
+pub(crate) async fn reject_post(
+    data: &ApplicationData,
+    id: i32,
+    reason: Option<&str>,
+) -> Result<Option<Post>> {
+    let mut posts = PsqlPostStore::from(data.pool.clone());
+    if !posts.moderate(id, PostStatus::Rejected, reason).await? {
+        return Ok(None);
+    }
+    let post = posts
+        .get(id)
+        .await?
+        .ok_or_else(|| anyhow!("Post {id} disappeared"))?;
+
+    info!("Rejected post {id} by {:?}", post.sender);
+    if let Some(reason) = reason {
+        notify::rejected(
+            data,
+            &post.sender,
+            format!(
+                "Your post to {} was rejected: {}",
+                data.list.name, post.subject
+            ),
+            reason,
+        )
+        .await?;
+    }
+    Ok(Some(post))
+}
+
+/// Activates a pending subscriber. Returns `None` unless they were pending.
+pub(crate) async fn approve_signup(data: &ApplicationData, id: i32) -> Result<Option<Subscriber>> {
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(mut subscriber) = pending(&subscribers, id).await? else {
+        return Ok(None);
+    };
+
+    // Another moderator may have decided on the signup in the meantime.
+    if !subscribers
+        .change_status(
+            &subscriber.email,
+            SubscriberStatus::Pending,
+            SubscriberStatus::Active,
+        )
+        .await?
+    {
+        return Ok(None);
+    }
+    subscriber.status = SubscriberStatus::Active;
+    info!("Approved signup of {:?}", subscriber.email);
+    automation::enroll(data, &subscriber, &Trigger::Subscribed).await?;
+    webhooks::emit_subscriber(data, WebhookEvent::Subscribed, &subscriber).await;
+    activity::publish_subscriber(data, ActivityKind::Confirmed, &subscriber).await;
+    Ok(Some(subscriber))
+}
+
+/// Removes a pending subscriber, telling them why when there is a reason.
+pub(crate) async fn reject_signup(
+    data: &ApplicationData,
+    id: i32,
+    reason: Option<&str>,
+) -> Result<Option<Subscriber>> {
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = pending(&subscribers, id).await? else {
+        return Ok(None);
+    };
+
+    subscribers.delete(&subscriber.email).await?;
+    info!("Rejected signup of {:?}", subscriber.email);
+    if let Some(reason) = reason {
+        notify::rejected(
+            data,
+            &subscriber.email,
+            format!("Your subscription to {} was declined", data.list.name),
+            reason,
+        )
+        .await?;
+    }
+    Ok(Some(subscriber))
+}
+
+async fn pending(subscribers: &PsqlSubscriberStore, id: i32) -> Result<Option<Subscriber>> {
+    Ok(subscribers
+        .get(id)
+        .await?
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Pending))
+}
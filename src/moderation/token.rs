@@ -0,0 +1,47 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+const VALID_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Item {
+    Post,
+    Signup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Approve,
+    Reject,
+}
+
+/// Lets a moderator act on one item with the link in their notification,
+/// without logging in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationToken {
+    #[serde(rename = "k")]
+    pub item: Item,
+    #[serde(rename = "i")]
+    pub id: i32,
+    #[serde(rename = "a")]
+    pub action: Action,
+    #[serde(rename = "x")]
+    pub expires: i64,
+}
+
+impl ModerationToken {
+    pub fn new(item: Item, id: i32, action: Action) -> Self {
+        Self {
+            item,
+            id,
+            action,
+            expires: (Utc::now() + Duration::days(VALID_DAYS)).timestamp(),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.expires < Utc::now().timestamp()
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    Message,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    data::ApplicationData,
+    inbound::Verp,
+    model::{NewDelivery, Subscriber, Template},
+    outbound::{CampaignId, ListUnsubscribe, ListUnsubscribePost},
+    pages::{preferences_url, unsubscribe_url},
+};
+
+/// Fills in a template for a subscriber, with tracking that counts towards
+/// the campaign, ready for the outbox.
+pub(crate) fn campaign_delivery(
+    data: &ApplicationData,
+    template: &Template,
+    subscriber: &Subscriber,
+    attributes: &Map<String, Value>,
+    campaign_id: i32,
+) -> Result<NewDelivery> {
+    campaign_delivery_from(
+        data,
+        &data.list.name,
+        template,
+        subscriber,
+        attributes,
+        campaign_id,
+    )
+}
+
+/// Like [`campaign_delivery`], but from someone other than the list.
+/// Templates can link to the subscriber's `{{ unsubscribe_url }}` and
+/// `{{ preferences_url }}`.
+pub(crate) fn campaign_delivery_from(
+    data: &ApplicationData,
+    from_name: &str,
+    template: &Template,
+    subscriber: &Subscriber,
+    attributes: &Map<String, Value>,
+    campaign_id: i32,
+) -> Result<NewDelivery> {
+    let unsubscribe = unsubscribe_url(data, subscriber, Some(campaign_id));
+    let mut attributes = attributes.clone();
+    attributes.insert(
+        "unsubscribe_url".to_string(),
+        Value::String(unsubscribe.clone()),
+    );
+    attributes.insert(
+        "preferences_url".to_string(),
+        Value::String(preferences_url(data, subscriber)),
+    );
+    let rendered = template.render(&subscriber.email, &attributes);
+
+    let from = Mailbox::new(Some(from_name.to_string()), data.list.address.parse()?);
+    let builder = Message::builder()
+        .from(from)
+        .to(subscriber.email.0.parse()?)
+        .subject(rendered.subject)
+        .header(CampaignId(campaign_id))
+        .header(ListUnsubscribe(unsubscribe))
+        .header(ListUnsubscribePost);
+    let message = match rendered.html {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            rendered.text,
+            data.tracker
+                .instrument(&html, subscriber.id, Some(campaign_id)),
+        ))?,
+        None => builder.body(rendered.text)?,
+    };
+
+    Ok(NewDelivery {
+        sender: Verp::from(data.bounce.clone()).return_path(&subscriber.email),
+        recipient: subscriber.email.0.clone(),
+        raw: message.formatted(),
+        campaign_id: Some(campaign_id),
+        engagement: 0,
+    })
+}
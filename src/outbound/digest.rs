@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use sha2::{Digest, Sha256};
+use tokio::time::Instant;
+
+use crate::{
+    config::ListSettings,
+    data::ApplicationData,
+    inbound::Verp,
+    model::{NewDelivery, Post, SubscriberStatus},
+    outbound::{
+        rewrite::{self, display_name, RawMessage},
+        unsuppressed,
+    },
+    store::{
+        OutboxStore, PostStore, PsqlOutboxStore, PsqlPostStore, PsqlSubscriberStore,
+        SubscriberStore,
+    },
+};
+
+/// Sends the digest every `list.digest` hours, forever.
+pub(crate) async fn deliver_digests(data: ApplicationData) {
+    let period = Duration::from_secs(data.list.digest * 60 * 60);
+    // Waits a full period first, so restarts do not send a digest each time.
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_digest(&data).await {
+            error!("Failed to send digest: {e}");
+        }
+    }
+}
+
+/// Queues one message with every post since the last digest for members who
+/// read the digest.
+pub(crate) async fn send_digest(data: &ApplicationData) -> Result<()> {
+    let mut posts = PsqlPostStore::from(data.pool.clone());
+    let undigested = posts.undigested().await?;
+    if undigested.is_empty() {
+        return Ok(());
+    }
+
+    let members: Vec<_> = PsqlSubscriberStore::from(data.pool.clone())
+        .all()
+        .await?
+        .into_iter()
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Active && subscriber.digest)
+        .collect();
+    let members = unsuppressed(data, members).await?;
+
+    let raw = build(&data.list, &undigested, Utc::now())?;
+    let verp = Verp::from(data.bounce.clone());
+    let mut outbox = PsqlOutboxStore::from(data.pool.clone());
+    for member in &members {
+        outbox
+            .enqueue(NewDelivery {
+                sender: verp.return_path(&member.email),
+                recipient: member.email.0.clone(),
+                raw: raw.clone(),
+                campaign_id: None,
+                engagement: 0,
+            })
+            .await?;
+    }
+
+    let ids: Vec<i32> = undigested.iter().map(|post| post.id).collect();
+    posts.mark_digested(&ids).await?;
+    info!(
+        "Queued digest of {} posts for {} members",
+        undigested.len(),
+        members.len()
+    );
+    Ok(())
+}
+
+/// Builds a MIME digest (RFC 2046) with a table of contents followed by
+/// every post as it was sent to us.
+fn build(list: &ListSettings, posts: &[Post], now: DateTime<Utc>) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    for post in posts {
+        hasher.update(&post.raw);
+    }
+    let boundary: String = hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let boundary = format!("minimail-digest-{boundary}");
+    let domain = list
+        .address
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or("localhost");
+
+    let mut contents = format!("{} digest of {}\n\n", list.name, now.format("%Y-%m-%d"));
+    for (number, post) in posts.iter().enumerate() {
+        contents.push_str(&format!(
+            "{:>3}. {} ({})\n",
+            number + 1,
+            post.subject,
+            post.sender.0
+        ));
+    }
+    let (encoding, contents) = rewrite::encode_text(&contents);
+
+    let mut raw = format!(
+        "From: {} <{}>\r\n\
+         To: {}\r\n\
+         Subject: {}\r\n\
+         Date: {}\r\n\
+         Message-ID: <digest.{}.{}@{domain}>\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: multipart/digest; boundary=\"{boundary}\"\r\n\
+         \r\n\
+         --{boundary}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: {encoding}\r\n\
+         \r\n",
+        display_name(&list.name),
+        list.address,
+        list.address,
+        digest_subject(list, posts.len(), now),
+        now.to_rfc2822(),
+        now.timestamp(),
+        posts[0].id,
+    )
+    .into_bytes();
+    raw.extend(contents);
+    for post in posts {
+        // Parts of a digest are messages unless they say otherwise.
+        raw.extend(format!("\r\n--{boundary}\r\n\r\n").into_bytes());
+        raw.extend_from_slice(&post.raw);
+        if !post.raw.ends_with(b"\n") {
+            raw.extend_from_slice(b"\r\n");
+        }
+    }
+    raw.extend(format!("\r\n--{boundary}--\r\n").into_bytes());
+
+    let mut message = RawMessage::from(raw.as_slice());
+    rewrite::add_list_headers(&mut message, list);
+    Ok(message.into_bytes())
+}
+
+fn digest_subject(list: &ListSettings, count: usize, now: DateTime<Utc>) -> String {
+    let subject = format!(
+        "{} digest, {} ({count} {})",
+        list.name,
+        now.format("%Y-%m-%d"),
+        if count == 1 { "message" } else { "messages" }
+    );
+    match &list.prefix {
+        Some(prefix) => format!("{prefix} {subject}"),
+        None => subject,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use mailparse::MailHeaderMap;
+
+    use super::*;
+    use crate::config::ListMode;
+    use crate::model::{Email, PostStatus};
+
+    fn post(id: i32, subject: &str) -> Post {
+        Post {
+            id,
+            sender: Email::from("alice@example.org"),
+            subject: subject.to_string(),
+            raw: format!(
+                "From: alice@example.org\r\nSubject: {subject}\r\n\r\nText of {subject}\r\n"
+            )
+            .into_bytes(),
+            status: PostStatus::Accepted,
+            reason: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn digest_contains_every_post() {
+        let list = ListSettings {
+            name: "News".to_string(),
+            address: "news@example.com".to_string(),
+            secret: "secret".to_string(),
+            mode: ListMode::Discussion,
+            prefix: None,
+            footer: None,
+            rewrite: true,
+            digest: 24,
+            timezone: "UTC".to_string(),
+            origins: Vec::new(),
+        };
+        let now = Utc.with_ymd_and_hms(2023, 4, 1, 8, 0, 0).unwrap();
+
+        let raw = build(&list, &[post(1, "First"), post(2, "Second")], now).unwrap();
+        let mail = mailparse::parse_mail(&raw).unwrap();
+
+        assert_eq!(
+            "News digest, 2023-04-01 (2 messages)",
+            mail.headers.get_first_value("Subject").unwrap()
+        );
+        assert_eq!("multipart/digest", mail.ctype.mimetype);
+        assert_eq!(3, mail.subparts.len());
+        let contents = mail.subparts[0].get_body().unwrap();
+        assert!(contents.contains("  1. First (alice@example.org)"));
+        assert!(contents.contains("  2. Second (alice@example.org)"));
+        assert_eq!("message/rfc822", mail.subparts[2].ctype.mimetype);
+        assert!(mail.subparts[2]
+            .get_body()
+            .unwrap()
+            .contains("Text of Second"));
+        assert!(mail.headers.get_first_value("List-Id").is_some());
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use log::info;
+
+use crate::{
+    data::ApplicationData,
+    inbound::Verp,
+    model::{NewDelivery, Post, SubscriberStatus},
+    outbound::{rewrite, unsuppressed},
+    store::{OutboxStore, PsqlOutboxStore, PsqlSubscriberStore, SubscriberStore},
+};
+
+/// Queues an accepted post for every member who does not read the digest.
+pub(crate) async fn distribute(data: &ApplicationData, post: &Post) -> Result<usize> {
+    let raw = rewrite::for_list(&post.raw, &data.list)?;
+    let verp = Verp::from(data.bounce.clone());
+    let mut outbox = PsqlOutboxStore::from(data.pool.clone());
+
+    let members: Vec<_> = PsqlSubscriberStore::from(data.pool.clone())
+        .all()
+        .await?
+        .into_iter()
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Active && !subscriber.digest)
+        .collect();
+    let members = unsuppressed(data, members).await?;
+
+    for member in &members {
+        outbox
+            .enqueue(NewDelivery {
+                sender: verp.return_path(&member.email),
+                recipient: member.email.0.clone(),
+                raw: raw.clone(),
+                campaign_id: None,
+                engagement: 0,
+            })
+            .await?;
+    }
+
+    info!("Queued post {} for {} members", post.id, members.len());
+    Ok(members.len())
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::{DomainLimits, GovernorSettings};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Keeps outgoing mail within what receiving domains accept: a global pace,
+/// and per recipient domain a number of concurrent sends, an hourly cap and
+/// a pause after the domain defers a message.
+#[derive(Clone, Debug)]
+pub struct Governor {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    settings: GovernorSettings,
+    // When the next message may go out.
+    next_slot: tokio::sync::Mutex<Instant>,
+    domains: Mutex<HashMap<String, DomainState>>,
+    held: AtomicU64,
+}
+
+#[derive(Debug)]
+struct DomainState {
+    limits: DomainLimits,
+    sending: Option<Arc<Semaphore>>,
+    // When messages were let through in the last hour.
+    sent: VecDeque<Instant>,
+    paused_until: Option<Instant>,
+    // The pause after the next deferral.
+    backoff: Duration,
+    deferrals: u64,
+    held: u64,
+}
+
+/// What the governor is doing, for the metrics endpoint.
+#[derive(Debug, Serialize)]
+pub struct GovernorReport {
+    pub per_second: f64,
+    // Deliveries put off because of a limit or pause.
+    pub held: u64,
+    pub domains: BTreeMap<String, DomainReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DomainReport {
+    pub sending: usize,
+    pub sent_last_hour: usize,
+    pub hourly_limit: usize,
+    pub deferrals: u64,
+    pub held: u64,
+    pub paused_until: Option<DateTime<Utc>>,
+}
+
+impl From<GovernorSettings> for Governor {
+    fn from(settings: GovernorSettings) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                settings,
+                next_slot: tokio::sync::Mutex::new(Instant::now()),
+                domains: Mutex::default(),
+                held: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl Governor {
+    /// Lets a message to `domain` through, counting it against the hourly
+    /// cap, or tells when to try it again.
+    pub fn admit(&self, domain: &str) -> Result<(), DateTime<Utc>> {
+        let now = Instant::now();
+        let result = self.with_domain(domain, |state| {
+            state.forget_before(now);
+            if let Some(until) = state.paused_until.filter(|until| *until > now) {
+                return Err(until);
+            }
+            let hourly = state.limits.hourly;
+            if hourly > 0 && state.sent.len() >= hourly {
+                return Err(state.sent[0] + HOUR);
+            }
+            state.sent.push_back(now);
+            Ok(())
+        });
+        result.map_err(|until| {
+            self.inner.held.fetch_add(1, Ordering::Relaxed);
+            self.with_domain(domain, |state| state.held += 1);
+            at(until)
+        })
+    }
+
+    /// Waits until a message to `domain` may be sent, within the domain's
+    /// concurrency and the global pace. Sending may go on until the permit
+    /// is dropped.
+    pub async fn slot(&self, domain: &str) -> Option<OwnedSemaphorePermit> {
+        let sending = self.with_domain(domain, |state| state.sending.clone());
+        let permit = match sending {
+            Some(sending) => Some(sending.acquire_owned().await.expect("Never closed")),
+            None => None,
+        };
+        self.pace().await;
+        permit
+    }
+
+    /// Notes that `domain` took a message, ending any back-off.
+    pub fn delivered(&self, domain: &str) {
+        let backoff = self.backoff();
+        self.with_domain(domain, |state| state.backoff = backoff);
+    }
+
+    /// Pauses sending to `domain` after it deferred a message, for twice as
+    /// long as the last time, and returns when the pause ends.
+    pub fn deferred(&self, domain: &str) -> DateTime<Utc> {
+        let max = Duration::from_secs(self.inner.settings.max_backoff);
+        let until = self.with_domain(domain, |state| {
+            let until = Instant::now() + state.backoff;
+            state.paused_until = Some(until);
+            state.backoff = (state.backoff * 2).min(max);
+            state.deferrals += 1;
+            until
+        });
+        at(until)
+    }
+
+    pub fn report(&self) -> GovernorReport {
+        let now = Instant::now();
+        let mut domains = self.inner.domains.lock().expect("Never poisoned");
+        let domains = domains
+            .iter_mut()
+            .map(|(domain, state)| {
+                state.forget_before(now);
+                let sending = state.sending.as_ref().map_or(0, |sending| {
+                    state.limits.concurrency - sending.available_permits()
+                });
+                let report = DomainReport {
+                    sending,
+                    sent_last_hour: state.sent.len(),
+                    hourly_limit: state.limits.hourly,
+                    deferrals: state.deferrals,
+                    held: state.held,
+                    paused_until: state.paused_until.filter(|until| *until > now).map(at),
+                };
+                (domain.clone(), report)
+            })
+            .collect();
+        GovernorReport {
+            per_second: self.inner.settings.per_second,
+            held: self.inner.held.load(Ordering::Relaxed),
+            domains,
+        }
+    }
+
+    async fn pace(&self) {
+        let per_second = self.inner.settings.per_second;
+        if per_second <= 0.0 {
+            return;
+        }
+        let slot = {
+            let mut next_slot = self.inner.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + Duration::from_secs_f64(1.0 / per_second);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    fn backoff(&self) -> Duration {
+        Duration::from_secs(self.inner.settings.backoff)
+    }
+
+    fn with_domain<T>(&self, domain: &str, f: impl FnOnce(&mut DomainState) -> T) -> T {
+        let mut domains = self.inner.domains.lock().expect("Never poisoned");
+        let state = domains.entry(domain.to_lowercase()).or_insert_with(|| {
+            let settings = &self.inner.settings;
+            let limits = settings
+                .domains
+                .get(&domain.to_lowercase())
+                .copied()
+                .unwrap_or(settings.default);
+            DomainState {
+                limits,
+                sending: (limits.concurrency > 0)
+                    .then(|| Arc::new(Semaphore::new(limits.concurrency))),
+                sent: VecDeque::new(),
+                paused_until: None,
+                backoff: self.backoff(),
+                deferrals: 0,
+                held: 0,
+            }
+        });
+        f(state)
+    }
+}
+
+impl DomainState {
+    fn forget_before(&mut self, now: Instant) {
+        while self.sent.front().is_some_and(|sent| now - *sent >= HOUR) {
+            self.sent.pop_front();
+        }
+    }
+}
+
+/// The wall clock time of an instant.
+fn at(instant: Instant) -> DateTime<Utc> {
+    let now = Instant::now();
+    let offset = if instant > now {
+        chrono::Duration::from_std(instant - now).unwrap_or_else(|_| chrono::Duration::zero())
+    } else {
+        -chrono::Duration::from_std(now - instant).unwrap_or_else(|_| chrono::Duration::zero())
+    };
+    Utc::now() + offset
+}
+
+/// The lowercased domain of a recipient address.
+pub fn recipient_domain(recipient: &str) -> String {
+    recipient
+        .rsplit_once('@')
+        .map_or(recipient, |(_, domain)| domain)
+        .trim_end_matches('>')
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn governor(per_second: f64, limits: DomainLimits) -> Governor {
+        Governor::from(GovernorSettings {
+            per_second,
+            default: limits,
+            domains: HashMap::from([(
+                "gmail.com".to_string(),
+                DomainLimits {
+                    concurrency: 1,
+                    hourly: 1,
+                },
+            )]),
+            backoff: 60,
+            max_backoff: 200,
+        })
+    }
+
+    #[test]
+    fn admit_caps_domains_per_hour() {
+        let governor = governor(
+            0.0,
+            DomainLimits {
+                concurrency: 0,
+                hourly: 2,
+            },
+        );
+
+        assert!(governor.admit("example.org").is_ok());
+        assert!(governor.admit("Example.org").is_ok());
+        let until = governor.admit("example.org").unwrap_err();
+        assert!(governor.admit("gmail.com").is_ok());
+        assert!(governor.admit("gmail.com").is_err());
+
+        assert!(until > Utc::now() + chrono::Duration::minutes(59));
+        let report = governor.report();
+        assert_eq!(2, report.held);
+        assert_eq!(2, report.domains["example.org"].sent_last_hour);
+        assert_eq!(1, report.domains["gmail.com"].held);
+    }
+
+    #[test]
+    fn deferrals_pause_domain_with_growing_backoff() {
+        let governor = governor(0.0, DomainLimits::default());
+
+        let first = governor.deferred("example.org");
+        let second = governor.deferred("example.org");
+        let third = governor.deferred("example.org");
+        let fourth = governor.deferred("example.org");
+
+        let seconds = |until: DateTime<Utc>| (until - Utc::now()).num_seconds();
+        assert!((58..=60).contains(&seconds(first)));
+        assert!((118..=120).contains(&seconds(second)));
+        assert!((198..=200).contains(&seconds(third)));
+        assert!((198..=200).contains(&seconds(fourth)));
+        assert!(governor.admit("example.org").is_err());
+        assert!(governor.admit("example.net").is_ok());
+        assert_eq!(4, governor.report().domains["example.org"].deferrals);
+
+        governor.delivered("example.org");
+        assert!((58..=60).contains(&seconds(governor.deferred("example.org"))));
+    }
+
+    #[tokio::test]
+    async fn slot_limits_concurrency_per_domain() {
+        let governor = governor(0.0, DomainLimits::default());
+
+        let first = governor.slot("gmail.com").await;
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(50), governor.slot("gmail.com")).await;
+        let other = governor.slot("example.org").await;
+
+        assert!(blocked.is_err());
+        assert!(other.is_some());
+        assert_eq!(1, governor.report().domains["gmail.com"].sending);
+        drop(first);
+        assert!(governor.slot("gmail.com").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn slot_paces_messages() {
+        let governor = governor(
+            50.0,
+            DomainLimits {
+                concurrency: 0,
+                hourly: 0,
+            },
+        );
+        let started = Instant::now();
+
+        for _ in 0..6 {
+            governor.slot("example.org").await;
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn recipient_domain_is_lowercased() {
+        assert_eq!("example.org", recipient_domain("Ada@Example.ORG"));
+        assert_eq!("example.org", recipient_domain("<ada@example.org>"));
+    }
+}
@@ -0,0 +1,96 @@
+use lettre::message::header::{Header, HeaderName, HeaderValue};
+
+use crate::inbound::CAMPAIGN_HEADER;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// `Auto-Submitted` (RFC 3834), which keeps vacation responders and other
+/// robots from answering our automatic mail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoSubmitted(String);
+
+impl AutoSubmitted {
+    pub fn replied() -> Self {
+        Self("auto-replied".to_string())
+    }
+
+    pub fn generated() -> Self {
+        Self("auto-generated".to_string())
+    }
+}
+
+impl Header for AutoSubmitted {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("Auto-Submitted")
+    }
+
+    fn parse(s: &str) -> Result<Self, BoxError> {
+        Ok(Self(s.to_string()))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.clone())
+    }
+}
+
+/// Names the campaign a message belongs to, so that bounces and complaints
+/// quoting it can be counted against the campaign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CampaignId(pub i32);
+
+impl Header for CampaignId {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str(CAMPAIGN_HEADER)
+    }
+
+    fn parse(s: &str) -> Result<Self, BoxError> {
+        Ok(Self(s.trim().parse()?))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), self.0.to_string())
+    }
+}
+
+/// `List-Unsubscribe` (RFC 2369), which mail clients show as an unsubscribe
+/// button.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListUnsubscribe(pub String);
+
+impl Header for ListUnsubscribe {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe")
+    }
+
+    fn parse(s: &str) -> Result<Self, BoxError> {
+        Ok(Self(
+            s.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string(),
+        ))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), format!("<{}>", self.0))
+    }
+}
+
+/// `List-Unsubscribe-Post` (RFC 8058), which lets mail clients unsubscribe
+/// with one POST to the `List-Unsubscribe` link, without opening the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListUnsubscribePost;
+
+impl Header for ListUnsubscribePost {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe-Post")
+    }
+
+    fn parse(_: &str) -> Result<Self, BoxError> {
+        Ok(Self)
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), "List-Unsubscribe=One-Click".to_string())
+    }
+}
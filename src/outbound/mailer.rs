@@ -0,0 +1,79 @@
+use anyhow::Result;
+use lettre::{
+    address::Envelope, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::config::MailerSettings;
+
+/// Sends our mail through the configured SMTP relay.
+#[derive(Clone, Debug)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+    interval: u64,
+    attempts: i32,
+}
+
+impl TryFrom<MailerSettings> for Mailer {
+    type Error = anyhow::Error;
+
+    fn try_from(settings: MailerSettings) -> Result<Self> {
+        let mut builder = if settings.tls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        }
+        .port(settings.port);
+
+        if let (Some(username), Some(password)) = (settings.username, settings.password) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: settings.from,
+            interval: settings.interval,
+            attempts: settings.attempts,
+        })
+    }
+}
+
+impl Mailer {
+    /// Passes a message on unchanged, with us as the envelope sender so that
+    /// it is not rejected for failing the original sender's SPF policy.
+    pub async fn forward(&self, to: &str, raw: &[u8]) -> Result<()> {
+        self.deliver(&self.from, to, raw).await
+    }
+
+    /// Sends a finished message with the given envelope.
+    pub async fn deliver(&self, from: &str, to: &str, raw: &[u8]) -> Result<()> {
+        let envelope = Envelope::new(Some(from.parse()?), vec![to.parse()?])?;
+        self.transport.send_raw(&envelope, raw).await?;
+        Ok(())
+    }
+
+    /// Envelope sender of our own mail.
+    pub fn sender(&self) -> &str {
+        &self.from
+    }
+
+    /// Seconds between looks at the outbox.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    /// Sends a message we wrote, with our address as the envelope sender so
+    /// that bounces of it never come back to the list.
+    pub async fn send(&self, message: Message) -> Result<()> {
+        let envelope = Envelope::new(Some(self.from.parse()?), message.envelope().to().to_vec())?;
+        self.transport
+            .send_raw(&envelope, &message.formatted())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+mod campaign;
+mod digest;
+mod distribute;
+mod governor;
+mod headers;
+mod mailer;
+mod outbox;
+mod recipients;
+mod rewrite;
+mod transactional;
+
+pub(crate) use campaign::{campaign_delivery, campaign_delivery_from};
+pub(crate) use digest::deliver_digests;
+pub(crate) use distribute::distribute;
+pub use governor::{Governor, GovernorReport};
+pub use headers::{AutoSubmitted, CampaignId, ListUnsubscribe, ListUnsubscribePost};
+pub use mailer::Mailer;
+pub(crate) use outbox::{deliver_outbox, queue};
+pub(crate) use recipients::unsuppressed;
+pub use transactional::{SendAttachment, SendRequest};
@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use lettre::Message;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+use tokio::task::JoinSet;
+
+use crate::{
+    activity,
+    data::ApplicationData,
+    model::{ActivityKind, Delivery, NewDelivery, WebhookEvent},
+    outbound::governor::recipient_domain,
+    store::{OutboxStore, PsqlOutboxStore},
+    tracking, warmup, webhooks,
+};
+
+const BATCH: i64 = 100;
+
+/// Puts a message we wrote in the outbox, once for each of its recipients.
+pub(crate) async fn queue(data: &ApplicationData, message: Message) -> Result<()> {
+    let raw = message.formatted();
+    let mut outbox = PsqlOutboxStore::from(data.pool.clone());
+    for recipient in message.envelope().to() {
+        outbox
+            .enqueue(NewDelivery {
+                sender: data.mailer.sender().to_string(),
+                recipient: recipient.to_string(),
+                raw: raw.clone(),
+                campaign_id: None,
+                engagement: 0,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends whatever is due in the outbox, forever.
+pub(crate) async fn deliver_outbox(data: ApplicationData) {
+    let mut interval = tokio::time::interval(Duration::from_secs(data.mailer.interval()));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = deliver_due(&data).await {
+            error!("Failed to deliver outbox: {e}");
+        }
+    }
+}
+
+async fn deliver_due(data: &ApplicationData) -> Result<()> {
+    let mut outbox = PsqlOutboxStore::from(data.pool.clone());
+
+    loop {
+        let deliveries = outbox.claim(BATCH).await?;
+        if deliveries.is_empty() {
+            return Ok(());
+        }
+
+        // Sends the batch at once, as far as the warm-up plan and the
+        // governor let it.
+        let campaign_mail = deliveries
+            .iter()
+            .filter(|delivery| delivery.campaign_id.is_some())
+            .count();
+        let mut allowance = warmup::reserve(data, campaign_mail as i64).await?;
+        let mut sending = JoinSet::new();
+        for delivery in deliveries {
+            // Only campaign mail counts towards warm-up caps.
+            let warming = delivery.campaign_id.is_some();
+            if let Some(allowance) = allowance.as_mut().filter(|_| warming) {
+                if let Err(resume_at) = allowance.take() {
+                    info!(
+                        "Carrying message {} to {} over to {resume_at}, past today's warm-up cap",
+                        delivery.id, delivery.recipient
+                    );
+                    outbox.defer(delivery.id, resume_at).await?;
+                    continue;
+                }
+            }
+            let domain = recipient_domain(&delivery.recipient);
+            if let Err(until) = data.governor.admit(&domain) {
+                info!(
+                    "Holding message {} to {} until {until}",
+                    delivery.id, delivery.recipient
+                );
+                if let Some(allowance) = allowance.as_mut().filter(|_| warming) {
+                    allowance.give_back();
+                }
+                outbox.defer(delivery.id, until).await?;
+                continue;
+            }
+            sending.spawn(deliver(data.clone(), delivery, domain));
+        }
+        if let Some(allowance) = &allowance {
+            warmup::release(data, allowance).await?;
+        }
+        // Waits for the whole batch, since dropping the set would abort
+        // sends that may already have gone out.
+        while let Some(result) = sending.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Failed to deliver: {e}"),
+                Err(e) => error!("Delivery task failed: {e}"),
+            }
+        }
+    }
+}
+
+async fn deliver(data: ApplicationData, delivery: Delivery, domain: String) -> Result<()> {
+    let mut outbox = PsqlOutboxStore::from(data.pool.clone());
+    let _slot = data.governor.slot(&domain).await;
+
+    match data
+        .mailer
+        .deliver(&delivery.sender, &delivery.recipient, &delivery.raw)
+        .await
+    {
+        Ok(()) => {
+            info!(
+                "Delivered message {} to {}",
+                delivery.id, delivery.recipient
+            );
+            data.governor.delivered(&domain);
+            outbox.sent(delivery.id).await?;
+            if let Some(campaign_id) = delivery.campaign_id {
+                tracking::delivered(&data, &delivery.recipient, campaign_id).await;
+            }
+            webhooks::emit(&data, WebhookEvent::Sent, delivery_fields(&delivery)).await;
+            activity::publish(&data, ActivityKind::Sent, delivery_fields(&delivery)).await;
+        }
+        Err(e) => {
+            let attempts = delivery.attempts + 1;
+            let smtp = e.downcast_ref::<lettre::transport::smtp::Error>();
+            let permanent = smtp.is_some_and(|e| e.is_permanent());
+            // Backs off exponentially, from two minutes up to about a day.
+            let retry = !permanent && attempts < data.mailer.attempts();
+            let mut retry_at = retry
+                .then(|| Utc::now() + chrono::Duration::minutes(2i64.pow(attempts.min(10) as u32)));
+            // A deferral, like 421, asks us to slow down for the whole domain.
+            if smtp.is_some_and(|e| e.is_transient()) {
+                let paused_until = data.governor.deferred(&domain);
+                warn!("Pausing delivery to {domain} until {paused_until}");
+                retry_at = retry_at.map(|at| at.max(paused_until));
+            }
+            warn!(
+                "Failed to deliver message {} to {} (attempt {attempts}): {e}",
+                delivery.id, delivery.recipient
+            );
+            outbox.failed(delivery.id, &e.to_string(), retry_at).await?;
+            if retry_at.is_none() {
+                let mut fields = delivery_fields(&delivery);
+                fields["error"] = json!(e.to_string());
+                webhooks::emit(&data, WebhookEvent::Failed, fields.clone()).await;
+                activity::publish(&data, ActivityKind::Failed, fields).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What webhooks and activity streams are told about a message.
+fn delivery_fields(delivery: &Delivery) -> Value {
+    json!({
+        "message_id": delivery.id,
+        "recipient": delivery.recipient,
+        "campaign_id": delivery.campaign_id,
+    })
+}
@@ -0,0 +1,27 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{
+    data::ApplicationData,
+    model::Subscriber,
+    store::{PsqlSuppressionStore, SuppressionStore},
+};
+
+/// Leaves out subscribers whose address is suppressed, so that nothing is
+/// sent to them even while their subscription is still around.
+pub(crate) async fn unsuppressed(
+    data: &ApplicationData,
+    subscribers: Vec<Subscriber>,
+) -> Result<Vec<Subscriber>> {
+    let suppressed: HashSet<String> = PsqlSuppressionStore::from(data.pool.clone())
+        .all()
+        .await?
+        .into_iter()
+        .map(|suppression| suppression.email.0)
+        .collect();
+    Ok(subscribers
+        .into_iter()
+        .filter(|subscriber| !suppressed.contains(&subscriber.email.0))
+        .collect())
+}
@@ -0,0 +1,492 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+use crate::config::ListSettings;
+
+// Headers that are wrong once we resend a message, or that would make
+// receivers think the author's domain signed or sent it.
+const DROPPED_HEADERS: [&str; 8] = [
+    "Return-Path",
+    "DKIM-Signature",
+    "ARC-Seal",
+    "ARC-Message-Signature",
+    "ARC-Authentication-Results",
+    "Precedence",
+    "Sender",
+    "Errors-To",
+];
+
+/// A message split into its header fields, kept byte for byte including
+/// folding, and its body.
+#[derive(Debug, Clone)]
+pub(crate) struct RawMessage {
+    fields: Vec<Vec<u8>>,
+    body: Vec<u8>,
+}
+
+impl From<&[u8]> for RawMessage {
+    fn from(raw: &[u8]) -> Self {
+        let mut fields: Vec<Vec<u8>> = Vec::new();
+        let mut offset = 0;
+
+        for line in raw.split_inclusive(|byte| *byte == b'\n') {
+            offset += line.len();
+            if line == b"\r\n" || line == b"\n" {
+                break;
+            }
+            match fields.last_mut() {
+                Some(field) if line.starts_with(b" ") || line.starts_with(b"\t") => {
+                    field.extend_from_slice(line)
+                }
+                _ => fields.push(line.to_vec()),
+            }
+        }
+
+        Self {
+            fields,
+            body: raw[offset.min(raw.len())..].to_vec(),
+        }
+    }
+}
+
+impl RawMessage {
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        let mut raw = self.fields.concat();
+        raw.extend_from_slice(b"\r\n");
+        raw.extend(self.body);
+        raw
+    }
+
+    /// The decoded, unfolded value of the first field called `name`.
+    pub(crate) fn header(&self, name: &str) -> Option<String> {
+        self.fields
+            .iter()
+            .filter(|field| is_named(field, name))
+            .find_map(|field| mailparse::parse_header(field).ok())
+            .map(|(header, _)| header.get_value())
+    }
+
+    fn raw_header(&self, name: &str) -> Option<String> {
+        let field = self.fields.iter().find(|field| is_named(field, name))?;
+        let (_, value) = field.split_at(field.iter().position(|byte| *byte == b':')? + 1);
+        Some(String::from_utf8_lossy(value).trim().to_string())
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) {
+        self.fields.retain(|field| !is_named(field, name));
+    }
+
+    /// Replaces the fields called `name` with a single one, where the first
+    /// of them was.
+    pub(crate) fn set(&mut self, name: &str, value: &str) {
+        let field = format!("{name}: {value}\r\n").into_bytes();
+        let position = self.fields.iter().position(|field| is_named(field, name));
+        self.remove(name);
+        match position {
+            Some(position) => self.fields.insert(position, field),
+            None => self.fields.push(field),
+        }
+    }
+
+    fn content_type(&self) -> (String, Option<String>) {
+        let content_type = mailparse::parse_content_type(
+            &self
+                .header("Content-Type")
+                .unwrap_or_else(|| "text/plain".to_string()),
+        );
+        let boundary = content_type.params.get("boundary").cloned();
+        (content_type.mimetype.to_ascii_lowercase(), boundary)
+    }
+}
+
+fn is_named(field: &[u8], name: &str) -> bool {
+    field.len() > name.len()
+        && field[name.len()] == b':'
+        && field[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+}
+
+/// Turns a member's post into the message the list sends out.
+pub(crate) fn for_list(raw: &[u8], list: &ListSettings) -> Result<Vec<u8>> {
+    let mut message = RawMessage::from(raw);
+
+    for name in DROPPED_HEADERS {
+        message.remove(name);
+    }
+    message.fields.retain(|field| {
+        !field
+            .get(..5)
+            .unwrap_or_default()
+            .eq_ignore_ascii_case(b"list-")
+    });
+
+    if let Some(prefix) = &list.prefix {
+        let subject = message.header("Subject").unwrap_or_default();
+        if !subject.contains(prefix.as_str()) {
+            let raw_subject = message.raw_header("Subject").unwrap_or_default();
+            message.set("Subject", format!("{prefix} {raw_subject}").trim_end());
+        }
+    }
+
+    if list.rewrite {
+        rewrite_from(&mut message, list)?;
+    }
+
+    add_list_headers(&mut message, list);
+
+    if let Some(footer) = &list.footer {
+        add_footer(&mut message, footer)?;
+    }
+
+    Ok(message.into_bytes())
+}
+
+/// Sends the post from the list address, since the author's domain may
+/// publish a DMARC policy that rejects mail we send in its name.
+fn rewrite_from(message: &mut RawMessage, list: &ListSettings) -> Result<()> {
+    let raw_from = message
+        .raw_header("From")
+        .ok_or_else(|| anyhow!("Post has no From header"))?;
+    let from = message.header("From").unwrap_or_default();
+    let author = match mailparse::addrparse(&from)
+        .ok()
+        .and_then(|addresses| addresses.extract_single_info())
+    {
+        Some(info) => info.display_name.unwrap_or(info.addr),
+        None => from,
+    };
+
+    message.set(
+        "From",
+        &format!(
+            "{} <{}>",
+            display_name(&format!("{author} via {}", list.name)),
+            list.address
+        ),
+    );
+    if message.header("Reply-To").is_none() {
+        message.set("Reply-To", &raw_from);
+    }
+    message.set("X-Original-From", &raw_from);
+    Ok(())
+}
+
+/// Adds the RFC 2369 and RFC 2919 headers that let mail clients offer list
+/// actions and filter by list.
+pub(crate) fn add_list_headers(message: &mut RawMessage, list: &ListSettings) {
+    let request = list.request_address();
+
+    message.set(
+        "List-Id",
+        &format!(
+            "{} <{}>",
+            display_name(&list.name),
+            list.address.replace('@', ".")
+        ),
+    );
+    message.set("List-Post", &format!("<mailto:{}>", list.address));
+    message.set("List-Help", &format!("<mailto:{request}?subject=help>"));
+    message.set(
+        "List-Subscribe",
+        &format!("<mailto:{request}?subject=subscribe>"),
+    );
+    message.set(
+        "List-Unsubscribe",
+        &format!("<mailto:{request}?subject=unsubscribe>"),
+    );
+    message.set("Precedence", "list");
+}
+
+/// Appends the footer to a plain text post, as an extra part of a multipart
+/// one, or wraps any other post into a multipart message with the footer.
+fn add_footer(message: &mut RawMessage, footer: &str) -> Result<()> {
+    let (mimetype, boundary) = message.content_type();
+
+    match (mimetype.as_str(), boundary) {
+        ("text/plain", _) => {
+            let raw = message.clone().into_bytes();
+            let parsed = mailparse::parse_mail(&raw)?;
+            let text = format!(
+                "{}\r\n{}",
+                parsed.get_body()?.trim_end_matches(['\r', '\n']),
+                footer_text(footer)
+            );
+            let (encoding, body) = encode_text(&text);
+            message.set("MIME-Version", "1.0");
+            message.set("Content-Type", "text/plain; charset=utf-8");
+            message.set("Content-Transfer-Encoding", encoding);
+            message.body = body;
+        }
+        ("multipart/mixed", Some(boundary)) => {
+            let close = format!("--{boundary}--");
+            let position = find(&message.body, close.as_bytes())
+                .ok_or_else(|| anyhow!("Multipart post has no closing boundary"))?;
+            let mut part = format!("--{boundary}\r\n").into_bytes();
+            part.extend(footer_part(footer));
+            part.extend_from_slice(b"\r\n");
+            message.body.splice(position..position, part);
+        }
+        _ => {
+            // Derived from the content, so that it cannot occur in it.
+            let digest = Sha256::digest(&message.body);
+            let boundary: String = digest[..8]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            let boundary = format!("minimail-{boundary}");
+
+            let mut original = Vec::new();
+            for name in ["Content-Type", "Content-Transfer-Encoding"] {
+                if let Some(value) = message.raw_header(name) {
+                    original.extend(format!("{name}: {value}\r\n").into_bytes());
+                    message.remove(name);
+                }
+            }
+
+            let mut body = format!("--{boundary}\r\n").into_bytes();
+            body.extend(original);
+            body.extend_from_slice(b"\r\n");
+            body.append(&mut message.body);
+            if !body.ends_with(b"\n") {
+                body.extend_from_slice(b"\r\n");
+            }
+            body.extend(format!("--{boundary}\r\n").into_bytes());
+            body.extend(footer_part(footer));
+            body.extend(format!("\r\n--{boundary}--\r\n").into_bytes());
+
+            message.set("MIME-Version", "1.0");
+            message.set(
+                "Content-Type",
+                &format!("multipart/mixed; boundary=\"{boundary}\""),
+            );
+            message.body = body;
+        }
+    }
+    Ok(())
+}
+
+fn footer_text(footer: &str) -> String {
+    format!("{}\r\n{}\r\n", "_".repeat(40), footer.trim_end())
+}
+
+fn footer_part(footer: &str) -> Vec<u8> {
+    let (encoding, body) = encode_text(&footer_text(footer));
+    let mut part = format!(
+        "Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Disposition: inline\r\n\
+         Content-Transfer-Encoding: {encoding}\r\n\r\n"
+    )
+    .into_bytes();
+    part.extend(body);
+    part
+}
+
+/// Encodes text for a body, as is when it is ASCII and in base64 otherwise,
+/// since not every relay supports 8BITMIME.
+pub(crate) fn encode_text(text: &str) -> (&'static str, Vec<u8>) {
+    let text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+    if text.is_ascii() {
+        return ("7bit", text.into_bytes());
+    }
+
+    let encoded = STANDARD.encode(text);
+    let mut body = Vec::with_capacity(encoded.len() + encoded.len() / 38);
+    for line in encoded.as_bytes().chunks(76) {
+        body.extend_from_slice(line);
+        body.extend_from_slice(b"\r\n");
+    }
+    ("base64", body)
+}
+
+/// Quotes a display name, or encodes it (RFC 2047) when it is not ASCII.
+/// Control characters become spaces, since a name decoded from a post may
+/// hold line breaks that would otherwise start headers of their own.
+pub(crate) fn display_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if name.is_ascii() {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("=?utf-8?b?{}?=", STANDARD.encode(name))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use mailparse::MailHeaderMap;
+
+    use super::*;
+    use crate::config::ListMode;
+
+    fn list(footer: Option<&str>) -> ListSettings {
+        ListSettings {
+            name: "News".to_string(),
+            address: "news@example.com".to_string(),
+            secret: "secret".to_string(),
+            mode: ListMode::Discussion,
+            prefix: Some("[news]".to_string()),
+            footer: footer.map(str::to_string),
+            rewrite: true,
+            digest: 24,
+            timezone: "UTC".to_string(),
+            origins: Vec::new(),
+        }
+    }
+
+    fn rewrite(raw: &str, list: &ListSettings) -> String {
+        String::from_utf8(for_list(raw.as_bytes(), list).unwrap()).unwrap()
+    }
+
+    const POST: &str = "From: Alice <alice@example.org>\r\n\
+                        To: news@example.com\r\n\
+                        Subject: Hello\r\n\
+                        DKIM-Signature: v=1; a=rsa-sha256;\r\n\
+                        \tb=abc\r\n\
+                        List-Id: <other.example.net>\r\n\
+                        \r\n\
+                        Hi all\r\n";
+
+    #[test]
+    fn rewrites_headers_for_list() {
+        let rewritten = rewrite(POST, &list(None));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+        let header = |name| mail.headers.get_first_value(name).unwrap();
+
+        assert_eq!("[news] Hello", header("Subject"));
+        assert_eq!("\"Alice via News\" <news@example.com>", header("From"));
+        assert_eq!("Alice <alice@example.org>", header("Reply-To"));
+        assert_eq!("\"News\" <news.example.com>", header("List-Id"));
+        assert_eq!("<mailto:news@example.com>", header("List-Post"));
+        assert_eq!(
+            "<mailto:news-request@example.com?subject=unsubscribe>",
+            header("List-Unsubscribe")
+        );
+        assert_eq!("list", header("Precedence"));
+        assert!(mail.headers.get_first_value("DKIM-Signature").is_none());
+        assert_eq!(1, mail.headers.get_all_values("List-Id").len());
+        assert_eq!("Hi all\r\n", mail.get_body().unwrap());
+    }
+
+    #[test]
+    fn keeps_existing_prefix_and_reply_to() {
+        let raw = "From: alice@example.org\r\n\
+                   Reply-To: team@example.org\r\n\
+                   Subject: Re: [news] Hello\r\n\
+                   \r\n\
+                   Hi\r\n";
+
+        let rewritten = rewrite(raw, &list(None));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        assert_eq!(
+            "Re: [news] Hello",
+            mail.headers.get_first_value("Subject").unwrap()
+        );
+        assert_eq!(
+            "team@example.org",
+            mail.headers.get_first_value("Reply-To").unwrap()
+        );
+        assert_eq!(
+            "\"alice@example.org via News\" <news@example.com>",
+            mail.headers.get_first_value("From").unwrap()
+        );
+    }
+
+    #[test]
+    fn line_breaks_in_from_name_do_not_add_headers() {
+        // The name decodes to "Eve\r\nBcc: all@example.net".
+        let raw = "From: =?utf-8?b?RXZlDQpCY2M6IGFsbEBleGFtcGxlLm5ldA==?= <eve@example.org>\r\n\
+                   Subject: Hello\r\n\
+                   \r\n\
+                   Hi\r\n";
+
+        let rewritten = rewrite(raw, &list(None));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        assert!(mail.headers.get_first_value("Bcc").is_none());
+        assert_eq!(
+            "\"Eve  Bcc: all@example.net <eve@example.org> via News\" <news@example.com>",
+            mail.headers.get_first_value("From").unwrap()
+        );
+    }
+
+    #[test]
+    fn leaves_from_alone_without_rewriting() {
+        let mut list = list(None);
+        list.rewrite = false;
+
+        let rewritten = rewrite(POST, &list);
+
+        assert!(rewritten.contains("From: Alice <alice@example.org>\r\n"));
+        assert!(!rewritten.contains("Reply-To"));
+    }
+
+    #[test]
+    fn appends_footer_to_plain_text() {
+        let rewritten = rewrite(POST, &list(Some("Unsubscribe: news-request@example.com")));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        let body = mail.get_body().unwrap();
+        assert!(body.starts_with("Hi all\r\n____"));
+        assert!(body.ends_with("Unsubscribe: news-request@example.com\r\n"));
+    }
+
+    #[test]
+    fn encodes_footer_that_is_not_ascii() {
+        let rewritten = rewrite(POST, &list(Some("Grüße")));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        assert!(rewritten.contains("Content-Transfer-Encoding: base64"));
+        assert!(mail.get_body().unwrap().ends_with("Grüße\r\n"));
+    }
+
+    #[test]
+    fn adds_footer_part_to_mixed_post() {
+        let raw = "From: alice@example.org\r\n\
+                   Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+                   \r\n\
+                   --b\r\n\
+                   Content-Type: text/plain\r\n\
+                   \r\n\
+                   Hi\r\n\
+                   --b--\r\n";
+
+        let rewritten = rewrite(raw, &list(Some("Footer")));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        assert_eq!(2, mail.subparts.len());
+        assert!(mail.subparts[1].get_body().unwrap().contains("Footer"));
+    }
+
+    #[test]
+    fn wraps_other_posts_with_footer() {
+        let raw = "From: alice@example.org\r\n\
+                   Content-Type: multipart/alternative; boundary=\"b\"\r\n\
+                   \r\n\
+                   --b\r\n\
+                   Content-Type: text/plain\r\n\
+                   \r\n\
+                   Hi\r\n\
+                   --b\r\n\
+                   Content-Type: text/html\r\n\
+                   \r\n\
+                   <p>Hi</p>\r\n\
+                   --b--\r\n";
+
+        let rewritten = rewrite(raw, &list(Some("Footer")));
+        let mail = mailparse::parse_mail(rewritten.as_bytes()).unwrap();
+
+        assert_eq!("multipart/mixed", mail.ctype.mimetype);
+        assert_eq!("multipart/alternative", mail.subparts[0].ctype.mimetype);
+        assert_eq!(2, mail.subparts[0].subparts.len());
+        assert!(mail.subparts[1].get_body().unwrap().contains("Footer"));
+    }
+}
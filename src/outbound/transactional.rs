@@ -0,0 +1,200 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+    Message,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    model::{Email, Rendered, Template},
+    outbound::AutoSubmitted,
+};
+
+/// A one-off message, such as a receipt or a password reset, sent for an
+/// application through `POST /api/send`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendRequest {
+    pub to: String,
+    // Either a template, which is filled in with `variables`...
+    pub template_id: Option<i32>,
+    #[serde(default)]
+    pub variables: Map<String, Value>,
+    // ...or the content as it is.
+    pub subject: Option<String>,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<SendAttachment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SendAttachment {
+    pub filename: String,
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+    // Base64 encoded.
+    pub content: String,
+}
+
+fn default_content_type() -> String {
+    "application/octet-stream".to_string()
+}
+
+enum Body {
+    Single(SinglePart),
+    Multi(MultiPart),
+}
+
+impl SendRequest {
+    /// Builds the message, with `template` being the one named by
+    /// `template_id`. Errors describe what is wrong with the request.
+    pub fn build(&self, from: Mailbox, template: Option<&Template>) -> Result<Message, String> {
+        let to: Mailbox = self
+            .to
+            .parse()
+            .map_err(|_| format!("{} is not a valid recipient.", self.to))?;
+
+        let rendered = match template {
+            Some(template) => template.render(&Email(to.email.to_string()), &self.variables),
+            None => Rendered {
+                subject: self
+                    .subject
+                    .clone()
+                    .ok_or("A subject or a template is required.")?,
+                text: self.text.clone().unwrap_or_default(),
+                html: self.html.clone(),
+            },
+        };
+        let body = match rendered.html {
+            Some(html) if rendered.text.is_empty() => Body::Single(SinglePart::html(html)),
+            Some(html) => Body::Multi(MultiPart::alternative_plain_html(rendered.text, html)),
+            None if rendered.text.is_empty() => {
+                return Err("A text or HTML body is required.".to_string())
+            }
+            None => Body::Single(SinglePart::plain(rendered.text)),
+        };
+
+        let builder = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(rendered.subject)
+            .header(AutoSubmitted::generated());
+        let message = if self.attachments.is_empty() {
+            match body {
+                Body::Single(part) => builder.singlepart(part),
+                Body::Multi(part) => builder.multipart(part),
+            }
+        } else {
+            let mut mixed = match body {
+                Body::Single(part) => MultiPart::mixed().singlepart(part),
+                Body::Multi(part) => MultiPart::mixed().multipart(part),
+            };
+            for attachment in &self.attachments {
+                mixed = mixed.singlepart(attachment.part()?);
+            }
+            builder.multipart(mixed)
+        };
+        message.map_err(|e| e.to_string())
+    }
+}
+
+impl SendAttachment {
+    fn part(&self) -> Result<SinglePart, String> {
+        let content = STANDARD
+            .decode(&self.content)
+            .map_err(|_| format!("Attachment {} is not valid base64.", self.filename))?;
+        let content_type = ContentType::parse(&self.content_type)
+            .map_err(|_| format!("{} is not a valid content type.", self.content_type))?;
+        Ok(Attachment::new(self.filename.clone()).body(content, content_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use mailparse::MailHeaderMap;
+    use serde_json::json;
+
+    use super::*;
+
+    fn request(body: Value) -> SendRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    fn from() -> Mailbox {
+        "Minimail <minimail@example.com>".parse().unwrap()
+    }
+
+    #[test]
+    fn build_fills_in_template() {
+        let template = Template {
+            id: 1,
+            name: "reset".to_string(),
+            subject: "Reset your password".to_string(),
+            text: "Go to {{ link }}".to_string(),
+            html: None,
+            created_at: Utc::now(),
+        };
+        let request = request(json!({
+            "to": "ada@example.org",
+            "template_id": 1,
+            "variables": {"link": "https://example.com/reset"}
+        }));
+
+        let raw = request.build(from(), Some(&template)).unwrap().formatted();
+
+        let mail = mailparse::parse_mail(&raw).unwrap();
+        assert_eq!(
+            Some("Reset your password".to_string()),
+            mail.headers.get_first_value("Subject")
+        );
+        assert_eq!(
+            "Go to https://example.com/reset",
+            mail.get_body().unwrap().trim_end()
+        );
+    }
+
+    #[test]
+    fn build_adds_attachments() {
+        let request = request(json!({
+            "to": "ada@example.org",
+            "subject": "Your receipt",
+            "text": "Thanks!",
+            "html": "<p>Thanks!</p>",
+            "attachments": [{
+                "filename": "receipt.txt",
+                "content_type": "text/plain",
+                "content": STANDARD.encode("Paid: 10 EUR")
+            }]
+        }));
+
+        let raw = request.build(from(), None).unwrap().formatted();
+
+        let mail = mailparse::parse_mail(&raw).unwrap();
+        assert_eq!("multipart/mixed", mail.ctype.mimetype);
+        assert_eq!("multipart/alternative", mail.subparts[0].ctype.mimetype);
+        assert_eq!(
+            "Paid: 10 EUR",
+            mail.subparts[1].get_body().unwrap().trim_end()
+        );
+    }
+
+    #[test]
+    fn build_rejects_incomplete_requests() {
+        let without_body = request(json!({"to": "ada@example.org", "subject": "Hi"}));
+        let without_subject = request(json!({"to": "ada@example.org", "text": "Hi"}));
+        let bad_recipient = request(json!({"to": "ada", "subject": "Hi", "text": "Hi"}));
+        let bad_attachment = request(json!({
+            "to": "ada@example.org",
+            "subject": "Hi",
+            "text": "Hi",
+            "attachments": [{"filename": "a.bin", "content": "not base64!"}]
+        }));
+
+        assert!(without_body.build(from(), None).is_err());
+        assert!(without_subject.build(from(), None).is_err());
+        assert!(bad_recipient.build(from(), None).is_err());
+        assert!(bad_attachment.build(from(), None).is_err());
+    }
+}
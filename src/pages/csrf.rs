@@ -0,0 +1,117 @@
+use axum::headers::Cookie;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::signing::Signer;
+
+const COOKIE: &str = "minimail_csrf";
+const FIELD: &str = "csrf";
+
+#[derive(Serialize, Deserialize)]
+struct CsrfToken {
+    #[serde(rename = "n")]
+    nonce: String,
+}
+
+/// Guards the forms of the hosted pages with a random nonce kept in a
+/// cookie, which the form has to repeat, signed, in a hidden field. Other
+/// sites can make a browser post a form, but cannot read the cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csrf {
+    nonce: String,
+    // The visitor has no cookie yet, so the page has to set one.
+    fresh: bool,
+}
+
+impl Csrf {
+    /// Reuses the nonce in the visitor's cookie, so that forms open in other
+    /// tabs keep working, or makes a new one.
+    pub fn new(cookie: Option<&Cookie>) -> Self {
+        match cookie.and_then(|cookie| cookie.get(COOKIE)) {
+            Some(nonce) if !nonce.is_empty() => Self {
+                nonce: nonce.to_string(),
+                fresh: false,
+            },
+            _ => Self {
+                nonce: URL_SAFE_NO_PAD.encode(rand::thread_rng().gen::<[u8; 16]>()),
+                fresh: true,
+            },
+        }
+    }
+
+    /// The hidden form field carrying the signed nonce.
+    pub fn field(&self, signer: &Signer) -> String {
+        let token = signer.sign(&CsrfToken {
+            nonce: self.nonce.clone(),
+        });
+        format!("<input type=\"hidden\" name=\"{FIELD}\" value=\"{token}\">")
+    }
+
+    /// The `Set-Cookie` header value, unless the visitor already has it.
+    pub fn set_cookie(&self) -> Option<String> {
+        self.fresh
+            .then(|| format!("{COOKIE}={}; Path=/; HttpOnly; SameSite=Lax", self.nonce))
+    }
+
+    /// Whether a submitted form repeats the nonce in the visitor's cookie.
+    pub fn verify(signer: &Signer, cookie: Option<&Cookie>, token: &str) -> bool {
+        let Some(nonce) = cookie.and_then(|cookie| cookie.get(COOKIE)) else {
+            return false;
+        };
+        signer
+            .verify::<CsrfToken>(token)
+            .is_some_and(|token| !nonce.is_empty() && token.nonce == nonce)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::headers::{Header, HeaderValue};
+
+    use super::*;
+
+    fn cookie(value: &str) -> Cookie {
+        Cookie::decode(&mut [HeaderValue::from_str(value).unwrap()].iter()).unwrap()
+    }
+
+    fn token(field: &str) -> &str {
+        field
+            .split("value=\"")
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap()
+    }
+
+    #[test]
+    fn sets_cookie_only_for_new_visitors() {
+        let fresh = Csrf::new(None);
+        let returning = Csrf::new(Some(&cookie("minimail_csrf=abc")));
+
+        assert!(fresh.set_cookie().unwrap().starts_with("minimail_csrf="));
+        assert_eq!(None, returning.set_cookie());
+    }
+
+    #[test]
+    fn verifies_form_matching_cookie() {
+        let signer = Signer::new("secret");
+        let field = Csrf::new(Some(&cookie("minimail_csrf=abc"))).field(&signer);
+
+        assert!(Csrf::verify(
+            &signer,
+            Some(&cookie("other=1; minimail_csrf=abc")),
+            token(&field)
+        ));
+        assert!(!Csrf::verify(
+            &signer,
+            Some(&cookie("minimail_csrf=xyz")),
+            token(&field)
+        ));
+        assert!(!Csrf::verify(&signer, None, token(&field)));
+        assert!(!Csrf::verify(
+            &Signer::new("other"),
+            Some(&cookie("minimail_csrf=abc")),
+            token(&field)
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+mod csrf;
+mod render;
+mod token;
+
+pub use csrf::Csrf;
+pub(crate) use render::fill;
+pub use render::{escape, page, Page};
+pub(crate) use token::{Confirmation, Link, Purpose, SubscriberToken};
+
+use anyhow::Result;
+use lettre::{message::Mailbox, Message};
+
+use crate::{
+    data::ApplicationData,
+    model::{Email, Subscriber},
+    outbound::{self, AutoSubmitted},
+    signing::Signer,
+    store::{PsqlSubscriberStore, SubscriberStore},
+};
+
+/// Mails a link to confirm a subscription from the hosted page. Nobody is
+/// subscribed until it is followed. Subscribers get a link to their
+/// preferences instead.
+pub(crate) async fn request_confirmation(
+    data: &ApplicationData,
+    email: &Email,
+    timezone: Option<String>,
+) -> Result<()> {
+    let list = &data.list.name;
+    let existing = PsqlSubscriberStore::from(data.pool.clone())
+        .find(email)
+        .await?;
+    let (subject, body) = match existing {
+        Some(subscriber) => (
+            format!("You are subscribed to {list}"),
+            format!(
+                "{} is already subscribed to {list}.\n\n\
+                 To change what you receive, or to unsubscribe, go to {}\n",
+                email.0,
+                preferences_url(data, &subscriber)
+            ),
+        ),
+        None => {
+            let token =
+                Signer::new(&data.list.secret).sign(&Confirmation::new(email.0.clone(), timezone));
+            (
+                format!("Confirm your subscription to {list}"),
+                format!(
+                    "Someone, hopefully you, asked to subscribe {} to {list}.\n\n\
+                     To confirm, go to {}\n\n\
+                     If you did not ask for this, ignore this message.\n",
+                    email.0,
+                    link(data, "confirm", &token)
+                ),
+            )
+        }
+    };
+
+    let from = Mailbox::new(Some(list.clone()), data.list.address.parse()?);
+    let message = Message::builder()
+        .from(from)
+        .to(email.0.parse()?)
+        .subject(subject)
+        .header(AutoSubmitted::generated())
+        .body(body)?;
+    outbound::queue(data, message).await
+}
+
+/// Links to leaving, counted towards the campaign the link was sent in.
+pub(crate) fn unsubscribe_url(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    campaign_id: Option<i32>,
+) -> String {
+    link(
+        data,
+        "unsubscribe",
+        &subscriber_token(data, subscriber, Link::Unsubscribe, campaign_id),
+    )
+}
+
+pub(crate) fn preferences_url(data: &ApplicationData, subscriber: &Subscriber) -> String {
+    link(
+        data,
+        "preferences",
+        &subscriber_token(data, subscriber, Link::Preferences, None),
+    )
+}
+
+fn subscriber_token(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    link: Link,
+    campaign_id: Option<i32>,
+) -> String {
+    Signer::new(&data.list.secret).sign(&SubscriberToken::new(subscriber.id, link, campaign_id))
+}
+
+fn link(data: &ApplicationData, path: &str, token: &str) -> String {
+    format!("{}/{path}/{token}", data.pages.url.trim_end_matches('/'))
+}
@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use log::warn;
+
+use crate::config::PagesSettings;
+
+/// A hosted page, filled into its template and then into the layout.
+/// Templates are looked up in the configured directory first, so that a
+/// list can restyle or reword any page, and otherwise built in.
+pub struct Page {
+    name: &'static str,
+    title: String,
+    // Placeholders and their values, already escaped.
+    variables: Vec<(&'static str, String)>,
+}
+
+impl Page {
+    pub fn new(name: &'static str, title: impl Into<String>) -> Self {
+        Self {
+            name,
+            title: title.into(),
+            variables: Vec::new(),
+        }
+    }
+
+    /// Fills `{{ name }}` with text, escaped.
+    pub fn text(mut self, name: &'static str, value: &str) -> Self {
+        self.variables.push((name, escape(value)));
+        self
+    }
+
+    /// Fills `{{ name }}` with markup as is.
+    pub fn html(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.variables.push((name, value.into()));
+        self
+    }
+
+    pub async fn render(self, settings: &PagesSettings, list: &str) -> String {
+        let list = escape(list);
+        let mut variables = self.variables;
+        variables.push(("list", list.clone()));
+        let content = fill(&template(settings, self.name).await, &variables);
+
+        let stylesheet = settings
+            .stylesheet
+            .as_deref()
+            .map(|href| format!("<link rel=\"stylesheet\" href=\"{}\">", escape(href)))
+            .unwrap_or_default();
+        fill(
+            &template(settings, "layout").await,
+            &[
+                ("title", escape(&self.title)),
+                ("list", list),
+                ("stylesheet", stylesheet),
+                ("content", content),
+            ],
+        )
+    }
+}
+
+async fn template(settings: &PagesSettings, name: &str) -> String {
+    if let Some(directory) = &settings.templates {
+        let path = Path::new(directory).join(format!("{name}.html"));
+        match tokio::fs::read_to_string(&path).await {
+            Ok(template) => return template,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read page template {}: {e}", path.display()),
+        }
+    }
+    builtin(name).to_string()
+}
+
+fn builtin(name: &str) -> &'static str {
+    match name {
+        "layout" => include_str!("templates/layout.html"),
+        "subscribe" => include_str!("templates/subscribe.html"),
+        "check_inbox" => include_str!("templates/check_inbox.html"),
+        "confirm" => include_str!("templates/confirm.html"),
+        "confirmed" => include_str!("templates/confirmed.html"),
+        "pending" => include_str!("templates/pending.html"),
+        "unsubscribe" => include_str!("templates/unsubscribe.html"),
+        "unsubscribed" => include_str!("templates/unsubscribed.html"),
+        "preferences" => include_str!("templates/preferences.html"),
+        "saved" => include_str!("templates/saved.html"),
+        "forbidden" => include_str!("templates/forbidden.html"),
+        "subscribed" => include_str!("templates/subscribed.html"),
+        "error" => include_str!("templates/error.html"),
+        _ => include_str!("templates/invalid.html"),
+    }
+}
+
+/// Replaces every `{{ name }}` with its value. Unknown placeholders are left
+/// out.
+pub(crate) fn fill(template: &str, variables: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        let name = rest[start + 2..start + end].trim();
+        if let Some((_, value)) = variables.iter().find(|(key, _)| *key == name) {
+            filled.push_str(value);
+        }
+        rest = &rest[start + end + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// A bare page with its title as heading, for pages that have no template.
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\
+         <body><h1>{0}</h1>{body}</body></html>\n",
+        escape(title)
+    )
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(templates: Option<String>) -> PagesSettings {
+        PagesSettings {
+            url: "https://news.example.com".to_string(),
+            templates,
+            stylesheet: Some("/style.css".to_string()),
+            topics: Vec::new(),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn fill_replaces_placeholders() {
+        let filled = fill(
+            "{{ a }}, {{b}} and {{ unknown }}{{ open",
+            &[("a", "1".to_string()), ("b", "2".to_string())],
+        );
+
+        assert_eq!("1, 2 and {{ open", filled);
+    }
+
+    #[tokio::test]
+    async fn render_escapes_text_into_layout() {
+        let page = Page::new("unsubscribed", "Bye")
+            .text("email", "<ada@example.org>")
+            .render(&settings(None), "News & Views")
+            .await;
+
+        assert!(page.contains("<title>Bye · News &amp; Views</title>"));
+        assert!(page.contains("<link rel=\"stylesheet\" href=\"/style.css\">"));
+        assert!(page.contains("&lt;ada@example.org&gt; has been unsubscribed"));
+    }
+
+    #[tokio::test]
+    async fn render_prefers_templates_from_directory() {
+        let directory = std::env::temp_dir().join(format!("minimail-pages-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("layout.html"), "<body>{{ content }}</body>").unwrap();
+        std::fs::write(directory.join("saved.html"), "Saved for {{ list }}").unwrap();
+
+        let settings = settings(Some(directory.to_string_lossy().into_owned()));
+        let saved = Page::new("saved", "Saved").render(&settings, "News").await;
+        let unsubscribed = Page::new("unsubscribed", "Bye")
+            .text("email", "ada@example.org")
+            .render(&settings, "News")
+            .await;
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!("<body>Saved for News</body>", saved);
+        assert_eq!(
+            "<body><p>ada@example.org has been unsubscribed from News.</p>\n</body>",
+            unsubscribed
+        );
+    }
+}
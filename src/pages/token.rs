@@ -0,0 +1,113 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+const CONFIRMATION_DAYS: i64 = 7;
+// Long enough for old mail to still be unsubscribed from.
+const LINK_DAYS: i64 = 90;
+
+/// Proves that whoever confirms a subscription, or leaving by mail, received
+/// mail at the address.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Confirmation {
+    #[serde(rename = "e")]
+    pub email: String,
+    // Tokens from before leaving was confirmed have no purpose and subscribe.
+    #[serde(rename = "p", default)]
+    pub purpose: Purpose,
+    // Where the subscriber signed up from, when their browser told us.
+    #[serde(rename = "t", default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(rename = "x")]
+    pub expires: i64,
+}
+
+/// What confirming does, so that one kind of token can't stand in for the
+/// other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Purpose {
+    #[default]
+    Subscribe,
+    Unsubscribe,
+}
+
+impl Confirmation {
+    pub fn new(email: String, timezone: Option<String>) -> Self {
+        Self {
+            email,
+            purpose: Purpose::Subscribe,
+            timezone,
+            expires: (Utc::now() + Duration::days(CONFIRMATION_DAYS)).timestamp(),
+        }
+    }
+
+    pub fn unsubscribe(email: String) -> Self {
+        Self {
+            purpose: Purpose::Unsubscribe,
+            ..Self::new(email, None)
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.expires < Utc::now().timestamp()
+    }
+}
+
+/// Lets a subscriber unsubscribe or change their preferences with the link
+/// in mail they got, without logging in. It lasts for a while, so that old
+/// mail can still be unsubscribed from, and only opens the page it was made
+/// for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SubscriberToken {
+    #[serde(rename = "s")]
+    pub subscriber_id: i32,
+    #[serde(rename = "p")]
+    pub link: Link,
+    // The campaign the link was sent in, which leaving is counted towards.
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<i32>,
+    #[serde(rename = "x")]
+    pub expires: i64,
+}
+
+/// The page a [`SubscriberToken`] opens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Link {
+    Unsubscribe,
+    Preferences,
+}
+
+impl SubscriberToken {
+    pub fn new(subscriber_id: i32, link: Link, campaign_id: Option<i32>) -> Self {
+        Self {
+            subscriber_id,
+            link,
+            campaign_id,
+            expires: (Utc::now() + Duration::days(LINK_DAYS)).timestamp(),
+        }
+    }
+
+    /// Whether the token opens `link` still.
+    pub fn opens(&self, link: Link) -> bool {
+        self.link == link && self.expires >= Utc::now().timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscriber_token_only_opens_its_page_until_it_expires() {
+        let token = SubscriberToken::new(1, Link::Unsubscribe, None);
+        let expired = SubscriberToken {
+            expires: Utc::now().timestamp() - 1,
+            ..token
+        };
+
+        assert!(token.opens(Link::Unsubscribe));
+        assert!(!token.opens(Link::Preferences));
+        assert!(!expired.opens(Link::Unsubscribe));
+    }
+}
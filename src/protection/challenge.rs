@@ -0,0 +1,166 @@
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::config::{ChallengeKind, ChallengeSettings};
+
+pub trait ChallengeVerifier {
+    // Whether `answer` solves the challenge shown to the client at `client`.
+    async fn verify(&self, answer: &str, client: Option<IpAddr>) -> Result<bool>;
+}
+
+/// Verifies answers with a provider's `siteverify` API, which Turnstile,
+/// hCaptcha and reCAPTCHA share.
+#[derive(Clone, Debug)]
+pub struct HttpVerifier {
+    client: reqwest::Client,
+    url: String,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    success: bool,
+}
+
+impl HttpVerifier {
+    pub fn new(url: &str, secret: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+        }
+    }
+}
+
+impl ChallengeVerifier for HttpVerifier {
+    async fn verify(&self, answer: &str, client: Option<IpAddr>) -> Result<bool> {
+        let mut form = vec![
+            ("secret", self.secret.clone()),
+            ("response", answer.to_string()),
+        ];
+        if let Some(client) = client {
+            form.push(("remoteip", client.to_string()));
+        }
+        let body = self
+            .client
+            .post(&self.url)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let verdict: Verdict = serde_json::from_slice(&body)?;
+        Ok(verdict.success)
+    }
+}
+
+/// Passes a fixed answer, so that tests can sign up without a provider.
+#[derive(Clone, Debug)]
+pub struct StubVerifier {
+    answer: String,
+}
+
+impl StubVerifier {
+    pub fn new(answer: &str) -> Self {
+        Self {
+            answer: answer.to_string(),
+        }
+    }
+}
+
+impl ChallengeVerifier for StubVerifier {
+    async fn verify(&self, answer: &str, _client: Option<IpAddr>) -> Result<bool> {
+        Ok(answer == self.answer)
+    }
+}
+
+/// The verifier picked in the settings.
+#[derive(Clone, Debug)]
+pub enum Verifier {
+    Http(HttpVerifier),
+    Stub(StubVerifier),
+}
+
+impl TryFrom<&ChallengeSettings> for Verifier {
+    type Error = anyhow::Error;
+
+    fn try_from(settings: &ChallengeSettings) -> Result<Self> {
+        match settings.kind {
+            ChallengeKind::Http => {
+                let url = settings
+                    .verify_url
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("An http challenge needs a verify_url"))?;
+                Ok(Self::Http(HttpVerifier::new(url, &settings.secret)))
+            }
+            ChallengeKind::Stub => Ok(Self::Stub(StubVerifier::new(&settings.secret))),
+        }
+    }
+}
+
+impl ChallengeVerifier for Verifier {
+    async fn verify(&self, answer: &str, client: Option<IpAddr>) -> Result<bool> {
+        match self {
+            Self::Http(verifier) => verifier.verify(answer, client).await,
+            Self::Stub(verifier) => verifier.verify(answer, client).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use axum::{routing::post, Form, Json, Router};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Starts a provider that accepts the answer `right` with secret `s3cret`.
+    fn start_provider() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}/siteverify", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/siteverify",
+            post(|Form(form): Form<Vec<(String, String)>>| async move {
+                let field = |name: &str| {
+                    form.iter()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.clone())
+                };
+                let success = field("secret").as_deref() == Some("s3cret")
+                    && field("response").as_deref() == Some("right")
+                    && field("remoteip").as_deref() == Some("192.0.2.1");
+                Json::<Value>(json!({ "success": success }))
+            }),
+        );
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+        address
+    }
+
+    #[tokio::test]
+    async fn http_verifier_asks_provider() -> Result<()> {
+        let verifier = HttpVerifier::new(&start_provider(), "s3cret");
+        let client = Some("192.0.2.1".parse()?);
+
+        assert!(verifier.verify("right", client).await?);
+        assert!(!verifier.verify("wrong", client).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stub_verifier_passes_its_answer() -> Result<()> {
+        let verifier = StubVerifier::new("pass");
+
+        assert!(verifier.verify("pass", None).await?);
+        assert!(!verifier.verify("fail", None).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{Map, Value};
+
+use crate::protection::Rejection;
+
+/// How many signups were turned away for each reason since the app started.
+#[derive(Debug, Default)]
+pub struct RejectionMetrics {
+    counts: [AtomicU64; Rejection::ALL.len()],
+}
+
+impl RejectionMetrics {
+    pub fn count(&self, rejection: Rejection) {
+        self.counts[rejection as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, rejection: Rejection) -> u64 {
+        self.counts[rejection as usize].load(Ordering::Relaxed)
+    }
+
+    /// Every count by reason, e.g. `{"honeypot": 3, ...}`.
+    pub fn to_json(&self) -> Map<String, Value> {
+        Rejection::ALL
+            .iter()
+            .map(|rejection| (rejection.as_str().to_string(), self.get(*rejection).into()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_by_reason() {
+        let metrics = RejectionMetrics::default();
+
+        metrics.count(Rejection::Honeypot);
+        metrics.count(Rejection::Honeypot);
+        metrics.count(Rejection::ChallengeFailed);
+
+        assert_eq!(2, metrics.get(Rejection::Honeypot));
+        assert_eq!(0, metrics.get(Rejection::TooFast));
+        assert_eq!(
+            Some(&Value::from(1)),
+            metrics.to_json().get("challenge_failed")
+        );
+    }
+}
@@ -0,0 +1,386 @@
+mod challenge;
+mod metrics;
+mod rate_limit;
+mod stamp;
+
+pub use challenge::{ChallengeVerifier, Verifier};
+pub use metrics::RejectionMetrics;
+pub use rate_limit::RateLimiter;
+pub use stamp::FormStamp;
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use axum::http::HeaderMap;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+
+use crate::{
+    config::ProtectionSettings,
+    forms::{is_bot, text, HONEYPOT},
+    pages::escape,
+    signing::Signer,
+};
+
+/// The form field that carries the signed time the form was loaded.
+pub const STAMP: &str = "started";
+
+/// Why a signup was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    Honeypot,
+    TooFast,
+    IpRateLimited,
+    AddressRateLimited,
+    ChallengeFailed,
+}
+
+impl Rejection {
+    pub const ALL: [Rejection; 5] = [
+        Rejection::Honeypot,
+        Rejection::TooFast,
+        Rejection::IpRateLimited,
+        Rejection::AddressRateLimited,
+        Rejection::ChallengeFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rejection::Honeypot => "honeypot",
+            Rejection::TooFast => "too_fast",
+            Rejection::IpRateLimited => "ip_rate_limited",
+            Rejection::AddressRateLimited => "address_rate_limited",
+            Rejection::ChallengeFailed => "challenge_failed",
+        }
+    }
+
+    /// What to tell the person who sent the form.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Rejection::Honeypot => "",
+            Rejection::TooFast => "The form was sent too quickly. Please try again.",
+            Rejection::IpRateLimited | Rejection::AddressRateLimited => {
+                "Too many signups. Please try again later."
+            }
+            Rejection::ChallengeFailed => "Please complete the challenge.",
+        }
+    }
+
+    pub fn is_rate_limit(&self) -> bool {
+        matches!(
+            self,
+            Rejection::IpRateLimited | Rejection::AddressRateLimited
+        )
+    }
+}
+
+/// Keeps bots and floods away from the public signup forms.
+#[derive(Clone, Debug)]
+pub struct Protection {
+    pub settings: ProtectionSettings,
+    signer: Signer,
+    limiter: RateLimiter,
+    verifier: Option<Verifier>,
+    pub metrics: Arc<RejectionMetrics>,
+}
+
+impl Protection {
+    pub fn new(settings: ProtectionSettings, secret: &str, pool: PgPool) -> Result<Self> {
+        let verifier = settings
+            .challenge
+            .as_ref()
+            .map(Verifier::try_from)
+            .transpose()?;
+        Ok(Self {
+            limiter: RateLimiter::new(&settings.rate_limit, pool),
+            signer: Signer::new(secret),
+            verifier,
+            metrics: Arc::default(),
+            settings,
+        })
+    }
+
+    /// A signed stamp for a form loaded now.
+    pub fn stamp(&self) -> String {
+        self.signer.sign(&FormStamp::new())
+    }
+
+    pub fn min_fill_seconds(&self) -> i64 {
+        self.settings.min_fill_seconds
+    }
+
+    /// The hidden fields and challenge widget for a form loaded now.
+    pub fn fields(&self) -> String {
+        let mut html = format!(
+            "<div style=\"position: absolute; left: -5000px\" aria-hidden=\"true\">\
+             <input name=\"{HONEYPOT}\" tabindex=\"-1\" autocomplete=\"off\"></div>\n"
+        );
+        if self.settings.min_fill_seconds > 0 {
+            html.push_str(&format!(
+                "<input type=\"hidden\" name=\"{STAMP}\" value=\"{}\">\n",
+                escape(&self.stamp())
+            ));
+        }
+        if let Some(widget) = self.widget() {
+            html.push_str(widget);
+            html.push('\n');
+        }
+        html
+    }
+
+    pub fn widget(&self) -> Option<&str> {
+        self.settings.challenge.as_ref()?.widget.as_deref()
+    }
+
+    /// The address of the client, from the proxy when it is trusted. Only
+    /// the entries that the trusted proxies added count, anything left of
+    /// them may be made up by the client.
+    pub fn client(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        if self.settings.trust_forwarded {
+            let entries: Vec<&str> = headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .collect();
+            let forwarded = entries
+                .len()
+                .checked_sub(self.settings.forwarded_hops.max(1))
+                .and_then(|index| entries[index].parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        peer.map(|peer| peer.ip())
+    }
+
+    /// Checks a signup form, counting it against the rate limits.
+    pub async fn check(
+        &self,
+        fields: &Map<String, Value>,
+        client: Option<IpAddr>,
+    ) -> Result<(), Rejection> {
+        let result = self.rejection(fields, client).await;
+        if let Err(rejection) = result {
+            info!("Rejecting signup from {client:?}: {}", rejection.as_str());
+            self.metrics.count(rejection);
+        }
+        result
+    }
+
+    async fn rejection(
+        &self,
+        fields: &Map<String, Value>,
+        client: Option<IpAddr>,
+    ) -> Result<(), Rejection> {
+        if is_bot(fields) {
+            return Err(Rejection::Honeypot);
+        }
+
+        let min_fill = self.settings.min_fill_seconds;
+        if min_fill > 0 {
+            let fresh = text(fields, STAMP)
+                .is_some_and(|stamp| FormStamp::verify(&self.signer, &stamp, min_fill));
+            if !fresh {
+                return Err(Rejection::TooFast);
+            }
+        }
+
+        let limits = &self.settings.rate_limit;
+        if let Some(client) = client {
+            if !self.allow(&format!("ip:{client}"), limits.per_ip).await {
+                return Err(Rejection::IpRateLimited);
+            }
+        }
+        if let Some(email) = text(fields, "email").filter(|email| !email.is_empty()) {
+            let key = format!("email:{}", email.to_lowercase());
+            if !self.allow(&key, limits.per_address).await {
+                return Err(Rejection::AddressRateLimited);
+            }
+        }
+
+        if let (Some(verifier), Some(challenge)) = (&self.verifier, &self.settings.challenge) {
+            let answer = text(fields, &challenge.field).unwrap_or_default();
+            match verifier.verify(&answer, client).await {
+                Ok(true) => {}
+                Ok(false) => return Err(Rejection::ChallengeFailed),
+                Err(e) => {
+                    error!("Failed to verify challenge: {e}");
+                    return Err(Rejection::ChallengeFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts an attempt to sign in to the admin interface from `client`,
+    /// and tells whether it stays within `limit` per window.
+    pub async fn allow_sign_in(&self, client: IpAddr, limit: i64) -> bool {
+        self.allow(&format!("admin:{client}"), limit).await
+    }
+
+    /// Lets signups through when the rate limit store fails, so that an
+    /// outage of the store doesn't close the list.
+    async fn allow(&self, key: &str, limit: i64) -> bool {
+        self.limiter.allow(key, limit).await.unwrap_or_else(|e| {
+            error!("Failed to check rate limit for {key}: {e}");
+            true
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::{ChallengeKind, ChallengeSettings, RateLimitSettings};
+
+    fn fields(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn protection(pool: PgPool, settings: ProtectionSettings) -> Protection {
+        Protection::new(settings, "secret", pool).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn check_rejects_in_order(pool: PgPool) {
+        let protection = protection(
+            pool,
+            ProtectionSettings {
+                min_fill_seconds: 3,
+                ..ProtectionSettings::default()
+            },
+        );
+
+        let bot = fields(json!({"email": "ada@example.org", "website": "spam"}));
+        let fast = fields(json!({"email": "ada@example.org", "started": protection.stamp()}));
+
+        assert_eq!(Err(Rejection::Honeypot), protection.check(&bot, None).await);
+        assert_eq!(Err(Rejection::TooFast), protection.check(&fast, None).await);
+        assert_eq!(1, protection.metrics.get(Rejection::Honeypot));
+        assert_eq!(1, protection.metrics.get(Rejection::TooFast));
+    }
+
+    #[sqlx::test]
+    async fn check_limits_addresses_and_clients(pool: PgPool) {
+        let protection = protection(
+            pool,
+            ProtectionSettings {
+                rate_limit: RateLimitSettings {
+                    per_ip: 3,
+                    per_address: 2,
+                    ..RateLimitSettings::default()
+                },
+                ..ProtectionSettings::default()
+            },
+        );
+        let client = Some("192.0.2.1".parse().unwrap());
+        let ada = fields(json!({"email": "ada@example.org"}));
+        let shouting = fields(json!({"email": "ADA@example.org"}));
+        let grace = fields(json!({"email": "grace@example.org"}));
+
+        assert_eq!(Ok(()), protection.check(&ada, client).await);
+        assert_eq!(Ok(()), protection.check(&shouting, None).await);
+        assert_eq!(
+            Err(Rejection::AddressRateLimited),
+            protection.check(&ada, None).await
+        );
+        assert_eq!(Ok(()), protection.check(&grace, client).await);
+        assert_eq!(Ok(()), protection.check(&grace, client).await);
+        assert_eq!(
+            Err(Rejection::IpRateLimited),
+            protection.check(&grace, client).await
+        );
+    }
+
+    #[sqlx::test]
+    async fn check_asks_verifier(pool: PgPool) {
+        let protection = protection(
+            pool,
+            ProtectionSettings {
+                challenge: Some(ChallengeSettings {
+                    kind: ChallengeKind::Stub,
+                    verify_url: None,
+                    secret: "pass".to_string(),
+                    field: "challenge".to_string(),
+                    widget: Some("<div class=\"widget\"></div>".to_string()),
+                }),
+                ..ProtectionSettings::default()
+            },
+        );
+
+        let wrong = fields(json!({"email": "ada@example.org", "challenge": "fail"}));
+        let right = fields(json!({"email": "ada@example.org", "challenge": "pass"}));
+
+        assert_eq!(
+            Err(Rejection::ChallengeFailed),
+            protection.check(&wrong, None).await
+        );
+        assert_eq!(Ok(()), protection.check(&right, None).await);
+        assert!(protection.fields().contains("<div class=\"widget\"></div>"));
+    }
+
+    #[sqlx::test]
+    async fn client_trusts_proxy_when_configured(pool: PgPool) {
+        let mut headers = HeaderMap::new();
+        // The client made up the first entry, the proxies added the others.
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.1, 203.0.113.7, 10.0.0.2".parse().unwrap(),
+        );
+        let peer = Some("10.0.0.1:4000".parse().unwrap());
+        let direct = protection(pool.clone(), ProtectionSettings::default());
+        let proxied = |hops| {
+            protection(
+                pool.clone(),
+                ProtectionSettings {
+                    trust_forwarded: true,
+                    forwarded_hops: hops,
+                    ..ProtectionSettings::default()
+                },
+            )
+        };
+
+        assert_eq!(
+            Some("10.0.0.1".parse().unwrap()),
+            direct.client(&headers, peer)
+        );
+        assert_eq!(
+            Some("10.0.0.2".parse().unwrap()),
+            proxied(1).client(&headers, peer)
+        );
+        assert_eq!(
+            Some("203.0.113.7".parse().unwrap()),
+            proxied(2).client(&headers, peer)
+        );
+        assert_eq!(
+            Some("10.0.0.1".parse().unwrap()),
+            proxied(4).client(&headers, peer)
+        );
+    }
+
+    #[sqlx::test]
+    async fn http_challenge_needs_url(pool: PgPool) {
+        let settings = ProtectionSettings {
+            challenge: Some(ChallengeSettings {
+                kind: ChallengeKind::Http,
+                verify_url: None,
+                secret: "secret".to_string(),
+                field: "challenge".to_string(),
+                widget: None,
+            }),
+            ..ProtectionSettings::default()
+        };
+
+        assert!(Protection::new(settings, "secret", pool).is_err());
+    }
+}
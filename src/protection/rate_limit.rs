@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{RateLimitBackend, RateLimitSettings},
+    store::{InMemoryRateLimitStore, PsqlRateLimitStore, RateLimitStore},
+};
+
+#[derive(Clone, Debug)]
+enum Backend {
+    Memory(Arc<Mutex<InMemoryRateLimitStore>>),
+    Postgres(PgPool),
+}
+
+/// Counts attempts per key in fixed windows, in the configured store.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    backend: Backend,
+    window: i64,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &RateLimitSettings, pool: PgPool) -> Self {
+        let backend = match settings.store {
+            RateLimitBackend::Memory => Backend::Memory(Arc::default()),
+            RateLimitBackend::Postgres => Backend::Postgres(pool),
+        };
+        Self {
+            backend,
+            window: settings.window.max(1),
+        }
+    }
+
+    /// Counts an attempt for `key`, and tells whether it stays within
+    /// `limit`. A `limit` of 0 allows everything.
+    pub async fn allow(&self, key: &str, limit: i64) -> Result<bool> {
+        if limit <= 0 {
+            return Ok(true);
+        }
+        let window_start = self.window_start(Utc::now());
+        let hits = match &self.backend {
+            Backend::Memory(store) => hit(&mut *store.lock().await, key, window_start).await?,
+            Backend::Postgres(pool) => {
+                hit(
+                    &mut PsqlRateLimitStore::from(pool.clone()),
+                    key,
+                    window_start,
+                )
+                .await?
+            }
+        };
+        Ok(hits <= limit)
+    }
+
+    fn window_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let start = now.timestamp() - now.timestamp().rem_euclid(self.window);
+        DateTime::from_timestamp(start, 0).unwrap_or(now)
+    }
+}
+
+/// Counts a hit, and forgets earlier windows whenever a key starts a new
+/// one, which keeps the store small without a worker.
+async fn hit(
+    store: &mut impl RateLimitStore,
+    key: &str,
+    window_start: DateTime<Utc>,
+) -> Result<i64> {
+    let hits = store.hit(key, window_start).await?;
+    if hits == 1 {
+        store.prune(window_start - Duration::seconds(1)).await?;
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(store: RateLimitBackend) -> RateLimitSettings {
+        RateLimitSettings {
+            store,
+            window: 3600,
+            ..RateLimitSettings::default()
+        }
+    }
+
+    #[sqlx::test]
+    async fn allow_counts_up_to_limit(pool: PgPool) -> Result<()> {
+        for store in [RateLimitBackend::Memory, RateLimitBackend::Postgres] {
+            let limiter = RateLimiter::new(&settings(store), pool.clone());
+            let key = format!("{store:?}");
+
+            assert!(limiter.allow(&key, 2).await?);
+            assert!(limiter.allow(&key, 2).await?);
+            assert!(!limiter.allow(&key, 2).await?);
+            assert!(limiter.allow("other", 2).await?);
+            assert!(limiter.allow(&key, 0).await?);
+        }
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn window_start_is_aligned(pool: PgPool) {
+        let limiter = RateLimiter::new(&settings(RateLimitBackend::Memory), pool);
+        let now = DateTime::from_timestamp(7_250, 0).unwrap();
+
+        assert_eq!(
+            DateTime::from_timestamp(7_200, 0).unwrap(),
+            limiter.window_start(now)
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::signing::Signer;
+
+// How long a loaded form can be sent.
+const VALID_SECONDS: i64 = 24 * 60 * 60;
+
+/// Tells when a form was loaded, so that forms sent faster than a person
+/// could fill them in can be turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormStamp {
+    #[serde(rename = "t")]
+    pub issued: i64,
+}
+
+impl FormStamp {
+    pub fn new() -> Self {
+        Self {
+            issued: Utc::now().timestamp(),
+        }
+    }
+
+    /// Whether a signed stamp is at least `min_seconds` old, and not stale.
+    pub fn verify(signer: &Signer, token: &str, min_seconds: i64) -> bool {
+        let Some(stamp) = signer.verify::<FormStamp>(token) else {
+            return false;
+        };
+        let age = Utc::now().timestamp() - stamp.issued;
+        (min_seconds..=VALID_SECONDS).contains(&age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(seconds_ago: i64) -> String {
+        Signer::new("secret").sign(&FormStamp {
+            issued: Utc::now().timestamp() - seconds_ago,
+        })
+    }
+
+    #[test]
+    fn verify_needs_minimum_age() {
+        let signer = Signer::new("secret");
+
+        assert!(FormStamp::verify(&signer, &signed(5), 3));
+        assert!(!FormStamp::verify(&signer, &signed(1), 3));
+        assert!(!FormStamp::verify(&signer, &signed(2 * VALID_SECONDS), 3));
+    }
+
+    #[test]
+    fn verify_rejects_forged_stamps() {
+        let forged = Signer::new("other").sign(&FormStamp { issued: 0 });
+
+        assert!(!FormStamp::verify(&Signer::new("secret"), &forged, 0));
+        assert!(!FormStamp::verify(&Signer::new("secret"), "garbage", 0));
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Duration,
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    headers::{authorization::Bearer, Authorization},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Json, TypedHeader,
+};
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{
+    activity::StreamToken,
+    data::ApplicationData,
+    model::{Activity, ActivityKind},
+    routes::{auth::authorize, internal_error},
+    store::{ActivityStore, PsqlActivityStore},
+};
+
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    // Comma separated kinds of activity, like `signup,unsubscribed`. All
+    // kinds when left out.
+    types: Option<String>,
+    // A stream token, for `EventSource`, which can't send an
+    // `Authorization` header.
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IssuedToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+fn unauthorized() -> (StatusCode, String) {
+    (StatusCode::UNAUTHORIZED, "Not authorized".to_string())
+}
+
+/// Issues a token that opens the activity stream for a while, so that the
+/// admin token never has to go in a URL.
+pub async fn issue_stream_token(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<IssuedToken>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    let (token, expires_at) = StreamToken::issue(&data);
+    Ok(Json(IssuedToken { token, expires_at }))
+}
+
+/// Streams activity as server-sent events named after its kind. Clients
+/// reconnecting with `Last-Event-ID` first get what they missed, as far as
+/// the activity log goes back.
+pub async fn activity_stream(
+    State(data): State<ApplicationData>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(query): Query<StreamQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, serde_json::Error>>>, (StatusCode, String)> {
+    match (authorization, &query.token) {
+        (Some(TypedHeader(authorization)), _) => authorize(&data, &authorization)?,
+        (None, Some(token)) if StreamToken::verify(&data, token) => {}
+        (None, _) => return Err(unauthorized()),
+    }
+    let types = match query.types.as_deref() {
+        None | Some("") => ActivityKind::ALL.into_iter().collect(),
+        Some(types) => types
+            .split(',')
+            .map(|kind| ActivityKind::try_from(kind.trim().to_string()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?,
+    };
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.parse().ok());
+
+    // Subscribing before reading the log leaves no gap between the two.
+    let mut follower = Follower {
+        receiver: data.activity.subscribe(),
+        data: data.clone(),
+        backlog: VecDeque::new(),
+        seen: 0,
+        caught_up_to: 0,
+        types,
+    };
+    if let Some(id) = last_event_id {
+        follower.catch_up(id).await.map_err(internal_error)?;
+    }
+
+    let events = stream::unfold(follower, |mut follower| async move {
+        let activity = follower.next().await?;
+        let event = Event::default()
+            .id(activity.id.to_string())
+            .event(activity.kind.as_str())
+            .json_data(&activity);
+        Some((event, follower))
+    });
+    let keep_alive = Duration::from_secs(data.activity.settings.keep_alive);
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(keep_alive)))
+}
+
+/// Follows the activity of one stream: first what it missed, then what
+/// happens live.
+struct Follower {
+    receiver: Receiver<Activity>,
+    data: ApplicationData,
+    backlog: VecDeque<Activity>,
+    // The newest activity passed on, wanted or not.
+    seen: i64,
+    // The newest activity taken from the log, so that it isn't passed on
+    // again when it also comes in live.
+    caught_up_to: i64,
+    types: HashSet<ActivityKind>,
+}
+
+impl Follower {
+    async fn catch_up(&mut self, after: i64) -> Result<()> {
+        let missed = PsqlActivityStore::from(self.data.pool.clone())
+            .since(after, self.data.activity.settings.log_size)
+            .await?;
+        if let Some(last) = missed.last() {
+            self.caught_up_to = self.caught_up_to.max(last.id);
+        }
+        self.seen = self.seen.max(after);
+        self.backlog.extend(missed);
+        Ok(())
+    }
+
+    /// The next activity of the wanted kinds, or `None` once there is no
+    /// more.
+    async fn next(&mut self) -> Option<Activity> {
+        loop {
+            let activity = match self.backlog.pop_front() {
+                Some(activity) => activity,
+                None => match self.receiver.recv().await {
+                    Ok(activity) if activity.id <= self.caught_up_to => continue,
+                    Ok(activity) => activity,
+                    // Too slow to keep up, so what was missed comes from
+                    // the log instead.
+                    Err(RecvError::Lagged(missed)) if self.seen > 0 => {
+                        warn!("Activity stream fell {missed} events behind");
+                        if let Err(e) = self.catch_up(self.seen).await {
+                            error!("Failed to catch up on activity: {e}");
+                            return None;
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Activity stream skipped {missed} events");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            };
+            self.seen = self.seen.max(activity.id);
+            if self.types.contains(&activity.kind) {
+                return Some(activity);
+            }
+        }
+    }
+}
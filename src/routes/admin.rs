@@ -0,0 +1,932 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    headers::Cookie,
+    http::{
+        header::{CONTENT_TYPE, SET_COOKIE},
+        HeaderMap, StatusCode,
+    },
+    response::{Html, IntoResponse, Redirect, Response},
+    Form, TypedHeader,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::info;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    activity,
+    admin::{self, AdminPage},
+    automation,
+    config::ListMode,
+    data::ApplicationData,
+    model::{
+        ActivityKind, AdminSession, ApiKey, CampaignReport, CampaignSummary, Email, NewApiKey,
+        NewCampaign, NewTemplate, Scope, SubscriberStatus, Tally, Trigger, WebhookEvent,
+    },
+    pages::escape,
+    scheduling::{self, ScheduleRequest},
+    store::{
+        ApiKeyStore, CampaignStore, PsqlApiKeyStore, PsqlCampaignStore, PsqlSubscriberStore,
+        PsqlSuppressionStore, PsqlTemplateStore, SubscriberStore, SuppressionStore, TemplateStore,
+    },
+    tracking, webhooks,
+};
+
+const PAGE_SIZE: i64 = 50;
+// Keeps the offset of far out pages from overflowing.
+const MAX_PAGE: i64 = 1_000_000;
+
+const STATUSES: [SubscriberStatus; 4] = [
+    SubscriberStatus::Active,
+    SubscriberStatus::Pending,
+    SubscriberStatus::Bounced,
+    SubscriberStatus::Complained,
+];
+
+#[derive(Deserialize)]
+pub struct Submitted {
+    #[serde(default)]
+    csrf: String,
+}
+
+#[derive(Deserialize)]
+pub struct Login {
+    #[serde(default)]
+    password: String,
+}
+
+fn internal_error(e: anyhow::Error) -> Response {
+    super::internal_error(e).into_response()
+}
+
+/// The session of a signed in admin, or where to send anyone else.
+async fn signed_in(
+    data: &ApplicationData,
+    cookie: Option<&Cookie>,
+) -> Result<AdminSession, Response> {
+    match admin::current_session(data, cookie).await {
+        Ok(Some(session)) => Ok(session),
+        Ok(None) => Err(Redirect::to("/admin/login").into_response()),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Like `signed_in`, for forms, which must repeat the session's CSRF token.
+async fn submitted(
+    data: &ApplicationData,
+    cookie: Option<&Cookie>,
+    csrf: &str,
+) -> Result<AdminSession, Response> {
+    let session = signed_in(data, cookie).await?;
+    if !session.verify(csrf) {
+        info!("Rejecting admin form without a valid CSRF token");
+        let page = AdminPage::new("message", "Form expired")
+            .text("message", "Reload the page and try again.");
+        return Err(html(data, StatusCode::FORBIDDEN, page, Some(&session)));
+    }
+    Ok(session)
+}
+
+fn html(
+    data: &ApplicationData,
+    status: StatusCode,
+    page: AdminPage,
+    session: Option<&AdminSession>,
+) -> Response {
+    (status, Html(page.render(&data.list.name, session))).into_response()
+}
+
+fn not_found(data: &ApplicationData, session: &AdminSession, what: &str) -> Response {
+    let page =
+        AdminPage::new("message", "Not found").text("message", &format!("{what} not found."));
+    html(data, StatusCode::NOT_FOUND, page, Some(session))
+}
+
+/// A table of cells that are already escaped, or `empty` without rows.
+fn table(head: &[&str], rows: Vec<Vec<String>>, empty: &str) -> String {
+    if rows.is_empty() {
+        return format!("<p>{}</p>", escape(empty));
+    }
+    let head: String = head
+        .iter()
+        .map(|name| format!("<th>{}</th>", escape(name)))
+        .collect();
+    let rows: String = rows
+        .into_iter()
+        .map(|cells| {
+            let cells: String = cells
+                .into_iter()
+                .map(|cell| format!("<td>{cell}</td>"))
+                .collect();
+            format!("<tr>{cells}</tr>")
+        })
+        .collect();
+    format!("<table><thead><tr>{head}</tr></thead><tbody>{rows}</tbody></table>")
+}
+
+/// A form with only a button, posting the session's CSRF token and the
+/// hidden fields.
+fn button(session: &AdminSession, action: &str, label: &str, fields: &[(&str, &str)]) -> String {
+    let fields: String = fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "<input type=\"hidden\" name=\"{name}\" value=\"{}\">",
+                escape(value)
+            )
+        })
+        .collect();
+    format!(
+        "<form method=\"post\" action=\"{}\">\
+         <input type=\"hidden\" name=\"csrf\" value=\"{}\">{fields}\
+         <button type=\"submit\">{}</button></form>",
+        escape(action),
+        escape(&session.csrf),
+        escape(label)
+    )
+}
+
+fn error_message(message: &str) -> String {
+    format!("<p class=\"error\">{}</p>", escape(message))
+}
+
+fn time(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+async fn status_counts(data: &ApplicationData) -> Result<String, Response> {
+    let counts = PsqlSubscriberStore::from(data.pool.clone())
+        .count_by_status()
+        .await
+        .map_err(internal_error)?;
+    let rows = STATUSES
+        .iter()
+        .map(|status| {
+            let count = counts
+                .iter()
+                .find(|(counted, _)| counted == status)
+                .map_or(0, |(_, count)| *count);
+            vec![status.as_str().to_string(), count.to_string()]
+        })
+        .collect();
+    Ok(table(&["Status", "Subscribers"], rows, ""))
+}
+
+async fn campaign_summaries(data: &ApplicationData, last: i64) -> Result<String, Response> {
+    let store = PsqlCampaignStore::from(data.pool.clone());
+    let campaigns = store.recent(last).await.map_err(internal_error)?;
+    let mut rows = Vec::with_capacity(campaigns.len());
+    for campaign in campaigns {
+        let stats = store.stats(campaign.id).await.map_err(internal_error)?;
+        let summary = CampaignSummary::new(campaign, &stats);
+        rows.push(vec![
+            format!(
+                "<a href=\"/admin/campaigns/{}\">{}</a>",
+                summary.campaign.id,
+                escape(&summary.campaign.name)
+            ),
+            summary
+                .campaign
+                .sent_at
+                .map_or("Not scheduled".to_string(), time),
+            summary.delivered.unique.to_string(),
+            percent(summary.rates.open),
+            percent(summary.rates.click),
+        ]);
+    }
+    Ok(table(
+        &["Campaign", "Sends from", "Delivered", "Opened", "Clicked"],
+        rows,
+        "No campaigns yet.",
+    ))
+}
+
+pub async fn admin_stylesheet() -> Response {
+    (
+        [(CONTENT_TYPE, "text/css; charset=utf-8")],
+        admin::STYLESHEET,
+    )
+        .into_response()
+}
+
+/// The sign in form, or the overview for those signed in already.
+pub async fn admin_login_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Response {
+    if let Ok(Some(_)) = admin::current_session(&data, cookie.as_deref()).await {
+        return Redirect::to("/admin").into_response();
+    }
+    let page = AdminPage::new("login", "Sign in").html("error", "");
+    html(&data, StatusCode::OK, page, None)
+}
+
+pub async fn admin_log_in(
+    State(data): State<ApplicationData>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Form(login): Form<Login>,
+) -> Response {
+    let client = data
+        .protection
+        .client(&headers, peer.map(|ConnectInfo(peer)| peer));
+    if let Some(client) = client {
+        if !data
+            .protection
+            .allow_sign_in(client, data.admin_ui.login_attempts)
+            .await
+        {
+            info!("Rejecting admin sign in from {client}: too many attempts");
+            let page = AdminPage::new("login", "Sign in").html(
+                "error",
+                error_message("Too many attempts. Please try again later."),
+            );
+            return html(&data, StatusCode::TOO_MANY_REQUESTS, page, None);
+        }
+    }
+    match admin::sign_in(&data, &login.password).await {
+        Ok(Some((token, _))) => {
+            let mut response = Redirect::to("/admin").into_response();
+            if let Ok(cookie) = admin::set_cookie(&data, &token).parse() {
+                response.headers_mut().insert(SET_COOKIE, cookie);
+            }
+            response
+        }
+        Ok(None) => {
+            let message = match data.admin_ui.password {
+                Some(_) => "Wrong password.",
+                None => "Signing in is off until admin_ui.password is set.",
+            };
+            let page = AdminPage::new("login", "Sign in").html("error", error_message(message));
+            html(&data, StatusCode::UNAUTHORIZED, page, None)
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+pub async fn admin_log_out(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(form): Form<Submitted>,
+) -> Result<Response, Response> {
+    submitted(&data, cookie.as_deref(), &form.csrf).await?;
+    admin::sign_out(&data, cookie.as_deref())
+        .await
+        .map_err(internal_error)?;
+
+    let mut response = Redirect::to("/admin/login").into_response();
+    if let Ok(cookie) = admin::clear_cookie().parse() {
+        response.headers_mut().insert(SET_COOKIE, cookie);
+    }
+    Ok(response)
+}
+
+pub async fn admin_overview(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let page = AdminPage::new("overview", "Overview")
+        .html("counts", status_counts(&data).await?)
+        .html("campaigns", campaign_summaries(&data, 5).await?);
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+#[derive(Deserialize)]
+pub struct SubscriberQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default = "first_page")]
+    page: i64,
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+/// Subscribers whose address contains the query, a page at a time.
+pub async fn admin_subscribers(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Query(query): Query<SubscriberQuery>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let status = SubscriberStatus::try_from(query.status.clone()).ok();
+    let page = query.page.clamp(1, MAX_PAGE);
+    let mut subscribers = PsqlSubscriberStore::from(data.pool.clone())
+        .search(
+            query.q.trim(),
+            status,
+            PAGE_SIZE + 1,
+            (page - 1) * PAGE_SIZE,
+        )
+        .await
+        .map_err(internal_error)?;
+    let more = subscribers.len() as i64 > PAGE_SIZE;
+    subscribers.truncate(PAGE_SIZE as usize);
+
+    let rows = subscribers
+        .iter()
+        .map(|subscriber| {
+            vec![
+                format!(
+                    "<a href=\"/admin/subscribers/{}\">{}</a>",
+                    subscriber.id,
+                    escape(&subscriber.email.0)
+                ),
+                subscriber.status.as_str().to_string(),
+                escape(subscriber.timezone.as_deref().unwrap_or("")),
+            ]
+        })
+        .collect();
+    let mut statuses = String::from("<option value=\"\">Any status</option>");
+    for option in STATUSES {
+        let selected = if Some(option) == status {
+            " selected"
+        } else {
+            ""
+        };
+        statuses.push_str(&format!(
+            "<option value=\"{0}\"{selected}>{0}</option>",
+            option.as_str()
+        ));
+    }
+    let status = status.map_or("", |status| status.as_str());
+    let page_link = |to: i64, label: &str| {
+        format!(
+            "<form method=\"get\" action=\"/admin/subscribers\">\
+             <input type=\"hidden\" name=\"q\" value=\"{}\">\
+             <input type=\"hidden\" name=\"status\" value=\"{status}\">\
+             <input type=\"hidden\" name=\"page\" value=\"{to}\">\
+             <button type=\"submit\">{label}</button></form>",
+            escape(&query.q)
+        )
+    };
+    let mut pages = String::new();
+    if page > 1 {
+        pages.push_str(&page_link(page - 1, "Previous"));
+    }
+    if more {
+        pages.push_str(&page_link(page + 1, "Next"));
+    }
+
+    let page = AdminPage::new("subscribers", "Subscribers")
+        .text("query", &query.q)
+        .html("statuses", statuses)
+        .html(
+            "subscribers",
+            table(
+                &["Address", "Status", "Timezone"],
+                rows,
+                "No subscribers found.",
+            ),
+        )
+        .html("pages", format!("<div class=\"pages\">{pages}</div>"));
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+pub async fn admin_subscriber(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let store = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = store.get(id).await.map_err(internal_error)? else {
+        return Ok(not_found(&data, &session, "Subscriber"));
+    };
+    let tags = store.tags(id).await.map_err(internal_error)?;
+    let attributes = store.attributes(id).await.map_err(internal_error)?;
+
+    let action = format!("/admin/subscribers/{id}/tags/remove");
+    let tags = tags
+        .iter()
+        .map(|tag| {
+            vec![
+                escape(tag),
+                button(&session, &action, "Remove", &[("tag", tag)]),
+            ]
+        })
+        .collect();
+    let attributes = attributes
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            vec![escape(name), escape(&value)]
+        })
+        .collect();
+
+    let page = AdminPage::new("subscriber", &subscriber.email.0)
+        .text("id", &id.to_string())
+        .text("email", &subscriber.email.0)
+        .text("status", subscriber.status.as_str())
+        .text(
+            "timezone",
+            subscriber.timezone.as_deref().unwrap_or("Unknown"),
+        )
+        .text("digest", if subscriber.digest { "Yes" } else { "No" })
+        .html("tags", table(&["Tag", ""], tags, "No tags."))
+        .html(
+            "attributes",
+            table(&["Attribute", "Value"], attributes, "No attributes."),
+        );
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+#[derive(Deserialize)]
+pub struct TagForm {
+    #[serde(default)]
+    csrf: String,
+    tag: String,
+}
+
+/// Tags a subscriber, starting the automations triggered by the tag.
+pub async fn admin_tag_subscriber(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+    Form(form): Form<TagForm>,
+) -> Result<Response, Response> {
+    let session = submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    let mut store = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = store.get(id).await.map_err(internal_error)? else {
+        return Ok(not_found(&data, &session, "Subscriber"));
+    };
+    let tag = form.tag.trim();
+    if !tag.is_empty() && store.add_tag(id, tag).await.map_err(internal_error)? {
+        let trigger = Trigger::TagAdded {
+            tag: tag.to_string(),
+        };
+        automation::enroll(&data, &subscriber, &trigger)
+            .await
+            .map_err(internal_error)?;
+    }
+    Ok(Redirect::to(&format!("/admin/subscribers/{id}")).into_response())
+}
+
+pub async fn admin_untag_subscriber(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+    Form(form): Form<TagForm>,
+) -> Result<Response, Response> {
+    submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    PsqlSubscriberStore::from(data.pool.clone())
+        .remove_tag(id, &form.tag)
+        .await
+        .map_err(internal_error)?;
+    Ok(Redirect::to(&format!("/admin/subscribers/{id}")).into_response())
+}
+
+pub async fn admin_unsubscribe(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+    Form(form): Form<Submitted>,
+) -> Result<Response, Response> {
+    let session = submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    let mut store = PsqlSubscriberStore::from(data.pool.clone());
+    let Some(subscriber) = store.get(id).await.map_err(internal_error)? else {
+        return Ok(not_found(&data, &session, "Subscriber"));
+    };
+    tracking::unsubscribed(&data, &subscriber, None).await;
+    store
+        .delete(&subscriber.email)
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "Unsubscribed from the admin interface: {:?}",
+        subscriber.email
+    );
+    let event = WebhookEvent::Unsubscribed;
+    webhooks::emit_subscriber(&data, event, &subscriber).await;
+    let kind = ActivityKind::Unsubscribed;
+    activity::publish_subscriber(&data, kind, &subscriber).await;
+    Ok(Redirect::to("/admin/subscribers").into_response())
+}
+
+/// The list as configured, with its tags and suppressed addresses.
+pub async fn admin_list(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let tags = PsqlSubscriberStore::from(data.pool.clone())
+        .count_by_tag()
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|(tag, count)| vec![escape(&tag), count.to_string()])
+        .collect();
+    let suppressions = PsqlSuppressionStore::from(data.pool.clone())
+        .all()
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|suppression| {
+            vec![
+                escape(&suppression.email.0),
+                escape(&suppression.reason),
+                time(suppression.created_at),
+                button(
+                    &session,
+                    "/admin/list/suppressions/remove",
+                    "Lift",
+                    &[("email", &suppression.email.0)],
+                ),
+            ]
+        })
+        .collect();
+
+    let mode = match data.list.mode {
+        ListMode::Newsletter => "Newsletter",
+        ListMode::Discussion => "Discussion",
+    };
+    let page = AdminPage::new("list", "List")
+        .text("address", &data.list.address)
+        .text("mode", mode)
+        .text("timezone", &data.list.timezone)
+        .html("counts", status_counts(&data).await?)
+        .html("tags", table(&["Tag", "Subscribers"], tags, "No tags yet."))
+        .html(
+            "suppressions",
+            table(
+                &["Address", "Reason", "Since", ""],
+                suppressions,
+                "No suppressed addresses.",
+            ),
+        );
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+#[derive(Deserialize)]
+pub struct SuppressionForm {
+    #[serde(default)]
+    csrf: String,
+    email: String,
+}
+
+pub async fn admin_suppress(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(form): Form<SuppressionForm>,
+) -> Result<Response, Response> {
+    let session = submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    let email = form.email.trim();
+    if !email.contains('@') {
+        let page = AdminPage::new("message", "Not suppressed")
+            .text("message", &format!("{email} is not an email address."));
+        return Ok(html(
+            &data,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            page,
+            Some(&session),
+        ));
+    }
+    PsqlSuppressionStore::from(data.pool.clone())
+        .add(&Email::from(email), "admin")
+        .await
+        .map_err(internal_error)?;
+    Ok(Redirect::to("/admin/list").into_response())
+}
+
+pub async fn admin_unsuppress(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(form): Form<SuppressionForm>,
+) -> Result<Response, Response> {
+    submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    PsqlSuppressionStore::from(data.pool.clone())
+        .remove(&Email::from(form.email.trim()))
+        .await
+        .map_err(internal_error)?;
+    Ok(Redirect::to("/admin/list").into_response())
+}
+
+#[derive(Deserialize, Default)]
+pub struct Compose {
+    #[serde(default)]
+    csrf: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    subject: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    html: String,
+    // Wall clock time in the list's timezone, as `datetime-local` inputs
+    // send it.
+    #[serde(default)]
+    send_at: String,
+    #[serde(default)]
+    local: bool,
+}
+
+impl Compose {
+    fn send_at(&self) -> Option<NaiveDateTime> {
+        ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(&self.send_at, format).ok())
+    }
+
+    fn problems(&self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Name the campaign.");
+        }
+        if self.subject.trim().is_empty() {
+            problems.push("Give it a subject.");
+        }
+        if self.text.trim().is_empty() {
+            problems.push("Write the text.");
+        }
+        if self.send_at().is_none() {
+            problems.push("Pick when to send it.");
+        }
+        problems
+    }
+}
+
+async fn campaigns_page(
+    data: &ApplicationData,
+    compose: &Compose,
+    error: &str,
+) -> Result<AdminPage, Response> {
+    Ok(AdminPage::new("campaigns", "Campaigns")
+        .html("error", error)
+        .text("name", &compose.name)
+        .text("subject", &compose.subject)
+        .text("text", &compose.text)
+        .text("html", &compose.html)
+        .text("send_at", &compose.send_at)
+        .html("campaigns", campaign_summaries(data, 20).await?))
+}
+
+/// Recent campaigns, and a form for the next one.
+pub async fn admin_campaigns(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let page = campaigns_page(&data, &Compose::default(), "").await?;
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+/// Creates a campaign with its own template and schedules it, the way the
+/// API does in three requests.
+pub async fn admin_compose_campaign(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(compose): Form<Compose>,
+) -> Result<Response, Response> {
+    let session = submitted(&data, cookie.as_deref(), &compose.csrf).await?;
+
+    let problems = compose.problems();
+    let Some(send_at) = compose.send_at().filter(|_| problems.is_empty()) else {
+        let page = campaigns_page(&data, &compose, &error_message(&problems.join(" "))).await?;
+        return Ok(html(
+            &data,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            page,
+            Some(&session),
+        ));
+    };
+
+    let campaign = PsqlCampaignStore::from(data.pool.clone())
+        .create(NewCampaign {
+            name: compose.name.trim().to_string(),
+        })
+        .await
+        .map_err(internal_error)?;
+    let html_body = Some(compose.html).filter(|html| !html.trim().is_empty());
+    let template = PsqlTemplateStore::from(data.pool.clone())
+        .save(NewTemplate {
+            name: format!("campaign-{}", campaign.id),
+            subject: compose.subject.trim().to_string(),
+            text: compose.text,
+            html: html_body,
+        })
+        .await
+        .map_err(internal_error)?;
+    let request = ScheduleRequest {
+        template_id: template.id,
+        send_at,
+        local: compose.local,
+        optimise: false,
+    };
+    scheduling::schedule(&data, &campaign, &template, &request)
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "Scheduled campaign {} from the admin interface",
+        campaign.id
+    );
+    Ok(Redirect::to(&format!("/admin/campaigns/{}", campaign.id)).into_response())
+}
+
+/// How a campaign did, like its report in the API.
+pub async fn admin_campaign(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let store = PsqlCampaignStore::from(data.pool.clone());
+    let Some(campaign) = store.get(id).await.map_err(internal_error)? else {
+        return Ok(not_found(&data, &session, "Campaign"));
+    };
+    let stats = store.stats(id).await.map_err(internal_error)?;
+    let report = CampaignReport::new(campaign, &stats);
+    let summary = &report.summary;
+
+    let sent = match summary.campaign.sent_at {
+        Some(at) if at > Utc::now() => format!("Sends from {}.", time(at)),
+        Some(at) => format!("Sent from {}.", time(at)),
+        None => "Not scheduled.".to_string(),
+    };
+    let row = |name: &str, tally: Tally, rate: Option<f64>| {
+        vec![
+            name.to_string(),
+            tally.total.to_string(),
+            tally.unique.to_string(),
+            rate.map(percent).unwrap_or_default(),
+        ]
+    };
+    let rates = summary.rates;
+    let tallies = vec![
+        row("Delivered", summary.delivered, None),
+        row("Bounced", summary.bounced, Some(rates.bounce)),
+        row("Opened", summary.opened, Some(rates.open)),
+        row("Clicked", summary.clicked, Some(rates.click)),
+        row(
+            "Unsubscribed",
+            summary.unsubscribed,
+            Some(rates.unsubscribe),
+        ),
+        row("Complained", summary.complained, Some(rates.complaint)),
+    ];
+    let links = report
+        .links
+        .iter()
+        .map(|link| {
+            vec![
+                escape(&link.url),
+                link.clicks.total.to_string(),
+                link.clicks.unique.to_string(),
+            ]
+        })
+        .collect();
+
+    let page = AdminPage::new("campaign", &summary.campaign.name)
+        .text("sent", &sent)
+        .html(
+            "summary",
+            table(&["", "Total", "Unique", "Rate"], tallies, ""),
+        )
+        .html(
+            "links",
+            table(&["Link", "Clicks", "Unique"], links, "No clicks yet."),
+        );
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+async fn keys_page(
+    data: &ApplicationData,
+    session: &AdminSession,
+    created: String,
+    error: &str,
+) -> Result<AdminPage, Response> {
+    let keys = PsqlApiKeyStore::from(data.pool.clone())
+        .all()
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|key| {
+            let scopes: Vec<_> = key.scopes.iter().map(Scope::as_str).collect();
+            let state = match key.revoked_at {
+                Some(at) => format!("Revoked {}", time(at)),
+                None => button(
+                    session,
+                    &format!("/admin/keys/{}/revoke", key.id),
+                    "Revoke",
+                    &[],
+                ),
+            };
+            vec![
+                escape(&key.name),
+                scopes.join(", "),
+                time(key.created_at),
+                state,
+            ]
+        })
+        .collect();
+    Ok(AdminPage::new("keys", "API keys")
+        .html("created", created)
+        .html("error", error)
+        .html(
+            "keys",
+            table(&["Name", "Scopes", "Created", ""], keys, "No API keys yet."),
+        ))
+}
+
+pub async fn admin_keys(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Result<Response, Response> {
+    let session = signed_in(&data, cookie.as_deref()).await?;
+
+    let page = keys_page(&data, &session, String::new(), "").await?;
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+/// Creates an API key and shows it, this once.
+pub async fn admin_create_key(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, Response> {
+    let field = |wanted: &str| {
+        fields
+            .iter()
+            .find(|(name, _)| name == wanted)
+            .map_or("", |(_, value)| value.as_str())
+    };
+    let session = submitted(&data, cookie.as_deref(), field("csrf")).await?;
+
+    let name = field("name").trim().to_string();
+    let scopes: Result<Vec<Scope>, String> = fields
+        .iter()
+        .filter(|(name, _)| name == "scope")
+        .map(|(_, value)| Scope::try_from(value.clone()))
+        .collect();
+    let problem = match &scopes {
+        _ if name.is_empty() => Some("Name the key.".to_string()),
+        Err(e) => Some(e.clone()),
+        Ok(scopes) if scopes.is_empty() => Some("Pick what the key may do.".to_string()),
+        Ok(_) => None,
+    };
+    if let Some(problem) = problem {
+        let page = keys_page(&data, &session, String::new(), &error_message(&problem)).await?;
+        return Ok(html(
+            &data,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            page,
+            Some(&session),
+        ));
+    }
+
+    let key = ApiKey::generate();
+    let new_api_key = NewApiKey {
+        name,
+        scopes: scopes.unwrap_or_default(),
+    };
+    let api_key = PsqlApiKeyStore::from(data.pool.clone())
+        .create(new_api_key, &ApiKey::hash(&key))
+        .await
+        .map_err(internal_error)?;
+    let created = format!(
+        "<p class=\"notice\">Copy the key for {} now, it isn't shown again: <code>{}</code></p>",
+        escape(&api_key.name),
+        escape(&key)
+    );
+    let page = keys_page(&data, &session, created, "").await?;
+    Ok(html(&data, StatusCode::OK, page, Some(&session)))
+}
+
+pub async fn admin_revoke_key(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(id): Path<i32>,
+    Form(form): Form<Submitted>,
+) -> Result<Response, Response> {
+    let session = submitted(&data, cookie.as_deref(), &form.csrf).await?;
+
+    match PsqlApiKeyStore::from(data.pool.clone()).revoke(id).await {
+        Ok(true) => Ok(Redirect::to("/admin/keys").into_response()),
+        Ok(false) => Ok(not_found(&data, &session, "Key")),
+        Err(e) => Err(internal_error(e)),
+    }
+}
@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    archive,
+    data::ApplicationData,
+    model::Issue,
+    routes::internal_error,
+    store::{CampaignStore, PsqlCampaignStore},
+};
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Not found".to_string())
+}
+
+pub async fn archive_index(
+    State(data): State<ApplicationData>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let issues = issues(&data).await?;
+    Ok(cached(
+        &data,
+        &headers,
+        "text/html; charset=utf-8",
+        archive::index_page(&data.list.name, &issues),
+    ))
+}
+
+pub async fn archive_issue(
+    State(data): State<ApplicationData>,
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    if !data.archive.enabled {
+        return Err(not_found());
+    }
+
+    let issue = PsqlCampaignStore::from(data.pool.clone())
+        .issue(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    Ok(cached(
+        &data,
+        &headers,
+        "text/html; charset=utf-8",
+        archive::issue_page(&data.list.name, &issue),
+    ))
+}
+
+pub async fn archive_atom(
+    State(data): State<ApplicationData>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let issues = issues(&data).await?;
+    Ok(cached(
+        &data,
+        &headers,
+        "application/atom+xml",
+        archive::atom(&data.list.name, &data.archive.url, &issues),
+    ))
+}
+
+pub async fn archive_json(
+    State(data): State<ApplicationData>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let issues = issues(&data).await?;
+    let feed = archive::json_feed(&data.list.name, &data.archive.url, &issues);
+    Ok(cached(
+        &data,
+        &headers,
+        "application/feed+json",
+        feed.to_string(),
+    ))
+}
+
+async fn issues(data: &ApplicationData) -> Result<Vec<Issue>, (StatusCode, String)> {
+    if !data.archive.enabled {
+        return Err(not_found());
+    }
+
+    PsqlCampaignStore::from(data.pool.clone())
+        .issues()
+        .await
+        .map_err(internal_error)
+}
+
+/// Lets clients cache a page for `archive.max_age` seconds, and answers
+/// `304 Not Modified` when they already have this version of it.
+fn cached(
+    data: &ApplicationData,
+    headers: &HeaderMap,
+    content_type: &str,
+    body: String,
+) -> Response {
+    let digest = Sha256::digest(body.as_bytes());
+    let etag = format!(
+        "\"{}\"",
+        digest[..16]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    );
+    let cache_control = format!("public, max-age={}", data.archive.max_age);
+    let caching = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, cache_control),
+    ];
+
+    let fresh = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if fresh {
+        (StatusCode::NOT_MODIFIED, caching).into_response()
+    } else {
+        (
+            caching,
+            [(header::CONTENT_TYPE, content_type.to_string())],
+            body,
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,48 @@
+use axum::{
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+};
+
+use crate::{
+    data::ApplicationData,
+    model::{ApiKey, Scope},
+    store::{ApiKeyStore, PsqlApiKeyStore},
+};
+
+pub fn authorize(
+    data: &ApplicationData,
+    authorization: &Authorization<Bearer>,
+) -> Result<(), (StatusCode, String)> {
+    authorize_with(&data.admin.token, authorization)
+}
+
+pub fn authorize_with(
+    token: &str,
+    authorization: &Authorization<Bearer>,
+) -> Result<(), (StatusCode, String)> {
+    if token.eq(authorization.0.token()) {
+        Ok(())
+    } else {
+        Err((StatusCode::UNAUTHORIZED, "Not authorized".to_string()))
+    }
+}
+
+/// Checks an API key, which must have been granted `scope`.
+pub async fn authorize_key(
+    data: &ApplicationData,
+    authorization: &Authorization<Bearer>,
+    scope: Scope,
+) -> Result<ApiKey, (StatusCode, String)> {
+    let key = PsqlApiKeyStore::from(data.pool.clone())
+        .find(&ApiKey::hash(authorization.0.token()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not authorized".to_string()))?;
+    if !key.allows(scope) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("Key lacks the {} scope", scope.as_str()),
+        ));
+    }
+    Ok(key)
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::Utc;
+
+use crate::{
+    automation::{self, Scenario, Simulation},
+    data::ApplicationData,
+    model::{Automation, AutomationRun, NewAutomation, NewTemplate, Template},
+    routes::{auth::authorize, internal_error},
+    store::{AutomationStore, PsqlAutomationStore, PsqlTemplateStore, TemplateStore},
+};
+
+pub async fn save_template(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_template): Json<NewTemplate>,
+) -> Result<Json<Template>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlTemplateStore::from(data.pool)
+        .save(new_template)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn get_templates(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Template>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlTemplateStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn create_automation(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_automation): Json<NewAutomation>,
+) -> Result<Json<Automation>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    new_automation
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    automation::create(&data, new_automation)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn get_automations(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Automation>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlAutomationStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn automation_runs(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<AutomationRun>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlAutomationStore::from(data.pool)
+        .runs(id)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn simulate_automation(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Json(scenario): Json<Scenario>,
+) -> Result<Json<Simulation>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let automation = PsqlAutomationStore::from(data.pool)
+        .get(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Automation not found".to_string()))?;
+    Ok(Json(automation::simulate(
+        &automation,
+        scenario,
+        Utc::now(),
+    )))
+}
@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Path, Query, State},
+    headers::{authorization::Bearer, Authorization},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json, TypedHeader,
+};
+use serde::Deserialize;
+
+use crate::{
+    ab_test,
+    data::ApplicationData,
+    model::{
+        AbTestReport, Campaign, CampaignReport, CampaignSummary, NewAbTest, NewCampaign, Template,
+    },
+    routes::{auth::authorize, internal_error},
+    scheduling::{self, Schedule, ScheduleRequest},
+    store::{CampaignStore, PsqlCampaignStore, PsqlTemplateStore, TemplateStore},
+};
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    #[serde(default)]
+    format: Format,
+}
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    #[serde(default = "default_last")]
+    last: i64,
+    #[serde(default)]
+    format: Format,
+}
+
+fn default_last() -> i64 {
+    5
+}
+
+// The most campaigns compared at once.
+const MAX_COMPARED: i64 = 100;
+
+pub async fn create_campaign(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_campaign): Json<NewCampaign>,
+) -> Result<Json<Campaign>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let mut store = PsqlCampaignStore::from(data.pool);
+    match store.create(new_campaign).await {
+        Ok(campaign) => Ok(Json(campaign)),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+pub async fn campaign_report(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let store = PsqlCampaignStore::from(data.pool.clone());
+    let campaign = match store.get(id).await {
+        Ok(Some(campaign)) => campaign,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Campaign not found".to_string())),
+        Err(e) => return Err(internal_error(e)),
+    };
+    let stats = store.stats(id).await.map_err(internal_error)?;
+
+    let mut report = CampaignReport::new(campaign, &stats);
+    report.ab_test = ab_test::report(&data, id).await.map_err(internal_error)?;
+    Ok(match query.format {
+        Format::Json => Json(report).into_response(),
+        Format::Csv => csv(report.to_csv()),
+    })
+}
+
+pub async fn compare_campaigns(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<CompareQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let store = PsqlCampaignStore::from(data.pool);
+    let campaigns = store
+        .recent(query.last.clamp(1, MAX_COMPARED))
+        .await
+        .map_err(internal_error)?;
+    let ids: Vec<i32> = campaigns.iter().map(|campaign| campaign.id).collect();
+    let mut totals = store.totals(&ids).await.map_err(internal_error)?;
+
+    let summaries: Vec<CampaignSummary> = campaigns
+        .into_iter()
+        .map(|campaign| {
+            let stats = totals.remove(&campaign.id).unwrap_or_default();
+            CampaignSummary::new(campaign, &stats)
+        })
+        .collect();
+
+    Ok(match query.format {
+        Format::Json => Json(summaries).into_response(),
+        Format::Csv => csv(CampaignSummary::to_csv(&summaries)),
+    })
+}
+
+/// Queues a campaign to go out at a wall clock time, optionally in each
+/// subscriber's timezone or at their usual reading hour.
+pub async fn schedule_campaign(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Json(request): Json<ScheduleRequest>,
+) -> Result<Json<Schedule>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let campaign = match PsqlCampaignStore::from(data.pool.clone()).get(id).await {
+        Ok(Some(campaign)) => campaign,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Campaign not found".to_string())),
+        Err(e) => return Err(internal_error(e)),
+    };
+    let template = find_template(&data, request.template_id).await?;
+
+    match scheduling::schedule(&data, &campaign, &template, &request).await {
+        Ok(Some(schedule)) => Ok(Json(schedule)),
+        Ok(None) => Err((
+            StatusCode::CONFLICT,
+            "Campaign was already scheduled".to_string(),
+        )),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Sends 2 to 4 variants of a campaign to a test group and the variant that
+/// does best to everyone else after the test window.
+pub async fn test_campaign(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Json(new_test): Json<NewAbTest>,
+) -> Result<Json<AbTestReport>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    new_test
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let campaign = match PsqlCampaignStore::from(data.pool.clone()).get(id).await {
+        Ok(Some(campaign)) => campaign,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Campaign not found".to_string())),
+        Err(e) => return Err(internal_error(e)),
+    };
+    let template = find_template(&data, new_test.template_id).await?;
+    for template_id in new_test.variants.iter().filter_map(|v| v.template_id) {
+        find_template(&data, template_id).await?;
+    }
+
+    match ab_test::start(&data, &campaign, &template, &new_test).await {
+        Ok(Some(report)) => Ok(Json(report)),
+        Ok(None) => Err((
+            StatusCode::CONFLICT,
+            "Campaign was already scheduled".to_string(),
+        )),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveFlag {
+    exclude: bool,
+}
+
+/// Takes a campaign out of the public archive, or puts it back.
+pub async fn exclude_from_archive(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Json(flag): Json<ArchiveFlag>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match PsqlCampaignStore::from(data.pool)
+        .exclude_from_archive(id, flag.exclude)
+        .await
+    {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Campaign not found".to_string())),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+async fn find_template(data: &ApplicationData, id: i32) -> Result<Template, (StatusCode, String)> {
+    match PsqlTemplateStore::from(data.pool.clone()).get(id).await {
+        Ok(Some(template)) => Ok(template),
+        Ok(None) => Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("No template with id {id}"),
+        )),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+fn csv(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], body).into_response()
+}
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use chrono::Utc;
+
+use crate::{
+    data::ApplicationData,
+    feeds::{self, FeedRun},
+    model::{next_run, Feed, NewFeed},
+    routes::{auth::authorize, internal_error},
+    store::{FeedStore, PsqlFeedStore, PsqlTemplateStore, TemplateStore},
+};
+
+pub async fn create_feed(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_feed): Json<NewFeed>,
+) -> Result<Json<Feed>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let timezone = feeds::timezone(&data).map_err(internal_error)?;
+    next_run(&new_feed.schedule, &timezone, Utc::now())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    if PsqlTemplateStore::from(data.pool.clone())
+        .get(new_feed.template_id)
+        .await
+        .map_err(internal_error)?
+        .is_none()
+    {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("No template with id {}", new_feed.template_id),
+        ));
+    }
+    let items = feeds::fetch(&data.feed, &new_feed.source)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Could not read {}: {e}", new_feed.source),
+            )
+        })?;
+
+    feeds::create(&data, new_feed, &items)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn get_feeds(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Feed>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlFeedStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+/// Looks at a feed right away instead of waiting for its schedule.
+pub async fn run_feed(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+) -> Result<Json<FeedRun>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let feed = PsqlFeedStore::from(data.pool.clone())
+        .get(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or((StatusCode::NOT_FOUND, "Feed not found".to_string()))?;
+    feeds::run(&data, &feed)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))
+}
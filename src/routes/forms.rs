@@ -0,0 +1,186 @@
+use std::net::SocketAddr;
+
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Query, State},
+    headers::{authorization::Bearer, Authorization, Origin},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json, TypedHeader,
+};
+use log::error;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    data::ApplicationData,
+    forms::{self, snippet, subscribe, FieldError},
+    model::SubscriberStatus,
+    protection::Rejection,
+    routes::auth::authorize,
+};
+
+#[derive(Deserialize)]
+pub struct SnippetOptions {
+    // Sends the form with `fetch` and stays on the page.
+    #[serde(default)]
+    ajax: bool,
+}
+
+/// A ready-to-paste signup form for the list.
+pub async fn form_snippet(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Query(options): Query<SnippetOptions>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    Ok(Html(snippet(
+        &data.form,
+        &data.protection.settings,
+        &data.pages.url,
+        &data.list.name,
+        options.ajax,
+    )))
+}
+
+/// A signed stamp of the time a form was loaded, for forms that can't be
+/// stamped when they are made, like pasted snippets.
+pub async fn form_stamp(
+    State(data): State<ApplicationData>,
+    origin: Option<TypedHeader<Origin>>,
+) -> Response {
+    let mut response = Json(json!({ "stamp": data.protection.stamp() })).into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    cors(&data, origin, response)
+}
+
+/// How many signups were rejected for each reason since the app started.
+pub async fn form_rejections(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Map<String, Value>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    Ok(Json(data.protection.metrics.to_json()))
+}
+
+/// Like `/api/subscribe`, but for `fetch`: it takes JSON and answers with
+/// the outcome or what is wrong with each field, instead of redirecting.
+/// Bots and suppressed addresses are told they subscribed.
+pub async fn subscribe_json(
+    State(data): State<ApplicationData>,
+    origin: Option<TypedHeader<Origin>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let client = data
+        .protection
+        .client(&headers, peer.map(|ConnectInfo(peer)| peer));
+    let fields = match serde_json::from_slice::<Map<String, Value>>(&body) {
+        Ok(fields) => Ok(data.protection.check(&fields, client).await.map(|_| fields)),
+        Err(e) => Err(e),
+    };
+    let response = match fields {
+        Err(e) => errors(
+            StatusCode::BAD_REQUEST,
+            vec![FieldError::new(
+                "",
+                &format!("The body is not a JSON object: {e}"),
+            )],
+        ),
+        Ok(Err(Rejection::Honeypot)) => status("subscribed", Vec::new()),
+        Ok(Err(rejection)) => errors(
+            if rejection.is_rate_limit() {
+                StatusCode::TOO_MANY_REQUESTS
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY
+            },
+            vec![FieldError::new("", rejection.message())],
+        ),
+        Ok(Ok(fields)) => match forms::validate(&data, &fields).await {
+            Err(field_errors) => errors(StatusCode::UNPROCESSABLE_ENTITY, field_errors),
+            Ok(submission) => {
+                let warnings = submission.warnings();
+                match subscribe(&data, submission).await {
+                    Ok(Some(subscriber)) if subscriber.status == SubscriberStatus::Pending => {
+                        status("pending", warnings)
+                    }
+                    Ok(_) => status("subscribed", warnings),
+                    Err(e) => {
+                        error!("Failed to subscribe: {e}");
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(json!({ "error": e.to_string() })),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        },
+    };
+    cors(&data, origin, response)
+}
+
+/// Answers the browser's preflight request for `/api/subscriptions`.
+pub async fn subscribe_preflight(
+    State(data): State<ApplicationData>,
+    origin: Option<TypedHeader<Origin>>,
+) -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static("POST"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        HeaderValue::from_static("content-type"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_MAX_AGE,
+        HeaderValue::from_static("86400"),
+    );
+    cors(&data, origin, response)
+}
+
+/// The outcome, with the flagged problems of the address when there are any.
+fn status(status: &str, warnings: Vec<FieldError>) -> Response {
+    if warnings.is_empty() {
+        Json(json!({ "status": status })).into_response()
+    } else {
+        Json(json!({ "status": status, "warnings": warnings })).into_response()
+    }
+}
+
+fn errors(status: StatusCode, errors: Vec<FieldError>) -> Response {
+    (status, Json(json!({ "errors": errors }))).into_response()
+}
+
+/// Lets the list's origins read the response.
+fn cors(
+    data: &ApplicationData,
+    origin: Option<TypedHeader<Origin>>,
+    mut response: Response,
+) -> Response {
+    let headers = response.headers_mut();
+    headers.insert(header::VARY, HeaderValue::from_static("origin"));
+    let Some(TypedHeader(origin)) = origin else {
+        return response;
+    };
+    let origin = origin.to_string();
+    let allowed = data
+        .list
+        .origins
+        .iter()
+        .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(&origin));
+    if allowed {
+        if let Ok(origin) = HeaderValue::from_str(&origin) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        }
+    }
+    response
+}
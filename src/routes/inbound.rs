@@ -0,0 +1,123 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use log::error;
+
+use crate::{
+    config::ListMode,
+    data::ApplicationData,
+    inbound::{
+        parse_bounces, parse_complaint, process_bounce, process_complaint, process_post,
+        process_request, Command, Verp,
+    },
+    model::{Bounce, Complaint, Post},
+    routes::auth::authorize_with,
+};
+
+/// Accepts a raw RFC 822 bounce, as forwarded by a mail provider's webhook.
+pub async fn bounces(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    body: Bytes,
+) -> Result<Json<Vec<Bounce>>, (StatusCode, String)> {
+    authorize_with(&data.inbound.token, &authorization)?;
+
+    let mail =
+        mailparse::parse_mail(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let Some(bounces) = parse_bounces(&mail, &Verp::from(data.bounce.clone())) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Not a delivery status notification".to_string(),
+        ));
+    };
+
+    for bounce in &bounces {
+        if let Err(e) = process_bounce(&data, bounce).await {
+            error!("Failed to process bounce: {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        }
+    }
+
+    Ok(Json(bounces))
+}
+
+/// Accepts a raw RFC 822 feedback loop report in Abuse Reporting Format.
+pub async fn complaints(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    body: Bytes,
+) -> Result<Json<Complaint>, (StatusCode, String)> {
+    authorize_with(&data.inbound.token, &authorization)?;
+
+    let mail =
+        mailparse::parse_mail(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let Some(complaint) = parse_complaint(&mail, &Verp::from(data.bounce.clone())) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Not an abuse report with a known recipient".to_string(),
+        ));
+    };
+
+    if let Err(e) = process_complaint(&data, &complaint).await {
+        error!("Failed to process complaint: {}", e);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    }
+
+    Ok(Json(complaint))
+}
+
+/// Accepts a raw RFC 822 message sent to the list's request address, and
+/// returns the commands that were carried out.
+pub async fn requests(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    body: Bytes,
+) -> Result<Json<Vec<Command>>, (StatusCode, String)> {
+    authorize_with(&data.inbound.token, &authorization)?;
+
+    let mail =
+        mailparse::parse_mail(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    match process_request(&data, &mail).await {
+        Ok(commands) => Ok(Json(commands)),
+        Err(e) => {
+            error!("Failed to process request: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+/// Accepts a raw RFC 822 message sent to the address of a discussion list.
+pub async fn posts(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    body: Bytes,
+) -> Result<Json<Post>, (StatusCode, String)> {
+    authorize_with(&data.inbound.token, &authorization)?;
+
+    if data.list.mode != ListMode::Discussion {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "This list does not take posts".to_string(),
+        ));
+    }
+
+    let mail =
+        mailparse::parse_mail(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    match process_post(&data, &mail, &body).await {
+        Ok(Some(post)) => Ok(Json(post)),
+        Ok(None) => Err((
+            StatusCode::BAD_REQUEST,
+            "Automatic messages are not posted".to_string(),
+        )),
+        Err(e) => {
+            error!("Failed to process post: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
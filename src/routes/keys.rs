@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use serde::Serialize;
+
+use crate::{
+    data::ApplicationData,
+    model::{ApiKey, NewApiKey},
+    routes::auth::authorize,
+    store::{ApiKeyStore, PsqlApiKeyStore},
+};
+
+#[derive(Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    // Shown only this once.
+    key: String,
+}
+
+pub async fn create_key(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_api_key): Json<NewApiKey>,
+) -> Result<Json<CreatedApiKey>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let key = ApiKey::generate();
+    let api_key = PsqlApiKeyStore::from(data.pool)
+        .create(new_api_key, &ApiKey::hash(&key))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(CreatedApiKey { api_key, key }))
+}
+
+pub async fn get_keys(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<ApiKey>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlApiKeyStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+pub async fn revoke_key(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match PsqlApiKeyStore::from(data.pool).revoke(id).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Key not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
@@ -1,3 +1,65 @@
+mod activity;
+mod admin;
+mod archive;
+mod auth;
+mod automations;
+mod campaigns;
+mod feeds;
+mod forms;
+mod inbound;
+mod keys;
+mod moderation;
+mod pages;
+mod send;
 mod subscribers;
+mod tracking;
+mod warmup;
+mod webhooks;
 
-pub use subscribers::{delete, get_subscribers, subscribe};
+use axum::http::StatusCode;
+use log::error;
+
+pub use activity::{activity_stream, issue_stream_token};
+pub use admin::{
+    admin_campaign, admin_campaigns, admin_compose_campaign, admin_create_key, admin_keys,
+    admin_list, admin_log_in, admin_log_out, admin_login_page, admin_overview, admin_revoke_key,
+    admin_stylesheet, admin_subscriber, admin_subscribers, admin_suppress, admin_tag_subscriber,
+    admin_unsubscribe, admin_unsuppress, admin_untag_subscriber,
+};
+pub use archive::{archive_atom, archive_index, archive_issue, archive_json};
+pub use automations::{
+    automation_runs, create_automation, get_automations, get_templates, save_template,
+    simulate_automation,
+};
+pub use campaigns::{
+    campaign_report, compare_campaigns, create_campaign, exclude_from_archive, schedule_campaign,
+    test_campaign,
+};
+pub use feeds::{create_feed, get_feeds, run_feed};
+pub use forms::{form_rejections, form_snippet, form_stamp, subscribe_json, subscribe_preflight};
+pub use inbound::{bounces, complaints, posts, requests};
+pub use keys::{create_key, get_keys, revoke_key};
+pub use moderation::{
+    allow_sender, approve_held_post, approve_pending_signup, disallow_sender, get_allowlist,
+    moderate_by_link, moderation_link, moderation_queue, reject_held_post, reject_pending_signup,
+};
+pub use pages::{
+    confirm_by_page, confirm_page, preferences_page, save_preferences, subscribe_by_page,
+    subscribe_page, unsubscribe_by_page, unsubscribe_page,
+};
+pub use send::{governor_report, message_status, send};
+pub use subscribers::{
+    add_tag, delete, get_subscribers, remove_tag, set_attributes, set_timezone, subscribe,
+};
+pub use tracking::{click, open};
+pub use warmup::{end_warmup_plan, save_warmup_plan, warmup_report};
+pub use webhooks::{
+    create_webhook, delete_webhook, get_webhooks, replay_webhook, webhook_deliveries,
+};
+
+/// Logs an error that the client can do nothing about and reports it as
+/// such.
+fn internal_error(e: anyhow::Error) -> (StatusCode, String) {
+    error!("Failed to handle request: {e}");
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
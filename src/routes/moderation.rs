@@ -0,0 +1,275 @@
+use axum::{
+    extract::{Path, Query, State},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    response::Html,
+    Form, Json, TypedHeader,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::ApplicationData,
+    model::{Email, Post, Subscriber, SubscriberStatus},
+    moderation::{
+        approve_post, approve_signup, reject_post, reject_signup, Action, Item, ModerationToken,
+    },
+    pages::{escape, page},
+    routes::{auth::authorize, internal_error},
+    signing::Signer,
+    store::{
+        AllowlistStore, PostStore, PsqlAllowlistStore, PsqlPostStore, PsqlSubscriberStore,
+        SubscriberStore,
+    },
+};
+
+#[derive(Serialize)]
+pub struct Queue {
+    posts: Vec<Post>,
+    signups: Vec<Subscriber>,
+}
+
+#[derive(Deserialize)]
+pub struct Approval {
+    // Also lets the sender post without moderation from now on.
+    #[serde(default)]
+    allow: bool,
+}
+
+#[derive(Default, Deserialize)]
+pub struct Rejection {
+    // Mailed to the sender. Without one, they are not told.
+    reason: Option<String>,
+}
+
+impl Rejection {
+    fn reason(&self) -> Option<&str> {
+        self.reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Allowed {
+    email: Email,
+}
+
+fn not_waiting(item: Item) -> (StatusCode, String) {
+    let message = match item {
+        Item::Post => "No held post with that id",
+        Item::Signup => "No pending signup with that id",
+    };
+    (StatusCode::NOT_FOUND, message.to_string())
+}
+
+/// Lists the held posts and pending signups.
+pub async fn moderation_queue(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Queue>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let posts = PsqlPostStore::from(data.pool.clone())
+        .held()
+        .await
+        .map_err(internal_error)?;
+    let signups = PsqlSubscriberStore::from(data.pool)
+        .all()
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .filter(|subscriber| subscriber.status == SubscriberStatus::Pending)
+        .collect();
+
+    Ok(Json(Queue { posts, signups }))
+}
+
+pub async fn approve_held_post(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Query(approval): Query<Approval>,
+) -> Result<Json<Post>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    approve_post(&data, id, approval.allow)
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| not_waiting(Item::Post))
+}
+
+pub async fn reject_held_post(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    rejection: Option<Json<Rejection>>,
+) -> Result<Json<Post>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let Json(rejection) = rejection.unwrap_or_default();
+    reject_post(&data, id, rejection.reason())
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| not_waiting(Item::Post))
+}
+
+pub async fn approve_pending_signup(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Subscriber>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    approve_signup(&data, id)
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| not_waiting(Item::Signup))
+}
+
+pub async fn reject_pending_signup(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    rejection: Option<Json<Rejection>>,
+) -> Result<Json<Subscriber>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let Json(rejection) = rejection.unwrap_or_default();
+    reject_signup(&data, id, rejection.reason())
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| not_waiting(Item::Signup))
+}
+
+pub async fn get_allowlist(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Email>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlAllowlistStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn allow_sender(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(allowed): Json<Allowed>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlAllowlistStore::from(data.pool)
+        .add(&allowed.email)
+        .await
+        .map_err(internal_error)?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn disallow_sender(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Query(allowed): Query<Allowed>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlAllowlistStore::from(data.pool)
+        .remove(&allowed.email)
+        .await
+        .map_err(internal_error)?;
+    Ok(StatusCode::OK)
+}
+
+/// Shows what a link from a notification does. It only happens once the
+/// moderator submits the form, since mail scanners follow links.
+pub async fn moderation_link(
+    State(data): State<ApplicationData>,
+    Path(token): Path<String>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let token = verify(&data, &token)?;
+
+    let description = match token.item {
+        Item::Post => {
+            let post = PsqlPostStore::from(data.pool.clone())
+                .get(token.id)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| not_waiting(Item::Post))?;
+            format!("“{}” by {}", post.subject, post.sender.0)
+        }
+        Item::Signup => {
+            let subscriber = PsqlSubscriberStore::from(data.pool.clone())
+                .get(token.id)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| not_waiting(Item::Signup))?;
+            format!("Signup of {}", subscriber.email.0)
+        }
+    };
+
+    let (title, reason) = match token.action {
+        Action::Approve => ("Approve", ""),
+        Action::Reject => (
+            "Reject",
+            "<p><label>Reason, mailed to the sender if given<br>\
+             <textarea name=\"reason\" rows=\"4\" cols=\"60\"></textarea></label></p>",
+        ),
+    };
+
+    Ok(Html(page(
+        &format!("{title} for {}", data.list.name),
+        &format!(
+            "<p>{}</p><form method=\"post\">{reason}<button type=\"submit\">{title}</button></form>",
+            escape(&description)
+        ),
+    )))
+}
+
+pub async fn moderate_by_link(
+    State(data): State<ApplicationData>,
+    Path(token): Path<String>,
+    Form(rejection): Form<Rejection>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let token = verify(&data, &token)?;
+
+    let done = match (token.item, token.action) {
+        (Item::Post, Action::Approve) => approve_post(&data, token.id, false)
+            .await
+            .map(|post| post.map(|_| "The post was sent to the list.")),
+        (Item::Post, Action::Reject) => reject_post(&data, token.id, rejection.reason())
+            .await
+            .map(|post| post.map(|_| "The post was rejected.")),
+        (Item::Signup, Action::Approve) => approve_signup(&data, token.id)
+            .await
+            .map(|signup| signup.map(|_| "The subscriber was approved.")),
+        (Item::Signup, Action::Reject) => reject_signup(&data, token.id, rejection.reason())
+            .await
+            .map(|signup| signup.map(|_| "The signup was rejected.")),
+    }
+    .map_err(internal_error)?;
+
+    let message = done.unwrap_or("This was already moderated.");
+    Ok(Html(page(
+        &format!("Moderation of {}", data.list.name),
+        &format!("<p>{message}</p>"),
+    )))
+}
+
+fn verify(data: &ApplicationData, token: &str) -> Result<ModerationToken, (StatusCode, String)> {
+    Signer::new(&data.list.secret)
+        .verify::<ModerationToken>(token)
+        .filter(|token| !token.expired())
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "This link is invalid or has expired".to_string(),
+            )
+        })
+}
@@ -0,0 +1,502 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    headers::Cookie,
+    http::{header::SET_COOKIE, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form, TypedHeader,
+};
+use log::info;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    activity, automation,
+    config::{DeliverabilityPolicy, ListMode},
+    data::ApplicationData,
+    forms::{self, text, AddressProblem},
+    model::{ActivityKind, Email, Subscriber, SubscriberStatus, Trigger, WebhookEvent},
+    moderation::signup,
+    pages::{
+        escape, preferences_url, request_confirmation, Confirmation, Csrf, Link, Page, Purpose,
+        SubscriberToken,
+    },
+    protection::Rejection,
+    routes::internal_error,
+    signing::Signer,
+    store::{PsqlSubscriberStore, PsqlSuppressionStore, SubscriberStore, SuppressionStore},
+    tracking, webhooks,
+};
+
+#[derive(Deserialize)]
+pub struct Submitted {
+    #[serde(default)]
+    csrf: String,
+}
+
+/// The signup form.
+pub async fn subscribe_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+) -> Response {
+    let csrf = Csrf::new(cookie.as_deref());
+    subscribe_form(&data, &csrf, StatusCode::OK, "").await
+}
+
+/// Mails a confirmation link. The page reads the same whether or not the
+/// address is subscribed or suppressed, so that it does not tell anyone.
+pub async fn subscribe_by_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, (StatusCode, String)> {
+    let fields: Map<String, Value> = fields
+        .into_iter()
+        .map(|(name, value)| (name, Value::String(value)))
+        .collect();
+    let csrf_token = text(&fields, "csrf").unwrap_or_default();
+    if let Some(forbidden) = check_csrf(&data, cookie.as_deref(), &csrf_token).await {
+        return Ok(forbidden);
+    }
+    let email = text(&fields, "email").unwrap_or_default();
+    let client = data
+        .protection
+        .client(&headers, peer.map(|ConnectInfo(peer)| peer));
+    match data.protection.check(&fields, client).await {
+        Ok(()) => {}
+        Err(Rejection::Honeypot) => {
+            let page = Page::new("check_inbox", "Check your inbox").text("email", &email);
+            return Ok(html(StatusCode::OK, render(&data, page).await, None));
+        }
+        Err(rejection) => {
+            let csrf = Csrf::new(cookie.as_deref());
+            let error = format!("<p class=\"error\">{}</p>", rejection.message());
+            let status = if rejection.is_rate_limit() {
+                StatusCode::TOO_MANY_REQUESTS
+            } else {
+                StatusCode::UNPROCESSABLE_ENTITY
+            };
+            return Ok(subscribe_form(&data, &csrf, status, &error).await);
+        }
+    }
+    if email.parse::<lettre::Address>().is_err() {
+        let csrf = Csrf::new(cookie.as_deref());
+        let error = "<p class=\"error\">Enter a valid email address.</p>";
+        return Ok(subscribe_form(&data, &csrf, StatusCode::UNPROCESSABLE_ENTITY, error).await);
+    }
+
+    let email = Email::from(email.as_str());
+    let rejected = data.address_checks.check(&email).rejected;
+    if !rejected.is_empty() {
+        let csrf = Csrf::new(cookie.as_deref());
+        let error: String = rejected
+            .iter()
+            .map(|problem| format!("<p class=\"error\">{}</p>", escape(&problem.message())))
+            .collect();
+        return Ok(subscribe_form(&data, &csrf, StatusCode::UNPROCESSABLE_ENTITY, &error).await);
+    }
+    // Other policies can wait, since confirming shows that mail arrives.
+    if let Some(check) = &data.deliverability {
+        if check.policy == DeliverabilityPolicy::Reject
+            && check.receives_mail(&email).await == Some(false)
+        {
+            let csrf = Csrf::new(cookie.as_deref());
+            let error = format!(
+                "<p class=\"error\">{}</p>",
+                AddressProblem::Undeliverable.message()
+            );
+            return Ok(
+                subscribe_form(&data, &csrf, StatusCode::UNPROCESSABLE_ENTITY, &error).await,
+            );
+        }
+    }
+    let suppressed = PsqlSuppressionStore::from(data.pool.clone())
+        .contains(&email)
+        .await
+        .map_err(internal_error)?;
+    if suppressed {
+        info!("Not confirming suppressed address: {email:?}");
+    } else {
+        let timezone =
+            text(&fields, "timezone").filter(|timezone| timezone.parse::<chrono_tz::Tz>().is_ok());
+        request_confirmation(&data, &email, timezone)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let page = Page::new("check_inbox", "Check your inbox").text("email", &email.0);
+    Ok(html(StatusCode::OK, render(&data, page).await, None))
+}
+
+/// Shows what following a confirmation link does. It only happens once the
+/// form is submitted, since mail scanners follow links.
+pub async fn confirm_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+) -> Response {
+    let Some(confirmation) = confirmation(&data, &token) else {
+        return invalid(&data).await;
+    };
+    let csrf = Csrf::new(cookie.as_deref());
+    let page = Page::new("confirm", "Confirm your subscription")
+        .text("email", &confirmation.email)
+        .html("csrf", csrf.field(&signer(&data)));
+    html(StatusCode::OK, render(&data, page).await, Some(&csrf))
+}
+
+pub async fn confirm_by_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+    Form(submitted): Form<Submitted>,
+) -> Result<Response, (StatusCode, String)> {
+    if let Some(forbidden) = check_csrf(&data, cookie.as_deref(), &submitted.csrf).await {
+        return Ok(forbidden);
+    }
+    let Some(confirmation) = confirmation(&data, &token) else {
+        return Ok(invalid(&data).await);
+    };
+    let email = Email(confirmation.email);
+    let suppressed = PsqlSuppressionStore::from(data.pool.clone())
+        .contains(&email)
+        .await
+        .map_err(internal_error)?;
+    if suppressed {
+        info!("Not subscribing suppressed address: {email:?}");
+        return Ok(invalid(&data).await);
+    }
+
+    let flagged = data.address_checks.check(&email).flagged;
+    let subscriber = signup(&data, email, false).await.map_err(internal_error)?;
+    forms::flag(&data, &subscriber, &flagged)
+        .await
+        .map_err(internal_error)?;
+    if subscriber.timezone.is_none() && confirmation.timezone.is_some() {
+        PsqlSubscriberStore::from(data.pool.clone())
+            .set_timezone(&subscriber.email, confirmation.timezone.as_deref())
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let page = if subscriber.status == SubscriberStatus::Pending {
+        Page::new("pending", "Almost there")
+    } else {
+        Page::new("confirmed", "You are subscribed")
+            .text("preferences_url", &preferences_url(&data, &subscriber))
+    };
+    let page = page.text("email", &subscriber.email.0);
+    Ok(html(StatusCode::OK, render(&data, page).await, None))
+}
+
+/// Asks before unsubscribing, since mail scanners follow links.
+pub async fn unsubscribe_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some((subscriber, _)) = subscriber(&data, &token, Link::Unsubscribe).await? else {
+        return Ok(invalid(&data).await);
+    };
+    let csrf = Csrf::new(cookie.as_deref());
+    let page = Page::new("unsubscribe", "Unsubscribe")
+        .text("email", &subscriber.email.0)
+        .html("csrf", csrf.field(&signer(&data)));
+    Ok(html(StatusCode::OK, render(&data, page).await, Some(&csrf)))
+}
+
+#[derive(Deserialize)]
+pub struct Unsubscription {
+    #[serde(default)]
+    csrf: String,
+    // Sent by mail clients for a one-click unsubscribe (RFC 8058).
+    #[serde(rename = "List-Unsubscribe", default)]
+    one_click: String,
+}
+
+/// Unsubscribes from the page, or with one click from a mail client, which
+/// has no cookie but whose request the token in the link authenticates.
+pub async fn unsubscribe_by_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+    Form(submitted): Form<Unsubscription>,
+) -> Result<Response, (StatusCode, String)> {
+    if submitted.one_click != "One-Click" {
+        if let Some(forbidden) = check_csrf(&data, cookie.as_deref(), &submitted.csrf).await {
+            return Ok(forbidden);
+        }
+    }
+    let Some((subscriber, token)) = subscriber(&data, &token, Link::Unsubscribe).await? else {
+        return Ok(invalid(&data).await);
+    };
+    unsubscribe(&data, &subscriber, token.campaign_id).await
+}
+
+/// Lets a subscriber pick their topics and how often they get posts, and
+/// edit the attributes they are allowed to.
+pub async fn preferences_page(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some((subscriber, _)) = subscriber(&data, &token, Link::Preferences).await? else {
+        return Ok(invalid(&data).await);
+    };
+    let store = PsqlSubscriberStore::from(data.pool.clone());
+    let tags = store.tags(subscriber.id).await.map_err(internal_error)?;
+    let attributes = store
+        .attributes(subscriber.id)
+        .await
+        .map_err(internal_error)?;
+
+    let csrf = Csrf::new(cookie.as_deref());
+    let page = Page::new("preferences", "Your preferences")
+        .text("email", &subscriber.email.0)
+        .html("csrf", csrf.field(&signer(&data)))
+        .html("frequency", frequency_fields(&data, &subscriber))
+        .html("topics", topic_fields(&data, &tags))
+        .html("attributes", attribute_fields(&data, &attributes));
+    Ok(html(StatusCode::OK, render(&data, page).await, Some(&csrf)))
+}
+
+/// Saves the preference form. Its fields repeat, one `topic` per picked
+/// topic, so they are read as pairs.
+pub async fn save_preferences(
+    State(data): State<ApplicationData>,
+    cookie: Option<TypedHeader<Cookie>>,
+    Path(token): Path<String>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, (StatusCode, String)> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let csrf = field("csrf").unwrap_or_default();
+    if let Some(forbidden) = check_csrf(&data, cookie.as_deref(), csrf).await {
+        return Ok(forbidden);
+    }
+    let Some((subscriber, token)) = subscriber(&data, &token, Link::Preferences).await? else {
+        return Ok(invalid(&data).await);
+    };
+    if field("subscribed").is_none() {
+        return unsubscribe(&data, &subscriber, token.campaign_id).await;
+    }
+
+    let mut store = PsqlSubscriberStore::from(data.pool.clone());
+    if data.list.mode == ListMode::Discussion {
+        if let Some(digest) = field("digest") {
+            store
+                .set_digest(&subscriber.email, digest == "true")
+                .await
+                .map_err(internal_error)?;
+        }
+    }
+
+    for topic in &data.pages.topics {
+        let picked = fields
+            .iter()
+            .any(|(key, value)| key == "topic" && value == topic);
+        if !picked {
+            store
+                .remove_tag(subscriber.id, topic)
+                .await
+                .map_err(internal_error)?;
+        } else if store
+            .add_tag(subscriber.id, topic)
+            .await
+            .map_err(internal_error)?
+        {
+            automation::enroll(
+                &data,
+                &subscriber,
+                &Trigger::TagAdded { tag: topic.clone() },
+            )
+            .await
+            .map_err(internal_error)?;
+        }
+    }
+
+    let attributes: Map<String, Value> = data
+        .pages
+        .attributes
+        .iter()
+        .map(|name| {
+            let value = field(&format!("attribute.{name}"))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map_or(Value::Null, |value| Value::String(value.to_string()));
+            (name.clone(), value)
+        })
+        .collect();
+    if !attributes.is_empty() {
+        store
+            .set_attributes(subscriber.id, attributes)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    let page = Page::new("saved", "Preferences saved")
+        .text("preferences_url", &preferences_url(&data, &subscriber));
+    Ok(html(StatusCode::OK, render(&data, page).await, None))
+}
+
+async fn unsubscribe(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    campaign_id: Option<i32>,
+) -> Result<Response, (StatusCode, String)> {
+    tracking::unsubscribed(data, subscriber, campaign_id).await;
+    PsqlSubscriberStore::from(data.pool.clone())
+        .delete(&subscriber.email)
+        .await
+        .map_err(internal_error)?;
+    info!("Unsubscribed by page: {:?}", subscriber.email);
+    webhooks::emit_subscriber(data, WebhookEvent::Unsubscribed, subscriber).await;
+    activity::publish_subscriber(data, ActivityKind::Unsubscribed, subscriber).await;
+
+    let page = Page::new("unsubscribed", "You are unsubscribed").text("email", &subscriber.email.0);
+    Ok(html(StatusCode::OK, render(data, page).await, None))
+}
+
+fn frequency_fields(data: &ApplicationData, subscriber: &Subscriber) -> String {
+    if data.list.mode != ListMode::Discussion {
+        return String::new();
+    }
+    let checked = |digest: bool| {
+        if subscriber.digest == digest {
+            " checked"
+        } else {
+            ""
+        }
+    };
+    format!(
+        "<fieldset><legend>Frequency</legend>\
+         <label><input type=\"radio\" name=\"digest\" value=\"false\"{}> Every post</label>\
+         <label><input type=\"radio\" name=\"digest\" value=\"true\"{}> A digest every {} hours</label>\
+         </fieldset>",
+        checked(false),
+        checked(true),
+        data.list.digest
+    )
+}
+
+fn topic_fields(data: &ApplicationData, tags: &[String]) -> String {
+    if data.pages.topics.is_empty() {
+        return String::new();
+    }
+    let topics: String = data
+        .pages
+        .topics
+        .iter()
+        .map(|topic| {
+            let checked = if tags.contains(topic) { " checked" } else { "" };
+            format!(
+                "<label><input type=\"checkbox\" name=\"topic\" value=\"{0}\"{checked}> {0}</label>",
+                escape(topic)
+            )
+        })
+        .collect();
+    format!("<fieldset><legend>Topics</legend>{topics}</fieldset>")
+}
+
+fn attribute_fields(data: &ApplicationData, attributes: &Map<String, Value>) -> String {
+    data.pages
+        .attributes
+        .iter()
+        .map(|name| {
+            let value = match attributes.get(name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            };
+            format!(
+                "<p><label>{0}<br><input name=\"attribute.{0}\" value=\"{1}\"></label></p>",
+                escape(name),
+                escape(&value)
+            )
+        })
+        .collect()
+}
+
+async fn subscribe_form(
+    data: &ApplicationData,
+    csrf: &Csrf,
+    status: StatusCode,
+    error: &str,
+) -> Response {
+    let page = Page::new("subscribe", format!("Subscribe to {}", data.list.name))
+        .html("csrf", csrf.field(&signer(data)))
+        .html("protection", data.protection.fields())
+        .html("error", error);
+    html(status, render(data, page).await, Some(csrf))
+}
+
+/// The page to show instead when a form was not sent from our own page.
+async fn check_csrf(
+    data: &ApplicationData,
+    cookie: Option<&Cookie>,
+    token: &str,
+) -> Option<Response> {
+    if Csrf::verify(&signer(data), cookie, token) {
+        return None;
+    }
+    info!("Rejecting form without a valid CSRF token");
+    let page = Page::new("forbidden", "Form expired");
+    Some(html(StatusCode::FORBIDDEN, render(data, page).await, None))
+}
+
+fn confirmation(data: &ApplicationData, token: &str) -> Option<Confirmation> {
+    signer(data)
+        .verify::<Confirmation>(token)
+        .filter(|confirmation| {
+            confirmation.purpose == Purpose::Subscribe && !confirmation.expired()
+        })
+}
+
+/// The subscriber that a token for `link` was made for, and the token.
+async fn subscriber(
+    data: &ApplicationData,
+    token: &str,
+    link: Link,
+) -> Result<Option<(Subscriber, SubscriberToken)>, (StatusCode, String)> {
+    let Some(token) = signer(data)
+        .verify::<SubscriberToken>(token)
+        .filter(|token| token.opens(link))
+    else {
+        return Ok(None);
+    };
+    Ok(PsqlSubscriberStore::from(data.pool.clone())
+        .get(token.subscriber_id)
+        .await
+        .map_err(internal_error)?
+        .map(|subscriber| (subscriber, token)))
+}
+
+async fn invalid(data: &ApplicationData) -> Response {
+    let page = Page::new("invalid", "Invalid link");
+    html(StatusCode::NOT_FOUND, render(data, page).await, None)
+}
+
+async fn render(data: &ApplicationData, page: Page) -> String {
+    page.render(&data.pages, &data.list.name).await
+}
+
+fn signer(data: &ApplicationData) -> Signer {
+    Signer::new(&data.list.secret)
+}
+
+fn html(status: StatusCode, body: String, csrf: Option<&Csrf>) -> Response {
+    let mut response = (status, Html(body)).into_response();
+    if let Some(cookie) = csrf.and_then(Csrf::set_cookie) {
+        if let Ok(cookie) = cookie.parse() {
+            response.headers_mut().insert(SET_COOKIE, cookie);
+        }
+    }
+    response
+}
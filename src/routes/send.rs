@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    headers::{authorization::Bearer, Authorization},
+    http::{HeaderMap, StatusCode},
+    Json, TypedHeader,
+};
+use lettre::message::Mailbox;
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    data::ApplicationData,
+    model::{DeliveryReport, NewDelivery, Scope},
+    outbound::{GovernorReport, SendRequest},
+    routes::auth::{authorize, authorize_key},
+    store::{OutboxStore, PsqlOutboxStore, PsqlTemplateStore, TemplateStore},
+};
+
+const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+
+#[derive(Serialize)]
+pub struct Sent {
+    id: i64,
+}
+
+/// Queues a one-off message. Repeating a request with the same
+/// `Idempotency-Key` returns the message queued the first time.
+pub async fn send(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    headers: HeaderMap,
+    Json(request): Json<SendRequest>,
+) -> Result<(StatusCode, Json<Sent>), (StatusCode, String)> {
+    let key = authorize_key(&data, &authorization, Scope::Send).await?;
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_HEADER)
+        .map(|value| value.to_str())
+        .transpose()
+        .map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("{IDEMPOTENCY_HEADER} must be text"),
+            )
+        })?;
+
+    let template = match request.template_id {
+        Some(id) => Some(
+            PsqlTemplateStore::from(data.pool.clone())
+                .get(id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .ok_or((
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("No template with id {id}"),
+                ))?,
+        ),
+        None => None,
+    };
+    let from = data
+        .mailer
+        .sender()
+        .parse()
+        .map(|address| Mailbox::new(Some(data.list.name.clone()), address))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let message = request
+        .build(from, template.as_ref())
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    // The bare address, without any display name that came with `to`.
+    let recipient = message
+        .envelope()
+        .to()
+        .first()
+        .map(ToString::to_string)
+        .ok_or((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "A recipient is required.".to_string(),
+        ))?;
+
+    let (id, created) = PsqlOutboxStore::from(data.pool)
+        .submit(
+            NewDelivery {
+                sender: data.mailer.sender().to_string(),
+                recipient,
+                raw: message.formatted(),
+                campaign_id: None,
+                engagement: 0,
+            },
+            key.id,
+            idempotency_key,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if created {
+        info!("Queued message {id} for key {}", key.name);
+        Ok((StatusCode::ACCEPTED, Json(Sent { id })))
+    } else {
+        Ok((StatusCode::OK, Json(Sent { id })))
+    }
+}
+
+pub async fn message_status(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i64>,
+) -> Result<Json<DeliveryReport>, (StatusCode, String)> {
+    let key = authorize_key(&data, &authorization, Scope::Status).await?;
+
+    PsqlOutboxStore::from(data.pool)
+        .report(id, key.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))
+}
+
+/// How fast mail goes out to each recipient domain, and which are paused.
+pub async fn governor_report(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<GovernorReport>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    Ok(Json(data.governor.report()))
+}
@@ -1,19 +1,27 @@
 use crate::{
+    activity, automation,
     data::ApplicationData,
-    model::{Email, NewSubscriber},
+    forms::{self, redirect_target, Outcome},
+    model::{ActivityKind, Email, Subscriber, SubscriberStatus, Trigger, WebhookEvent},
+    pages::Page,
+    protection::Rejection,
+    routes::auth::authorize,
     store::{PsqlSubscriberStore, SubscriberStore},
+    webhooks,
 };
 use axum::{
-    extract::{Query, State},
+    extract::{ConnectInfo, Query, State},
     headers::{authorization::Bearer, Authorization, Origin},
-    http::StatusCode,
-    response::{IntoResponse, Redirect},
-    Form, TypedHeader,
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    Form, Json, TypedHeader,
 };
 use log::debug;
 use log::error;
 use log::info;
 use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::net::SocketAddr;
 
 pub async fn get_subscribers(
     State(data): State<ApplicationData>,
@@ -21,11 +29,7 @@ pub async fn get_subscribers(
 ) -> Result<String, (StatusCode, String)> {
     debug!("{authorization:?}");
 
-    let authorized = data.admin.token.eq(authorization.0.token());
-
-    if !authorized {
-        return Err((StatusCode::UNAUTHORIZED, "Not authorized".to_string()));
-    }
+    authorize(&data, &authorization)?;
 
     let store = PsqlSubscriberStore::from(data.pool);
     let subscribers = match store.all().await {
@@ -37,18 +41,72 @@ pub async fn get_subscribers(
     Ok(emails.join("\n"))
 }
 
+/// Subscribes the address of a signup form, then sends the visitor on, or
+/// shows them the outcome when there is nowhere allowed to send them.
 pub async fn subscribe(
     State(data): State<ApplicationData>,
-    TypedHeader(origin): TypedHeader<Origin>,
-    Form(new_subscriber): Form<NewSubscriber>,
-) -> Redirect {
-    let mut store = PsqlSubscriberStore::from(data.pool);
-    store.create(new_subscriber).await.unwrap();
-    let redirect_url = data
-        .subscribed
-        .redirect
-        .unwrap_or_else(|| origin.to_string());
-    Redirect::to(&redirect_url)
+    origin: Option<TypedHeader<Origin>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<Response, (StatusCode, String)> {
+    let fields: Map<String, Value> = fields
+        .into_iter()
+        .map(|(name, value)| (name, Value::String(value)))
+        .collect();
+    let client = data
+        .protection
+        .client(&headers, peer.map(|ConnectInfo(peer)| peer));
+    let (outcome, page) = match data.protection.check(&fields, client).await {
+        Err(Rejection::Honeypot) => (Outcome::Subscribed, Page::new("subscribed", "Thanks")),
+        Err(rejection) => (
+            Outcome::Invalid,
+            Page::new("error", "Not subscribed").text("message", rejection.message()),
+        ),
+        Ok(()) => match forms::validate(&data, &fields).await {
+            Ok(submission) => {
+                let email = submission.email.clone();
+                let subscriber = forms::subscribe(&data, submission)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                match subscriber {
+                    Some(subscriber) if subscriber.status == SubscriberStatus::Pending => (
+                        Outcome::Pending,
+                        Page::new("pending", "Almost there").text("email", &email.0),
+                    ),
+                    _ => (Outcome::Subscribed, Page::new("subscribed", "Thanks")),
+                }
+            }
+            Err(errors) => {
+                info!("Ignoring invalid signup: {errors:?}");
+                let message: Vec<_> = errors.into_iter().map(|error| error.message).collect();
+                (
+                    Outcome::Invalid,
+                    Page::new("error", "Not subscribed").text("message", &message.join(" ")),
+                )
+            }
+        },
+    };
+
+    let field = fields.get("redirect").and_then(Value::as_str);
+    let origin = origin.map(|TypedHeader(origin)| origin.to_string());
+    let target = redirect_target(
+        &data.subscribed,
+        &data.pages.url,
+        outcome,
+        field,
+        origin.as_deref(),
+    );
+    if let Some(target) = target {
+        return Ok(Redirect::to(&target).into_response());
+    }
+    info!("Showing signup outcome, since no redirect is allowed for {origin:?}");
+    let status = match outcome {
+        Outcome::Invalid => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::OK,
+    };
+    let page = page.render(&data.pages, &data.list.name).await;
+    Ok((status, Html(page)).into_response())
 }
 
 #[derive(Deserialize)]
@@ -61,7 +119,7 @@ pub async fn delete(
     query: Query<Delete>,
     TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
 ) -> impl IntoResponse {
-    let mut store = PsqlSubscriberStore::from(data.pool);
+    let mut store = PsqlSubscriberStore::from(data.pool.clone());
 
     let authorized = data.admin.token.eq(authorization.0.token());
 
@@ -69,9 +127,22 @@ pub async fn delete(
         return StatusCode::UNAUTHORIZED;
     }
 
+    let subscriber = match store.find(&query.0.email).await {
+        Ok(subscriber) => subscriber,
+        Err(e) => {
+            error!("Failed to delete subscriber: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
     match store.delete(&query.0.email).await {
         Ok(_) => {
             info!("Deleted subscriber: {:?}", query.0.email);
+            if let Some(subscriber) = subscriber {
+                let event = WebhookEvent::Unsubscribed;
+                webhooks::emit_subscriber(&data, event, &subscriber).await;
+                let kind = ActivityKind::Unsubscribed;
+                activity::publish_subscriber(&data, kind, &subscriber).await;
+            }
             StatusCode::OK
         }
         Err(e) => {
@@ -80,3 +151,116 @@ pub async fn delete(
         }
     }
 }
+
+#[derive(Deserialize)]
+pub struct Tag {
+    email: Email,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+pub struct Attributes {
+    email: Email,
+    attributes: Map<String, Value>,
+}
+
+async fn find_subscriber(
+    store: &PsqlSubscriberStore,
+    email: &Email,
+) -> Result<Subscriber, (StatusCode, String)> {
+    store
+        .find(email)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, "Subscriber not found".to_string()))
+}
+
+/// Tags a subscriber, starting the automations triggered by the tag.
+pub async fn add_tag(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(tag): Json<Tag>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let mut store = PsqlSubscriberStore::from(data.pool.clone());
+    let subscriber = find_subscriber(&store, &tag.email).await?;
+    let added = store
+        .add_tag(subscriber.id, &tag.tag)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if added {
+        automation::enroll(&data, &subscriber, &Trigger::TagAdded { tag: tag.tag })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    Ok(StatusCode::OK)
+}
+
+pub async fn remove_tag(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Query(tag): Query<Tag>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let mut store = PsqlSubscriberStore::from(data.pool);
+    let subscriber = find_subscriber(&store, &tag.email).await?;
+    store
+        .remove_tag(subscriber.id, &tag.tag)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn set_attributes(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(attributes): Json<Attributes>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let mut store = PsqlSubscriberStore::from(data.pool);
+    let subscriber = find_subscriber(&store, &attributes.email).await?;
+    store
+        .set_attributes(subscriber.id, attributes.attributes)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct Timezone {
+    email: Email,
+    timezone: Option<String>,
+}
+
+fn valid_timezone(timezone: &str) -> bool {
+    timezone.parse::<chrono_tz::Tz>().is_ok()
+}
+
+pub async fn set_timezone(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(timezone): Json<Timezone>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    if let Some(name) = timezone
+        .timezone
+        .as_deref()
+        .filter(|name| !valid_timezone(name))
+    {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("{name} is not a known timezone"),
+        ));
+    }
+
+    let mut store = PsqlSubscriberStore::from(data.pool);
+    let subscriber = find_subscriber(&store, &timezone.email).await?;
+    store
+        .set_timezone(&subscriber.email, timezone.timezone.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, State},
+    headers::UserAgent,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    TypedHeader,
+};
+use log::error;
+use serde_json::json;
+
+use crate::{
+    data::ApplicationData,
+    model::{EventKind, NewEvent, WebhookEvent},
+    store::{EventStore, PsqlEventStore},
+    tracking::TrackingToken,
+    webhooks,
+};
+
+// The smallest transparent GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+pub async fn open(
+    State(data): State<ApplicationData>,
+    Path(token): Path<String>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+) -> Response {
+    if let Some(token) = data.tracker.decode(&token) {
+        record(&data, EventKind::Open, token, user_agent).await;
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (header::CACHE_CONTROL, "no-store, max-age=0"),
+        ],
+        PIXEL,
+    )
+        .into_response()
+}
+
+pub async fn click(
+    State(data): State<ApplicationData>,
+    Path(token): Path<String>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+) -> Response {
+    let Some(token) = data.tracker.decode(&token) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(url) = token.url.clone() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    record(&data, EventKind::Click, token, user_agent).await;
+
+    (
+        StatusCode::FOUND,
+        [
+            (header::LOCATION, url.as_str()),
+            (header::CACHE_CONTROL, "no-store, max-age=0"),
+        ],
+    )
+        .into_response()
+}
+
+async fn record(
+    data: &ApplicationData,
+    kind: EventKind,
+    token: TrackingToken,
+    user_agent: Option<TypedHeader<UserAgent>>,
+) {
+    if !data.tracker.enabled() {
+        return;
+    }
+
+    let mut store = PsqlEventStore::from(data.pool.clone());
+    let event = NewEvent {
+        kind,
+        subscriber_id: token.subscriber_id,
+        campaign_id: token.campaign_id,
+        url: token.url,
+        user_agent: user_agent.map(|TypedHeader(agent)| agent.to_string()),
+    };
+    let event = match store.record(event).await {
+        Ok(event) => event,
+        Err(e) => {
+            error!("Failed to record {} event: {}", kind.as_str(), e);
+            return;
+        }
+    };
+
+    let webhook_event = match kind {
+        EventKind::Click => WebhookEvent::Clicked,
+        _ => WebhookEvent::Opened,
+    };
+    let fields = json!({
+        "subscriber_id": event.subscriber_id,
+        "campaign_id": event.campaign_id,
+        "url": event.url,
+    });
+    webhooks::emit(data, webhook_event, fields).await;
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::State,
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+
+use crate::{
+    data::ApplicationData,
+    model::{NewWarmupPlan, WarmupPlan, WarmupReport},
+    routes::auth::authorize,
+    store::{PsqlWarmupStore, WarmupStore},
+    warmup,
+};
+
+/// Caps campaign mail per day according to a new plan, in place of any
+/// earlier one.
+pub async fn save_warmup_plan(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_plan): Json<NewWarmupPlan>,
+) -> Result<Json<WarmupPlan>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    new_plan
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    PsqlWarmupStore::from(data.pool)
+        .replace(new_plan)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Today's cap of the warm-up plan, with how much was sent and how much
+/// is waiting.
+pub async fn warmup_report(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<WarmupReport>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match warmup::report(&data).await {
+        Ok(Some(report)) => Ok(Json(report)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "No warm-up plan".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+/// Lifts the caps of the warm-up plan.
+pub async fn end_warmup_plan(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match PsqlWarmupStore::from(data.pool).remove().await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "No warm-up plan".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
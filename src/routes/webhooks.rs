@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Path, Query, State},
+    headers::{authorization::Bearer, Authorization},
+    http::StatusCode,
+    Json, TypedHeader,
+};
+use serde::Deserialize;
+
+use crate::{
+    data::ApplicationData,
+    model::{NewWebhook, Webhook, WebhookDelivery},
+    routes::{auth::authorize, internal_error},
+    store::{PsqlWebhookStore, WebhookStore},
+};
+
+#[derive(Deserialize)]
+pub struct LogQuery {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+/// Adds an endpoint that is called with the events it asks for, signed
+/// with its secret.
+pub async fn create_webhook(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Json(new_webhook): Json<NewWebhook>,
+) -> Result<Json<Webhook>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+    new_webhook
+        .validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let secret = new_webhook
+        .secret
+        .clone()
+        .unwrap_or_else(Webhook::generate_secret);
+    PsqlWebhookStore::from(data.pool)
+        .create(new_webhook, &secret)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+pub async fn get_webhooks(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<Webhook>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    PsqlWebhookStore::from(data.pool)
+        .all()
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+/// Removes an endpoint along with its log.
+pub async fn delete_webhook(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match PsqlWebhookStore::from(data.pool).delete(id).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Webhook not found".to_string())),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// The latest events sent to an endpoint, with how the calls went.
+pub async fn webhook_deliveries(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path(id): Path<i32>,
+    Query(query): Query<LogQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    let store = PsqlWebhookStore::from(data.pool);
+    if store.get(id).await.map_err(internal_error)?.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Webhook not found".to_string()));
+    }
+    store
+        .deliveries(id, query.limit)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+/// Sends an event to an endpoint again, as a new delivery.
+pub async fn replay_webhook(
+    State(data): State<ApplicationData>,
+    TypedHeader(authorization): TypedHeader<Authorization<Bearer>>,
+    Path((id, delivery_id)): Path<(i32, i64)>,
+) -> Result<Json<WebhookDelivery>, (StatusCode, String)> {
+    authorize(&data, &authorization)?;
+
+    match PsqlWebhookStore::from(data.pool)
+        .replay(id, delivery_id)
+        .await
+    {
+        Ok(Some(delivery)) => Ok(Json(delivery)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Delivery not found".to_string())),
+        Err(e) => Err(internal_error(e)),
+    }
+}
@@ -0,0 +1,128 @@
+mod timing;
+
+pub use timing::{at_local, best_hour, send_time};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+use crate::{
+    archive,
+    data::ApplicationData,
+    model::{Campaign, NewDelivery, SubscriberStatus, Template},
+    outbound::{campaign_delivery, unsuppressed},
+    store::{
+        CampaignStore, EventStore, PsqlCampaignStore, PsqlEventStore, PsqlSubscriberStore,
+        SubscriberStore,
+    },
+    warmup,
+};
+
+// How far back opens count when picking send hours.
+const OPEN_HISTORY_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleRequest {
+    pub template_id: i32,
+    // Wall clock time, e.g. `2023-05-01T09:00:00`.
+    pub send_at: NaiveDateTime,
+    // Sends at `send_at` in the timezone of each subscriber rather than the
+    // timezone of the list.
+    #[serde(default)]
+    pub local: bool,
+    // Sends at the hour each subscriber usually opens mail, within a day of
+    // `send_at`.
+    #[serde(default)]
+    pub optimise: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Schedule {
+    pub campaign_id: i32,
+    pub deliveries: usize,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+
+/// Queues a campaign for every active subscriber at their send time. Returns
+/// `None` when the campaign was already scheduled.
+pub(crate) async fn schedule(
+    data: &ApplicationData,
+    campaign: &Campaign,
+    template: &Template,
+    request: &ScheduleRequest,
+) -> Result<Option<Schedule>> {
+    let list_timezone: Tz = data
+        .list
+        .timezone
+        .parse()
+        .map_err(|e| anyhow!("Bad list timezone: {e}"))?;
+    let subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let opens = if request.optimise {
+        PsqlEventStore::from(data.pool.clone())
+            .open_times(Utc::now() - Duration::days(OPEN_HISTORY_DAYS))
+            .await?
+    } else {
+        Default::default()
+    };
+
+    let now = Utc::now();
+    let everyone = unsuppressed(data, subscribers.all().await?).await?;
+    let engagement = warmup::engagement(data).await?;
+    let mut timed = Vec::new();
+    for subscriber in everyone {
+        if subscriber.status != SubscriberStatus::Active {
+            continue;
+        }
+        let timezone = match &subscriber.timezone {
+            Some(timezone) if request.local => timezone.parse().unwrap_or(list_timezone),
+            _ => list_timezone,
+        };
+        let best_hour = opens
+            .get(&subscriber.id)
+            .and_then(|opens| best_hour(opens, timezone));
+        let mut at = send_time(request.send_at, timezone, best_hour);
+        // Local sends reach whoever's time has passed at the same time the
+        // next day, anybody else gets it straight away.
+        if request.local && at < now {
+            at = send_time(request.send_at + Duration::days(1), timezone, best_hour);
+        }
+        timed.push((at.max(now), subscriber));
+    }
+    timed.sort_by_key(|(at, _)| *at);
+
+    let start = at_local(request.send_at, list_timezone).max(now);
+    let first = timed.first().map_or(start, |(at, _)| *at);
+    let last = timed.last().map_or(start, |(at, _)| *at);
+    let mut deliveries = Vec::with_capacity(timed.len());
+    for (at, subscriber) in &timed {
+        let attributes = subscribers.attributes(subscriber.id).await?;
+        let delivery = NewDelivery {
+            engagement: engagement.of(subscriber),
+            ..campaign_delivery(data, template, subscriber, &attributes, campaign.id)?
+        };
+        deliveries.push((delivery, *at));
+    }
+    if !PsqlCampaignStore::from(data.pool.clone())
+        .schedule(campaign.id, template.id, first, &deliveries)
+        .await?
+    {
+        return Ok(None);
+    }
+    archive::save_issue(data, campaign.id, template, &Map::new()).await?;
+
+    info!(
+        "Scheduled campaign {} for {} subscribers from {first} to {last}",
+        campaign.id,
+        timed.len()
+    );
+    Ok(Some(Schedule {
+        campaign_id: campaign.id,
+        deliveries: timed.len(),
+        first,
+        last,
+    }))
+}
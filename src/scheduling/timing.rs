@@ -0,0 +1,102 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+// Fewer opens than this say little about when someone reads their mail.
+const MIN_OPENS: usize = 3;
+
+/// The moment a wall clock time happens in a timezone. Times skipped by a
+/// daylight saving change are moved past the gap, repeated ones happen the
+/// first time round.
+pub fn at_local(local: NaiveDateTime, timezone: Tz) -> DateTime<Utc> {
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.with_timezone(&Utc),
+        LocalResult::None => at_local(local + Duration::hours(1), timezone),
+    }
+}
+
+/// The local hour at which a subscriber opened most of their mail, or `None`
+/// without enough opens to tell.
+pub fn best_hour(opens: &[DateTime<Utc>], timezone: Tz) -> Option<u32> {
+    if opens.len() < MIN_OPENS {
+        return None;
+    }
+
+    let mut hours = [0; 24];
+    for open in opens {
+        hours[open.with_timezone(&timezone).hour() as usize] += 1;
+    }
+    // The earliest of equally good hours wins.
+    (0..24u32).rev().max_by_key(|hour| hours[*hour as usize])
+}
+
+/// When a subscriber in `timezone` gets mail scheduled for `local`. With a
+/// `best_hour`, it goes out at that hour instead, within a day of `local`.
+pub fn send_time(local: NaiveDateTime, timezone: Tz, best_hour: Option<u32>) -> DateTime<Utc> {
+    let local = match best_hour.and_then(|hour| local.date().and_hms_opt(hour, 0, 0)) {
+        Some(best) if best < local => best + Duration::days(1),
+        Some(best) => best,
+        None => local,
+    };
+    at_local(local, timezone)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn local(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn at_local_converts_wall_clock_time() {
+        let berlin = at_local(local(1, 9, 0), chrono_tz::Europe::Berlin);
+        let new_york = at_local(local(1, 9, 0), chrono_tz::America::New_York);
+
+        assert_eq!(Utc.with_ymd_and_hms(2023, 3, 1, 8, 0, 0).unwrap(), berlin);
+        assert_eq!(
+            Utc.with_ymd_and_hms(2023, 3, 1, 14, 0, 0).unwrap(),
+            new_york
+        );
+    }
+
+    #[test]
+    fn at_local_skips_daylight_saving_gap() {
+        // Clocks in Berlin went from 2:00 straight to 3:00 on 26 March 2023.
+        let time = at_local(local(26, 2, 30), chrono_tz::Europe::Berlin);
+
+        assert_eq!(Utc.with_ymd_and_hms(2023, 3, 26, 1, 30, 0).unwrap(), time);
+    }
+
+    #[test]
+    fn best_hour_needs_enough_opens() {
+        let opens = [
+            Utc.with_ymd_and_hms(2023, 3, 1, 6, 10, 0).unwrap(),
+            Utc.with_ymd_and_hms(2023, 3, 2, 6, 40, 0).unwrap(),
+            Utc.with_ymd_and_hms(2023, 3, 3, 18, 0, 0).unwrap(),
+        ];
+
+        assert_eq!(None, best_hour(&opens[..2], chrono_tz::UTC));
+        assert_eq!(Some(6), best_hour(&opens, chrono_tz::UTC));
+        assert_eq!(Some(7), best_hour(&opens, chrono_tz::Europe::Berlin));
+    }
+
+    #[test]
+    fn send_time_moves_to_best_hour_within_a_day() {
+        let later = send_time(local(1, 9, 0), chrono_tz::UTC, Some(18));
+        let next_day = send_time(local(1, 9, 0), chrono_tz::UTC, Some(7));
+        let unchanged = send_time(local(1, 9, 0), chrono_tz::UTC, None);
+
+        assert_eq!(Utc.with_ymd_and_hms(2023, 3, 1, 18, 0, 0).unwrap(), later);
+        assert_eq!(Utc.with_ymd_and_hms(2023, 3, 2, 7, 0, 0).unwrap(), next_day);
+        assert_eq!(
+            Utc.with_ymd_and_hms(2023, 3, 1, 9, 0, 0).unwrap(),
+            unchanged
+        );
+    }
+}
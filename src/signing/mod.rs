@@ -0,0 +1,3 @@
+mod signer;
+
+pub use signer::Signer;
@@ -1,36 +1,264 @@
 use crate::{
-    config::{AdminSettings, SubscribedSettings},
+    ab_test,
+    activity::{self, ActivityFeed},
+    automation,
+    config::{ListMode, Settings},
     data::ApplicationData,
+    deliverability::DomainCheck,
+    feeds,
+    forms::AddressChecks,
+    inbound::{self, Envelope, SmtpServer},
+    outbound::{self, Governor, Mailer},
+    protection::Protection,
     routes,
+    tracking::Tracker,
+    webhooks,
 };
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use axum::{
-    routing::{delete, get, post},
+    routing::{delete, get, options, post, put},
     Router,
 };
+use log::{error, info};
 use sqlx::{Pool, Postgres};
-use std::net::TcpListener;
+use std::{
+    net::{SocketAddr, TcpListener},
+    time::Duration,
+};
 
 pub async fn run(
     listener: TcpListener,
+    smtp_listener: Option<TcpListener>,
     pool: Pool<Postgres>,
-    admin: AdminSettings,
-    subscribed_settings: SubscribedSettings,
+    settings: Settings,
 ) -> Result<()> {
+    ensure!(
+        !settings.tracking.secret.is_empty(),
+        "Set tracking.secret, for example with TRACKING_SECRET"
+    );
+    ensure!(
+        !settings.list.secret.is_empty(),
+        "Set list.secret, for example with LIST_SECRET"
+    );
+    ensure!(
+        !settings.inbound.token.is_empty(),
+        "Set inbound.token, for example with INBOUND_TOKEN"
+    );
+    let protection = Protection::new(settings.protection, &settings.list.secret, pool.clone())?;
+    let data = ApplicationData {
+        admin: settings.admin,
+        admin_ui: settings.admin_ui,
+        pool,
+        subscribed: settings.application.subscribed,
+        tracker: Tracker::from(settings.tracking),
+        inbound: settings.inbound,
+        bounce: settings.bounce,
+        mailer: Mailer::try_from(settings.mailer)?,
+        governor: Governor::from(settings.governor),
+        list: settings.list,
+        moderation: settings.moderation,
+        automation: settings.automation,
+        ab_test: settings.ab_test,
+        feed: settings.feed,
+        archive: settings.archive,
+        pages: settings.pages,
+        form: settings.form,
+        protection,
+        address_checks: AddressChecks::new(settings.address_checks)?,
+        deliverability: settings.deliverability.map(DomainCheck::new).transpose()?,
+        webhooks: settings.webhooks,
+        activity: ActivityFeed::from(settings.activity),
+    };
+
+    tokio::spawn(outbound::deliver_outbox(data.clone()));
+    tokio::spawn(automation::run_automations(data.clone()));
+    tokio::spawn(ab_test::run_ab_tests(data.clone()));
+    tokio::spawn(feeds::run_feeds(data.clone()));
+    tokio::spawn(webhooks::call_webhooks(data.clone()));
+    tokio::spawn(activity::listen_for_activity(data.clone()));
+    if data.list.mode == ListMode::Discussion {
+        tokio::spawn(outbound::deliver_digests(data.clone()));
+    }
+
+    if let Some(mailbox) = data.inbound.mailbox.clone() {
+        tokio::spawn(poll_mailbox(data.clone(), mailbox));
+    }
+
+    if let (Some(smtp_listener), Some(smtp)) = (smtp_listener, data.inbound.smtp.clone()) {
+        smtp_listener.set_nonblocking(true)?;
+        let smtp_listener = tokio::net::TcpListener::from_std(smtp_listener)?;
+        info!("Receiving mail on {}", smtp_listener.local_addr()?);
+        let data = data.clone();
+        tokio::spawn(async move {
+            let server = SmtpServer::from(smtp);
+            if let Err(e) = server
+                .serve(smtp_listener, move |envelope, raw| {
+                    smtp_message(data.clone(), envelope, raw)
+                })
+                .await
+            {
+                error!("SMTP listener stopped: {e}");
+            }
+        });
+    }
+
     let app = Router::new()
         .route("/", get(|| async { "Minimail v0.1.0" }))
         .route("/api/subscribers", get(routes::get_subscribers))
         .route("/api/subscribers", delete(routes::delete))
+        .route("/api/subscribers/tags", post(routes::add_tag))
+        .route("/api/subscribers/tags", delete(routes::remove_tag))
+        .route("/api/subscribers/attributes", put(routes::set_attributes))
+        .route("/api/subscribers/timezone", put(routes::set_timezone))
         .route("/api/subscribe", post(routes::subscribe))
-        .with_state(ApplicationData {
-            admin,
-            pool,
-            subscribed: subscribed_settings,
-        });
+        .route("/api/subscriptions", post(routes::subscribe_json))
+        .route("/api/subscriptions", options(routes::subscribe_preflight))
+        .route("/api/forms/snippet", get(routes::form_snippet))
+        .route("/api/forms/stamp", get(routes::form_stamp))
+        .route("/api/forms/rejections", get(routes::form_rejections))
+        .route("/api/campaigns", post(routes::create_campaign))
+        .route("/api/campaigns/compare", get(routes::compare_campaigns))
+        .route("/api/campaigns/:id/report", get(routes::campaign_report))
+        .route(
+            "/api/campaigns/:id/schedule",
+            post(routes::schedule_campaign),
+        )
+        .route("/api/campaigns/:id/ab-test", post(routes::test_campaign))
+        .route(
+            "/api/campaigns/:id/archive",
+            put(routes::exclude_from_archive),
+        )
+        .route("/api/keys", get(routes::get_keys))
+        .route("/api/keys", post(routes::create_key))
+        .route("/api/keys/:id", delete(routes::revoke_key))
+        .route("/api/send", post(routes::send))
+        .route("/api/messages/:id", get(routes::message_status))
+        .route("/api/outbound/governor", get(routes::governor_report))
+        .route("/api/warmup", get(routes::warmup_report))
+        .route("/api/warmup", put(routes::save_warmup_plan))
+        .route("/api/warmup", delete(routes::end_warmup_plan))
+        .route("/api/webhooks", get(routes::get_webhooks))
+        .route("/api/webhooks", post(routes::create_webhook))
+        .route("/api/webhooks/:id", delete(routes::delete_webhook))
+        .route(
+            "/api/webhooks/:id/deliveries",
+            get(routes::webhook_deliveries),
+        )
+        .route(
+            "/api/webhooks/:id/deliveries/:delivery_id/replay",
+            post(routes::replay_webhook),
+        )
+        .route("/api/activity/stream", get(routes::activity_stream))
+        .route("/api/activity/tokens", post(routes::issue_stream_token))
+        .route("/api/templates", get(routes::get_templates))
+        .route("/api/templates", post(routes::save_template))
+        .route("/archive", get(routes::archive_index))
+        .route("/archive/:id", get(routes::archive_issue))
+        .route("/archive.atom", get(routes::archive_atom))
+        .route("/archive.json", get(routes::archive_json))
+        .route("/api/feeds", get(routes::get_feeds))
+        .route("/api/feeds", post(routes::create_feed))
+        .route("/api/feeds/:id/run", post(routes::run_feed))
+        .route("/api/automations", get(routes::get_automations))
+        .route("/api/automations", post(routes::create_automation))
+        .route("/api/automations/:id/runs", get(routes::automation_runs))
+        .route(
+            "/api/automations/:id/simulate",
+            post(routes::simulate_automation),
+        )
+        .route("/api/inbound/bounces", post(routes::bounces))
+        .route("/api/inbound/complaints", post(routes::complaints))
+        .route("/api/inbound/requests", post(routes::requests))
+        .route("/api/inbound/posts", post(routes::posts))
+        .route("/api/moderation", get(routes::moderation_queue))
+        .route(
+            "/api/moderation/posts/:id/approve",
+            post(routes::approve_held_post),
+        )
+        .route(
+            "/api/moderation/posts/:id/reject",
+            post(routes::reject_held_post),
+        )
+        .route(
+            "/api/moderation/signups/:id/approve",
+            post(routes::approve_pending_signup),
+        )
+        .route(
+            "/api/moderation/signups/:id/reject",
+            post(routes::reject_pending_signup),
+        )
+        .route("/api/moderation/allowlist", get(routes::get_allowlist))
+        .route("/api/moderation/allowlist", post(routes::allow_sender))
+        .route("/api/moderation/allowlist", delete(routes::disallow_sender))
+        .route("/moderate/:token", get(routes::moderation_link))
+        .route("/moderate/:token", post(routes::moderate_by_link))
+        .route("/subscribe", get(routes::subscribe_page))
+        .route("/subscribe", post(routes::subscribe_by_page))
+        .route("/confirm/:token", get(routes::confirm_page))
+        .route("/confirm/:token", post(routes::confirm_by_page))
+        .route("/unsubscribe/:token", get(routes::unsubscribe_page))
+        .route("/unsubscribe/:token", post(routes::unsubscribe_by_page))
+        .route("/preferences/:token", get(routes::preferences_page))
+        .route("/preferences/:token", post(routes::save_preferences))
+        .route("/admin", get(routes::admin_overview))
+        .route("/admin/assets/admin.css", get(routes::admin_stylesheet))
+        .route("/admin/login", get(routes::admin_login_page))
+        .route("/admin/login", post(routes::admin_log_in))
+        .route("/admin/logout", post(routes::admin_log_out))
+        .route("/admin/subscribers", get(routes::admin_subscribers))
+        .route("/admin/subscribers/:id", get(routes::admin_subscriber))
+        .route(
+            "/admin/subscribers/:id/tags",
+            post(routes::admin_tag_subscriber),
+        )
+        .route(
+            "/admin/subscribers/:id/tags/remove",
+            post(routes::admin_untag_subscriber),
+        )
+        .route(
+            "/admin/subscribers/:id/unsubscribe",
+            post(routes::admin_unsubscribe),
+        )
+        .route("/admin/list", get(routes::admin_list))
+        .route("/admin/list/suppressions", post(routes::admin_suppress))
+        .route(
+            "/admin/list/suppressions/remove",
+            post(routes::admin_unsuppress),
+        )
+        .route("/admin/campaigns", get(routes::admin_campaigns))
+        .route("/admin/campaigns", post(routes::admin_compose_campaign))
+        .route("/admin/campaigns/:id", get(routes::admin_campaign))
+        .route("/admin/keys", get(routes::admin_keys))
+        .route("/admin/keys", post(routes::admin_create_key))
+        .route("/admin/keys/:id/revoke", post(routes::admin_revoke_key))
+        .route("/t/o/:token", get(routes::open))
+        .route("/t/c/:token", get(routes::click))
+        .with_state(data);
 
     axum::Server::from_tcp(listener)?
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
 
     Ok(())
 }
+
+async fn poll_mailbox(data: ApplicationData, mailbox: String) {
+    info!("Polling mailbox {mailbox}");
+    let maildir = inbound::Maildir::from(mailbox.as_str());
+    let mut interval = tokio::time::interval(Duration::from_secs(data.inbound.interval));
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = maildir.deliver(|raw| inbound_message(&data, raw)).await {
+            error!("Failed to poll mailbox {mailbox}: {e}");
+        }
+    }
+}
+
+async fn inbound_message(data: &ApplicationData, raw: Vec<u8>) -> Result<()> {
+    inbound::handle(data, &raw).await
+}
+
+async fn smtp_message(data: ApplicationData, envelope: Envelope, raw: Vec<u8>) -> Result<()> {
+    inbound::deliver(&data, envelope, raw).await
+}
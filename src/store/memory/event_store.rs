@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    model::{Event, EventKind, NewEvent},
+    store::EventStore,
+};
+
+#[derive(Debug, Default)]
+pub struct InMemoryEventStore {
+    events: Vec<Event>,
+}
+
+impl EventStore for InMemoryEventStore {
+    async fn record(&mut self, new_event: NewEvent) -> Result<Event> {
+        let event = Event {
+            id: self.events.len() as i64 + 1,
+            kind: new_event.kind,
+            subscriber_id: new_event.subscriber_id,
+            campaign_id: new_event.campaign_id,
+            url: new_event.url,
+            user_agent: new_event.user_agent,
+            created_at: Utc::now(),
+        };
+        self.events.push(event.clone());
+        Ok(event)
+    }
+
+    async fn occurred(
+        &self,
+        subscriber_id: i32,
+        campaign_id: i32,
+        kind: EventKind,
+    ) -> Result<bool> {
+        Ok(self.events.iter().any(|event| {
+            event.subscriber_id == subscriber_id
+                && event.campaign_id == Some(campaign_id)
+                && event.kind == kind
+        }))
+    }
+
+    async fn open_times(&self, since: DateTime<Utc>) -> Result<HashMap<i32, Vec<DateTime<Utc>>>> {
+        let mut opens: HashMap<i32, Vec<DateTime<Utc>>> = HashMap::new();
+        self.events
+            .iter()
+            .filter(|event| event.kind == EventKind::Open && event.created_at >= since)
+            .for_each(|event| {
+                opens
+                    .entry(event.subscriber_id)
+                    .or_default()
+                    .push(event.created_at)
+            });
+        Ok(opens)
+    }
+
+    async fn engagement(&self, since: DateTime<Utc>) -> Result<HashMap<i32, i64>> {
+        let mut engagement = HashMap::new();
+        self.events
+            .iter()
+            .filter(|event| {
+                matches!(event.kind, EventKind::Open | EventKind::Click)
+                    && event.created_at >= since
+            })
+            .for_each(|event| *engagement.entry(event.subscriber_id).or_default() += 1);
+        Ok(engagement)
+    }
+
+    async fn last_delivered(&self, subscriber_id: i32) -> Result<Option<i32>> {
+        Ok(self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| {
+                event.subscriber_id == subscriber_id && event.kind == EventKind::Delivered
+            })
+            .find_map(|event| event.campaign_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_returns_event() -> Result<()> {
+        let mut store = InMemoryEventStore::default();
+        let open = NewEvent {
+            kind: EventKind::Open,
+            subscriber_id: 1,
+            campaign_id: None,
+            url: None,
+            user_agent: None,
+        };
+
+        store.record(open.clone()).await?;
+        let event = store.record(open).await?;
+
+        assert_eq!(2, event.id);
+        assert_eq!(EventKind::Open, event.kind);
+        assert_eq!(2, store.events.len());
+
+        Ok(())
+    }
+}
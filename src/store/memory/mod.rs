@@ -1,3 +1,7 @@
+mod event_store;
+mod rate_limit_store;
 mod subscriber_store;
 
+pub use event_store::InMemoryEventStore;
+pub use rate_limit_store::InMemoryRateLimitStore;
 pub use subscriber_store::InMemorySubscriberStore;
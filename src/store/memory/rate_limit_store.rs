@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::store::RateLimitStore;
+
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitStore {
+    hits: HashMap<(String, DateTime<Utc>), i64>,
+}
+
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn hit(&mut self, key: &str, window_start: DateTime<Utc>) -> Result<i64> {
+        let hits = self
+            .hits
+            .entry((key.to_string(), window_start))
+            .or_default();
+        *hits += 1;
+        Ok(*hits)
+    }
+
+    async fn prune(&mut self, before: DateTime<Utc>) -> Result<()> {
+        self.hits
+            .retain(|(_, window_start), _| *window_start >= before);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn hit_counts_per_key_and_window() -> Result<()> {
+        let mut store = InMemoryRateLimitStore::default();
+        let window = Utc::now();
+
+        assert_eq!(1, store.hit("ip:127.0.0.1", window).await?);
+        assert_eq!(2, store.hit("ip:127.0.0.1", window).await?);
+        assert_eq!(1, store.hit("ip:10.0.0.1", window).await?);
+
+        store.prune(window + Duration::seconds(1)).await?;
+
+        assert_eq!(1, store.hit("ip:127.0.0.1", window).await?);
+        Ok(())
+    }
+}
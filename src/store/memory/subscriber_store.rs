@@ -1,21 +1,34 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::debug;
+use serde_json::{Map, Value};
 
 use crate::{
-    model::{Email, NewSubscriber, Subscriber},
+    model::{Email, NewSubscriber, Subscriber, SubscriberStatus},
     store::SubscriberStore,
 };
 
 #[derive(Debug, Default)]
 pub struct InMemorySubscriberStore {
     subscribers: HashMap<i32, Subscriber>,
+    soft_bounces: HashMap<i32, i32>,
+    tags: HashMap<i32, BTreeSet<String>>,
+    attributes: HashMap<i32, Map<String, Value>>,
     next_id: i32,
 }
 
 impl SubscriberStore for InMemorySubscriberStore {
     async fn create(&mut self, new_subscriber: NewSubscriber) -> Result<Subscriber> {
+        self.create_with_status(new_subscriber, SubscriberStatus::Active)
+            .await
+    }
+
+    async fn create_with_status(
+        &mut self,
+        new_subscriber: NewSubscriber,
+        status: SubscriberStatus,
+    ) -> Result<Subscriber> {
         let existing_subscriber = self
             .subscribers
             .values()
@@ -24,7 +37,7 @@ impl SubscriberStore for InMemorySubscriberStore {
         if let Some(subscriber) = existing_subscriber {
             Ok(subscriber.to_owned())
         } else {
-            Ok(self.insert_subscriber(new_subscriber.email))
+            Ok(self.insert_subscriber(new_subscriber.email, status))
         }
     }
 
@@ -37,6 +50,146 @@ impl SubscriberStore for InMemorySubscriberStore {
             .retain(|_key, subscriber| subscriber.email.ne(email));
         Ok(())
     }
+
+    async fn find(&self, email: &Email) -> Result<Option<Subscriber>> {
+        Ok(self
+            .subscribers
+            .values()
+            .find(|subscriber| subscriber.email.eq(email))
+            .cloned())
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Subscriber>> {
+        Ok(self.subscribers.get(&id).cloned())
+    }
+
+    async fn set_status(&mut self, email: &Email, status: SubscriberStatus) -> Result<()> {
+        self.subscribers
+            .values_mut()
+            .filter(|subscriber| subscriber.email.eq(email))
+            .for_each(|subscriber| subscriber.status = status);
+        Ok(())
+    }
+
+    async fn change_status(
+        &mut self,
+        email: &Email,
+        from: SubscriberStatus,
+        to: SubscriberStatus,
+    ) -> Result<bool> {
+        let mut changed = false;
+        self.subscribers
+            .values_mut()
+            .filter(|subscriber| subscriber.email.eq(email) && subscriber.status == from)
+            .for_each(|subscriber| {
+                subscriber.status = to;
+                changed = true;
+            });
+        Ok(changed)
+    }
+
+    async fn set_digest(&mut self, email: &Email, digest: bool) -> Result<()> {
+        self.subscribers
+            .values_mut()
+            .filter(|subscriber| subscriber.email.eq(email))
+            .for_each(|subscriber| subscriber.digest = digest);
+        Ok(())
+    }
+
+    async fn add_soft_bounce(&mut self, email: &Email) -> Result<i32> {
+        let subscriber = self
+            .find(email)
+            .await?
+            .ok_or_else(|| anyhow!("No subscriber with that email"))?;
+        let soft_bounces = self.soft_bounces.entry(subscriber.id).or_default();
+        *soft_bounces += 1;
+        Ok(*soft_bounces)
+    }
+
+    async fn set_timezone(&mut self, email: &Email, timezone: Option<&str>) -> Result<()> {
+        self.subscribers
+            .values_mut()
+            .filter(|subscriber| subscriber.email.eq(email))
+            .for_each(|subscriber| subscriber.timezone = timezone.map(str::to_string));
+        Ok(())
+    }
+
+    async fn tags(&self, id: i32) -> Result<Vec<String>> {
+        Ok(self
+            .tags
+            .get(&id)
+            .map(|tags| tags.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn add_tag(&mut self, id: i32, tag: &str) -> Result<bool> {
+        Ok(self.tags.entry(id).or_default().insert(tag.to_string()))
+    }
+
+    async fn remove_tag(&mut self, id: i32, tag: &str) -> Result<()> {
+        if let Some(tags) = self.tags.get_mut(&id) {
+            tags.remove(tag);
+        }
+        Ok(())
+    }
+
+    async fn attributes(&self, id: i32) -> Result<Map<String, Value>> {
+        Ok(self.attributes.get(&id).cloned().unwrap_or_default())
+    }
+
+    async fn set_attributes(&mut self, id: i32, attributes: Map<String, Value>) -> Result<()> {
+        let existing = self.attributes.entry(id).or_default();
+        for (name, value) in attributes {
+            if value.is_null() {
+                existing.remove(&name);
+            } else {
+                existing.insert(name, value);
+            }
+        }
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        status: Option<SubscriberStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Subscriber>> {
+        let query = query.to_lowercase();
+        let mut found: Vec<Subscriber> = self
+            .subscribers
+            .values()
+            .filter(|s| s.email.0.to_lowercase().contains(&query))
+            .filter(|s| status.is_none_or(|status| s.status == status))
+            .cloned()
+            .collect();
+        found.sort_by_key(|s| s.id);
+        Ok(found
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn count_by_status(&self) -> Result<Vec<(SubscriberStatus, i64)>> {
+        let mut counts: BTreeMap<&str, (SubscriberStatus, i64)> = BTreeMap::new();
+        for subscriber in self.subscribers.values() {
+            counts
+                .entry(subscriber.status.as_str())
+                .or_insert((subscriber.status, 0))
+                .1 += 1;
+        }
+        Ok(counts.into_values().collect())
+    }
+
+    async fn count_by_tag(&self) -> Result<Vec<(String, i64)>> {
+        let mut counts: BTreeMap<String, i64> = BTreeMap::new();
+        for tag in self.tags.values().flatten() {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+        Ok(counts.into_iter().collect())
+    }
 }
 
 impl InMemorySubscriberStore {
@@ -45,9 +198,15 @@ impl InMemorySubscriberStore {
         self.next_id
     }
 
-    fn insert_subscriber(&mut self, email: Email) -> Subscriber {
+    fn insert_subscriber(&mut self, email: Email, status: SubscriberStatus) -> Subscriber {
         let id = self.get_next_id();
-        let subscriber = Subscriber { id, email };
+        let subscriber = Subscriber {
+            id,
+            email,
+            status,
+            digest: false,
+            timezone: None,
+        };
         self.subscribers.insert(id, subscriber.clone());
         debug!("subscriber created");
         subscriber
@@ -125,4 +284,55 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn set_status_updates_subscriber() -> Result<()> {
+        let mut store = InMemorySubscriberStore::default();
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.set_status(&email, SubscriberStatus::Bounced).await?;
+        let subscriber = store.find(&email).await?.unwrap();
+
+        assert_eq!(SubscriberStatus::Bounced, subscriber.status);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_soft_bounce_counts_bounces() -> Result<()> {
+        let mut store = InMemorySubscriberStore::default();
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.add_soft_bounce(&email).await?;
+        let soft_bounces = store.add_soft_bounce(&email).await?;
+
+        assert_eq!(2, soft_bounces);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_tag_reports_new_tags() -> Result<()> {
+        let mut store = InMemorySubscriberStore::default();
+
+        let added = store.add_tag(1, "trial").await?;
+        let again = store.add_tag(1, "trial").await?;
+        store.remove_tag(1, "trial").await?;
+
+        assert!(added);
+        assert!(!again);
+        assert!(store.tags(1).await?.is_empty());
+
+        Ok(())
+    }
 }
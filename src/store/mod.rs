@@ -1,17 +1,367 @@
+// Most in-memory stores are only exercised by tests for now.
+#[allow(dead_code)]
 mod memory;
 mod postgres;
 
-pub use memory::InMemorySubscriberStore;
-pub use postgres::PsqlSubscriberStore;
+pub use memory::InMemoryRateLimitStore;
+#[allow(unused_imports)]
+pub use memory::{InMemoryEventStore, InMemorySubscriberStore};
+pub use postgres::{
+    PsqlAbTestStore, PsqlActivityStore, PsqlAdminSessionStore, PsqlAllowlistStore, PsqlApiKeyStore,
+    PsqlAutomationStore, PsqlCampaignStore, PsqlEventStore, PsqlFeedStore, PsqlOutboxStore,
+    PsqlPostStore, PsqlRateLimitStore, PsqlSubscriberStore, PsqlSuppressionStore,
+    PsqlTemplateStore, PsqlWarmupStore, PsqlWebhookStore,
+};
+
+use std::collections::HashMap;
 
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{Map, Value};
+
+use crate::model::AbTest;
+use crate::model::Activity;
+use crate::model::ActivityKind;
+use crate::model::AdminSession;
+use crate::model::ApiKey;
+use crate::model::Automation;
+use crate::model::AutomationRun;
 
+use crate::model::Campaign;
+use crate::model::CampaignStat;
+use crate::model::Delivery;
+use crate::model::DeliveryReport;
 use crate::model::Email;
+use crate::model::Event;
+use crate::model::EventKind;
+use crate::model::Feed;
+use crate::model::Issue;
+use crate::model::NewAbTest;
+use crate::model::NewApiKey;
+use crate::model::NewAutomation;
+use crate::model::NewCampaign;
+use crate::model::NewDelivery;
+use crate::model::NewEvent;
+use crate::model::NewFeed;
+use crate::model::NewPost;
 use crate::model::NewSubscriber;
+use crate::model::NewTemplate;
+use crate::model::NewWarmupPlan;
+use crate::model::NewWebhook;
+use crate::model::Post;
+use crate::model::PostStatus;
+use crate::model::Rendered;
+use crate::model::RunStatus;
 use crate::model::Subscriber;
+use crate::model::SubscriberStatus;
+use crate::model::Suppression;
+use crate::model::Template;
+use crate::model::Trigger;
+use crate::model::Variant;
+use crate::model::VariantTally;
+use crate::model::WarmupPlan;
+use crate::model::Webhook;
+use crate::model::WebhookCall;
+use crate::model::WebhookDelivery;
+use crate::model::WebhookEvent;
 
 pub trait SubscriberStore {
     async fn create(&mut self, new_subscriber: NewSubscriber) -> Result<Subscriber>;
+    // Like create, but a new subscriber starts out with the given status.
+    async fn create_with_status(
+        &mut self,
+        new_subscriber: NewSubscriber,
+        status: SubscriberStatus,
+    ) -> Result<Subscriber>;
     async fn all(&self) -> Result<Vec<Subscriber>>;
     async fn delete(&mut self, email: &Email) -> Result<()>;
+    async fn find(&self, email: &Email) -> Result<Option<Subscriber>>;
+    async fn get(&self, id: i32) -> Result<Option<Subscriber>>;
+    async fn set_status(&mut self, email: &Email, status: SubscriberStatus) -> Result<()>;
+    // Like set_status, but only while the status is still `from`. Returns
+    // false when it was not.
+    async fn change_status(
+        &mut self,
+        email: &Email,
+        from: SubscriberStatus,
+        to: SubscriberStatus,
+    ) -> Result<bool>;
+    async fn set_digest(&mut self, email: &Email, digest: bool) -> Result<()>;
+    async fn add_soft_bounce(&mut self, email: &Email) -> Result<i32>;
+    async fn set_timezone(&mut self, email: &Email, timezone: Option<&str>) -> Result<()>;
+    async fn tags(&self, id: i32) -> Result<Vec<String>>;
+    // Returns false when the subscriber already had the tag.
+    async fn add_tag(&mut self, id: i32, tag: &str) -> Result<bool>;
+    async fn remove_tag(&mut self, id: i32, tag: &str) -> Result<()>;
+    async fn attributes(&self, id: i32) -> Result<Map<String, Value>>;
+    // Merges into the existing attributes, removing those set to null.
+    async fn set_attributes(&mut self, id: i32, attributes: Map<String, Value>) -> Result<()>;
+    // Subscribers whose address contains `query`, by id.
+    async fn search(
+        &self,
+        query: &str,
+        status: Option<SubscriberStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Subscriber>>;
+    async fn count_by_status(&self) -> Result<Vec<(SubscriberStatus, i64)>>;
+    // Every tag in use, with how many subscribers have it.
+    async fn count_by_tag(&self) -> Result<Vec<(String, i64)>>;
+}
+
+pub trait EventStore {
+    async fn record(&mut self, new_event: NewEvent) -> Result<Event>;
+    async fn occurred(&self, subscriber_id: i32, campaign_id: i32, kind: EventKind)
+        -> Result<bool>;
+    // When each subscriber opened mail since `since`.
+    async fn open_times(&self, since: DateTime<Utc>) -> Result<HashMap<i32, Vec<DateTime<Utc>>>>;
+    // How many opens and clicks each subscriber had since `since`.
+    async fn engagement(&self, since: DateTime<Utc>) -> Result<HashMap<i32, i64>>;
+    // The campaign that was delivered to the subscriber last.
+    async fn last_delivered(&self, subscriber_id: i32) -> Result<Option<i32>>;
+}
+
+pub trait CampaignStore {
+    async fn create(&mut self, new_campaign: NewCampaign) -> Result<Campaign>;
+    async fn get(&self, id: i32) -> Result<Option<Campaign>>;
+    async fn recent(&self, limit: i64) -> Result<Vec<Campaign>>;
+    async fn stats(&self, id: i32) -> Result<Vec<CampaignStat>>;
+    // The counts of each kind of event of several campaigns, summed over
+    // every hour into one stat, which starts at the first hour.
+    async fn totals(&self, ids: &[i32]) -> Result<HashMap<i32, Vec<CampaignStat>>>;
+    // Records what a campaign sends and when it starts, and queues its
+    // deliveries for their times along with it. Returns false, queueing
+    // nothing, when it was already scheduled.
+    async fn schedule(
+        &mut self,
+        id: i32,
+        template_id: i32,
+        sent_at: DateTime<Utc>,
+        deliveries: &[(NewDelivery, DateTime<Utc>)],
+    ) -> Result<bool>;
+    // Returns false when there is no such campaign.
+    async fn exclude_from_archive(&mut self, id: i32, exclude: bool) -> Result<bool>;
+    async fn save_issue(&mut self, id: i32, issue: &Rendered) -> Result<()>;
+    // Issues of the public archive, newest first.
+    async fn issues(&self) -> Result<Vec<Issue>>;
+    async fn issue(&self, id: i32) -> Result<Option<Issue>>;
+}
+
+pub trait AllowlistStore {
+    async fn add(&mut self, email: &Email) -> Result<()>;
+    async fn remove(&mut self, email: &Email) -> Result<()>;
+    async fn contains(&self, email: &Email) -> Result<bool>;
+    async fn all(&self) -> Result<Vec<Email>>;
+}
+
+pub trait SuppressionStore {
+    async fn add(&mut self, email: &Email, reason: &str) -> Result<()>;
+    async fn contains(&self, email: &Email) -> Result<bool>;
+    // Newest first.
+    async fn all(&self) -> Result<Vec<Suppression>>;
+    // Returns false when the address wasn't suppressed.
+    async fn remove(&mut self, email: &Email) -> Result<bool>;
+}
+
+pub trait PostStore {
+    async fn create(&mut self, new_post: NewPost) -> Result<Post>;
+    async fn get(&self, id: i32) -> Result<Option<Post>>;
+    async fn held(&self) -> Result<Vec<Post>>;
+    // Sets the outcome of moderation, returning false unless the post was held.
+    async fn moderate(&mut self, id: i32, status: PostStatus, reason: Option<&str>)
+        -> Result<bool>;
+    // Accepted posts that have not gone out in a digest yet.
+    async fn undigested(&self) -> Result<Vec<Post>>;
+    async fn mark_digested(&mut self, ids: &[i32]) -> Result<()>;
+}
+
+pub trait OutboxStore {
+    async fn enqueue(&mut self, new_delivery: NewDelivery) -> Result<i64>;
+    // Enqueues a message sent with an API key, unless the key already sent
+    // one with the same idempotency key. Returns the id and whether it is new.
+    async fn submit(
+        &mut self,
+        new_delivery: NewDelivery,
+        api_key_id: i32,
+        idempotency_key: Option<&str>,
+    ) -> Result<(i64, bool)>;
+    // Reports on a message sent with the API key.
+    async fn report(&self, id: i64, api_key_id: i32) -> Result<Option<DeliveryReport>>;
+    // Takes due deliveries, keeping them from being claimed again for a while.
+    async fn claim(&mut self, limit: i64) -> Result<Vec<Delivery>>;
+    async fn sent(&mut self, id: i64) -> Result<()>;
+    // Campaign messages sent since `since`.
+    async fn campaign_sent_since(&self, since: DateTime<Utc>) -> Result<i64>;
+    // Campaign messages still to be sent.
+    async fn campaign_waiting(&self) -> Result<i64>;
+    // Puts a claimed delivery off until `at`, without counting an attempt.
+    async fn defer(&mut self, id: i64, at: DateTime<Utc>) -> Result<()>;
+    // Retries at `retry_at`, or gives up on the delivery when there is none.
+    async fn failed(&mut self, id: i64, error: &str, retry_at: Option<DateTime<Utc>>)
+        -> Result<()>;
+}
+
+pub trait TemplateStore {
+    // Replaces any template with the same name.
+    async fn save(&mut self, new_template: NewTemplate) -> Result<Template>;
+    async fn get(&self, id: i32) -> Result<Option<Template>>;
+    async fn find(&self, name: &str) -> Result<Option<Template>>;
+    async fn all(&self) -> Result<Vec<Template>>;
+}
+
+pub trait AutomationStore {
+    async fn create(
+        &mut self,
+        new_automation: NewAutomation,
+        campaign_id: i32,
+    ) -> Result<Automation>;
+    async fn get(&self, id: i32) -> Result<Option<Automation>>;
+    async fn all(&self) -> Result<Vec<Automation>>;
+    async fn triggered_by(&self, trigger: &Trigger) -> Result<Vec<Automation>>;
+    // Starts a run, returning false when the subscriber already had one.
+    async fn enroll(&mut self, automation_id: i32, subscriber_id: i32) -> Result<bool>;
+    async fn runs(&self, automation_id: i32) -> Result<Vec<AutomationRun>>;
+    // Takes due runs, keeping them from being claimed again for a while.
+    async fn claim(&mut self, limit: i64) -> Result<Vec<AutomationRun>>;
+    // Records the steps before `step` as taken, keeping the claim.
+    async fn progress(&mut self, id: i32, step: i32) -> Result<()>;
+    // Queues mail of a step and records the steps before `step` as taken,
+    // together, so that a retried run never sends it twice.
+    async fn send(&mut self, id: i32, step: i32, new_delivery: NewDelivery) -> Result<i64>;
+    async fn advance(&mut self, id: i32, step: i32, wake_at: DateTime<Utc>) -> Result<()>;
+    async fn finish(&mut self, id: i32, status: RunStatus) -> Result<()>;
+}
+
+pub trait ApiKeyStore {
+    async fn create(&mut self, new_api_key: NewApiKey, key_hash: &str) -> Result<ApiKey>;
+    async fn find(&self, key_hash: &str) -> Result<Option<ApiKey>>;
+    async fn all(&self) -> Result<Vec<ApiKey>>;
+    // Returns false unless there was such a key that was not revoked yet.
+    async fn revoke(&mut self, id: i32) -> Result<bool>;
+}
+
+pub trait AbTestStore {
+    async fn create(
+        &mut self,
+        campaign_id: i32,
+        new_test: &NewAbTest,
+        decide_at: DateTime<Utc>,
+    ) -> Result<(AbTest, Vec<Variant>)>;
+    async fn get(&self, campaign_id: i32) -> Result<Option<AbTest>>;
+    async fn variants(&self, campaign_id: i32) -> Result<Vec<Variant>>;
+    // Records which variant each subscriber gets and queues it, all at once,
+    // from (subscriber id, variant id, delivery). Skips subscribers who
+    // already got a variant, and returns how many were queued.
+    async fn send(
+        &mut self,
+        campaign_id: i32,
+        test: bool,
+        deliveries: &[(i32, i32, NewDelivery)],
+    ) -> Result<usize>;
+    // Takes back a test that could not be started, and with it the campaign
+    // being scheduled, so that it can be sent again.
+    async fn abandon(&mut self, campaign_id: i32) -> Result<()>;
+    async fn tallies(&self, campaign_id: i32) -> Result<Vec<VariantTally>>;
+    async fn remainder(&self, campaign_id: i32) -> Result<i64>;
+    // Tests whose window is over but whose winner has not gone out yet.
+    async fn due(&self) -> Result<Vec<AbTest>>;
+    // Sets the winner unless there is one already, and returns the winner.
+    async fn pick(&mut self, campaign_id: i32, winner_id: i32) -> Result<i32>;
+    async fn finish(&mut self, campaign_id: i32) -> Result<()>;
+}
+
+pub trait FeedStore {
+    async fn create(&mut self, new_feed: NewFeed, next_run_at: DateTime<Utc>) -> Result<Feed>;
+    async fn get(&self, id: i32) -> Result<Option<Feed>>;
+    async fn all(&self) -> Result<Vec<Feed>>;
+    async fn due(&self) -> Result<Vec<Feed>>;
+    async fn reschedule(&mut self, id: i32, next_run_at: DateTime<Utc>) -> Result<()>;
+    // The entries out of `entry_ids` that were not sent yet.
+    async fn unsent(&self, id: i32, entry_ids: &[String]) -> Result<Vec<String>>;
+    // Creates a campaign for whichever of `entry_ids` were not sent yet and
+    // records them as sent in it, together, so that they never go out twice.
+    // Returns `None`, creating nothing, when they were all sent already.
+    async fn claim(
+        &mut self,
+        id: i32,
+        entry_ids: &[String],
+        new_campaign: NewCampaign,
+    ) -> Result<Option<(Campaign, Vec<String>)>>;
+    // Records entries as sent in a campaign, or as seen without one.
+    async fn mark_sent(
+        &mut self,
+        id: i32,
+        entry_ids: &[String],
+        campaign_id: Option<i32>,
+    ) -> Result<()>;
+}
+
+pub trait RateLimitStore {
+    // Counts a hit on `key` in the window starting at `window_start`, and
+    // returns the hits in that window so far.
+    async fn hit(&mut self, key: &str, window_start: DateTime<Utc>) -> Result<i64>;
+    // Forgets the windows that started before `before`.
+    async fn prune(&mut self, before: DateTime<Utc>) -> Result<()>;
+}
+
+pub trait WarmupStore {
+    // Puts a plan in place of any other.
+    async fn replace(&mut self, new_plan: NewWarmupPlan) -> Result<WarmupPlan>;
+    async fn current(&self) -> Result<Option<WarmupPlan>>;
+    // Returns false when there was no plan.
+    async fn remove(&mut self) -> Result<bool>;
+    // Reserves up to `wanted` of the campaign mail `cap` allows on `day`,
+    // which starts at `start`, returning how many were granted.
+    async fn reserve(
+        &mut self,
+        day: NaiveDate,
+        start: DateTime<Utc>,
+        cap: i64,
+        wanted: i64,
+    ) -> Result<i64>;
+    // Returns reserved mail that was not sent.
+    async fn release(&mut self, day: NaiveDate, count: i64) -> Result<()>;
+}
+
+pub trait WebhookStore {
+    async fn create(&mut self, new_webhook: NewWebhook, secret: &str) -> Result<Webhook>;
+    async fn all(&self) -> Result<Vec<Webhook>>;
+    async fn get(&self, id: i32) -> Result<Option<Webhook>>;
+    // Returns false when there is no such webhook.
+    async fn delete(&mut self, id: i32) -> Result<bool>;
+    // Queues an event for every endpoint that wants it, returning how many.
+    async fn enqueue(&mut self, event: WebhookEvent, payload: &Value) -> Result<u64>;
+    // Takes due deliveries, keeping them from being claimed again for a while.
+    async fn claim(&mut self, limit: i64) -> Result<Vec<WebhookCall>>;
+    async fn delivered(&mut self, id: i64, response_status: i32) -> Result<()>;
+    // Retries at `retry_at`, or gives up on the delivery when there is none.
+    async fn failed(
+        &mut self,
+        id: i64,
+        response_status: Option<i32>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+    // The endpoint's deliveries, newest first.
+    async fn deliveries(&self, webhook_id: i32, limit: i64) -> Result<Vec<WebhookDelivery>>;
+    // Queues a delivery of the endpoint again. Returns `None` when the
+    // endpoint has no such delivery.
+    async fn replay(&mut self, webhook_id: i32, id: i64) -> Result<Option<WebhookDelivery>>;
+}
+
+pub trait ActivityStore {
+    // Logs activity, dropping all but the newest `keep` rows.
+    async fn record(&mut self, kind: ActivityKind, data: &Value, keep: i64) -> Result<Activity>;
+    // Logged activity after `id`, oldest first.
+    async fn since(&self, id: i64, limit: i64) -> Result<Vec<Activity>>;
+    // Tells every instance listening on `channel`.
+    async fn notify(&self, channel: &str, payload: &str) -> Result<()>;
+}
+
+pub trait AdminSessionStore {
+    // Also drops sessions that ran out.
+    async fn create(&mut self, id: &str, session: &AdminSession) -> Result<()>;
+    // Returns `None` once the session ran out.
+    async fn find(&self, id: &str) -> Result<Option<AdminSession>>;
+    async fn delete(&mut self, id: &str) -> Result<()>;
 }
@@ -0,0 +1,450 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{AbTest, NewAbTest, NewDelivery, Variant, VariantTally},
+    store::AbTestStore,
+};
+
+pub struct PsqlAbTestStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlAbTestStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct AbTestRow {
+    campaign_id: i32,
+    test_percent: i32,
+    metric: String,
+    decide_at: DateTime<Utc>,
+    winner_id: Option<i32>,
+    decided_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<AbTestRow> for AbTest {
+    type Error = anyhow::Error;
+
+    fn try_from(row: AbTestRow) -> Result<Self> {
+        Ok(AbTest {
+            campaign_id: row.campaign_id,
+            test_percent: row.test_percent,
+            metric: row.metric.try_into().map_err(|e: String| anyhow!(e))?,
+            decide_at: row.decide_at,
+            winner_id: row.winner_id,
+            decided_at: row.decided_at,
+        })
+    }
+}
+
+impl AbTestStore for PsqlAbTestStore {
+    async fn create(
+        &mut self,
+        campaign_id: i32,
+        new_test: &NewAbTest,
+        decide_at: DateTime<Utc>,
+    ) -> Result<(AbTest, Vec<Variant>)> {
+        let mut tx = self.pool.begin().await?;
+        let test = sqlx::query_as!(
+            AbTestRow,
+            r#"
+            INSERT INTO ab_tests(campaign_id, test_percent, metric, decide_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            campaign_id,
+            new_test.test_percent,
+            new_test.metric.as_str(),
+            decide_at,
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .try_into()?;
+
+        let mut variants = Vec::with_capacity(new_test.variants.len());
+        for (index, new_variant) in new_test.variants.iter().enumerate() {
+            variants.push(
+                sqlx::query_as!(
+                    Variant,
+                    r#"
+                    INSERT INTO campaign_variants(campaign_id, name, subject, from_name, template_id)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING *
+                    "#,
+                    campaign_id,
+                    NewAbTest::variant_name(index),
+                    new_variant.subject,
+                    new_variant.from_name,
+                    new_variant.template_id,
+                )
+                .fetch_one(&mut tx)
+                .await?,
+            );
+        }
+        tx.commit().await?;
+
+        Ok((test, variants))
+    }
+
+    async fn get(&self, campaign_id: i32) -> Result<Option<AbTest>> {
+        sqlx::query_as!(
+            AbTestRow,
+            "SELECT * FROM ab_tests WHERE campaign_id = $1",
+            campaign_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(TryInto::try_into)
+        .transpose()
+    }
+
+    async fn variants(&self, campaign_id: i32) -> Result<Vec<Variant>> {
+        Ok(sqlx::query_as!(
+            Variant,
+            "SELECT * FROM campaign_variants WHERE campaign_id = $1 ORDER BY name",
+            campaign_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn send(
+        &mut self,
+        campaign_id: i32,
+        test: bool,
+        deliveries: &[(i32, i32, NewDelivery)],
+    ) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut queued = 0;
+        for (subscriber_id, variant_id, new_delivery) in deliveries {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO campaign_recipients(campaign_id, subscriber_id, variant_id, test)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT DO NOTHING
+                "#,
+                campaign_id,
+                subscriber_id,
+                variant_id,
+                test,
+            )
+            .execute(&mut tx)
+            .await?;
+            if result.rows_affected() != 1 {
+                continue;
+            }
+            sqlx::query!(
+                r#"
+                INSERT INTO outbox(sender, recipient, raw, campaign_id, engagement)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                new_delivery.sender,
+                new_delivery.recipient,
+                new_delivery.raw,
+                new_delivery.campaign_id,
+                new_delivery.engagement,
+            )
+            .execute(&mut tx)
+            .await?;
+            queued += 1;
+        }
+        tx.commit().await?;
+        Ok(queued)
+    }
+
+    async fn abandon(&mut self, campaign_id: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM ab_tests WHERE campaign_id = $1", campaign_id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM campaign_variants WHERE campaign_id = $1",
+            campaign_id
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE campaigns SET template_id = NULL, sent_at = NULL WHERE id = $1",
+            campaign_id
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn tallies(&self, campaign_id: i32) -> Result<Vec<VariantTally>> {
+        // Only looks at the events of this campaign's test recipients.
+        Ok(sqlx::query_as!(
+            VariantTally,
+            r#"
+            SELECT
+                r.variant_id,
+                COUNT(*) AS "recipients!",
+                COUNT(*) FILTER (WHERE EXISTS (
+                    SELECT 1 FROM events e
+                    WHERE e.campaign_id = r.campaign_id AND e.subscriber_id = r.subscriber_id
+                    AND e.kind = 'open'
+                )) AS "opened!",
+                COUNT(*) FILTER (WHERE EXISTS (
+                    SELECT 1 FROM events e
+                    WHERE e.campaign_id = r.campaign_id AND e.subscriber_id = r.subscriber_id
+                    AND e.kind = 'click'
+                )) AS "clicked!"
+            FROM campaign_recipients r
+            WHERE r.campaign_id = $1 AND r.test
+            GROUP BY r.variant_id
+            "#,
+            campaign_id
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn remainder(&self, campaign_id: i32) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM campaign_recipients
+            WHERE campaign_id = $1 AND NOT test
+            "#,
+            campaign_id
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count)
+    }
+
+    async fn due(&self) -> Result<Vec<AbTest>> {
+        sqlx::query_as!(
+            AbTestRow,
+            r#"
+            SELECT * FROM ab_tests
+            WHERE decided_at IS NULL AND decide_at <= now()
+            ORDER BY decide_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    async fn pick(&mut self, campaign_id: i32, winner_id: i32) -> Result<i32> {
+        Ok(sqlx::query!(
+            r#"
+            UPDATE ab_tests SET winner_id = COALESCE(winner_id, $2)
+            WHERE campaign_id = $1
+            RETURNING winner_id AS "winner_id!"
+            "#,
+            campaign_id,
+            winner_id,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .winner_id)
+    }
+
+    async fn finish(&mut self, campaign_id: i32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE ab_tests SET decided_at = now() WHERE campaign_id = $1",
+            campaign_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{EventKind, Metric, NewCampaign, NewEvent, NewSubscriber, NewTemplate, NewVariant},
+        store::{
+            CampaignStore, EventStore, PsqlCampaignStore, PsqlEventStore, PsqlSubscriberStore,
+            PsqlTemplateStore, SubscriberStore, TemplateStore,
+        },
+    };
+
+    use super::*;
+
+    async fn start(pool: &PgPool, decide_at: DateTime<Utc>) -> Result<(AbTest, Vec<Variant>)> {
+        let campaign = PsqlCampaignStore::from(pool.clone())
+            .create(NewCampaign {
+                name: "May".to_string(),
+            })
+            .await?;
+        let subject = |subject: &str| NewVariant {
+            subject: Some(subject.to_string()),
+            ..Default::default()
+        };
+        PsqlAbTestStore::from(pool.clone())
+            .create(
+                campaign.id,
+                &NewAbTest {
+                    template_id: 1,
+                    variants: vec![subject("Hello"), subject("Hi")],
+                    test_percent: 20,
+                    window_hours: 4,
+                    metric: Metric::Open,
+                },
+                decide_at,
+            )
+            .await
+    }
+
+    async fn subscriber(pool: &PgPool, email: &str) -> Result<i32> {
+        Ok(PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: email.into(),
+            })
+            .await?
+            .id)
+    }
+
+    fn delivery(test: &AbTest) -> NewDelivery {
+        NewDelivery {
+            sender: "news@example.com".to_string(),
+            recipient: "someone@example.org".to_string(),
+            raw: b"Subject: News".to_vec(),
+            campaign_id: Some(test.campaign_id),
+            engagement: 0,
+        }
+    }
+
+    #[sqlx::test]
+    async fn create_names_variants_in_order(pool: PgPool) -> Result<()> {
+        let (test, variants) = start(&pool, Utc::now()).await?;
+        let store = PsqlAbTestStore { pool };
+
+        assert_eq!(
+            vec!["A", "B"],
+            variants.iter().map(|v| v.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("Hi".to_string()), variants[1].subject);
+        assert_eq!(2, store.variants(test.campaign_id).await?.len());
+        assert_eq!(
+            Metric::Open,
+            store.get(test.campaign_id).await?.unwrap().metric
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn tallies_count_test_recipients_who_opened(pool: PgPool) -> Result<()> {
+        let (test, variants) = start(&pool, Utc::now()).await?;
+        let ada = subscriber(&pool, "ada@example.org").await?;
+        let bob = subscriber(&pool, "bob@example.org").await?;
+        let eve = subscriber(&pool, "eve@example.org").await?;
+        let mut store = PsqlAbTestStore { pool: pool.clone() };
+
+        let tested = store
+            .send(
+                test.campaign_id,
+                true,
+                &[
+                    (ada, variants[0].id, delivery(&test)),
+                    (bob, variants[0].id, delivery(&test)),
+                ],
+            )
+            .await?;
+        let remaining = store
+            .send(
+                test.campaign_id,
+                false,
+                &[
+                    (ada, variants[1].id, delivery(&test)),
+                    (eve, variants[0].id, delivery(&test)),
+                ],
+            )
+            .await?;
+        let queued = sqlx::query!("SELECT count(*) AS \"count!\" FROM outbox")
+            .fetch_one(&pool)
+            .await?;
+        for _ in 0..2 {
+            PsqlEventStore::from(pool.clone())
+                .record(NewEvent {
+                    kind: EventKind::Open,
+                    subscriber_id: ada,
+                    campaign_id: Some(test.campaign_id),
+                    url: None,
+                    user_agent: None,
+                })
+                .await?;
+        }
+        let tallies = store.tallies(test.campaign_id).await?;
+
+        assert_eq!(
+            vec![VariantTally {
+                variant_id: variants[0].id,
+                recipients: 2,
+                opened: 1,
+                clicked: 0,
+            }],
+            tallies
+        );
+        assert_eq!(1, store.remainder(test.campaign_id).await?);
+        assert_eq!((2, 1), (tested, remaining));
+        assert_eq!(3, queued.count);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn abandoned_test_leaves_campaign_unscheduled(pool: PgPool) -> Result<()> {
+        let (test, _) = start(&pool, Utc::now()).await?;
+        let template = PsqlTemplateStore::from(pool.clone())
+            .save(NewTemplate {
+                name: "news".to_string(),
+                subject: "News".to_string(),
+                text: "Hi".to_string(),
+                html: None,
+            })
+            .await?;
+        let mut campaigns = PsqlCampaignStore::from(pool.clone());
+        campaigns
+            .schedule(test.campaign_id, template.id, Utc::now(), &[])
+            .await?;
+        let mut store = PsqlAbTestStore { pool };
+
+        store.abandon(test.campaign_id).await?;
+
+        assert!(store.get(test.campaign_id).await?.is_none());
+        assert!(store.variants(test.campaign_id).await?.is_empty());
+        assert!(
+            campaigns
+                .schedule(test.campaign_id, template.id, Utc::now(), &[])
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn winner_is_only_picked_once(pool: PgPool) -> Result<()> {
+        let (test, variants) = start(&pool, Utc::now() - chrono::Duration::minutes(1)).await?;
+        let (later, _) = start(&pool, Utc::now() + chrono::Duration::hours(1)).await?;
+        let mut store = PsqlAbTestStore { pool };
+
+        let due = store.due().await?;
+        let first = store.pick(test.campaign_id, variants[1].id).await?;
+        let second = store.pick(test.campaign_id, variants[0].id).await?;
+        store.finish(test.campaign_id).await?;
+
+        assert_eq!(
+            vec![test.campaign_id],
+            due.iter().map(|t| t.campaign_id).collect::<Vec<_>>()
+        );
+        assert_ne!(test.campaign_id, later.campaign_id);
+        assert_eq!(variants[1].id, first);
+        assert_eq!(variants[1].id, second);
+        assert!(store.due().await?.is_empty());
+
+        Ok(())
+    }
+}
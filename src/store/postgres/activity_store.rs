@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Activity, ActivityKind},
+    store::ActivityStore,
+};
+
+pub struct PsqlActivityStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlActivityStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct ActivityRow {
+    id: i64,
+    kind: String,
+    data: Value,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<ActivityRow> for Activity {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ActivityRow) -> Result<Self> {
+        Ok(Activity {
+            id: row.id,
+            kind: row.kind.try_into().map_err(|e: String| anyhow!(e))?,
+            data: row.data,
+            created_at: row.created_at,
+        })
+    }
+}
+
+impl ActivityStore for PsqlActivityStore {
+    async fn record(&mut self, kind: ActivityKind, data: &Value, keep: i64) -> Result<Activity> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query_as!(
+            ActivityRow,
+            "INSERT INTO activity(kind, data) VALUES ($1, $2) RETURNING *",
+            kind.as_str(),
+            data,
+        )
+        .fetch_one(&mut tx)
+        .await?;
+        sqlx::query!("DELETE FROM activity WHERE id <= $1", row.id - keep)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        row.try_into()
+    }
+
+    async fn since(&self, id: i64, limit: i64) -> Result<Vec<Activity>> {
+        sqlx::query_as!(
+            ActivityRow,
+            "SELECT * FROM activity WHERE id > $1 ORDER BY id LIMIT $2",
+            id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Activity::try_from)
+        .collect()
+    }
+
+    async fn notify(&self, channel: &str, payload: &str) -> Result<()> {
+        sqlx::query!("SELECT pg_notify($1, $2)", channel, payload)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn record_keeps_newest_activity(pool: PgPool) -> Result<()> {
+        let mut store = PsqlActivityStore { pool };
+        let mut ids = vec![];
+        for n in 0..5 {
+            let activity = store
+                .record(ActivityKind::Signup, &json!({ "n": n }), 3)
+                .await?;
+            ids.push(activity.id);
+        }
+
+        let all = store.since(0, 10).await?;
+        let later = store.since(ids[3], 10).await?;
+        let first = store.since(0, 1).await?;
+
+        assert_eq!(ids[2..], all.iter().map(|a| a.id).collect::<Vec<_>>());
+        assert_eq!(json!({"n": 2}), all[0].data);
+        assert_eq!(vec![ids[4]], later.iter().map(|a| a.id).collect::<Vec<_>>());
+        assert_eq!(1, first.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{model::AdminSession, store::AdminSessionStore};
+
+pub struct PsqlAdminSessionStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlAdminSessionStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl AdminSessionStore for PsqlAdminSessionStore {
+    async fn create(&mut self, id: &str, session: &AdminSession) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM admin_sessions WHERE expires_at <= now()")
+            .execute(&mut tx)
+            .await?;
+        sqlx::query!(
+            "INSERT INTO admin_sessions(id, csrf, expires_at) VALUES ($1, $2, $3)",
+            id,
+            session.csrf,
+            session.expires_at,
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find(&self, id: &str) -> Result<Option<AdminSession>> {
+        Ok(sqlx::query_as!(
+            AdminSession,
+            "SELECT csrf, expires_at FROM admin_sessions WHERE id = $1 AND expires_at > now()",
+            id,
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn delete(&mut self, id: &str) -> Result<()> {
+        sqlx::query!("DELETE FROM admin_sessions WHERE id = $1", id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn session(hours: i64) -> AdminSession {
+        AdminSession {
+            csrf: AdminSession::generate_token(),
+            expires_at: Utc::now() + Duration::hours(hours),
+        }
+    }
+
+    #[sqlx::test]
+    async fn find_skips_sessions_that_ran_out(pool: PgPool) -> Result<()> {
+        let mut store = PsqlAdminSessionStore { pool };
+        let current = session(1);
+        store.create("current", &current).await?;
+        store.create("old", &session(-1)).await?;
+
+        let found = store.find("current").await?.unwrap();
+        let old = store.find("old").await?;
+        store.delete("current").await?;
+
+        assert_eq!(current.csrf, found.csrf);
+        assert!(old.is_none());
+        assert!(store.find("current").await?.is_none());
+
+        Ok(())
+    }
+}
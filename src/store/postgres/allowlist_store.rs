@@ -0,0 +1,51 @@
+use anyhow::Result;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{model::Email, store::AllowlistStore};
+
+pub struct PsqlAllowlistStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlAllowlistStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl AllowlistStore for PsqlAllowlistStore {
+    async fn add(&mut self, email: &Email) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO allowlist(email) VALUES ($1) ON CONFLICT (email) DO NOTHING",
+            email.0
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn remove(&mut self, email: &Email) -> Result<()> {
+        sqlx::query!("DELETE FROM allowlist WHERE email = $1", email.0)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn contains(&self, email: &Email) -> Result<bool> {
+        Ok(
+            sqlx::query!("SELECT email FROM allowlist WHERE email = $1", email.0)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn all(&self) -> Result<Vec<Email>> {
+        Ok(sqlx::query!("SELECT email FROM allowlist ORDER BY email")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| Email::from(row.email))
+            .collect())
+    }
+}
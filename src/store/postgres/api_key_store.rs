@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{ApiKey, NewApiKey, Scope},
+    store::ApiKeyStore,
+};
+
+pub struct PsqlApiKeyStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlApiKeyStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct ApiKeyRow {
+    id: i32,
+    name: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self> {
+        Ok(ApiKey {
+            id: row.id,
+            name: row.name,
+            scopes: row
+                .scopes
+                .into_iter()
+                .map(Scope::try_from)
+                .collect::<Result<_, String>>()
+                .map_err(|e| anyhow!(e))?,
+            created_at: row.created_at,
+            revoked_at: row.revoked_at,
+        })
+    }
+}
+
+impl ApiKeyStore for PsqlApiKeyStore {
+    async fn create(&mut self, new_api_key: NewApiKey, key_hash: &str) -> Result<ApiKey> {
+        let scopes: Vec<String> = new_api_key
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect();
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            INSERT INTO api_keys(name, key_hash, scopes)
+            VALUES ($1, $2, $3)
+            RETURNING id, name, scopes, created_at, revoked_at
+            "#,
+            new_api_key.name,
+            key_hash,
+            &scopes,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn find(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, name, scopes, created_at, revoked_at FROM api_keys
+            WHERE key_hash = $1 AND revoked_at IS NULL
+            "#,
+            key_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(ApiKey::try_from)
+        .transpose()
+    }
+
+    async fn all(&self) -> Result<Vec<ApiKey>> {
+        sqlx::query_as!(
+            ApiKeyRow,
+            "SELECT id, name, scopes, created_at, revoked_at FROM api_keys ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(ApiKey::try_from)
+        .collect()
+    }
+
+    async fn revoke(&mut self, id: i32) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE api_keys SET revoked_at = now() WHERE id = $1 AND revoked_at IS NULL",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn find_ignores_revoked_keys(pool: PgPool) -> Result<()> {
+        let mut store = PsqlApiKeyStore { pool };
+        let key = store
+            .create(
+                NewApiKey {
+                    name: "App".to_string(),
+                    scopes: vec![Scope::Send, Scope::Status],
+                },
+                "hash",
+            )
+            .await?;
+
+        let found = store.find("hash").await?.unwrap();
+        let revoked = store.revoke(key.id).await?;
+        let again = store.revoke(key.id).await?;
+
+        assert_eq!(vec![Scope::Send, Scope::Status], found.scopes);
+        assert!(revoked);
+        assert!(!again);
+        assert!(store.find("hash").await?.is_none());
+        assert!(store.all().await?[0].revoked_at.is_some());
+
+        Ok(())
+    }
+}
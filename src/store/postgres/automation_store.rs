@@ -0,0 +1,390 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Automation, AutomationRun, NewAutomation, NewDelivery, RunStatus, Trigger},
+    store::AutomationStore,
+};
+
+pub struct PsqlAutomationStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlAutomationStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct AutomationRow {
+    id: i32,
+    name: String,
+    trigger: Value,
+    steps: Value,
+    campaign_id: i32,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<AutomationRow> for Automation {
+    type Error = anyhow::Error;
+
+    fn try_from(row: AutomationRow) -> Result<Self> {
+        Ok(Automation {
+            id: row.id,
+            name: row.name,
+            trigger: serde_json::from_value(row.trigger)?,
+            steps: serde_json::from_value(row.steps)?,
+            campaign_id: row.campaign_id,
+            created_at: row.created_at,
+        })
+    }
+}
+
+struct RunRow {
+    id: i32,
+    automation_id: i32,
+    subscriber_id: i32,
+    step: i32,
+    status: String,
+    wake_at: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+    attempts: i32,
+}
+
+impl TryFrom<RunRow> for AutomationRun {
+    type Error = anyhow::Error;
+
+    fn try_from(row: RunRow) -> Result<Self> {
+        Ok(AutomationRun {
+            id: row.id,
+            automation_id: row.automation_id,
+            subscriber_id: row.subscriber_id,
+            step: row.step,
+            status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+            wake_at: row.wake_at,
+            started_at: row.started_at,
+            attempts: row.attempts,
+        })
+    }
+}
+
+impl AutomationStore for PsqlAutomationStore {
+    async fn create(
+        &mut self,
+        new_automation: NewAutomation,
+        campaign_id: i32,
+    ) -> Result<Automation> {
+        sqlx::query_as!(
+            AutomationRow,
+            r#"
+            INSERT INTO automations(name, trigger, steps, campaign_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, name, trigger, steps, campaign_id, created_at
+            "#,
+            new_automation.name,
+            serde_json::to_value(&new_automation.trigger)?,
+            serde_json::to_value(&new_automation.steps)?,
+            campaign_id,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Automation>> {
+        sqlx::query_as!(
+            AutomationRow,
+            r#"
+            SELECT id, name, trigger, steps, campaign_id, created_at FROM automations
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(Automation::try_from)
+        .transpose()
+    }
+
+    async fn all(&self) -> Result<Vec<Automation>> {
+        sqlx::query_as!(
+            AutomationRow,
+            "SELECT id, name, trigger, steps, campaign_id, created_at FROM automations ORDER BY id"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Automation::try_from)
+        .collect()
+    }
+
+    async fn triggered_by(&self, trigger: &Trigger) -> Result<Vec<Automation>> {
+        sqlx::query_as!(
+            AutomationRow,
+            r#"
+            SELECT id, name, trigger, steps, campaign_id, created_at FROM automations
+            WHERE trigger = $1
+            ORDER BY id
+            "#,
+            serde_json::to_value(trigger)?,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(Automation::try_from)
+        .collect()
+    }
+
+    async fn enroll(&mut self, automation_id: i32, subscriber_id: i32) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO automation_runs(automation_id, subscriber_id)
+            VALUES ($1, $2)
+            ON CONFLICT (automation_id, subscriber_id) DO NOTHING
+            "#,
+            automation_id,
+            subscriber_id,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn runs(&self, automation_id: i32) -> Result<Vec<AutomationRun>> {
+        sqlx::query_as!(
+            RunRow,
+            r#"
+            SELECT id, automation_id, subscriber_id, step, status, wake_at, started_at, attempts
+            FROM automation_runs
+            WHERE automation_id = $1
+            ORDER BY id
+            "#,
+            automation_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(AutomationRun::try_from)
+        .collect()
+    }
+
+    async fn claim(&mut self, limit: i64) -> Result<Vec<AutomationRun>> {
+        // Should we crash mid-step, the claim runs out and the step is retried.
+        sqlx::query_as!(
+            RunRow,
+            r#"
+            UPDATE automation_runs
+            SET wake_at = now() + interval '10 minutes', attempts = attempts + 1
+            WHERE id IN (
+                SELECT id FROM automation_runs
+                WHERE status = 'active' AND wake_at <= now()
+                ORDER BY wake_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, automation_id, subscriber_id, step, status, wake_at, started_at,
+                attempts
+            "#,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(AutomationRun::try_from)
+        .collect()
+    }
+
+    async fn progress(&mut self, id: i32, step: i32) -> Result<()> {
+        sqlx::query!(
+            "UPDATE automation_runs SET step = $2 WHERE id = $1",
+            id,
+            step,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn send(&mut self, id: i32, step: i32, new_delivery: NewDelivery) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let delivery_id = sqlx::query!(
+            r#"
+            INSERT INTO outbox(sender, recipient, raw, campaign_id, engagement)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            new_delivery.sender,
+            new_delivery.recipient,
+            new_delivery.raw,
+            new_delivery.campaign_id,
+            new_delivery.engagement,
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .id;
+        sqlx::query!(
+            "UPDATE automation_runs SET step = $2 WHERE id = $1",
+            id,
+            step,
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+        Ok(delivery_id)
+    }
+
+    async fn advance(&mut self, id: i32, step: i32, wake_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE automation_runs SET step = $2, wake_at = $3, attempts = 0 WHERE id = $1",
+            id,
+            step,
+            wake_at,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish(&mut self, id: i32, status: RunStatus) -> Result<()> {
+        sqlx::query!(
+            "UPDATE automation_runs SET status = $2 WHERE id = $1",
+            id,
+            status.as_str(),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{Email, NewCampaign, NewSubscriber, Step},
+        store::{CampaignStore, PsqlCampaignStore, PsqlSubscriberStore, SubscriberStore},
+    };
+
+    use super::*;
+
+    async fn setup(pool: &PgPool) -> Result<(Automation, i32)> {
+        let campaign = PsqlCampaignStore::from(pool.clone())
+            .create(NewCampaign {
+                name: "Welcome".to_string(),
+            })
+            .await?;
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let automation = PsqlAutomationStore::from(pool.clone())
+            .create(
+                NewAutomation {
+                    name: "Welcome".to_string(),
+                    trigger: Trigger::TagAdded {
+                        tag: "trial".to_string(),
+                    },
+                    steps: vec![Step::Send {
+                        template: "welcome".to_string(),
+                    }],
+                },
+                campaign.id,
+            )
+            .await?;
+        Ok((automation, subscriber.id))
+    }
+
+    #[sqlx::test]
+    async fn triggered_by_matches_trigger(pool: PgPool) -> Result<()> {
+        let (automation, _) = setup(&pool).await?;
+        let store = PsqlAutomationStore { pool };
+
+        let matching = store
+            .triggered_by(&Trigger::TagAdded {
+                tag: "trial".to_string(),
+            })
+            .await?;
+        let other = store.triggered_by(&Trigger::Subscribed).await?;
+
+        assert_eq!(1, matching.len());
+        assert_eq!(automation.id, matching[0].id);
+        assert_eq!(automation.steps, matching[0].steps);
+        assert!(other.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn enroll_starts_one_run_per_subscriber(pool: PgPool) -> Result<()> {
+        let (automation, subscriber_id) = setup(&pool).await?;
+        let mut store = PsqlAutomationStore { pool };
+
+        let first = store.enroll(automation.id, subscriber_id).await?;
+        let second = store.enroll(automation.id, subscriber_id).await?;
+        let runs = store.runs(automation.id).await?;
+
+        assert!(first);
+        assert!(!second);
+        assert_eq!(1, runs.len());
+        assert_eq!(0, runs[0].step);
+        assert_eq!(RunStatus::Active, runs[0].status);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn claim_takes_due_runs_once(pool: PgPool) -> Result<()> {
+        let (automation, subscriber_id) = setup(&pool).await?;
+        let mut store = PsqlAutomationStore { pool };
+        store.enroll(automation.id, subscriber_id).await?;
+
+        let claimed = store.claim(10).await?;
+        let again = store.claim(10).await?;
+        store
+            .advance(claimed[0].id, 1, Utc::now() - chrono::Duration::seconds(1))
+            .await?;
+        let woken = store.claim(10).await?;
+        store.finish(woken[0].id, RunStatus::Completed).await?;
+
+        assert_eq!(1, claimed.len());
+        assert!(again.is_empty());
+        assert_eq!(1, woken[0].step);
+        assert_eq!(1, woken[0].attempts);
+        assert!(store.claim(10).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn send_queues_mail_and_records_step(pool: PgPool) -> Result<()> {
+        let (automation, subscriber_id) = setup(&pool).await?;
+        let mut store = PsqlAutomationStore { pool: pool.clone() };
+        store.enroll(automation.id, subscriber_id).await?;
+        let run = store.claim(10).await?.remove(0);
+
+        store
+            .send(
+                run.id,
+                1,
+                NewDelivery {
+                    sender: "bounces@example.com".to_string(),
+                    recipient: "test@email.com".to_string(),
+                    raw: b"Subject: Welcome\r\n\r\nHi\r\n".to_vec(),
+                    campaign_id: Some(automation.campaign_id),
+                    engagement: 0,
+                },
+            )
+            .await?;
+        let runs = store.runs(automation.id).await?;
+        let queued = sqlx::query!("SELECT count(*) AS \"count!\" FROM outbox")
+            .fetch_one(&pool)
+            .await?;
+
+        assert_eq!(1, runs[0].step);
+        assert_eq!(1, queued.count);
+
+        Ok(())
+    }
+}
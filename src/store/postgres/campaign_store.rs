@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Campaign, CampaignStat, Issue, NewCampaign, NewDelivery, Rendered},
+    store::CampaignStore,
+};
+
+pub struct PsqlCampaignStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlCampaignStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl CampaignStore for PsqlCampaignStore {
+    async fn create(&mut self, new_campaign: NewCampaign) -> Result<Campaign> {
+        Ok(sqlx::query_as!(
+            Campaign,
+            "INSERT INTO campaigns(name) VALUES ($1) RETURNING *",
+            new_campaign.name,
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Campaign>> {
+        Ok(
+            sqlx::query_as!(Campaign, "SELECT * FROM campaigns WHERE id = $1", id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn recent(&self, limit: i64) -> Result<Vec<Campaign>> {
+        Ok(sqlx::query_as!(
+            Campaign,
+            r#"
+            SELECT * FROM campaigns
+            ORDER BY COALESCE(sent_at, created_at) DESC, id DESC
+            LIMIT $1
+            "#,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn stats(&self, id: i32) -> Result<Vec<CampaignStat>> {
+        sqlx::query!(
+            "SELECT kind, url, hour, total, uniques FROM campaign_stats WHERE campaign_id = $1",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(CampaignStat {
+                kind: row.kind.try_into().map_err(|e: String| anyhow!(e))?,
+                url: row.url,
+                hour: row.hour,
+                total: row.total,
+                uniques: row.uniques,
+            })
+        })
+        .collect()
+    }
+
+    async fn totals(&self, ids: &[i32]) -> Result<HashMap<i32, Vec<CampaignStat>>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                campaign_id,
+                kind,
+                min(hour) AS "hour!",
+                sum(total)::BIGINT AS "total!",
+                sum(uniques)::BIGINT AS "uniques!"
+            FROM campaign_stats
+            WHERE campaign_id = ANY($1) AND url = ''
+            GROUP BY campaign_id, kind
+            "#,
+            ids,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut totals: HashMap<i32, Vec<CampaignStat>> = HashMap::new();
+        for row in rows {
+            totals
+                .entry(row.campaign_id)
+                .or_default()
+                .push(CampaignStat {
+                    kind: row.kind.try_into().map_err(|e: String| anyhow!(e))?,
+                    url: String::new(),
+                    hour: row.hour,
+                    total: row.total,
+                    uniques: row.uniques,
+                });
+        }
+        Ok(totals)
+    }
+
+    async fn schedule(
+        &mut self,
+        id: i32,
+        template_id: i32,
+        sent_at: DateTime<Utc>,
+        deliveries: &[(NewDelivery, DateTime<Utc>)],
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE campaigns SET template_id = $2, sent_at = $3
+            WHERE id = $1 AND sent_at IS NULL
+            "#,
+            id,
+            template_id,
+            sent_at,
+        )
+        .execute(&mut tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+        for (delivery, at) in deliveries {
+            sqlx::query!(
+                r#"
+                INSERT INTO outbox(
+                    sender, recipient, raw, campaign_id, engagement, next_attempt_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                delivery.sender,
+                delivery.recipient,
+                delivery.raw,
+                delivery.campaign_id,
+                delivery.engagement,
+                at,
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn exclude_from_archive(&mut self, id: i32, exclude: bool) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE campaigns SET exclude_from_archive = $2 WHERE id = $1",
+            id,
+            exclude,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn save_issue(&mut self, id: i32, issue: &Rendered) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO issues(campaign_id, subject, text, html)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (campaign_id) DO UPDATE
+            SET subject = EXCLUDED.subject, text = EXCLUDED.text, html = EXCLUDED.html
+            "#,
+            id,
+            issue.subject,
+            issue.text,
+            issue.html,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn issues(&self) -> Result<Vec<Issue>> {
+        Ok(sqlx::query_as!(
+            Issue,
+            r#"
+            SELECT i.campaign_id, i.subject, i.text, i.html, c.sent_at AS "sent_at!"
+            FROM issues i JOIN campaigns c ON c.id = i.campaign_id
+            WHERE NOT c.exclude_from_archive AND c.sent_at <= now()
+            ORDER BY c.sent_at DESC, c.id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn issue(&self, id: i32) -> Result<Option<Issue>> {
+        Ok(sqlx::query_as!(
+            Issue,
+            r#"
+            SELECT i.campaign_id, i.subject, i.text, i.html, c.sent_at AS "sent_at!"
+            FROM issues i JOIN campaigns c ON c.id = i.campaign_id
+            WHERE i.campaign_id = $1 AND NOT c.exclude_from_archive AND c.sent_at <= now()
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::NewTemplate,
+        store::{OutboxStore, PsqlOutboxStore, PsqlTemplateStore, TemplateStore},
+    };
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn recent_lists_newest_first(pool: PgPool) -> Result<()> {
+        let mut store = PsqlCampaignStore { pool };
+
+        let first = store
+            .create(NewCampaign {
+                name: "First".to_string(),
+            })
+            .await?;
+        let second = store
+            .create(NewCampaign {
+                name: "Second".to_string(),
+            })
+            .await?;
+        let recent = store.recent(1).await?;
+
+        assert_eq!(1, recent.len());
+        assert_eq!(second.id, recent[0].id);
+        assert_eq!("First", store.get(first.id).await?.unwrap().name);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn schedule_only_happens_once(pool: PgPool) -> Result<()> {
+        let template = PsqlTemplateStore::from(pool.clone())
+            .save(NewTemplate {
+                name: "news".to_string(),
+                subject: "News".to_string(),
+                text: "Hi".to_string(),
+                html: None,
+            })
+            .await?;
+        let mut store = PsqlCampaignStore { pool };
+        let campaign = store
+            .create(NewCampaign {
+                name: "May".to_string(),
+            })
+            .await?;
+
+        let delivery = NewDelivery {
+            sender: "news@example.com".to_string(),
+            recipient: "ada@example.org".to_string(),
+            raw: b"Subject: News".to_vec(),
+            campaign_id: Some(campaign.id),
+            engagement: 0,
+        };
+        let deliveries = [(delivery, Utc::now() + chrono::Duration::hours(1))];
+
+        let scheduled = store
+            .schedule(campaign.id, template.id, Utc::now(), &deliveries)
+            .await?;
+        let again = store
+            .schedule(campaign.id, template.id, Utc::now(), &deliveries)
+            .await?;
+        let saved = store.get(campaign.id).await?.unwrap();
+        let queued = sqlx::query!("SELECT count(*) AS \"count!\" FROM outbox")
+            .fetch_one(&store.pool)
+            .await?;
+        let due = PsqlOutboxStore::from(store.pool.clone()).claim(10).await?;
+
+        assert!(scheduled);
+        assert!(!again);
+        assert_eq!(1, queued.count);
+        assert!(due.is_empty());
+        assert_eq!(Some(template.id), saved.template_id);
+        assert!(saved.sent_at.is_some());
+
+        Ok(())
+    }
+
+    async fn sent(
+        store: &mut PsqlCampaignStore,
+        template_id: i32,
+        name: &str,
+        sent_at: DateTime<Utc>,
+    ) -> Result<i32> {
+        let campaign = store
+            .create(NewCampaign {
+                name: name.to_string(),
+            })
+            .await?;
+        store
+            .schedule(campaign.id, template_id, sent_at, &[])
+            .await?;
+        store
+            .save_issue(
+                campaign.id,
+                &Rendered {
+                    subject: name.to_string(),
+                    text: "Hi".to_string(),
+                    html: None,
+                },
+            )
+            .await?;
+        Ok(campaign.id)
+    }
+
+    #[sqlx::test]
+    async fn issues_leave_out_excluded_and_future_campaigns(pool: PgPool) -> Result<()> {
+        let template = PsqlTemplateStore::from(pool.clone())
+            .save(NewTemplate {
+                name: "news".to_string(),
+                subject: "News".to_string(),
+                text: "Hi".to_string(),
+                html: None,
+            })
+            .await?;
+        let mut store = PsqlCampaignStore { pool };
+        let hour = chrono::Duration::hours(1);
+        let older = sent(&mut store, template.id, "Older", Utc::now() - hour * 2).await?;
+        let newer = sent(&mut store, template.id, "Newer", Utc::now() - hour).await?;
+        let excluded = sent(&mut store, template.id, "Excluded", Utc::now() - hour).await?;
+        let future = sent(&mut store, template.id, "Future", Utc::now() + hour).await?;
+
+        assert!(store.exclude_from_archive(excluded, true).await?);
+        let issues = store.issues().await?;
+
+        assert_eq!(
+            vec![newer, older],
+            issues
+                .iter()
+                .map(|issue| issue.campaign_id)
+                .collect::<Vec<_>>()
+        );
+        assert!(store.issue(excluded).await?.is_none());
+        assert!(store.issue(future).await?.is_none());
+        assert_eq!("Older", store.issue(older).await?.unwrap().subject);
+
+        Ok(())
+    }
+}
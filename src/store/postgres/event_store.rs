@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Event, EventKind, NewEvent},
+    store::EventStore,
+};
+
+pub struct PsqlEventStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlEventStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl EventStore for PsqlEventStore {
+    async fn record(&mut self, new_event: NewEvent) -> Result<Event> {
+        let mut transaction = self.pool.begin().await?;
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO events(kind, subscriber_id, campaign_id, url, user_agent)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, created_at
+            "#,
+            new_event.kind.as_str(),
+            new_event.subscriber_id,
+            new_event.campaign_id,
+            new_event.url,
+            new_event.user_agent,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+
+        if let Some(campaign_id) = new_event.campaign_id {
+            let mut urls = vec![""];
+            if new_event.kind == EventKind::Click {
+                urls.extend(new_event.url.as_deref());
+            }
+
+            for url in urls {
+                let unique = sqlx::query!(
+                    r#"
+                    INSERT INTO campaign_stat_uniques(campaign_id, kind, url, subscriber_id)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                    campaign_id,
+                    new_event.kind.as_str(),
+                    url,
+                    new_event.subscriber_id,
+                )
+                .execute(&mut transaction)
+                .await?
+                .rows_affected();
+
+                sqlx::query!(
+                    r#"
+                    INSERT INTO campaign_stats(campaign_id, kind, url, hour, total, uniques)
+                    VALUES ($1, $2, $3, date_trunc('hour', $4::timestamptz), 1, $5)
+                    ON CONFLICT (campaign_id, kind, url, hour) DO UPDATE
+                    SET total = campaign_stats.total + 1,
+                        uniques = campaign_stats.uniques + EXCLUDED.uniques
+                    "#,
+                    campaign_id,
+                    new_event.kind.as_str(),
+                    url,
+                    row.created_at,
+                    unique as i64,
+                )
+                .execute(&mut transaction)
+                .await?;
+            }
+        }
+
+        transaction.commit().await?;
+
+        Ok(Event {
+            id: row.id,
+            kind: new_event.kind,
+            subscriber_id: new_event.subscriber_id,
+            campaign_id: new_event.campaign_id,
+            url: new_event.url,
+            user_agent: new_event.user_agent,
+            created_at: row.created_at,
+        })
+    }
+
+    async fn occurred(
+        &self,
+        subscriber_id: i32,
+        campaign_id: i32,
+        kind: EventKind,
+    ) -> Result<bool> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM campaign_stat_uniques
+                WHERE campaign_id = $1 AND kind = $2 AND url = '' AND subscriber_id = $3
+            ) AS "occurred!"
+            "#,
+            campaign_id,
+            kind.as_str(),
+            subscriber_id,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .occurred)
+    }
+
+    async fn open_times(&self, since: DateTime<Utc>) -> Result<HashMap<i32, Vec<DateTime<Utc>>>> {
+        let mut opens: HashMap<i32, Vec<DateTime<Utc>>> = HashMap::new();
+        for row in sqlx::query!(
+            r#"
+            SELECT subscriber_id, created_at FROM events
+            WHERE kind = 'open' AND created_at >= $1
+            ORDER BY id
+            "#,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await?
+        {
+            opens
+                .entry(row.subscriber_id)
+                .or_default()
+                .push(row.created_at);
+        }
+        Ok(opens)
+    }
+
+    async fn engagement(&self, since: DateTime<Utc>) -> Result<HashMap<i32, i64>> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT subscriber_id, count(*) AS "count!" FROM events
+            WHERE kind IN ('open', 'click') AND created_at >= $1
+            GROUP BY subscriber_id
+            "#,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.subscriber_id, row.count))
+        .collect())
+    }
+
+    async fn last_delivered(&self, subscriber_id: i32) -> Result<Option<i32>> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT campaign_id AS "campaign_id!" FROM events
+            WHERE subscriber_id = $1 AND kind = 'delivered' AND campaign_id IS NOT NULL
+            ORDER BY id DESC
+            LIMIT 1
+            "#,
+            subscriber_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| row.campaign_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{Email, NewSubscriber},
+        store::{PsqlSubscriberStore, SubscriberStore},
+    };
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn record_stores_event(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+
+        let event = store
+            .record(NewEvent {
+                kind: EventKind::Click,
+                subscriber_id: subscriber.id,
+                campaign_id: Some(7),
+                url: Some("https://example.com".to_string()),
+                user_agent: Some("test-agent".to_string()),
+            })
+            .await?;
+        let saved = sqlx::query!("SELECT * FROM events WHERE id = $1", event.id)
+            .fetch_one(&store.pool)
+            .await?;
+
+        assert_eq!("click", saved.kind);
+        assert_eq!(subscriber.id, saved.subscriber_id);
+        assert_eq!(Some(7), saved.campaign_id);
+        assert_eq!(Some("https://example.com".to_string()), saved.url);
+        assert_eq!(Some("test-agent".to_string()), saved.user_agent);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn record_rolls_up_campaign_events(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+        let click = NewEvent {
+            kind: EventKind::Click,
+            subscriber_id: subscriber.id,
+            campaign_id: Some(1),
+            url: Some("https://example.com".to_string()),
+            user_agent: None,
+        };
+
+        store.record(click.clone()).await?;
+        store.record(click).await?;
+        let stats = sqlx::query!("SELECT url, total, uniques FROM campaign_stats ORDER BY url")
+            .fetch_all(&store.pool)
+            .await?;
+
+        assert_eq!(2, stats.len());
+        assert_eq!("", stats[0].url);
+        assert_eq!("https://example.com", stats[1].url);
+        assert!(stats
+            .iter()
+            .all(|stat| stat.total == 2 && stat.uniques == 1));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn occurred_finds_campaign_events(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+
+        store
+            .record(NewEvent {
+                kind: EventKind::Open,
+                subscriber_id: subscriber.id,
+                campaign_id: Some(3),
+                url: None,
+                user_agent: None,
+            })
+            .await?;
+
+        assert!(store.occurred(subscriber.id, 3, EventKind::Open).await?);
+        assert!(!store.occurred(subscriber.id, 3, EventKind::Click).await?);
+        assert!(!store.occurred(subscriber.id, 4, EventKind::Open).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn open_times_groups_opens_by_subscriber(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+        let open = NewEvent {
+            kind: EventKind::Open,
+            subscriber_id: subscriber.id,
+            campaign_id: None,
+            url: None,
+            user_agent: None,
+        };
+
+        let first = store.record(open.clone()).await?;
+        store.record(open).await?;
+        let opens = store.open_times(first.created_at).await?;
+        let later = store
+            .open_times(Utc::now() + chrono::Duration::hours(1))
+            .await?;
+
+        assert_eq!(2, opens[&subscriber.id].len());
+        assert!(later.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn engagement_counts_opens_and_clicks(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+        let since = Utc::now() - chrono::Duration::seconds(1);
+        for kind in [EventKind::Open, EventKind::Click, EventKind::Unsubscribe] {
+            store
+                .record(NewEvent {
+                    kind,
+                    subscriber_id: subscriber.id,
+                    campaign_id: None,
+                    url: None,
+                    user_agent: None,
+                })
+                .await?;
+        }
+
+        let engagement = store.engagement(since).await?;
+
+        assert_eq!(HashMap::from([(subscriber.id, 2)]), engagement);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn last_delivered_finds_latest_campaign(pool: PgPool) -> Result<()> {
+        let subscriber = PsqlSubscriberStore::from(pool.clone())
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let mut store = PsqlEventStore { pool };
+        assert_eq!(None, store.last_delivered(subscriber.id).await?);
+
+        for (kind, campaign_id) in [
+            (EventKind::Delivered, Some(3)),
+            (EventKind::Delivered, Some(4)),
+            (EventKind::Open, Some(5)),
+            (EventKind::Delivered, None),
+        ] {
+            store
+                .record(NewEvent {
+                    kind,
+                    subscriber_id: subscriber.id,
+                    campaign_id,
+                    url: None,
+                    user_agent: None,
+                })
+                .await?;
+        }
+
+        assert_eq!(Some(4), store.last_delivered(subscriber.id).await?);
+
+        Ok(())
+    }
+}
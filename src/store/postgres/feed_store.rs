@@ -0,0 +1,259 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Campaign, Feed, NewCampaign, NewFeed},
+    store::FeedStore,
+};
+
+pub struct PsqlFeedStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlFeedStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl FeedStore for PsqlFeedStore {
+    async fn create(&mut self, new_feed: NewFeed, next_run_at: DateTime<Utc>) -> Result<Feed> {
+        Ok(sqlx::query_as!(
+            Feed,
+            r#"
+            INSERT INTO feeds(name, source, schedule, template_id, next_run_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+            new_feed.name,
+            new_feed.source,
+            new_feed.schedule,
+            new_feed.template_id,
+            next_run_at,
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Feed>> {
+        Ok(
+            sqlx::query_as!(Feed, "SELECT * FROM feeds WHERE id = $1", id)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn all(&self) -> Result<Vec<Feed>> {
+        Ok(sqlx::query_as!(Feed, "SELECT * FROM feeds ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn due(&self) -> Result<Vec<Feed>> {
+        Ok(sqlx::query_as!(
+            Feed,
+            "SELECT * FROM feeds WHERE next_run_at <= now() ORDER BY next_run_at"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn reschedule(&mut self, id: i32, next_run_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE feeds SET next_run_at = $2 WHERE id = $1",
+            id,
+            next_run_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn unsent(&self, id: i32, entry_ids: &[String]) -> Result<Vec<String>> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT c.entry_id AS "entry_id!" FROM UNNEST($2::TEXT[]) AS c(entry_id)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM feed_entries e WHERE e.feed_id = $1 AND e.entry_id = c.entry_id
+            )
+            "#,
+            id,
+            entry_ids,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.entry_id)
+        .collect())
+    }
+
+    async fn claim(
+        &mut self,
+        id: i32,
+        entry_ids: &[String],
+        new_campaign: NewCampaign,
+    ) -> Result<Option<(Campaign, Vec<String>)>> {
+        let mut tx = self.pool.begin().await?;
+        let campaign = sqlx::query_as!(
+            Campaign,
+            "INSERT INTO campaigns(name) VALUES ($1) RETURNING *",
+            new_campaign.name,
+        )
+        .fetch_one(&mut tx)
+        .await?;
+        // Waits for a run that claims the same entries at the same time, and
+        // then leaves them to it.
+        let claimed: Vec<String> = sqlx::query!(
+            r#"
+            INSERT INTO feed_entries(feed_id, entry_id, campaign_id)
+            SELECT $1, entry_id, $3 FROM UNNEST($2::TEXT[]) AS entry_id
+            ON CONFLICT DO NOTHING
+            RETURNING entry_id
+            "#,
+            id,
+            entry_ids,
+            campaign.id,
+        )
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map(|row| row.entry_id)
+        .collect();
+        if claimed.is_empty() {
+            return Ok(None);
+        }
+        sqlx::query!("UPDATE feeds SET last_sent_at = now() WHERE id = $1", id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some((campaign, claimed)))
+    }
+
+    async fn mark_sent(
+        &mut self,
+        id: i32,
+        entry_ids: &[String],
+        campaign_id: Option<i32>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO feed_entries(feed_id, entry_id, campaign_id)
+            SELECT $1, entry_id, $3 FROM UNNEST($2::TEXT[]) AS entry_id
+            ON CONFLICT DO NOTHING
+            "#,
+            id,
+            entry_ids,
+            campaign_id,
+        )
+        .execute(&mut tx)
+        .await?;
+        if campaign_id.is_some() {
+            sqlx::query!("UPDATE feeds SET last_sent_at = now() WHERE id = $1", id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::NewTemplate,
+        store::{PsqlTemplateStore, TemplateStore},
+    };
+
+    use super::*;
+
+    async fn create(pool: &PgPool, next_run_at: DateTime<Utc>) -> Result<Feed> {
+        let template = PsqlTemplateStore::from(pool.clone())
+            .save(NewTemplate {
+                name: "blog".to_string(),
+                subject: "New on the blog".to_string(),
+                text: "{{#items}}{{ title }}{{/items}}".to_string(),
+                html: None,
+            })
+            .await?;
+        PsqlFeedStore::from(pool.clone())
+            .create(
+                NewFeed {
+                    name: "Blog".to_string(),
+                    source: "https://example.com/atom.xml".to_string(),
+                    schedule: "0 9 * * Mon".to_string(),
+                    template_id: template.id,
+                    send_existing: false,
+                },
+                next_run_at,
+            )
+            .await
+    }
+
+    #[sqlx::test]
+    async fn due_feeds_wait_for_their_next_run(pool: PgPool) -> Result<()> {
+        let feed = create(&pool, Utc::now() + chrono::Duration::hours(1)).await?;
+        let mut store = PsqlFeedStore { pool };
+
+        let before = store.due().await?;
+        store.reschedule(feed.id, Utc::now()).await?;
+        let after = store.due().await?;
+
+        assert!(before.is_empty());
+        assert_eq!(
+            vec![feed.id],
+            after.iter().map(|f| f.id).collect::<Vec<_>>()
+        );
+        assert_eq!("Blog", store.get(feed.id).await?.unwrap().name);
+        assert_eq!(1, store.all().await?.len());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn unsent_skips_entries_marked_sent(pool: PgPool) -> Result<()> {
+        let feed = create(&pool, Utc::now()).await?;
+        let mut store = PsqlFeedStore { pool };
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        store.mark_sent(feed.id, &ids(&["a", "b"]), None).await?;
+        let unsent = store.unsent(feed.id, &ids(&["a", "b", "c"])).await?;
+
+        assert_eq!(ids(&["c"]), unsent);
+        assert_eq!(None, store.get(feed.id).await?.unwrap().last_sent_at);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn entries_are_only_claimed_once(pool: PgPool) -> Result<()> {
+        let feed = create(&pool, Utc::now()).await?;
+        let mut store = PsqlFeedStore { pool: pool.clone() };
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let campaign = |name: &str| NewCampaign {
+            name: name.to_string(),
+        };
+
+        let first = store
+            .claim(feed.id, &ids(&["a"]), campaign("First"))
+            .await?;
+        let second = store
+            .claim(feed.id, &ids(&["a", "b"]), campaign("Second"))
+            .await?;
+        let again = store
+            .claim(feed.id, &ids(&["b"]), campaign("Again"))
+            .await?;
+        let campaigns = sqlx::query!("SELECT count(*) AS \"count!\" FROM campaigns")
+            .fetch_one(&pool)
+            .await?;
+
+        assert_eq!(ids(&["a"]), first.unwrap().1);
+        assert_eq!(ids(&["b"]), second.unwrap().1);
+        assert!(again.is_none());
+        assert_eq!(2, campaigns.count);
+        assert!(store.get(feed.id).await?.unwrap().last_sent_at.is_some());
+
+        Ok(())
+    }
+}
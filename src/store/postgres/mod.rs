@@ -1,3 +1,35 @@
+mod ab_test_store;
+mod activity_store;
+mod admin_session_store;
+mod allowlist_store;
+mod api_key_store;
+mod automation_store;
+mod campaign_store;
+mod event_store;
+mod feed_store;
+mod outbox_store;
+mod post_store;
+mod rate_limit_store;
 mod subscriber_store;
+mod suppression_store;
+mod template_store;
+mod warmup_store;
+mod webhook_store;
 
+pub use ab_test_store::PsqlAbTestStore;
+pub use activity_store::PsqlActivityStore;
+pub use admin_session_store::PsqlAdminSessionStore;
+pub use allowlist_store::PsqlAllowlistStore;
+pub use api_key_store::PsqlApiKeyStore;
+pub use automation_store::PsqlAutomationStore;
+pub use campaign_store::PsqlCampaignStore;
+pub use event_store::PsqlEventStore;
+pub use feed_store::PsqlFeedStore;
+pub use outbox_store::PsqlOutboxStore;
+pub use post_store::PsqlPostStore;
+pub use rate_limit_store::PsqlRateLimitStore;
 pub use subscriber_store::PsqlSubscriberStore;
+pub use suppression_store::PsqlSuppressionStore;
+pub use template_store::PsqlTemplateStore;
+pub use warmup_store::PsqlWarmupStore;
+pub use webhook_store::PsqlWebhookStore;
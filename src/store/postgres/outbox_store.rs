@@ -0,0 +1,383 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Delivery, DeliveryReport, NewDelivery},
+    store::OutboxStore,
+};
+
+pub struct PsqlOutboxStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlOutboxStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl OutboxStore for PsqlOutboxStore {
+    async fn enqueue(&mut self, new_delivery: NewDelivery) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"
+            INSERT INTO outbox(sender, recipient, raw, campaign_id, engagement)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            new_delivery.sender,
+            new_delivery.recipient,
+            new_delivery.raw,
+            new_delivery.campaign_id,
+            new_delivery.engagement,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .id)
+    }
+
+    async fn submit(
+        &mut self,
+        new_delivery: NewDelivery,
+        api_key_id: i32,
+        idempotency_key: Option<&str>,
+    ) -> Result<(i64, bool)> {
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO outbox(sender, recipient, raw, api_key_id, idempotency_key)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (api_key_id, idempotency_key) WHERE idempotency_key IS NOT NULL
+            DO NOTHING
+            RETURNING id
+            "#,
+            new_delivery.sender,
+            new_delivery.recipient,
+            new_delivery.raw,
+            api_key_id,
+            idempotency_key,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(row) = inserted {
+            return Ok((row.id, true));
+        }
+
+        let existing = sqlx::query!(
+            "SELECT id FROM outbox WHERE api_key_id = $1 AND idempotency_key = $2",
+            api_key_id,
+            idempotency_key,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((existing.id, false))
+    }
+
+    async fn report(&self, id: i64, api_key_id: i32) -> Result<Option<DeliveryReport>> {
+        sqlx::query!(
+            r#"
+            SELECT id, recipient, status, attempts, error, created_at, sent_at FROM outbox
+            WHERE id = $1 AND api_key_id = $2
+            "#,
+            id,
+            api_key_id,
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| {
+            Ok(DeliveryReport {
+                id: row.id,
+                recipient: row.recipient,
+                status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                attempts: row.attempts,
+                error: row.error,
+                created_at: row.created_at,
+                sent_at: row.sent_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn claim(&mut self, limit: i64) -> Result<Vec<Delivery>> {
+        // Should we crash mid-delivery, the claim runs out and they are retried.
+        // Confirmations and other mail of one subscriber go ahead of campaigns.
+        Ok(sqlx::query_as!(
+            Delivery,
+            r#"
+            WITH claimed AS (
+                UPDATE outbox SET next_attempt_at = now() + interval '10 minutes'
+                WHERE id IN (
+                    SELECT id FROM outbox
+                    WHERE status = 'pending' AND next_attempt_at <= now()
+                    ORDER BY campaign_id IS NOT NULL, engagement DESC, id
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, sender, recipient, raw, attempts, campaign_id, engagement
+            )
+            SELECT id, sender, recipient, raw, attempts, campaign_id FROM claimed
+            ORDER BY campaign_id IS NOT NULL, engagement DESC, id
+            "#,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn sent(&mut self, id: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE outbox SET status = 'sent', sent_at = now(), error = NULL WHERE id = $1",
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn campaign_sent_since(&self, since: DateTime<Utc>) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT count(*) AS "count!" FROM outbox
+            WHERE campaign_id IS NOT NULL AND sent_at >= $1
+            "#,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count)
+    }
+
+    async fn campaign_waiting(&self) -> Result<i64> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT count(*) AS "count!" FROM outbox
+            WHERE campaign_id IS NOT NULL AND status = 'pending'
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .count)
+    }
+
+    async fn defer(&mut self, id: i64, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE outbox SET next_attempt_at = $2 WHERE id = $1",
+            id,
+            at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn failed(
+        &mut self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE outbox SET
+                attempts = attempts + 1,
+                error = $2,
+                status = CASE WHEN $3::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = COALESCE($3, next_attempt_at)
+            WHERE id = $1
+            "#,
+            id,
+            error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{DeliveryStatus, NewApiKey, Scope},
+        store::{ApiKeyStore, PsqlApiKeyStore},
+    };
+
+    use super::*;
+
+    fn new_delivery(recipient: &str) -> NewDelivery {
+        NewDelivery {
+            sender: "bounces@example.com".to_string(),
+            recipient: recipient.to_string(),
+            raw: b"Subject: Hi\r\n\r\nHi\r\n".to_vec(),
+            campaign_id: None,
+            engagement: 0,
+        }
+    }
+
+    #[sqlx::test]
+    async fn claim_takes_each_delivery_once(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        store.enqueue(new_delivery("first@example.org")).await?;
+        store.enqueue(new_delivery("second@example.org")).await?;
+
+        let first = store.claim(1).await?;
+        let second = store.claim(10).await?;
+        let third = store.claim(10).await?;
+
+        assert_eq!("first@example.org", first[0].recipient);
+        assert_eq!(1, second.len());
+        assert_eq!("second@example.org", second[0].recipient);
+        assert!(third.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn claim_takes_most_engaged_first(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        store.enqueue(new_delivery("first@example.org")).await?;
+        store
+            .enqueue(NewDelivery {
+                engagement: 3,
+                ..new_delivery("engaged@example.org")
+            })
+            .await?;
+
+        let claimed = store.claim(10).await?;
+
+        assert_eq!("engaged@example.org", claimed[0].recipient);
+        assert_eq!("first@example.org", claimed[1].recipient);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn claim_takes_other_mail_before_campaigns(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        store
+            .enqueue(NewDelivery {
+                campaign_id: Some(1),
+                engagement: 3,
+                ..new_delivery("campaign@example.org")
+            })
+            .await?;
+        store.enqueue(new_delivery("confirm@example.org")).await?;
+
+        let claimed = store.claim(1).await?;
+
+        assert_eq!("confirm@example.org", claimed[0].recipient);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn defer_keeps_attempts(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        store.enqueue(new_delivery("ada@example.org")).await?;
+        let claimed = store.claim(1).await?;
+
+        store
+            .defer(claimed[0].id, Utc::now() - chrono::Duration::seconds(1))
+            .await?;
+        let again = store.claim(1).await?;
+
+        assert_eq!(claimed[0].id, again[0].id);
+        assert_eq!(0, again[0].attempts);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn failed_delivery_is_retried_when_due(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        let id = store.enqueue(new_delivery("user@example.org")).await?;
+        store.claim(10).await?;
+
+        store.failed(id, "421 try again", Some(Utc::now())).await?;
+        let retried = store.claim(10).await?;
+
+        assert_eq!(1, retried.len());
+        assert_eq!(1, retried[0].attempts);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn failed_delivery_without_retry_is_given_up(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool: pool.clone() };
+        let id = store.enqueue(new_delivery("user@example.org")).await?;
+        store.claim(10).await?;
+
+        store.failed(id, "550 no such user", None).await?;
+
+        let row = sqlx::query!("SELECT status FROM outbox WHERE id = $1", id)
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!("failed", row.status);
+        assert!(store.claim(10).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn submit_deduplicates_idempotency_keys(pool: PgPool) -> Result<()> {
+        let key = PsqlApiKeyStore::from(pool.clone())
+            .create(
+                NewApiKey {
+                    name: "App".to_string(),
+                    scopes: vec![Scope::Send],
+                },
+                "hash",
+            )
+            .await?;
+        let mut store = PsqlOutboxStore { pool };
+
+        let (first, created) = store
+            .submit(new_delivery("user@example.org"), key.id, Some("receipt-1"))
+            .await?;
+        let (again, created_again) = store
+            .submit(new_delivery("user@example.org"), key.id, Some("receipt-1"))
+            .await?;
+        let (other, _) = store
+            .submit(new_delivery("user@example.org"), key.id, None)
+            .await?;
+        store.sent(first).await?;
+        let report = store.report(first, key.id).await?.unwrap();
+
+        assert!(created);
+        assert!(!created_again);
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        assert_eq!(DeliveryStatus::Sent, report.status);
+        assert!(report.sent_at.is_some());
+        assert!(store.report(first, key.id + 1).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn campaign_sent_since_counts_sent_campaign_mail(pool: PgPool) -> Result<()> {
+        let mut store = PsqlOutboxStore { pool };
+        let start = Utc::now();
+        for (recipient, campaign_id) in [
+            ("ada@example.org", Some(1)),
+            ("grace@example.org", Some(1)),
+            ("hedy@example.org", None),
+        ] {
+            let id = store
+                .enqueue(NewDelivery {
+                    campaign_id,
+                    ..new_delivery(recipient)
+                })
+                .await?;
+            if recipient != "grace@example.org" {
+                store.sent(id).await?;
+            }
+        }
+
+        assert_eq!(1, store.campaign_sent_since(start).await?);
+        assert_eq!(1, store.campaign_waiting().await?);
+        assert_eq!(0, store.campaign_sent_since(Utc::now()).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{anyhow, Result};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Email, NewPost, Post, PostStatus},
+    store::PostStore,
+};
+
+pub struct PsqlPostStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlPostStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl PostStore for PsqlPostStore {
+    async fn create(&mut self, new_post: NewPost) -> Result<Post> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO posts(sender, subject, raw, status)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, created_at
+            "#,
+            new_post.sender.0,
+            new_post.subject,
+            new_post.raw,
+            new_post.status.as_str(),
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Post {
+            id: row.id,
+            sender: new_post.sender,
+            subject: new_post.subject,
+            raw: new_post.raw,
+            status: new_post.status,
+            reason: None,
+            created_at: row.created_at,
+        })
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Post>> {
+        sqlx::query!(
+            "SELECT id, sender, subject, raw, status, reason, created_at FROM posts WHERE id = $1",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| {
+            Ok(Post {
+                id: row.id,
+                sender: Email::from(row.sender),
+                subject: row.subject,
+                raw: row.raw,
+                status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                reason: row.reason,
+                created_at: row.created_at,
+            })
+        })
+        .transpose()
+    }
+
+    async fn held(&self) -> Result<Vec<Post>> {
+        sqlx::query!(
+            r#"
+            SELECT id, sender, subject, raw, status, reason, created_at FROM posts
+            WHERE status = 'held'
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Post {
+                id: row.id,
+                sender: Email::from(row.sender),
+                subject: row.subject,
+                raw: row.raw,
+                status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                reason: row.reason,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+    }
+
+    async fn moderate(
+        &mut self,
+        id: i32,
+        status: PostStatus,
+        reason: Option<&str>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE posts SET status = $2, reason = $3 WHERE id = $1 AND status = 'held'",
+            id,
+            status.as_str(),
+            reason,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn undigested(&self) -> Result<Vec<Post>> {
+        sqlx::query!(
+            r#"
+            SELECT id, sender, subject, raw, status, reason, created_at FROM posts
+            WHERE status = 'accepted' AND NOT digested
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Post {
+                id: row.id,
+                sender: Email::from(row.sender),
+                subject: row.subject,
+                raw: row.raw,
+                status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                reason: row.reason,
+                created_at: row.created_at,
+            })
+        })
+        .collect()
+    }
+
+    async fn mark_digested(&mut self, ids: &[i32]) -> Result<()> {
+        sqlx::query!("UPDATE posts SET digested = true WHERE id = ANY($1)", ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_post(status: PostStatus) -> NewPost {
+        NewPost {
+            sender: Email::from("member@example.org"),
+            subject: "Hello".to_string(),
+            raw: b"Subject: Hello\r\n\r\nHi\r\n".to_vec(),
+            status,
+        }
+    }
+
+    #[sqlx::test]
+    async fn undigested_lists_accepted_posts(pool: PgPool) -> Result<()> {
+        let mut store = PsqlPostStore { pool };
+
+        let accepted = store.create(new_post(PostStatus::Accepted)).await?;
+        store.create(new_post(PostStatus::Held)).await?;
+        let undigested = store.undigested().await?;
+
+        assert_eq!(1, undigested.len());
+        assert_eq!(accepted.id, undigested[0].id);
+        assert_eq!(accepted.raw, undigested[0].raw);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn moderate_only_changes_held_posts(pool: PgPool) -> Result<()> {
+        let mut store = PsqlPostStore { pool };
+
+        let held = store.create(new_post(PostStatus::Held)).await?;
+        let rejected = store
+            .moderate(held.id, PostStatus::Rejected, Some("Off topic"))
+            .await?;
+        let approved = store.moderate(held.id, PostStatus::Accepted, None).await?;
+        let post = store.get(held.id).await?.unwrap();
+
+        assert!(rejected);
+        assert!(!approved);
+        assert_eq!(PostStatus::Rejected, post.status);
+        assert_eq!(Some("Off topic".to_string()), post.reason);
+        assert!(store.held().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn mark_digested_removes_posts_from_next_digest(pool: PgPool) -> Result<()> {
+        let mut store = PsqlPostStore { pool };
+
+        let post = store.create(new_post(PostStatus::Accepted)).await?;
+        store.mark_digested(&[post.id]).await?;
+
+        assert!(store.undigested().await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::store::RateLimitStore;
+
+pub struct PsqlRateLimitStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlRateLimitStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl RateLimitStore for PsqlRateLimitStore {
+    async fn hit(&mut self, key: &str, window_start: DateTime<Utc>) -> Result<i64> {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO rate_limits(key, window_start)
+            VALUES ($1, $2)
+            ON CONFLICT (key, window_start) DO UPDATE SET hits = rate_limits.hits + 1
+            RETURNING hits
+            "#,
+            key,
+            window_start
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.hits.into())
+    }
+
+    async fn prune(&mut self, before: DateTime<Utc>) -> Result<()> {
+        sqlx::query!("DELETE FROM rate_limits WHERE window_start < $1", before)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn hit_counts_per_key_and_window(pool: PgPool) -> Result<()> {
+        let mut store = PsqlRateLimitStore::from(pool);
+        let window = Utc::now();
+        let next = window + Duration::hours(1);
+
+        assert_eq!(1, store.hit("ip:127.0.0.1", window).await?);
+        assert_eq!(2, store.hit("ip:127.0.0.1", window).await?);
+        assert_eq!(1, store.hit("email:ada@example.org", window).await?);
+        assert_eq!(1, store.hit("ip:127.0.0.1", next).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn prune_forgets_old_windows(pool: PgPool) -> Result<()> {
+        let mut store = PsqlRateLimitStore::from(pool);
+        let window = Utc::now() - Duration::hours(2);
+        store.hit("ip:127.0.0.1", window).await?;
+        store.hit("ip:127.0.0.1", window).await?;
+
+        store.prune(Utc::now() - Duration::hours(1)).await?;
+
+        assert_eq!(1, store.hit("ip:127.0.0.1", window).await?);
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
 use sqlx::{PgPool, Pool, Postgres};
 
 use crate::{
-    model::{Email, NewSubscriber, Subscriber},
+    model::{Email, NewSubscriber, Subscriber, SubscriberStatus},
     store::SubscriberStore,
 };
 
@@ -18,14 +19,24 @@ impl From<PgPool> for PsqlSubscriberStore {
 
 impl SubscriberStore for PsqlSubscriberStore {
     async fn create(&mut self, new_subscriber: NewSubscriber) -> Result<Subscriber> {
+        self.create_with_status(new_subscriber, SubscriberStatus::Active)
+            .await
+    }
+
+    async fn create_with_status(
+        &mut self,
+        new_subscriber: NewSubscriber,
+        status: SubscriberStatus,
+    ) -> Result<Subscriber> {
         let row = sqlx::query!(
             r#"
-            INSERT INTO subscribers(email)
-            VALUES ($1)
+            INSERT INTO subscribers(email, status)
+            VALUES ($1, $2)
             ON CONFLICT (email) DO UPDATE SET email = EXCLUDED.email
             RETURNING *
             "#,
             new_subscriber.email.0,
+            status.as_str(),
         )
         .fetch_one(&self.pool)
         .await?;
@@ -33,19 +44,27 @@ impl SubscriberStore for PsqlSubscriberStore {
         Ok(Subscriber {
             id: row.id,
             email: Email::from(row.email),
+            status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+            digest: row.digest,
+            timezone: row.timezone,
         })
     }
 
     async fn all(&self) -> Result<Vec<Subscriber>> {
-        Ok(sqlx::query!("SELECT * FROM SUBSCRIBERS")
+        sqlx::query!("SELECT * FROM SUBSCRIBERS")
             .fetch_all(&self.pool)
             .await?
             .into_iter()
-            .map(|row| Subscriber {
-                id: row.id,
-                email: Email::from(row.email),
+            .map(|row| {
+                Ok(Subscriber {
+                    id: row.id,
+                    email: Email::from(row.email),
+                    status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                    digest: row.digest,
+                    timezone: row.timezone,
+                })
             })
-            .collect())
+            .collect()
     }
 
     async fn delete(&mut self, email: &Email) -> Result<()> {
@@ -54,6 +73,230 @@ impl SubscriberStore for PsqlSubscriberStore {
             .await?;
         Ok(())
     }
+
+    async fn find(&self, email: &Email) -> Result<Option<Subscriber>> {
+        sqlx::query!("SELECT * FROM subscribers WHERE email = $1", email.0)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| {
+                Ok(Subscriber {
+                    id: row.id,
+                    email: Email::from(row.email),
+                    status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                    digest: row.digest,
+                    timezone: row.timezone,
+                })
+            })
+            .transpose()
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Subscriber>> {
+        sqlx::query!("SELECT * FROM subscribers WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| {
+                Ok(Subscriber {
+                    id: row.id,
+                    email: Email::from(row.email),
+                    status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                    digest: row.digest,
+                    timezone: row.timezone,
+                })
+            })
+            .transpose()
+    }
+
+    async fn set_status(&mut self, email: &Email, status: SubscriberStatus) -> Result<()> {
+        sqlx::query!(
+            "UPDATE subscribers SET status = $2 WHERE email = $1",
+            email.0,
+            status.as_str()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn change_status(
+        &mut self,
+        email: &Email,
+        from: SubscriberStatus,
+        to: SubscriberStatus,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE subscribers SET status = $3 WHERE email = $1 AND status = $2",
+            email.0,
+            from.as_str(),
+            to.as_str()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_digest(&mut self, email: &Email, digest: bool) -> Result<()> {
+        sqlx::query!(
+            "UPDATE subscribers SET digest = $2 WHERE email = $1",
+            email.0,
+            digest
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_soft_bounce(&mut self, email: &Email) -> Result<i32> {
+        Ok(sqlx::query!(
+            r#"
+            UPDATE subscribers SET soft_bounces = soft_bounces + 1
+            WHERE email = $1
+            RETURNING soft_bounces
+            "#,
+            email.0
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .soft_bounces)
+    }
+
+    async fn set_timezone(&mut self, email: &Email, timezone: Option<&str>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE subscribers SET timezone = $2 WHERE email = $1",
+            email.0,
+            timezone
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn tags(&self, id: i32) -> Result<Vec<String>> {
+        Ok(sqlx::query!(
+            "SELECT tag FROM subscriber_tags WHERE subscriber_id = $1 ORDER BY tag",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.tag)
+        .collect())
+    }
+
+    async fn add_tag(&mut self, id: i32, tag: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO subscriber_tags(subscriber_id, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            id,
+            tag
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn remove_tag(&mut self, id: i32, tag: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM subscriber_tags WHERE subscriber_id = $1 AND tag = $2",
+            id,
+            tag
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn attributes(&self, id: i32) -> Result<Map<String, Value>> {
+        let attributes = sqlx::query!("SELECT attributes FROM subscribers WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.attributes);
+        match attributes {
+            Some(Value::Object(attributes)) => Ok(attributes),
+            _ => Ok(Map::new()),
+        }
+    }
+
+    async fn set_attributes(&mut self, id: i32, attributes: Map<String, Value>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE subscribers SET attributes = jsonb_strip_nulls(attributes || $2) WHERE id = $1",
+            id,
+            Value::Object(attributes),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        status: Option<SubscriberStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Subscriber>> {
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        sqlx::query!(
+            r#"
+            SELECT * FROM subscribers
+            WHERE email ILIKE $1 AND ($2::text IS NULL OR status = $2)
+            ORDER BY id
+            LIMIT $3 OFFSET $4
+            "#,
+            pattern,
+            status.map(|status| status.as_str()),
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(Subscriber {
+                id: row.id,
+                email: Email::from(row.email),
+                status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+                digest: row.digest,
+                timezone: row.timezone,
+            })
+        })
+        .collect()
+    }
+
+    async fn count_by_status(&self) -> Result<Vec<(SubscriberStatus, i64)>> {
+        sqlx::query!(
+            r#"
+            SELECT status, count(*) AS "count!" FROM subscribers
+            GROUP BY status ORDER BY status
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let status = row.status.try_into().map_err(|e: String| anyhow!(e))?;
+            Ok((status, row.count))
+        })
+        .collect()
+    }
+
+    async fn count_by_tag(&self) -> Result<Vec<(String, i64)>> {
+        Ok(sqlx::query!(
+            r#"
+            SELECT tag, count(*) AS "count!" FROM subscriber_tags
+            GROUP BY tag ORDER BY tag
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.tag, row.count))
+        .collect())
+    }
 }
 
 #[cfg(test)]
@@ -89,6 +332,26 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn create_with_status_keeps_existing_status(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let new_subscriber = NewSubscriber {
+            email: Email::from("test@email.com"),
+        };
+
+        let pending = store
+            .create_with_status(new_subscriber.clone(), SubscriberStatus::Pending)
+            .await?;
+        let existing = store
+            .create_with_status(new_subscriber, SubscriberStatus::Active)
+            .await?;
+
+        assert_eq!(SubscriberStatus::Pending, pending.status);
+        assert_eq!(SubscriberStatus::Pending, existing.status);
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn delete_removes_subscriber(pool: PgPool) -> Result<()> {
         let mut store = PsqlSubscriberStore { pool };
@@ -122,4 +385,191 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn set_status_updates_subscriber(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.set_status(&email, SubscriberStatus::Bounced).await?;
+        let subscriber = store.find(&email).await?.unwrap();
+
+        assert_eq!(SubscriberStatus::Bounced, subscriber.status);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn change_status_only_changes_the_expected_status(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let email = Email::from("test@email.com");
+        store
+            .create_with_status(
+                NewSubscriber {
+                    email: email.clone(),
+                },
+                SubscriberStatus::Pending,
+            )
+            .await?;
+
+        let first = store
+            .change_status(&email, SubscriberStatus::Pending, SubscriberStatus::Active)
+            .await?;
+        let second = store
+            .change_status(&email, SubscriberStatus::Pending, SubscriberStatus::Active)
+            .await?;
+        let subscriber = store.find(&email).await?.unwrap();
+
+        assert!(first);
+        assert!(!second);
+        assert_eq!(SubscriberStatus::Active, subscriber.status);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn set_digest_updates_subscriber(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.set_digest(&email, true).await?;
+        let subscriber = store.find(&email).await?.unwrap();
+
+        assert!(subscriber.digest);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn set_timezone_updates_subscriber(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.set_timezone(&email, Some("Europe/Berlin")).await?;
+        let subscriber = store.find(&email).await?.unwrap();
+
+        assert_eq!(Some("Europe/Berlin".to_string()), subscriber.timezone);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn add_soft_bounce_counts_bounces(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let email = Email::from("test@email.com");
+        store
+            .create(NewSubscriber {
+                email: email.clone(),
+            })
+            .await?;
+
+        store.add_soft_bounce(&email).await?;
+        let soft_bounces = store.add_soft_bounce(&email).await?;
+
+        assert_eq!(2, soft_bounces);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn add_tag_reports_new_tags(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let subscriber = store
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+
+        let added = store.add_tag(subscriber.id, "trial").await?;
+        let again = store.add_tag(subscriber.id, "trial").await?;
+        store.add_tag(subscriber.id, "beta").await?;
+        store.remove_tag(subscriber.id, "beta").await?;
+
+        assert!(added);
+        assert!(!again);
+        assert_eq!(vec!["trial".to_string()], store.tags(subscriber.id).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn set_attributes_merges_attributes(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        let subscriber = store
+            .create(NewSubscriber {
+                email: Email::from("test@email.com"),
+            })
+            .await?;
+        let first = serde_json::json!({"name": "Ada", "plan": "free"});
+        let second = serde_json::json!({"plan": "pro", "name": null});
+
+        store
+            .set_attributes(subscriber.id, first.as_object().unwrap().clone())
+            .await?;
+        store
+            .set_attributes(subscriber.id, second.as_object().unwrap().clone())
+            .await?;
+        let attributes = store.attributes(subscriber.id).await?;
+
+        assert_eq!(1, attributes.len());
+        assert_eq!(Some(&Value::from("pro")), attributes.get("plan"));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn search_matches_part_of_address(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSubscriberStore { pool };
+        for email in ["ada@example.org", "grace@example.org", "ada_l@example.com"] {
+            store
+                .create(NewSubscriber {
+                    email: Email::from(email),
+                })
+                .await?;
+        }
+        let grace = Email::from("grace@example.org");
+        store.set_status(&grace, SubscriberStatus::Bounced).await?;
+        let id = store.find(&grace).await?.unwrap().id;
+        store.add_tag(id, "vip").await?;
+
+        let ada = store.search("ADA", None, 10, 0).await?;
+        let underscore = store.search("a_l", None, 10, 0).await?;
+        let bounced = store
+            .search("example", Some(SubscriberStatus::Bounced), 10, 0)
+            .await?;
+        let second_page = store.search("example", None, 2, 2).await?;
+
+        assert_eq!(2, ada.len());
+        assert_eq!(1, underscore.len());
+        assert_eq!(
+            vec![grace.clone()],
+            bounced.into_iter().map(|s| s.email).collect::<Vec<_>>()
+        );
+        assert_eq!(1, second_page.len());
+        assert_eq!(
+            vec![
+                (SubscriberStatus::Active, 2),
+                (SubscriberStatus::Bounced, 1)
+            ],
+            store.count_by_status().await?
+        );
+        assert_eq!(vec![("vip".to_string(), 1)], store.count_by_tag().await?);
+
+        Ok(())
+    }
 }
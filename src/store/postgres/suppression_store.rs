@@ -0,0 +1,103 @@
+use anyhow::Result;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{Email, Suppression},
+    store::SuppressionStore,
+};
+
+pub struct PsqlSuppressionStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlSuppressionStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl SuppressionStore for PsqlSuppressionStore {
+    async fn add(&mut self, email: &Email, reason: &str) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO suppressions(email, reason)
+            VALUES ($1, $2)
+            ON CONFLICT (email) DO NOTHING
+            "#,
+            email.0,
+            reason
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn contains(&self, email: &Email) -> Result<bool> {
+        Ok(
+            sqlx::query!("SELECT email FROM suppressions WHERE email = $1", email.0)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn all(&self) -> Result<Vec<Suppression>> {
+        Ok(
+            sqlx::query!("SELECT * FROM suppressions ORDER BY created_at DESC, email")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row| Suppression {
+                    email: Email::from(row.email),
+                    reason: row.reason,
+                    created_at: row.created_at,
+                })
+                .collect(),
+        )
+    }
+
+    async fn remove(&mut self, email: &Email) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM suppressions WHERE email = $1", email.0)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn add_suppresses_email(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSuppressionStore { pool };
+        let email = Email::from("test@email.com");
+
+        store.add(&email, "complaint").await?;
+        store.add(&email, "complaint").await?;
+
+        assert!(store.contains(&email).await?);
+        assert!(!store.contains(&Email::from("other@email.com")).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn remove_lifts_suppression(pool: PgPool) -> Result<()> {
+        let mut store = PsqlSuppressionStore { pool };
+        let email = Email::from("test@email.com");
+        store.add(&email, "admin").await?;
+
+        let listed = store.all().await?;
+        let removed = store.remove(&email).await?;
+        let again = store.remove(&email).await?;
+
+        assert_eq!(1, listed.len());
+        assert_eq!("admin", listed[0].reason);
+        assert!(removed);
+        assert!(!again);
+        assert!(!store.contains(&email).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{NewTemplate, Template},
+    store::TemplateStore,
+};
+
+pub struct PsqlTemplateStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlTemplateStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl TemplateStore for PsqlTemplateStore {
+    async fn save(&mut self, new_template: NewTemplate) -> Result<Template> {
+        Ok(sqlx::query_as!(
+            Template,
+            r#"
+            INSERT INTO templates(name, subject, text, html)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (name) DO UPDATE
+            SET subject = EXCLUDED.subject, text = EXCLUDED.text, html = EXCLUDED.html
+            RETURNING id, name, subject, text, html, created_at
+            "#,
+            new_template.name,
+            new_template.subject,
+            new_template.text,
+            new_template.html,
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Template>> {
+        Ok(sqlx::query_as!(
+            Template,
+            "SELECT id, name, subject, text, html, created_at FROM templates WHERE id = $1",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn find(&self, name: &str) -> Result<Option<Template>> {
+        Ok(sqlx::query_as!(
+            Template,
+            "SELECT id, name, subject, text, html, created_at FROM templates WHERE name = $1",
+            name
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn all(&self) -> Result<Vec<Template>> {
+        Ok(sqlx::query_as!(
+            Template,
+            "SELECT id, name, subject, text, html, created_at FROM templates ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_template(subject: &str) -> NewTemplate {
+        NewTemplate {
+            name: "welcome".to_string(),
+            subject: subject.to_string(),
+            text: "Hi {{ email }}".to_string(),
+            html: None,
+        }
+    }
+
+    #[sqlx::test]
+    async fn save_replaces_template_with_same_name(pool: PgPool) -> Result<()> {
+        let mut store = PsqlTemplateStore { pool };
+
+        let first = store.save(new_template("Welcome")).await?;
+        let second = store.save(new_template("Welcome aboard")).await?;
+        let found = store.find("welcome").await?.unwrap();
+
+        assert_eq!(first.id, second.id);
+        assert_eq!("Welcome aboard", found.subject);
+        assert_eq!(found.id, store.get(first.id).await?.unwrap().id);
+        assert_eq!(1, store.all().await?.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{NewWarmupPlan, WarmupPlan},
+    store::WarmupStore,
+};
+
+pub struct PsqlWarmupStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlWarmupStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl WarmupStore for PsqlWarmupStore {
+    async fn replace(&mut self, new_plan: NewWarmupPlan) -> Result<WarmupPlan> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM warmup_plans")
+            .execute(&mut transaction)
+            .await?;
+        let plan = sqlx::query_as!(
+            WarmupPlan,
+            r#"
+            INSERT INTO warmup_plans(name, starts_on, daily_caps)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            new_plan.name,
+            new_plan.starts_on,
+            &new_plan.daily_caps,
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(plan)
+    }
+
+    async fn current(&self) -> Result<Option<WarmupPlan>> {
+        Ok(sqlx::query_as!(
+            WarmupPlan,
+            "SELECT * FROM warmup_plans ORDER BY id DESC LIMIT 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn remove(&mut self) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM warmup_plans")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn reserve(
+        &mut self,
+        day: NaiveDate,
+        start: DateTime<Utc>,
+        cap: i64,
+        wanted: i64,
+    ) -> Result<i64> {
+        let mut transaction = self.pool.begin().await?;
+        // A day starts out with what was sent before the plan counted it.
+        sqlx::query!(
+            r#"
+            INSERT INTO warmup_days(day, reserved)
+            SELECT $1, count(*) FROM outbox
+            WHERE campaign_id IS NOT NULL AND sent_at >= $2
+            ON CONFLICT (day) DO NOTHING
+            "#,
+            day,
+            start,
+        )
+        .execute(&mut transaction)
+        .await?;
+        let reserved = sqlx::query!(
+            "SELECT reserved FROM warmup_days WHERE day = $1 FOR UPDATE",
+            day
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .reserved;
+        let granted = wanted.min(cap - reserved).max(0);
+        sqlx::query!(
+            "UPDATE warmup_days SET reserved = reserved + $2 WHERE day = $1",
+            day,
+            granted,
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(granted)
+    }
+
+    async fn release(&mut self, day: NaiveDate, count: i64) -> Result<()> {
+        sqlx::query!(
+            "UPDATE warmup_days SET reserved = reserved - $2 WHERE day = $1",
+            day,
+            count,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn new_plan(name: &str) -> NewWarmupPlan {
+        NewWarmupPlan {
+            name: name.to_string(),
+            starts_on: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+            daily_caps: vec![50, 100],
+        }
+    }
+
+    #[sqlx::test]
+    async fn replace_keeps_only_newest_plan(pool: PgPool) -> Result<()> {
+        let mut store = PsqlWarmupStore { pool };
+
+        store.replace(new_plan("First")).await?;
+        let second = store.replace(new_plan("Second")).await?;
+        let current = store.current().await?;
+        let removed = store.remove().await?;
+
+        assert_eq!(Some(second.id), current.map(|plan| plan.id));
+        assert_eq!(vec![50, 100], second.daily_caps);
+        assert!(removed);
+        assert!(store.current().await?.is_none());
+        assert!(!store.remove().await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn reservations_stop_at_the_cap(pool: PgPool) -> Result<()> {
+        let mut store = PsqlWarmupStore { pool };
+        let day = NaiveDate::from_ymd_opt(2023, 6, 1).unwrap();
+        let start = Utc::now();
+
+        let first = store.reserve(day, start, 5, 3).await?;
+        let second = store.reserve(day, start, 5, 3).await?;
+        let full = store.reserve(day, start, 5, 3).await?;
+        store.release(day, 1).await?;
+        let released = store.reserve(day, start, 5, 3).await?;
+
+        assert_eq!((3, 2, 0, 1), (first, second, full, released));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{PgPool, Pool, Postgres};
+
+use crate::{
+    model::{NewWebhook, Webhook, WebhookCall, WebhookDelivery, WebhookEvent},
+    store::WebhookStore,
+};
+
+pub struct PsqlWebhookStore {
+    pool: Pool<Postgres>,
+}
+
+impl From<PgPool> for PsqlWebhookStore {
+    fn from(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+struct WebhookRow {
+    id: i32,
+    url: String,
+    events: Vec<String>,
+    secret: String,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<WebhookRow> for Webhook {
+    type Error = anyhow::Error;
+
+    fn try_from(row: WebhookRow) -> Result<Self> {
+        Ok(Webhook {
+            id: row.id,
+            url: row.url,
+            events: row
+                .events
+                .into_iter()
+                .map(WebhookEvent::try_from)
+                .collect::<Result<_, String>>()
+                .map_err(|e| anyhow!(e))?,
+            secret: row.secret,
+            created_at: row.created_at,
+        })
+    }
+}
+
+struct WebhookDeliveryRow {
+    id: i64,
+    webhook_id: i32,
+    event: String,
+    payload: Value,
+    status: String,
+    attempts: i32,
+    response_status: Option<i32>,
+    error: Option<String>,
+    next_attempt_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<WebhookDeliveryRow> for WebhookDelivery {
+    type Error = anyhow::Error;
+
+    fn try_from(row: WebhookDeliveryRow) -> Result<Self> {
+        Ok(WebhookDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event.try_into().map_err(|e: String| anyhow!(e))?,
+            payload: row.payload,
+            status: row.status.try_into().map_err(|e: String| anyhow!(e))?,
+            attempts: row.attempts,
+            response_status: row.response_status,
+            error: row.error,
+            next_attempt_at: row.next_attempt_at,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+impl WebhookStore for PsqlWebhookStore {
+    async fn create(&mut self, new_webhook: NewWebhook, secret: &str) -> Result<Webhook> {
+        let events: Vec<String> = new_webhook
+            .events
+            .iter()
+            .map(|event| event.as_str().to_string())
+            .collect();
+        sqlx::query_as!(
+            WebhookRow,
+            "INSERT INTO webhooks(url, events, secret) VALUES ($1, $2, $3) RETURNING *",
+            new_webhook.url,
+            &events,
+            secret,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .try_into()
+    }
+
+    async fn all(&self) -> Result<Vec<Webhook>> {
+        sqlx::query_as!(WebhookRow, "SELECT * FROM webhooks ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(Webhook::try_from)
+            .collect()
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Webhook>> {
+        sqlx::query_as!(WebhookRow, "SELECT * FROM webhooks WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(Webhook::try_from)
+            .transpose()
+    }
+
+    async fn delete(&mut self, id: i32) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM webhooks WHERE id = $1", id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn enqueue(&mut self, event: WebhookEvent, payload: &Value) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries(webhook_id, event, payload)
+            SELECT id, $1, $2 FROM webhooks WHERE $1 = ANY(events)
+            "#,
+            event.as_str(),
+            payload,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn claim(&mut self, limit: i64) -> Result<Vec<WebhookCall>> {
+        // Should we crash mid-call, the claim runs out and they are retried.
+        Ok(sqlx::query_as!(
+            WebhookCall,
+            r#"
+            UPDATE webhook_deliveries d SET next_attempt_at = now() + interval '10 minutes'
+            FROM webhooks w
+            WHERE w.id = d.webhook_id AND d.id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= now()
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING d.id, w.url, w.secret, d.payload, d.attempts
+            "#,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn delivered(&mut self, id: i64, response_status: i32) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries SET
+                status = 'delivered',
+                attempts = attempts + 1,
+                response_status = $2,
+                error = NULL,
+                delivered_at = now()
+            WHERE id = $1
+            "#,
+            id,
+            response_status,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn failed(
+        &mut self,
+        id: i64,
+        response_status: Option<i32>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries SET
+                attempts = attempts + 1,
+                response_status = $2,
+                error = $3,
+                status = CASE WHEN $4::timestamptz IS NULL THEN 'failed' ELSE 'pending' END,
+                next_attempt_at = COALESCE($4, next_attempt_at)
+            WHERE id = $1
+            "#,
+            id,
+            response_status,
+            error,
+            retry_at,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn deliveries(&self, webhook_id: i32, limit: i64) -> Result<Vec<WebhookDelivery>> {
+        sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            SELECT * FROM webhook_deliveries WHERE webhook_id = $1
+            ORDER BY id DESC
+            LIMIT $2
+            "#,
+            webhook_id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(WebhookDelivery::try_from)
+        .collect()
+    }
+
+    async fn replay(&mut self, webhook_id: i32, id: i64) -> Result<Option<WebhookDelivery>> {
+        sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            INSERT INTO webhook_deliveries(webhook_id, event, payload)
+            SELECT webhook_id, event, payload FROM webhook_deliveries
+            WHERE webhook_id = $1 AND id = $2
+            RETURNING *
+            "#,
+            webhook_id,
+            id,
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(WebhookDelivery::try_from)
+        .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::model::WebhookStatus;
+
+    use super::*;
+
+    async fn create(store: &mut PsqlWebhookStore, events: Vec<WebhookEvent>) -> Result<Webhook> {
+        store
+            .create(
+                NewWebhook {
+                    url: "https://crm.example.com/hooks".to_string(),
+                    events,
+                    secret: None,
+                },
+                "whsec_test",
+            )
+            .await
+    }
+
+    #[sqlx::test]
+    async fn enqueue_only_reaches_endpoints_wanting_event(pool: PgPool) -> Result<()> {
+        let mut store = PsqlWebhookStore { pool };
+        let subscriptions = create(&mut store, vec![WebhookEvent::Subscribed]).await?;
+        let deliveries = create(&mut store, vec![WebhookEvent::Sent]).await?;
+
+        let queued = store
+            .enqueue(
+                WebhookEvent::Subscribed,
+                &json!({"email": "ada@example.org"}),
+            )
+            .await?;
+        let calls = store.claim(10).await?;
+        let again = store.claim(10).await?;
+
+        assert_eq!(1, queued);
+        assert_eq!(1, calls.len());
+        assert_eq!("whsec_test", calls[0].secret);
+        assert!(again.is_empty());
+        assert_eq!(1, store.deliveries(subscriptions.id, 10).await?.len());
+        assert!(store.deliveries(deliveries.id, 10).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn replay_queues_copy_of_delivery(pool: PgPool) -> Result<()> {
+        let mut store = PsqlWebhookStore { pool };
+        let webhook = create(&mut store, vec![WebhookEvent::Subscribed]).await?;
+        store
+            .enqueue(
+                WebhookEvent::Subscribed,
+                &json!({"email": "ada@example.org"}),
+            )
+            .await?;
+        let call = store.claim(1).await?.remove(0);
+        store
+            .failed(call.id, Some(500), "Server error", None)
+            .await?;
+
+        let replayed = store.replay(webhook.id, call.id).await?.unwrap();
+        let log = store.deliveries(webhook.id, 10).await?;
+        let elsewhere = store.replay(webhook.id + 1, call.id).await?;
+
+        assert_eq!(WebhookStatus::Pending, replayed.status);
+        assert_eq!(call.payload, replayed.payload);
+        assert_eq!(
+            vec![replayed.id, call.id],
+            log.iter().map(|d| d.id).collect::<Vec<_>>()
+        );
+        assert_eq!(WebhookStatus::Failed, log[1].status);
+        assert_eq!(Some(500), log[1].response_status);
+        assert!(elsewhere.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use log::error;
+
+use crate::{
+    data::ApplicationData,
+    model::{Email, EventKind, NewEvent, Subscriber},
+    store::{EventStore, PsqlEventStore, PsqlSubscriberStore, SubscriberStore},
+};
+
+/// Counts campaign mail that went out towards the campaign's report, which
+/// its rates are based on.
+pub(crate) async fn delivered(data: &ApplicationData, recipient: &str, campaign_id: i32) {
+    let subscribers = PsqlSubscriberStore::from(data.pool.clone());
+    let subscriber = match subscribers.find(&Email::from(recipient)).await {
+        Ok(Some(subscriber)) => subscriber,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to record delivery to {recipient}: {e}");
+            return;
+        }
+    };
+    record(data, EventKind::Delivered, subscriber.id, Some(campaign_id)).await;
+}
+
+/// Counts a subscriber leaving towards the campaign they left from, or,
+/// when they didn't leave through a campaign's link, the last one they got.
+/// Has to be called before the subscriber is deleted.
+pub(crate) async fn unsubscribed(
+    data: &ApplicationData,
+    subscriber: &Subscriber,
+    campaign_id: Option<i32>,
+) {
+    let campaign_id = match campaign_id {
+        Some(campaign_id) => Some(campaign_id),
+        None => match PsqlEventStore::from(data.pool.clone())
+            .last_delivered(subscriber.id)
+            .await
+        {
+            Ok(campaign_id) => campaign_id,
+            Err(e) => {
+                error!(
+                    "Failed to find the last campaign of {:?}: {e}",
+                    subscriber.email
+                );
+                None
+            }
+        },
+    };
+    record(data, EventKind::Unsubscribe, subscriber.id, campaign_id).await;
+}
+
+async fn record(
+    data: &ApplicationData,
+    kind: EventKind,
+    subscriber_id: i32,
+    campaign_id: Option<i32>,
+) {
+    let event = NewEvent {
+        kind,
+        subscriber_id,
+        campaign_id,
+        url: None,
+        user_agent: None,
+    };
+    if let Err(e) = PsqlEventStore::from(data.pool.clone()).record(event).await {
+        error!("Failed to record {} event: {e}", kind.as_str());
+    }
+}
@@ -0,0 +1,7 @@
+mod events;
+mod token;
+mod tracker;
+
+pub(crate) use events::{delivered, unsubscribed};
+pub use token::TrackingToken;
+pub use tracker::Tracker;
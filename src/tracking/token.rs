@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Identifies who an open or click belongs to. Click tokens carry their
+/// destination, so only URLs we signed can ever be redirected to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackingToken {
+    #[serde(rename = "s")]
+    pub subscriber_id: i32,
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<i32>,
+    #[serde(rename = "u", default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
@@ -0,0 +1,167 @@
+use crate::{config::TrackingSettings, signing::Signer};
+
+use super::TrackingToken;
+
+#[derive(Clone, Debug)]
+pub struct Tracker {
+    enabled: bool,
+    url: String,
+    signer: Signer,
+}
+
+impl From<TrackingSettings> for Tracker {
+    fn from(settings: TrackingSettings) -> Self {
+        Self {
+            enabled: settings.enabled,
+            url: settings.url.trim_end_matches('/').to_string(),
+            signer: Signer::new(&settings.secret),
+        }
+    }
+}
+
+impl Tracker {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn open_url(&self, subscriber_id: i32, campaign_id: Option<i32>) -> String {
+        let token = self.signer.sign(&TrackingToken {
+            subscriber_id,
+            campaign_id,
+            url: None,
+        });
+        format!("{}/t/o/{token}", self.url)
+    }
+
+    pub fn click_url(&self, subscriber_id: i32, campaign_id: Option<i32>, url: &str) -> String {
+        let token = self.signer.sign(&TrackingToken {
+            subscriber_id,
+            campaign_id,
+            url: Some(url.to_string()),
+        });
+        format!("{}/t/c/{token}", self.url)
+    }
+
+    pub fn decode(&self, token: &str) -> Option<TrackingToken> {
+        self.signer.verify(token)
+    }
+
+    /// Rewrites every http(s) link in an outgoing HTML body to go through the
+    /// click redirector and adds the open pixel. Does nothing when tracking is
+    /// turned off.
+    pub fn instrument(&self, html: &str, subscriber_id: i32, campaign_id: Option<i32>) -> String {
+        if !self.enabled {
+            return html.to_string();
+        }
+
+        // Lowercasing keeps byte offsets, so positions found in it hold for
+        // `html` too.
+        let lowercase = html.to_ascii_lowercase();
+        let mut output = String::with_capacity(html.len());
+        let mut copied = 0;
+        while let Some((start, end)) = find_href(html, &lowercase, copied) {
+            let url = html[start..end].replace("&amp;", "&");
+            output.push_str(&html[copied..start]);
+            if url.starts_with("http://") || url.starts_with("https://") {
+                output.push_str(&self.click_url(subscriber_id, campaign_id, &url));
+            } else {
+                output.push_str(&html[start..end]);
+            }
+            copied = end;
+        }
+        output.push_str(&html[copied..]);
+
+        let pixel = format!(
+            r#"<img src="{}" width="1" height="1" alt="" style="display:none">"#,
+            self.open_url(subscriber_id, campaign_id)
+        );
+        match output.to_ascii_lowercase().rfind("</body>") {
+            Some(index) => output.insert_str(index, &pixel),
+            None => output.push_str(&pixel),
+        }
+        output
+    }
+}
+
+/// Finds the value of the next quoted `href` attribute from `offset` on,
+/// returning its byte range within `html`. `lowercase` is `html` in ASCII
+/// lowercase.
+fn find_href(html: &str, lowercase: &str, offset: usize) -> Option<(usize, usize)> {
+    let mut offset = offset;
+    while let Some(index) = lowercase[offset..].find("href") {
+        let mut position = offset + index + "href".len();
+        offset = position;
+
+        let after = &lowercase[position..];
+        let trimmed = after.trim_start();
+        if !trimmed.starts_with('=') {
+            continue;
+        }
+        position += after.len() - trimmed.len() + 1;
+
+        let after = &lowercase[position..];
+        let trimmed = after.trim_start();
+        let quote = match trimmed.chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => continue,
+        };
+        let start = position + after.len() - trimmed.len() + 1;
+        let end = start + html[start..].find(quote)?;
+        return Some((start, end));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(enabled: bool) -> Tracker {
+        Tracker::from(TrackingSettings {
+            enabled,
+            url: "https://mail.example.com/".to_string(),
+            secret: "secret".to_string(),
+        })
+    }
+
+    #[test]
+    fn click_url_round_trips_through_decode() {
+        let tracker = tracker(true);
+
+        let url = tracker.click_url(1, Some(2), "https://example.com/post");
+        let token = url.strip_prefix("https://mail.example.com/t/c/").unwrap();
+
+        assert_eq!(
+            Some(TrackingToken {
+                subscriber_id: 1,
+                campaign_id: Some(2),
+                url: Some("https://example.com/post".to_string()),
+            }),
+            tracker.decode(token)
+        );
+    }
+
+    #[test]
+    fn instrument_rewrites_links_and_adds_pixel() {
+        let tracker = tracker(true);
+        let html = r#"<body><a href="https://example.com/?a=1&amp;b=2">Read</a><a href='mailto:me@example.com'>Mail</a></body>"#;
+
+        let instrumented = tracker.instrument(html, 1, None);
+
+        let click = tracker.click_url(1, None, "https://example.com/?a=1&b=2");
+        let open = tracker.open_url(1, None);
+        assert_eq!(
+            format!(
+                r#"<body><a href="{click}">Read</a><a href='mailto:me@example.com'>Mail</a><img src="{open}" width="1" height="1" alt="" style="display:none"></body>"#
+            ),
+            instrumented
+        );
+    }
+
+    #[test]
+    fn instrument_leaves_html_alone_when_disabled() {
+        let html = r#"<a href="https://example.com">Read</a>"#;
+
+        assert_eq!(html, tracker(false).instrument(html, 1, None));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    data::ApplicationData,
+    model::{Subscriber, WarmupPlan, WarmupReport},
+    scheduling::at_local,
+    store::{
+        EventStore, OutboxStore, PsqlEventStore, PsqlOutboxStore, PsqlWarmupStore, WarmupStore,
+    },
+};
+
+// How far back opens and clicks count towards who gets mail first.
+const ENGAGEMENT_DAYS: i64 = 90;
+
+/// Campaign mail reserved against today's cap for one batch.
+#[derive(Debug, Clone)]
+pub(crate) struct Allowance {
+    day: NaiveDate,
+    remaining: i64,
+    // When the next day of the plan starts.
+    pub resume_at: DateTime<Utc>,
+}
+
+impl Allowance {
+    /// Counts a message against the cap, or returns when the next day
+    /// starts if today's cap is used up.
+    pub fn take(&mut self) -> Result<(), DateTime<Utc>> {
+        if self.remaining <= 0 {
+            return Err(self.resume_at);
+        }
+        self.remaining -= 1;
+        Ok(())
+    }
+
+    /// Returns a message taken but not sent.
+    pub fn give_back(&mut self) {
+        self.remaining += 1;
+    }
+}
+
+/// Reserves up to `wanted` campaign messages against today's cap, or
+/// returns `None` when no warm-up plan limits today. The reservation is
+/// kept in the database, so that every instance shares the cap.
+pub(crate) async fn reserve(data: &ApplicationData, wanted: i64) -> Result<Option<Allowance>> {
+    if wanted == 0 {
+        return Ok(None);
+    }
+    let mut store = PsqlWarmupStore::from(data.pool.clone());
+    let plan = match store.current().await? {
+        Some(plan) => plan,
+        None => return Ok(None),
+    };
+    let timezone = list_timezone(data)?;
+    let today = today(timezone);
+    let cap = match plan.cap(today) {
+        Some(cap) => cap,
+        None => return Ok(None),
+    };
+
+    let granted = store
+        .reserve(today, start_of(today, timezone), i64::from(cap), wanted)
+        .await?;
+    Ok(Some(Allowance {
+        day: today,
+        remaining: granted,
+        resume_at: start_of(today + Duration::days(1), timezone),
+    }))
+}
+
+/// Returns what was reserved but not sent, for later batches.
+pub(crate) async fn release(data: &ApplicationData, allowance: &Allowance) -> Result<()> {
+    if allowance.remaining <= 0 {
+        return Ok(());
+    }
+    PsqlWarmupStore::from(data.pool.clone())
+        .release(allowance.day, allowance.remaining)
+        .await
+}
+
+/// Recent opens and clicks of each subscriber while a warm-up plan is in
+/// place, so that the most engaged get campaign mail before anyone is
+/// carried over to the next day. Empty without a plan.
+pub(crate) async fn engagement(data: &ApplicationData) -> Result<Engagement> {
+    let plan = PsqlWarmupStore::from(data.pool.clone()).current().await?;
+    let today = today(list_timezone(data)?);
+    if !plan.is_some_and(|plan| in_force(&plan, today)) {
+        return Ok(Engagement::default());
+    }
+
+    Ok(Engagement(
+        PsqlEventStore::from(data.pool.clone())
+            .engagement(Utc::now() - Duration::days(ENGAGEMENT_DAYS))
+            .await?,
+    ))
+}
+
+/// Opens and clicks by subscriber id.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Engagement(HashMap<i32, i64>);
+
+impl Engagement {
+    pub fn of(&self, subscriber: &Subscriber) -> i64 {
+        self.0.get(&subscriber.id).copied().unwrap_or(0)
+    }
+}
+
+/// How the current warm-up plan is going, or `None` without one.
+pub(crate) async fn report(data: &ApplicationData) -> Result<Option<WarmupReport>> {
+    let plan = match PsqlWarmupStore::from(data.pool.clone()).current().await? {
+        Some(plan) => plan,
+        None => return Ok(None),
+    };
+    let timezone = list_timezone(data)?;
+    let today = today(timezone);
+    let outbox = PsqlOutboxStore::from(data.pool.clone());
+    Ok(Some(WarmupReport {
+        today,
+        day: plan.day(today),
+        cap: plan.cap(today),
+        sent: outbox
+            .campaign_sent_since(start_of(today, timezone))
+            .await?,
+        waiting: outbox.campaign_waiting().await?,
+        plan,
+    }))
+}
+
+/// Whether the plan limits `today` or a later day.
+fn in_force(plan: &WarmupPlan, today: NaiveDate) -> bool {
+    today < plan.starts_on + Duration::days(plan.daily_caps.len() as i64)
+}
+
+fn list_timezone(data: &ApplicationData) -> Result<Tz> {
+    data.list
+        .timezone
+        .parse()
+        .map_err(|e| anyhow!("Bad list timezone: {e}"))
+}
+
+fn today(timezone: Tz) -> NaiveDate {
+    Utc::now().with_timezone(&timezone).date_naive()
+}
+
+fn start_of(date: NaiveDate, timezone: Tz) -> DateTime<Utc> {
+    at_local(date.and_time(Default::default()), timezone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowance_runs_out_until_next_day() {
+        let resume_at = Utc::now() + Duration::hours(3);
+        let mut allowance = Allowance {
+            day: Utc::now().date_naive(),
+            remaining: 2,
+            resume_at,
+        };
+
+        assert!(allowance.take().is_ok());
+        assert!(allowance.take().is_ok());
+        assert_eq!(Err(resume_at), allowance.take());
+        allowance.give_back();
+        assert!(allowance.take().is_ok());
+    }
+
+    #[test]
+    fn in_force_until_last_day() {
+        let plan = WarmupPlan {
+            id: 1,
+            name: "New domain".to_string(),
+            starts_on: NaiveDate::from_ymd_opt(2023, 6, 10).unwrap(),
+            daily_caps: vec![50, 100],
+            created_at: Utc::now(),
+        };
+
+        assert!(in_force(
+            &plan,
+            NaiveDate::from_ymd_opt(2023, 6, 1).unwrap()
+        ));
+        assert!(in_force(
+            &plan,
+            NaiveDate::from_ymd_opt(2023, 6, 11).unwrap()
+        ));
+        assert!(!in_force(
+            &plan,
+            NaiveDate::from_ymd_opt(2023, 6, 12).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+mod worker;
+
+pub(crate) use worker::call_webhooks;
+
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::{error, info};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::{
+    data::ApplicationData,
+    model::{Subscriber, WebhookEvent},
+    store::{PsqlWebhookStore, WebhookStore},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Queues an event for every endpoint that wants it. Endpoints get
+/// `{"type": ..., "created_at": ..., "data": ...}`. What the event is about
+/// has already happened, so failing to queue it is only logged.
+pub(crate) async fn emit(data: &ApplicationData, event: WebhookEvent, fields: Value) {
+    if let Err(e) = enqueue(data, event, fields).await {
+        error!("Failed to queue {} webhooks: {e}", event.as_str());
+    }
+}
+
+async fn enqueue(data: &ApplicationData, event: WebhookEvent, fields: Value) -> Result<()> {
+    let payload = json!({
+        "type": event.as_str(),
+        "created_at": Utc::now(),
+        "data": fields,
+    });
+    let queued = PsqlWebhookStore::from(data.pool.clone())
+        .enqueue(event, &payload)
+        .await?;
+    if queued > 0 {
+        info!("Queued {} for {queued} webhooks", event.as_str());
+    }
+    Ok(())
+}
+
+/// Queues an event about a subscriber.
+pub(crate) async fn emit_subscriber(
+    data: &ApplicationData,
+    event: WebhookEvent,
+    subscriber: &Subscriber,
+) {
+    emit(
+        data,
+        event,
+        json!({
+            "subscriber_id": subscriber.id,
+            "email": subscriber.email.0,
+        }),
+    )
+    .await
+}
+
+/// The `Minimail-Signature` header of a call: when it was made and the
+/// HMAC-SHA256 of the timestamp, a dot and the body, in hex.
+pub(crate) fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("t={timestamp},v1={digest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signature = signature("whsec_test", 1_700_000_000, b"{}");
+
+        assert!(signature.starts_with("t=1700000000,v1="));
+        assert_eq!(16 + 64, signature.len());
+        assert_ne!(
+            super::signature("whsec_test", 1_700_000_001, b"{}"),
+            signature
+        );
+        assert_ne!(
+            super::signature("whsec_other", 1_700_000_000, b"{}"),
+            signature
+        );
+    }
+}
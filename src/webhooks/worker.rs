@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{error, info, warn};
+use reqwest::{header::CONTENT_TYPE, Client};
+
+use crate::{
+    data::ApplicationData,
+    model::WebhookCall,
+    store::{PsqlWebhookStore, WebhookStore},
+    webhooks::signature,
+};
+
+const BATCH: i64 = 50;
+
+/// Calls webhook endpoints with the events that are due, forever.
+pub(crate) async fn call_webhooks(data: ApplicationData) {
+    let mut interval = tokio::time::interval(Duration::from_secs(data.webhooks.interval));
+    let client = Client::builder()
+        .timeout(Duration::from_secs(data.webhooks.timeout))
+        .build()
+        .expect("Failed to build webhook client");
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = call_due(&data, &client).await {
+            error!("Failed to call webhooks: {e}");
+        }
+    }
+}
+
+async fn call_due(data: &ApplicationData, client: &Client) -> Result<()> {
+    let mut store = PsqlWebhookStore::from(data.pool.clone());
+
+    loop {
+        let calls = store.claim(BATCH).await?;
+        if calls.is_empty() {
+            return Ok(());
+        }
+        for webhook_call in calls {
+            call(data, client, &mut store, webhook_call).await?;
+        }
+    }
+}
+
+async fn call(
+    data: &ApplicationData,
+    client: &Client,
+    store: &mut PsqlWebhookStore,
+    webhook_call: WebhookCall,
+) -> Result<()> {
+    let body = serde_json::to_vec(&webhook_call.payload)?;
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(&webhook_call.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(
+            "Minimail-Signature",
+            signature(&webhook_call.secret, timestamp, &body),
+        )
+        .header("Minimail-Delivery", webhook_call.id.to_string())
+        .body(body)
+        .send()
+        .await;
+
+    let (response_status, error) = match response {
+        Ok(response) if response.status().is_success() => {
+            info!(
+                "Delivered webhook {} to {}",
+                webhook_call.id, webhook_call.url
+            );
+            return store
+                .delivered(webhook_call.id, response.status().as_u16().into())
+                .await;
+        }
+        Ok(response) => (
+            Some(response.status().as_u16().into()),
+            format!("Endpoint answered {}", response.status()),
+        ),
+        Err(e) => (None, e.to_string()),
+    };
+
+    // Backs off exponentially from `backoff` seconds.
+    let attempts = webhook_call.attempts + 1;
+    let retry_at = (attempts < data.webhooks.attempts).then(|| {
+        let delay = data.webhooks.backoff * 2u64.pow((attempts - 1).min(16) as u32);
+        Utc::now() + chrono::Duration::seconds(delay as i64)
+    });
+    warn!(
+        "Failed to deliver webhook {} to {} (attempt {attempts}): {error}",
+        webhook_call.id, webhook_call.url
+    );
+    store
+        .failed(webhook_call.id, response_status, &error, retry_at)
+        .await
+}
@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{post_json, spawn_app_with, TestApp};
+
+async fn spawn_tested(pool: PgPool) -> TestApp {
+    spawn_app_with(pool, |settings| settings.ab_test.interval = 1).await
+}
+
+/// Creates a campaign, a template and 40 active subscribers, and returns the
+/// ids of the campaign and the template.
+async fn prepare(app: &TestApp) -> (i64, i64) {
+    let campaign: Value = post_json(app, "/api/campaigns", json!({"name": "May"}))
+        .await
+        .json()
+        .await
+        .expect("Failed to parse campaign.");
+    let template: Value = post_json(
+        app,
+        "/api/templates",
+        json!({"name": "news", "subject": "News", "text": "Hello {{ email }}"}),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse template.");
+    for i in 0..40 {
+        sqlx::query!(
+            "INSERT INTO subscribers(email) VALUES ($1)",
+            format!("reader{i}@example.org")
+        )
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+    }
+    (
+        campaign["id"].as_i64().unwrap(),
+        template["id"].as_i64().unwrap(),
+    )
+}
+
+async fn start_test(app: &TestApp, campaign_id: i64, body: Value) -> reqwest::Response {
+    post_json(app, &format!("/api/campaigns/{campaign_id}/ab-test"), body).await
+}
+
+/// The subject of every queued message, by recipient.
+async fn queued_subjects(app: &TestApp) -> Vec<(String, String)> {
+    sqlx::query!("SELECT recipient, raw FROM outbox ORDER BY recipient")
+        .fetch_all(&app.pool)
+        .await
+        .expect("Failed to fetch outbox.")
+        .into_iter()
+        .map(|row| {
+            let subject = String::from_utf8_lossy(&row.raw)
+                .lines()
+                .find_map(|line| line.strip_prefix("Subject: ").map(str::to_string))
+                .expect("A campaign has a subject");
+            (row.recipient, subject)
+        })
+        .collect()
+}
+
+async fn report(app: &TestApp, campaign_id: i64) -> Value {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/api/campaigns/{campaign_id}/report",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse report.")
+}
+
+#[sqlx::test]
+async fn ab_test_sends_variants_to_test_group(pool: PgPool) {
+    // Arrange
+    let app = spawn_tested(pool).await;
+    let (campaign_id, template_id) = prepare(&app).await;
+
+    // Act
+    let response = start_test(
+        &app,
+        campaign_id,
+        json!({
+            "template_id": template_id,
+            "variants": [{"subject": "Subject A"}, {"subject": "Subject B", "from_name": "Ada"}],
+            "test_percent": 50,
+            "window_hours": 4
+        }),
+    )
+    .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let test: Value = response.json().await.unwrap();
+    let variants = test["variants"].as_array().unwrap();
+    assert_eq!(2, variants.len());
+    let tested =
+        variants[0]["recipients"].as_i64().unwrap() + variants[1]["recipients"].as_i64().unwrap();
+    assert!((5..=35).contains(&tested), "{tested} tested");
+    let queued = queued_subjects(&app).await;
+    assert_eq!(tested as usize, queued.len());
+    assert!(queued
+        .iter()
+        .all(|(_, subject)| subject == "Subject A" || subject == "Subject B"));
+    assert_eq!(
+        json!("B"),
+        report(&app, campaign_id).await["ab_test"]["variants"][1]["name"]
+    );
+}
+
+#[sqlx::test]
+async fn winner_goes_to_the_remainder(pool: PgPool) {
+    // Arrange
+    let app = spawn_tested(pool).await;
+    let (campaign_id, template_id) = prepare(&app).await;
+    start_test(
+        &app,
+        campaign_id,
+        json!({
+            "template_id": template_id,
+            "variants": [{"subject": "Subject A"}, {"subject": "Subject B"}],
+            "test_percent": 50,
+            "metric": "open"
+        }),
+    )
+    .await;
+    sqlx::query!(
+        r#"
+        INSERT INTO events(kind, subscriber_id, campaign_id)
+        SELECT 'open', r.subscriber_id, r.campaign_id
+        FROM campaign_recipients r JOIN campaign_variants v ON v.id = r.variant_id
+        WHERE v.name = 'B'
+        "#
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to record opens.");
+
+    // Act
+    sqlx::query!("UPDATE ab_tests SET decide_at = now()")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to end test window.");
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // Assert
+    let queued = queued_subjects(&app).await;
+    assert_eq!(40, queued.len());
+    let report = report(&app, campaign_id).await;
+    let variants = &report["ab_test"]["variants"];
+    assert_eq!(json!(true), variants[1]["winner"]);
+    assert_eq!(json!(1.0), variants[1]["open_rate"]);
+    assert_eq!(json!(0.0), variants[0]["open_rate"]);
+    let remainder = report["ab_test"]["remainder"].as_i64().unwrap();
+    let with_b = queued
+        .iter()
+        .filter(|(_, subject)| subject == "Subject B")
+        .count();
+    assert_eq!(
+        variants[1]["recipients"].as_i64().unwrap() + remainder,
+        with_b as i64
+    );
+    assert!(report["ab_test"]["decided_at"].is_string());
+}
+
+#[sqlx::test]
+async fn suppressed_addresses_get_neither_test_nor_winner(pool: PgPool) {
+    // Arrange
+    let app = spawn_tested(pool).await;
+    let (campaign_id, template_id) = prepare(&app).await;
+    sqlx::query!(
+        r#"
+        INSERT INTO suppressions(email, reason)
+        SELECT email, 'complaint' FROM subscribers WHERE email LIKE 'reader1%'
+        "#
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to suppress addresses.");
+
+    // Act
+    start_test(
+        &app,
+        campaign_id,
+        json!({
+            "template_id": template_id,
+            "variants": [{"subject": "Subject A"}, {"subject": "Subject B"}],
+            "test_percent": 50
+        }),
+    )
+    .await;
+    sqlx::query!("UPDATE ab_tests SET decide_at = now()")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to end test window.");
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // Assert
+    let queued = queued_subjects(&app).await;
+    assert_eq!(29, queued.len());
+    assert!(queued
+        .iter()
+        .all(|(recipient, _)| !recipient.starts_with("reader1")));
+}
+
+#[sqlx::test]
+async fn ab_test_needs_two_to_four_variants(pool: PgPool) {
+    // Arrange
+    let app = spawn_tested(pool).await;
+    let (campaign_id, template_id) = prepare(&app).await;
+
+    // Act
+    let response = start_test(
+        &app,
+        campaign_id,
+        json!({"template_id": template_id, "variants": [{"subject": "Only"}]}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+    assert!(queued_subjects(&app).await.is_empty());
+}
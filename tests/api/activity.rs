@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+/// A server-sent event: its id, name and data.
+type Event = (i64, String, Value);
+
+struct EventStream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl EventStream {
+    async fn next(&mut self) -> Event {
+        tokio::time::timeout(Duration::from_secs(10), self.read())
+            .await
+            .expect("No event was streamed.")
+    }
+
+    async fn read(&mut self) -> Event {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let block: String = self.buffer.drain(..end + 2).collect();
+                let (mut id, mut name, mut data) = (None, None, None);
+                for line in block.lines() {
+                    match line.split_once(':') {
+                        Some(("id", value)) => id = Some(value.trim().parse().unwrap()),
+                        Some(("event", value)) => name = Some(value.trim().to_string()),
+                        Some(("data", value)) => data = Some(serde_json::from_str(value).unwrap()),
+                        _ => {}
+                    }
+                }
+                // Comments that keep the stream open have none of these.
+                if let (Some(id), Some(name), Some(data)) = (id, name, data) {
+                    return (id, name, data);
+                }
+                continue;
+            }
+            let chunk = self
+                .response
+                .chunk()
+                .await
+                .expect("Failed to read stream.")
+                .expect("The stream ended.");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+async fn stream(app: &TestApp, types: &str, last_event_id: Option<i64>) -> EventStream {
+    let mut request = reqwest::Client::new()
+        .get(format!("{}/api/activity/stream?types={types}", app.address))
+        .bearer_auth("admin");
+    if let Some(id) = last_event_id {
+        request = request.header("Last-Event-ID", id.to_string());
+    }
+    let response = request.send().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    EventStream {
+        response,
+        buffer: String::new(),
+    }
+}
+
+async fn subscribe(app: &TestApp, email: &str) {
+    reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .form(&[("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+}
+
+#[sqlx::test]
+async fn stream_sends_wanted_activity_as_it_happens(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let mut events = stream(&app, "confirmed,unsubscribed", None).await;
+
+    // Act
+    subscribe(&app, "ada@example.org").await;
+    reqwest::Client::new()
+        .delete(format!(
+            "{}/api/subscribers?email=ada%40example.org",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let (confirmed_id, confirmed, subscriber) = events.next().await;
+    let (unsubscribed_id, unsubscribed, activity) = events.next().await;
+
+    // Assert
+    assert_eq!("confirmed", confirmed);
+    assert_eq!("confirmed", subscriber["type"]);
+    assert_eq!("ada@example.org", subscriber["data"]["email"]);
+    assert_eq!("unsubscribed", unsubscribed);
+    assert_eq!(unsubscribed_id, activity["id"]);
+    assert!(confirmed_id < unsubscribed_id);
+}
+
+#[sqlx::test]
+async fn stream_resumes_after_last_event_id(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    subscribe(&app, "ada@example.org").await;
+    subscribe(&app, "grace@example.org").await;
+    let ids: Vec<i64> = sqlx::query!("SELECT id FROM activity ORDER BY id")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+    // Act
+    let mut events = stream(&app, "signup", Some(ids[1])).await;
+    let (missed_id, missed, activity) = events.next().await;
+    subscribe(&app, "hedy@example.org").await;
+    let (_, live, later) = events.next().await;
+
+    // Assert
+    assert_eq!(4, ids.len());
+    assert_eq!(ids[2], missed_id);
+    assert_eq!("signup", missed);
+    assert_eq!("grace@example.org", activity["data"]["email"]);
+    assert_eq!("signup", live);
+    assert_eq!("hedy@example.org", later["data"]["email"]);
+}
+
+#[sqlx::test]
+async fn activity_reaches_streams_of_other_instances(pool: PgPool) {
+    // Arrange
+    let watched = spawn_app_with(pool.clone(), |_| {}).await;
+    let other = spawn_app_with(pool, |_| {}).await;
+    let mut events = stream(&watched, "signup", None).await;
+    // Gives the instances time to start listening.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    // Act
+    subscribe(&other, "ada@example.org").await;
+    let (_, name, activity) = events.next().await;
+
+    // Assert
+    assert_eq!("signup", name);
+    assert_eq!("ada@example.org", activity["data"]["email"]);
+}
+
+#[sqlx::test]
+async fn stream_needs_token_and_known_types(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let anonymous = client
+        .get(format!("{}/api/activity/stream", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let unknown = client
+        .get(format!(
+            "{}/api/activity/stream?types=signup,opened",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, anonymous.status().as_u16());
+    assert_eq!(422, unknown.status().as_u16());
+}
+
+#[sqlx::test]
+async fn stream_opens_with_stream_token_only(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let client = reqwest::Client::new();
+    let issued: Value = client
+        .post(format!("{}/api/activity/tokens", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let token = issued["token"].as_str().unwrap();
+    let open = |token: String| {
+        client
+            .get(format!("{}/api/activity/stream?token={token}", app.address))
+            .send()
+    };
+
+    // Act
+    let admin = open("admin".to_string())
+        .await
+        .expect("Failed to execute request.");
+    let streamed = open(token.to_string())
+        .await
+        .expect("Failed to execute request.");
+    let elsewhere = client
+        .get(format!("{}/api/subscribers", app.address))
+        .bearer_auth(token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, admin.status().as_u16());
+    assert_eq!(200, streamed.status().as_u16());
+    assert_eq!(401, elsewhere.status().as_u16());
+}
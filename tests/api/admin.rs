@@ -0,0 +1,300 @@
+use reqwest::{
+    header::{COOKIE, LOCATION, SET_COOKIE},
+    redirect::Policy,
+    Response,
+};
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+/// A browser signed in to the admin interface, keeping the form token of
+/// the last page.
+struct Admin {
+    client: reqwest::Client,
+    address: String,
+    cookie: String,
+    csrf: String,
+}
+
+impl Admin {
+    async fn sign_in(app: &TestApp) -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .unwrap();
+        let response = client
+            .post(format!("{}/admin/login", app.address))
+            .form(&[("password", "secret")])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(303, response.status().as_u16());
+        let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+        Self {
+            client,
+            address: app.address.clone(),
+            cookie: cookie.split(';').next().unwrap().to_string(),
+            csrf: String::new(),
+        }
+    }
+
+    async fn get(&mut self, path: &str) -> (u16, String) {
+        let response = self
+            .client
+            .get(format!("{}{path}", self.address))
+            .header(COOKIE, &self.cookie)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        let status = response.status().as_u16();
+        let page = response.text().await.unwrap();
+        if let Some((_, rest)) = page.split_once("name=\"csrf\" value=\"") {
+            self.csrf = rest.split('"').next().unwrap().to_string();
+        }
+        (status, page)
+    }
+
+    async fn post(&self, path: &str, form: &[(&str, &str)]) -> Response {
+        let mut form = form.to_vec();
+        form.push(("csrf", &self.csrf));
+        self.client
+            .post(format!("{}{path}", self.address))
+            .header(COOKIE, &self.cookie)
+            .form(&form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+async fn spawn_admin_app(pool: PgPool) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.admin_ui.password = Some("secret".to_string());
+    })
+    .await
+}
+
+async fn subscribe(app: &TestApp, email: &str) {
+    reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .form(&[("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+}
+
+fn location(response: &Response) -> &str {
+    response.headers()[LOCATION].to_str().unwrap()
+}
+
+#[sqlx::test]
+async fn admin_pages_need_the_password(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    let client = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let anonymous = client
+        .get(format!("{}/admin/subscribers", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let wrong = client
+        .post(format!("{}/admin/login", app.address))
+        .form(&[("password", "guess")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(303, anonymous.status().as_u16());
+    assert_eq!("/admin/login", location(&anonymous));
+    assert_eq!(401, wrong.status().as_u16());
+    assert!(wrong.headers().get(SET_COOKIE).is_none());
+}
+
+#[sqlx::test]
+async fn sign_in_attempts_are_limited(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |settings| {
+        settings.admin_ui.password = Some("secret".to_string());
+        settings.admin_ui.login_attempts = 2;
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let log_in = |password: &'static str| {
+        client
+            .post(format!("{}/admin/login", app.address))
+            .form(&[("password", password)])
+            .send()
+    };
+
+    // Act
+    let first = log_in("guess").await.expect("Failed to execute request.");
+    let second = log_in("guess").await.expect("Failed to execute request.");
+    let third = log_in("secret").await.expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, first.status().as_u16());
+    assert_eq!(401, second.status().as_u16());
+    assert_eq!(429, third.status().as_u16());
+    assert!(third.headers().get(SET_COOKIE).is_none());
+}
+
+#[sqlx::test]
+async fn sign_in_is_closed_without_a_password(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/admin/login", app.address))
+        .form(&[("password", "")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn admin_searches_subscribers(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    subscribe(&app, "ada@example.org").await;
+    subscribe(&app, "grace@example.org").await;
+    let mut admin = Admin::sign_in(&app).await;
+
+    // Act
+    let (status, page) = admin.get("/admin/subscribers?q=ADA&status=active").await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert!(page.contains("ada@example.org"));
+    assert!(!page.contains("grace@example.org"));
+}
+
+#[sqlx::test]
+async fn far_out_subscriber_pages_are_empty(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    subscribe(&app, "ada@example.org").await;
+    let mut admin = Admin::sign_in(&app).await;
+
+    // Act
+    let (status, page) = admin
+        .get(&format!("/admin/subscribers?page={}", i64::MAX))
+        .await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert!(!page.contains("ada@example.org"));
+}
+
+#[sqlx::test]
+async fn admin_composes_and_schedules_campaign(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    subscribe(&app, "ada@example.org").await;
+    let mut admin = Admin::sign_in(&app).await;
+    admin.get("/admin/campaigns").await;
+
+    // Act
+    let response = admin
+        .post(
+            "/admin/campaigns",
+            &[
+                ("name", "June"),
+                ("subject", "News for June"),
+                ("text", "Hello"),
+                ("html", ""),
+                ("send_at", "2099-06-01T09:00"),
+            ],
+        )
+        .await;
+    let status = response.status().as_u16();
+    let campaign = location(&response).to_string();
+    let (_, page) = admin.get(&campaign).await;
+    let queued = sqlx::query!("SELECT count(*) AS \"count!\" FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(303, status);
+    assert!(campaign.starts_with("/admin/campaigns/"));
+    assert!(page.contains("<h1>June</h1>"));
+    assert!(page.contains("Sends from 2099-06-01"));
+    assert_eq!(1, queued.count);
+}
+
+#[sqlx::test]
+async fn forms_need_the_session_token(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    let admin = Admin::sign_in(&app).await;
+
+    // Act
+    let response = admin
+        .post("/admin/list/suppressions", &[("email", "ada@example.org")])
+        .await;
+
+    // Assert
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn admin_creates_api_key_shown_once(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    let mut admin = Admin::sign_in(&app).await;
+    admin.get("/admin/keys").await;
+
+    // Act
+    let response = admin
+        .post("/admin/keys", &[("name", "shop"), ("scope", "send")])
+        .await;
+    let status = response.status().as_u16();
+    let created = response.text().await.unwrap();
+    let key = created
+        .split("<code>")
+        .nth(1)
+        .and_then(|rest| rest.split("</code>").next())
+        .unwrap()
+        .to_string();
+    let (_, keys) = admin.get("/admin/keys").await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert!(key.starts_with("mm_"));
+    assert!(keys.contains("shop"));
+    assert!(!keys.contains(&key));
+}
+
+#[sqlx::test]
+async fn session_ends_on_sign_out_and_never_opens_the_api(pool: PgPool) {
+    // Arrange
+    let app = spawn_admin_app(pool).await;
+    let mut admin = Admin::sign_in(&app).await;
+    admin.get("/admin").await;
+
+    // Act
+    let api = admin
+        .client
+        .get(format!("{}/api/subscribers", app.address))
+        .header(COOKIE, &admin.cookie)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let signed_out = admin.post("/admin/logout", &[]).await;
+    let (after, _) = admin.get("/admin").await;
+
+    // Assert
+    assert_ne!(200, api.status().as_u16());
+    assert_eq!(303, signed_out.status().as_u16());
+    assert_eq!(303, after);
+}
@@ -0,0 +1,168 @@
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+async fn spawn_archived(pool: PgPool, enabled: bool) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.archive.enabled = enabled;
+        settings.archive.url = "https://news.example.com".to_string();
+    })
+    .await
+}
+
+async fn send_json(request: reqwest::RequestBuilder, body: Value) -> reqwest::Response {
+    request
+        .bearer_auth("admin")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Sends a campaign to one subscriber and returns its id.
+async fn send_campaign(app: &TestApp, name: &str) -> i64 {
+    let client = reqwest::Client::new();
+    sqlx::query!(
+        "INSERT INTO subscribers(email) VALUES ('ada@example.org') ON CONFLICT DO NOTHING"
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+    let template: Value = send_json(
+        client.post(format!("{}/api/templates", app.address)),
+        json!({
+            "name": name,
+            "subject": name,
+            "text": "Hi {{ email }}",
+            "html": "<p>Hi {{ email }}</p><a href=\"https://example.com/post\">Read</a>"
+        }),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    let campaign: Value = send_json(
+        client.post(format!("{}/api/campaigns", app.address)),
+        json!({ "name": name }),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+    send_json(
+        client.post(format!(
+            "{}/api/campaigns/{}/schedule",
+            app.address, campaign["id"]
+        )),
+        json!({"template_id": template["id"], "send_at": "2020-01-01T09:00:00"}),
+    )
+    .await;
+    campaign["id"].as_i64().unwrap()
+}
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    reqwest::get(format!("{}{path}", app.address))
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[sqlx::test]
+async fn archive_shows_issues_without_tracking_or_personal_data(pool: PgPool) {
+    // Arrange
+    let app = spawn_archived(pool, true).await;
+    let campaign_id = send_campaign(&app, "May news").await;
+
+    // Act
+    let index = get(&app, "/archive").await.text().await.unwrap();
+    let issue = get(&app, &format!("/archive/{campaign_id}")).await;
+
+    // Assert
+    assert!(index.contains(&format!("<a href=\"/archive/{campaign_id}\">May news</a>")));
+    assert!(issue.status().is_success());
+    let issue = issue.text().await.unwrap();
+    assert!(issue.contains("<p>Hi </p><a href=\"https://example.com/post\">Read</a>"));
+    assert!(!issue.contains("ada@example.org"));
+    let sent = sqlx::query!("SELECT raw FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&sent.raw).contains("href=\"https://example.com/post\""));
+}
+
+#[sqlx::test]
+async fn excluded_campaigns_stay_out_of_the_archive(pool: PgPool) {
+    // Arrange
+    let app = spawn_archived(pool, true).await;
+    let kept = send_campaign(&app, "Kept").await;
+    let excluded = send_campaign(&app, "Private").await;
+
+    // Act
+    let response = send_json(
+        reqwest::Client::new().put(format!("{}/api/campaigns/{excluded}/archive", app.address)),
+        json!({"exclude": true}),
+    )
+    .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let index = get(&app, "/archive").await.text().await.unwrap();
+    assert!(index.contains(&format!("/archive/{kept}\"")));
+    assert!(!index.contains("Private"));
+    assert_eq!(
+        404,
+        get(&app, &format!("/archive/{excluded}"))
+            .await
+            .status()
+            .as_u16()
+    );
+}
+
+#[sqlx::test]
+async fn archive_feeds_can_be_cached(pool: PgPool) {
+    // Arrange
+    let app = spawn_archived(pool, true).await;
+    let campaign_id = send_campaign(&app, "May news").await;
+
+    // Act
+    let atom = get(&app, "/archive.atom").await;
+    let etag = atom.headers()["etag"].clone();
+    let again = reqwest::Client::new()
+        .get(format!("{}/archive.atom", app.address))
+        .header("If-None-Match", etag)
+        .send()
+        .await
+        .unwrap();
+    let json = get(&app, "/archive.json").await;
+
+    // Assert
+    assert_eq!("public, max-age=300", atom.headers()["cache-control"]);
+    assert_eq!("application/atom+xml", atom.headers()["content-type"]);
+    assert!(atom.text().await.unwrap().contains(&format!(
+        "<id>https://news.example.com/archive/{campaign_id}</id>"
+    )));
+    assert_eq!(304, again.status().as_u16());
+    assert_eq!("application/feed+json", json.headers()["content-type"]);
+    let feed: Value = json.json().await.unwrap();
+    assert_eq!(json!("May news"), feed["items"][0]["title"]);
+}
+
+#[sqlx::test]
+async fn archive_is_off_by_default(pool: PgPool) {
+    // Arrange
+    let app = spawn_archived(pool, false).await;
+    let campaign_id = send_campaign(&app, "May news").await;
+
+    // Act
+    let responses = [
+        get(&app, "/archive").await,
+        get(&app, &format!("/archive/{campaign_id}")).await,
+        get(&app, "/archive.atom").await,
+        get(&app, "/archive.json").await,
+    ];
+
+    // Assert
+    for response in responses {
+        assert_eq!(404, response.status().as_u16());
+    }
+}
@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use mailparse::MailHeaderMap;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{
+    post_json, relayed_message, spawn_app_with, start_relay, use_relay, Relayed, TestApp,
+};
+
+async fn spawn_automated(pool: PgPool) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        use_relay(settings, port);
+        settings.automation.interval = 1;
+    })
+    .await;
+    (app, relayed)
+}
+
+async fn create_welcome(app: &TestApp, trigger: Value) -> Value {
+    post_json(
+        app,
+        "/api/templates",
+        json!({
+            "name": "welcome",
+            "subject": "Welcome, {{ name }}",
+            "text": "Thanks for subscribing with {{ email }}."
+        }),
+    )
+    .await;
+    post_json(
+        app,
+        "/api/automations",
+        json!({
+            "name": "Welcome",
+            "trigger": trigger,
+            "steps": [
+                {"type": "send", "template": "welcome"},
+                {"type": "wait", "days": 3},
+                {"type": "branch", "when": {"type": "opened"}, "goto": 4},
+                {"type": "add_tag", "tag": "unengaged"}
+            ]
+        }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to read automation.")
+}
+
+async fn subscribe(app: &TestApp, email: &str) {
+    reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .header("origin", &app.address)
+        .form(&[("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+}
+
+async fn run_status(app: &TestApp) -> (String, i32) {
+    let run = sqlx::query!("SELECT status, step FROM automation_runs")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch automation run.");
+    (run.status, run.step)
+}
+
+#[sqlx::test]
+async fn new_subscribers_get_the_welcome_mail(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_automated(pool).await;
+    let automation = create_welcome(&app, json!({"type": "subscribed"})).await;
+
+    // Act
+    subscribe(&app, "ada@example.org").await;
+    let (recipients, raw) = relayed_message(&mut relayed).await;
+
+    // Assert
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    assert_eq!(vec!["ada@example.org".to_string()], recipients);
+    assert_eq!(
+        Some("Welcome,"),
+        mail.headers
+            .get_first_value("Subject")
+            .as_deref()
+            .map(str::trim_end)
+    );
+    assert_eq!(
+        Some(automation["campaign_id"].to_string()),
+        mail.headers.get_first_value("X-Minimail-Campaign")
+    );
+    assert_eq!(
+        "Thanks for subscribing with ada@example.org.",
+        mail.get_body().unwrap().trim_end()
+    );
+    assert_eq!(("active".to_string(), 2), run_status(&app).await);
+}
+
+#[sqlx::test]
+async fn added_tags_trigger_automations(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_automated(pool).await;
+    subscribe(&app, "ada@example.org").await;
+    create_welcome(&app, json!({"type": "tag_added", "tag": "trial"})).await;
+    reqwest::Client::new()
+        .put(format!("{}/api/subscribers/attributes", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"email": "ada@example.org", "attributes": {"name": "Ada"}}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Act
+    let response = post_json(
+        &app,
+        "/api/subscribers/tags",
+        json!({"email": "ada@example.org", "tag": "trial"}),
+    )
+    .await;
+    let (_, raw) = relayed_message(&mut relayed).await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    assert_eq!(
+        Some("Welcome, Ada".to_string()),
+        mail.headers.get_first_value("Subject")
+    );
+}
+
+#[sqlx::test]
+async fn subscribers_who_left_exit_automations(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_automated(pool).await;
+    let automation = create_welcome(&app, json!({"type": "subscribed"})).await;
+    let subscriber = sqlx::query!(
+        "INSERT INTO subscribers(email, status) VALUES ('ada@example.org', 'bounced') RETURNING id"
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+
+    // Act
+    sqlx::query!(
+        "INSERT INTO automation_runs(automation_id, subscriber_id) VALUES ($1, $2)",
+        automation["id"].as_i64().unwrap() as i32,
+        subscriber.id
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create automation run.");
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // Assert
+    assert_eq!(("exited".to_string(), 0), run_status(&app).await);
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(0, queued.count);
+}
+
+#[sqlx::test]
+async fn suppressed_subscribers_get_no_automation_mail(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_automated(pool).await;
+    let automation = create_welcome(&app, json!({"type": "subscribed"})).await;
+    let subscriber =
+        sqlx::query!("INSERT INTO subscribers(email) VALUES ('ada@example.org') RETURNING id")
+            .fetch_one(&app.pool)
+            .await
+            .expect("Failed to create subscriber.");
+    sqlx::query!("INSERT INTO suppressions(email, reason) VALUES ('ada@example.org', 'complaint')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to suppress address.");
+
+    // Act
+    sqlx::query!(
+        "INSERT INTO automation_runs(automation_id, subscriber_id) VALUES ($1, $2)",
+        automation["id"].as_i64().unwrap() as i32,
+        subscriber.id
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create automation run.");
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // Assert
+    assert_eq!(("active".to_string(), 2), run_status(&app).await);
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(0, queued.count);
+}
+
+#[sqlx::test]
+async fn simulate_fast_forwards_an_automation(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_automated(pool).await;
+    let automation = create_welcome(&app, json!({"type": "subscribed"})).await;
+
+    // Act
+    let simulation: Value = post_json(
+        &app,
+        &format!("/api/automations/{}/simulate", automation["id"]),
+        json!({"opened": false}),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to read simulation.");
+
+    // Assert
+    let timeline = simulation["timeline"].as_array().unwrap();
+    assert_eq!(2, timeline.len());
+    assert_eq!(json!("send"), timeline[0]["type"]);
+    assert_eq!(json!("add_tag"), timeline[1]["type"]);
+    assert_eq!(json!(3), timeline[1]["step"]);
+    let sent = chrono::DateTime::parse_from_rfc3339(timeline[0]["at"].as_str().unwrap()).unwrap();
+    let tagged = chrono::DateTime::parse_from_rfc3339(timeline[1]["at"].as_str().unwrap()).unwrap();
+    assert_eq!(chrono::Duration::days(3), tagged - sent);
+    assert_eq!(
+        0,
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap()
+            .count
+    );
+}
+
+#[sqlx::test]
+async fn automations_with_loops_are_rejected(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_automated(pool).await;
+
+    // Act
+    let response = post_json(
+        &app,
+        "/api/automations",
+        json!({
+            "name": "Loop",
+            "trigger": {"type": "subscribed"},
+            "steps": [{"type": "branch", "when": {"type": "clicked"}, "goto": 0}]
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn failing_steps_do_not_resend_and_are_given_up_on(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_automated(pool).await;
+    post_json(
+        &app,
+        "/api/templates",
+        json!({"name": "welcome", "subject": "Welcome", "text": "Hi"}),
+    )
+    .await;
+    post_json(
+        &app,
+        "/api/automations",
+        json!({
+            "name": "Broken",
+            "trigger": {"type": "tag_added", "tag": "broken"},
+            "steps": [
+                {"type": "send", "template": "welcome"},
+                {"type": "send", "template": "missing"}
+            ]
+        }),
+    )
+    .await;
+    subscribe(&app, "ada@example.org").await;
+
+    // Act
+    post_json(
+        &app,
+        "/api/subscribers/tags",
+        json!({"email": "ada@example.org", "tag": "broken"}),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    for _ in 0..5 {
+        sqlx::query!("UPDATE automation_runs SET wake_at = now()")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+
+    // Assert
+    let queued =
+        sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox WHERE campaign_id IS NOT NULL")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(1, queued.count);
+    assert_eq!(("failed".to_string(), 1), run_status(&app).await);
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+fn dsn(recipient: &str, action: &str, status: &str) -> String {
+    let (user, domain) = recipient.split_once('@').unwrap();
+    format!(
+        "From: MAILER-DAEMON@mx.example.org\r\n\
+         To: bounces+{user}={domain}@localhost\r\n\
+         Subject: Undelivered Mail Returned to Sender\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n\
+         \r\n\
+         --b\r\n\
+         Content-Type: message/delivery-status\r\n\
+         \r\n\
+         Reporting-MTA: dns; mx.example.org\r\n\
+         \r\n\
+         Final-Recipient: rfc822; {recipient}\r\n\
+         Action: {action}\r\n\
+         Status: {status}\r\n\
+         --b--\r\n"
+    )
+}
+
+async fn create_subscriber(app: &TestApp, email: &str) {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ($1)", email)
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+}
+
+async fn status(app: &TestApp, email: &str) -> String {
+    sqlx::query!("SELECT status FROM subscribers WHERE email = $1", email)
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch subscriber.")
+        .status
+}
+
+async fn post_bounce(app: &TestApp, token: &str, body: String) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/inbound/bounces", app.address))
+        .bearer_auth(token)
+        .header("Content-Type", "message/rfc822")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[sqlx::test]
+async fn hard_bounce_marks_subscriber_bounced(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    create_subscriber(&app, "user@example.org").await;
+
+    // Act
+    let response = post_bounce(
+        &app,
+        "inbound-token",
+        dsn("user@example.org", "failed", "5.1.1"),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("bounced", status(&app, "user@example.org").await);
+    let event = sqlx::query!("SELECT kind FROM events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch event.");
+    assert_eq!("bounce", event.kind);
+}
+
+#[sqlx::test]
+async fn soft_bounces_mark_subscriber_bounced_at_threshold(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |settings| settings.bounce.threshold = 2).await;
+    create_subscriber(&app, "user@example.org").await;
+
+    // Act
+    post_bounce(
+        &app,
+        "inbound-token",
+        dsn("user@example.org", "failed", "4.2.2"),
+    )
+    .await;
+    let after_first = status(&app, "user@example.org").await;
+    post_bounce(
+        &app,
+        "inbound-token",
+        dsn("user@example.org", "failed", "4.2.2"),
+    )
+    .await;
+
+    // Assert
+    assert_eq!("active", after_first);
+    assert_eq!("bounced", status(&app, "user@example.org").await);
+}
+
+#[sqlx::test]
+async fn bounce_with_invalid_auth_fails(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+
+    // Act
+    let response = post_bounce(&app, "admin", dsn("user@example.org", "failed", "5.1.1")).await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn bounce_rejects_other_messages(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+
+    // Act
+    let response = post_bounce(
+        &app,
+        "inbound-token",
+        "Subject: Hello\r\n\r\nJust saying hi.\r\n".to_string(),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn mailbox_poller_processes_bounces(pool: PgPool) {
+    // Arrange
+    let mailbox = std::env::temp_dir().join(format!("minimail-mailbox-{}", std::process::id()));
+    std::fs::create_dir_all(mailbox.join("new")).unwrap();
+    std::fs::write(
+        mailbox.join("new").join("1"),
+        dsn("user@example.org", "failed", "5.1.1"),
+    )
+    .unwrap();
+    let path = mailbox.to_str().unwrap().to_string();
+    let app = spawn_app_with(pool, |settings| {
+        settings.inbound.mailbox = Some(path);
+        settings.inbound.interval = 1;
+    })
+    .await;
+    create_subscriber(&app, "user@example.org").await;
+
+    // Act
+    let mut bounced = false;
+    for _ in 0..50 {
+        if status(&app, "user@example.org").await == "bounced" {
+            bounced = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Assert
+    assert!(bounced);
+    std::fs::remove_dir_all(mailbox).unwrap();
+}
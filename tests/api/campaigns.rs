@@ -0,0 +1,426 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use minimail::{
+    config::{AdminSettings, SubscribedSettings, TrackingSettings},
+    tracking::Tracker,
+};
+
+use crate::helpers::{
+    post_json, relayed_text, spawn_app, spawn_app_with, start_relay, use_relay, TestApp,
+};
+
+async fn app(pool: PgPool) -> TestApp {
+    spawn_app(
+        pool,
+        AdminSettings {
+            token: "admin".to_string(),
+        },
+        SubscribedSettings::default(),
+    )
+    .await
+}
+
+async fn create_campaign(app: &TestApp, name: &str) -> i64 {
+    let campaign: Value = reqwest::Client::new()
+        .post(format!("{}/api/campaigns", app.address))
+        .bearer_auth("admin")
+        .json(&json!({ "name": name }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse campaign.");
+    campaign["id"].as_i64().unwrap()
+}
+
+async fn open_and_click(app: &TestApp, campaign_id: i32, email: &str) {
+    let subscriber_id = sqlx::query!(
+        "INSERT INTO subscribers(email) VALUES ($1) RETURNING id",
+        email
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to create subscriber.")
+    .id;
+    let tracker = Tracker::from(TrackingSettings {
+        enabled: true,
+        url: app.address.clone(),
+        secret: "secret".to_string(),
+    });
+    let client = reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    for url in [
+        tracker.open_url(subscriber_id, Some(campaign_id)),
+        tracker.open_url(subscriber_id, Some(campaign_id)),
+        tracker.click_url(subscriber_id, Some(campaign_id), "https://example.com"),
+    ] {
+        client
+            .get(url)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+    }
+}
+
+#[sqlx::test]
+async fn report_counts_unique_and_total_events(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let campaign_id = create_campaign(&app, "Launch").await;
+    open_and_click(&app, campaign_id as i32, "first@email.com").await;
+    open_and_click(&app, campaign_id as i32, "second@email.com").await;
+
+    // Act
+    let report: Value = reqwest::Client::new()
+        .get(format!(
+            "{}/api/campaigns/{campaign_id}/report",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse report.");
+
+    // Assert
+    assert_eq!(json!({ "total": 4, "unique": 2 }), report["opened"]);
+    assert_eq!(json!({ "total": 2, "unique": 2 }), report["clicked"]);
+    assert_eq!("https://example.com", report["links"][0]["url"]);
+    assert_eq!(48, report["timeline"].as_array().unwrap().len());
+}
+
+#[sqlx::test]
+async fn report_can_be_downloaded_as_csv(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let campaign_id = create_campaign(&app, "Launch").await;
+    open_and_click(&app, campaign_id as i32, "first@email.com").await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/api/campaigns/{campaign_id}/report",
+            app.address
+        ))
+        .query(&[("format", "csv")])
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(
+        "text/csv; charset=utf-8",
+        response.headers()["Content-Type"]
+    );
+    let csv = response.text().await.expect("No text in body");
+    assert!(csv.starts_with("id,name,sent_at,"));
+    assert!(csv.contains("\nhttps://example.com,1,1\n"));
+}
+
+#[sqlx::test]
+async fn compare_lists_last_campaigns(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    create_campaign(&app, "First").await;
+    let second = create_campaign(&app, "Second").await;
+    create_campaign(&app, "Third").await;
+    open_and_click(&app, second as i32, "first@email.com").await;
+    let compare = |last: &'static str| {
+        reqwest::Client::new()
+            .get(format!("{}/api/campaigns/compare", app.address))
+            .query(&[("last", last)])
+            .bearer_auth("admin")
+            .send()
+    };
+
+    // Act
+    let summaries: Value = compare("2")
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse summaries.");
+    let none: Value = compare("0")
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse summaries.");
+
+    // Assert
+    let summaries = summaries.as_array().unwrap();
+    let names: Vec<&str> = summaries
+        .iter()
+        .map(|summary| summary["campaign"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(vec!["Third", "Second"], names);
+    assert_eq!(0, summaries[0]["opened"]["unique"]);
+    assert_eq!(1, summaries[1]["opened"]["unique"]);
+    assert_eq!(1, none.as_array().unwrap().len());
+}
+
+#[sqlx::test]
+async fn report_for_unknown_campaign_is_not_found(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/campaigns/42/report", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+async fn create_template(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .post(format!("{}/api/templates", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"name": "news", "subject": "News", "text": "Hello {{ email }}"}))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse template.")
+}
+
+async fn create_subscriber(app: &TestApp, email: &str, timezone: Option<&str>) -> i32 {
+    sqlx::query!(
+        "INSERT INTO subscribers(email, timezone) VALUES ($1, $2) RETURNING id",
+        email,
+        timezone
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to create subscriber.")
+    .id
+}
+
+async fn schedule(app: &TestApp, campaign_id: i64, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!(
+            "{}/api/campaigns/{campaign_id}/schedule",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn scheduled_at(app: &TestApp, email: &str) -> String {
+    sqlx::query!(
+        "SELECT next_attempt_at FROM outbox WHERE recipient = $1",
+        email
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to fetch delivery.")
+    .next_attempt_at
+    .to_rfc3339()
+}
+
+#[sqlx::test]
+async fn schedule_sends_at_local_time(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let template = create_template(&app).await;
+    let campaign_id = create_campaign(&app, "January").await;
+    create_subscriber(&app, "berlin@example.org", Some("Europe/Berlin")).await;
+    create_subscriber(&app, "newyork@example.org", Some("America/New_York")).await;
+    create_subscriber(&app, "unknown@example.org", None).await;
+
+    // Act
+    let response = schedule(
+        &app,
+        campaign_id,
+        json!({"template_id": template["id"], "send_at": "2030-01-15T09:00:00", "local": true}),
+    )
+    .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let schedule: Value = response.json().await.unwrap();
+    assert_eq!(json!(3), schedule["deliveries"]);
+    assert_eq!(
+        "2030-01-15T08:00:00+00:00",
+        scheduled_at(&app, "berlin@example.org").await
+    );
+    assert_eq!(
+        "2030-01-15T14:00:00+00:00",
+        scheduled_at(&app, "newyork@example.org").await
+    );
+    assert_eq!(
+        "2030-01-15T09:00:00+00:00",
+        scheduled_at(&app, "unknown@example.org").await
+    );
+}
+
+#[sqlx::test]
+async fn schedule_sends_passed_local_time_the_next_day(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let template = create_template(&app).await;
+    let campaign_id = create_campaign(&app, "January").await;
+    create_subscriber(&app, "tokyo@example.org", Some("Asia/Tokyo")).await;
+    // Three hours from now in the list's timezone, which is long past in Tokyo.
+    let send_at = (Utc::now() + Duration::hours(3)).naive_utc();
+
+    // Act
+    let response = schedule(
+        &app,
+        campaign_id,
+        json!({"template_id": template["id"], "send_at": send_at, "local": true}),
+    )
+    .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let at = DateTime::parse_from_rfc3339(&scheduled_at(&app, "tokyo@example.org").await).unwrap();
+    assert!(at > Utc::now() + Duration::hours(17));
+}
+
+#[sqlx::test]
+async fn schedule_picks_usual_open_hour(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let template = create_template(&app).await;
+    let campaign_id = create_campaign(&app, "January").await;
+    let subscriber_id = create_subscriber(&app, "evening@example.org", None).await;
+    for days in 1..=3 {
+        sqlx::query!(
+            r#"
+            INSERT INTO events(kind, subscriber_id, created_at)
+            VALUES ('open', $1, date_trunc('day', now()) - make_interval(days => $2) + interval '18 hours 20 minutes')
+            "#,
+            subscriber_id,
+            days
+        )
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create event.");
+    }
+
+    // Act
+    let response = schedule(
+        &app,
+        campaign_id,
+        json!({"template_id": template["id"], "send_at": "2030-01-15T09:00:00", "optimise": true}),
+    )
+    .await;
+
+    // Assert
+    assert!(response.status().is_success());
+    assert_eq!(
+        "2030-01-15T18:00:00+00:00",
+        scheduled_at(&app, "evening@example.org").await
+    );
+}
+
+#[sqlx::test]
+async fn schedule_only_happens_once(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let template = create_template(&app).await;
+    let campaign_id = create_campaign(&app, "January").await;
+    let body = json!({"template_id": template["id"], "send_at": "2030-01-15T09:00:00"});
+
+    // Act
+    let first = schedule(&app, campaign_id, body.clone()).await;
+    let second = schedule(&app, campaign_id, body).await;
+
+    // Assert
+    assert!(first.status().is_success());
+    assert_eq!(409, second.status().as_u16());
+}
+
+#[sqlx::test]
+async fn report_rates_are_based_on_delivered_mail(pool: PgPool) {
+    // Arrange
+    let (port, mut relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| use_relay(settings, port)).await;
+    create_subscriber(&app, "ada@example.org", None).await;
+    let bob = create_subscriber(&app, "bob@example.org", None).await;
+    let template = post_json(
+        &app,
+        "/api/templates",
+        json!({"name": "news", "subject": "News", "text": "Leave: {{ unsubscribe_url }}"}),
+    )
+    .await
+    .json::<Value>()
+    .await
+    .expect("Failed to parse template.");
+    let campaign_id = create_campaign(&app, "Launch").await;
+    schedule(
+        &app,
+        campaign_id,
+        json!({"template_id": template["id"], "send_at": "2020-01-01T09:00:00"}),
+    )
+    .await;
+    let mut unsubscribe = None;
+    for _ in 0..2 {
+        let (recipient, text) = relayed_text(&mut relayed).await;
+        if recipient == "ada@example.org" {
+            unsubscribe = text
+                .lines()
+                .find_map(|line| line.strip_prefix("Leave: "))
+                .map(|url| url.trim().to_string());
+        }
+    }
+    let tracker = Tracker::from(TrackingSettings {
+        enabled: true,
+        url: app.address.clone(),
+        secret: "secret".to_string(),
+    });
+    let client = reqwest::Client::new();
+    client
+        .get(tracker.open_url(bob, Some(campaign_id as i32)))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    client
+        .post(unsubscribe.expect("No unsubscribe link in the campaign."))
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    // Deliveries are recorded right after the relay took the message.
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    // Act
+    let report: Value = client
+        .get(format!(
+            "{}/api/campaigns/{campaign_id}/report",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to parse report.");
+
+    // Assert
+    assert_eq!(json!({ "total": 2, "unique": 2 }), report["delivered"]);
+    assert_eq!(json!({ "total": 1, "unique": 1 }), report["opened"]);
+    assert_eq!(json!({ "total": 1, "unique": 1 }), report["unsubscribed"]);
+    assert_eq!(0.5, report["rates"]["open"]);
+    assert_eq!(0.5, report["rates"]["unsubscribe"]);
+    assert_eq!(0.0, report["rates"]["click"]);
+}
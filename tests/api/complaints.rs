@@ -0,0 +1,105 @@
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+fn arf(recipient: &str) -> String {
+    format!(
+        "From: feedback@isp.example.net\r\n\
+         To: abuse@localhost\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: multipart/report; report-type=feedback-report; boundary=\"b\"\r\n\
+         \r\n\
+         --b\r\n\
+         Content-Type: message/feedback-report\r\n\
+         \r\n\
+         Feedback-Type: abuse\r\n\
+         Version: 1\r\n\
+         Original-Rcpt-To: {recipient}\r\n\
+         --b\r\n\
+         Content-Type: text/rfc822-headers\r\n\
+         \r\n\
+         Subject: Newsletter\r\n\
+         \r\n\
+         --b--\r\n"
+    )
+}
+
+async fn post_complaint(app: &TestApp, body: String) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/inbound/complaints", app.address))
+        .bearer_auth("inbound-token")
+        .header("Content-Type", "message/rfc822")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[sqlx::test]
+async fn complaint_marks_subscriber_and_suppresses_address(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ('user@example.org')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+
+    // Act
+    let response = post_complaint(&app, arf("user@example.org")).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let subscriber = sqlx::query!("SELECT status FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch subscriber.");
+    assert_eq!("complained", subscriber.status);
+    let suppression =
+        sqlx::query!("SELECT reason FROM suppressions WHERE email = 'user@example.org'")
+            .fetch_one(&app.pool)
+            .await
+            .expect("Failed to fetch suppression.");
+    assert_eq!("complaint", suppression.reason);
+    let event = sqlx::query!("SELECT kind FROM events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch event.");
+    assert_eq!("complaint", event.kind);
+}
+
+#[sqlx::test]
+async fn suppressed_address_is_not_subscribed_again(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    post_complaint(&app, arf("user@example.org")).await;
+
+    // Act
+    reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("origin", &app.address)
+        .body("email=user%40example.org")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let count = sqlx::query!("SELECT COUNT(*) AS count FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count subscribers.")
+        .count;
+    assert_eq!(Some(0), count);
+}
+
+#[sqlx::test]
+async fn complaint_rejects_other_messages(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+
+    // Act
+    let response = post_complaint(&app, "Subject: Hello\r\n\r\nHi.\r\n".to_string()).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
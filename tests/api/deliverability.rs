@@ -0,0 +1,188 @@
+use std::{
+    net::Ipv4Addr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use hickory_proto::{
+    op::{Message, MessageType, ResponseCode},
+    rr::{
+        rdata::{A, MX},
+        Name, RData, Record, RecordType,
+    },
+};
+use minimail::config::{DeliverabilityPolicy, DeliverabilitySettings};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::net::UdpSocket;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+/// A name server that knows `mail.example` by its MX record, `web.example`
+/// by its A record, `null.example` by a null MX record and nothing else,
+/// counting the questions it gets.
+struct FakeDns {
+    address: String,
+    questions: Arc<AtomicUsize>,
+}
+
+async fn start_dns(answer: bool) -> FakeDns {
+    let socket = UdpSocket::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let address = socket.local_addr().unwrap().to_string();
+    let questions = Arc::new(AtomicUsize::new(0));
+    let counter = questions.clone();
+    tokio::spawn(async move {
+        let mut buffer = [0; 512];
+        loop {
+            let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            if !answer {
+                continue;
+            }
+            let request = Message::from_vec(&buffer[..len]).unwrap();
+            let response = respond(&request);
+            socket
+                .send_to(&response.to_vec().unwrap(), peer)
+                .await
+                .unwrap();
+        }
+    });
+    FakeDns { address, questions }
+}
+
+fn respond(request: &Message) -> Message {
+    let mut response = Message::new();
+    response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .set_recursion_desired(true)
+        .set_recursion_available(true)
+        .set_response_code(ResponseCode::NoError)
+        .add_queries(request.queries().to_vec());
+    let query = &request.queries()[0];
+    let name = query.name().clone();
+    let rdata = match (name.to_ascii().as_str(), query.query_type()) {
+        ("mail.example.", RecordType::MX) => Some(RData::MX(MX::new(
+            10,
+            Name::from_str("mx.mail.example.").unwrap(),
+        ))),
+        ("null.example.", RecordType::MX) => Some(RData::MX(MX::new(0, Name::root()))),
+        ("web.example.", RecordType::A) => Some(RData::A(A(Ipv4Addr::new(192, 0, 2, 1)))),
+        _ => None,
+    };
+    if let Some(rdata) = rdata {
+        response.add_answer(Record::from_rdata(name, 300, rdata));
+    }
+    response
+}
+
+async fn spawn_checked(pool: PgPool, dns: &FakeDns, policy: DeliverabilityPolicy) -> TestApp {
+    let nameserver = dns.address.clone();
+    spawn_app_with(pool, |settings| {
+        settings.deliverability = Some(DeliverabilitySettings {
+            policy,
+            nameservers: vec![nameserver],
+            timeout_ms: 500,
+            cache_seconds: 3600,
+        });
+    })
+    .await
+}
+
+async fn post_json(app: &TestApp, email: &str) -> (u16, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/subscriptions", app.address))
+        .json(&json!({ "email": email }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+#[sqlx::test]
+async fn domains_without_mail_servers_are_rejected(pool: PgPool) {
+    // Arrange
+    let dns = start_dns(true).await;
+    let app = spawn_checked(pool, &dns, DeliverabilityPolicy::Reject).await;
+
+    // Act
+    let exchanger = post_json(&app, "ada@mail.example").await;
+    let fallback = post_json(&app, "ada@web.example").await;
+    let null = post_json(&app, "ada@null.example").await;
+    let (status, body) = post_json(&app, "ada@nowhere.example").await;
+
+    // Assert
+    assert_eq!(200, exchanger.0);
+    assert_eq!(200, fallback.0);
+    assert_eq!(422, null.0);
+    assert_eq!(422, status);
+    assert_eq!(
+        json!({"errors": [{
+            "field": "email",
+            "message": "This address doesn't seem to receive mail."
+        }]}),
+        body
+    );
+    let subscribers = sqlx::query!("SELECT email FROM subscribers ORDER BY email")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    let emails: Vec<_> = subscribers.into_iter().map(|row| row.email).collect();
+    assert_eq!(vec!["ada@mail.example", "ada@web.example"], emails);
+}
+
+#[sqlx::test]
+async fn pending_policy_holds_undeliverable_signups(pool: PgPool) {
+    // Arrange
+    let dns = start_dns(true).await;
+    let app = spawn_checked(pool, &dns, DeliverabilityPolicy::Pending).await;
+
+    // Act
+    let (status, body) = post_json(&app, "ada@nowhere.example").await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert_eq!("pending", body["status"]);
+    let subscriber = sqlx::query!(
+        r#"SELECT status, ARRAY(SELECT tag FROM subscriber_tags t WHERE t.subscriber_id = s.id) AS "tags!" FROM subscribers s"#
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!("pending", subscriber.status);
+    assert_eq!(vec!["flagged:undeliverable".to_string()], subscriber.tags);
+}
+
+#[sqlx::test]
+async fn answers_are_cached_by_domain(pool: PgPool) {
+    // Arrange
+    let dns = start_dns(true).await;
+    let app = spawn_checked(pool, &dns, DeliverabilityPolicy::Reject).await;
+
+    // Act
+    post_json(&app, "ada@mail.example").await;
+    post_json(&app, "grace@mail.example").await;
+
+    // Assert
+    assert_eq!(1, dns.questions.load(Ordering::SeqCst));
+}
+
+#[sqlx::test]
+async fn unanswered_lookups_let_signups_through(pool: PgPool) {
+    // Arrange
+    let dns = start_dns(false).await;
+    let app = spawn_checked(pool, &dns, DeliverabilityPolicy::Reject).await;
+
+    // Act
+    let (status, body) = post_json(&app, "ada@mail.example").await;
+
+    // Assert
+    assert_eq!(200, status);
+    assert_eq!(json!({"status": "subscribed"}), body);
+    assert!(dns.questions.load(Ordering::SeqCst) >= 1);
+}
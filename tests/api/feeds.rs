@@ -0,0 +1,287 @@
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use axum::{extract::State, routing::get, Router};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{post_json, spawn_app_with, TestApp};
+
+type Entries = Arc<Mutex<Vec<(&'static str, &'static str)>>>;
+
+fn atom(entries: &[(&str, &str)]) -> String {
+    let entries: String = entries
+        .iter()
+        .map(|(id, title)| {
+            format!(
+                "<entry><id>urn:blog:{id}</id><title>{title}</title>\
+                 <link href=\"https://example.com/{id}\"/>\
+                 <updated>2023-05-01T10:00:00Z</updated></entry>"
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\"><title>Blog</title><id>urn:blog</id>\
+         <updated>2023-05-01T10:00:00Z</updated>{entries}</feed>"
+    )
+}
+
+/// Serves an Atom feed of `entries` and returns its URL.
+async fn serve_feed(entries: Entries) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let app = Router::new()
+        .route(
+            "/atom.xml",
+            get(|State(entries): State<Entries>| async move { atom(&entries.lock().unwrap()) }),
+        )
+        .with_state(entries);
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+    format!("http://127.0.0.1:{port}/atom.xml")
+}
+
+/// Adds a digest template and a subscriber, and returns the template's id.
+async fn prepare(app: &TestApp) -> i64 {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ('ada@example.org')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+    let template: Value = post_json(
+        app,
+        "/api/templates",
+        json!({
+            "name": "digest",
+            "subject": "{{ count }} new on {{ feed }}",
+            "text": "{{#items}}* {{ title }}: {{ link }}\n{{/items}}"
+        }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse template.");
+    template["id"].as_i64().unwrap()
+}
+
+async fn run_feed(app: &TestApp, feed: &Value) -> Value {
+    post_json(app, &format!("/api/feeds/{}/run", feed["id"]), json!({}))
+        .await
+        .json()
+        .await
+        .expect("Failed to parse run.")
+}
+
+async fn queued(app: &TestApp) -> Vec<String> {
+    sqlx::query!("SELECT raw FROM outbox ORDER BY id")
+        .fetch_all(&app.pool)
+        .await
+        .expect("Failed to fetch outbox.")
+        .into_iter()
+        .map(|row| String::from_utf8_lossy(&row.raw).to_string())
+        .collect()
+}
+
+#[sqlx::test]
+async fn feed_digests_only_new_entries(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let template_id = prepare(&app).await;
+    let entries: Entries = Arc::new(Mutex::new(vec![("1", "Old news")]));
+    let url = serve_feed(entries.clone()).await;
+    let feed: Value = post_json(
+        &app,
+        "/api/feeds",
+        json!({"name": "Blog", "source": url, "schedule": "0 9 * * Mon", "template_id": template_id}),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse feed.");
+    entries.lock().unwrap().push(("2", "Fresh news"));
+
+    // Act
+    let first = run_feed(&app, &feed).await;
+    let second = run_feed(&app, &feed).await;
+
+    // Assert
+    assert_eq!(json!(1), first["items"]);
+    assert_eq!(json!(1), first["deliveries"]);
+    assert_eq!(json!(0), second["items"]);
+    assert_eq!(Value::Null, second["campaign_id"]);
+    let queued = queued(&app).await;
+    assert_eq!(1, queued.len());
+    assert!(queued[0].contains("Subject: 1 new on Blog"));
+    assert!(queued[0].contains("* Fresh news: https://example.com/2"));
+    assert!(!queued[0].contains("Old news"));
+}
+
+#[sqlx::test]
+async fn feed_digests_skip_suppressed_addresses(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let template_id = prepare(&app).await;
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ('grace@example.org')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+    sqlx::query!("INSERT INTO suppressions(email, reason) VALUES ('ada@example.org', 'complaint')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to suppress address.");
+    let url = serve_feed(Arc::new(Mutex::new(vec![("1", "News")]))).await;
+    let feed: Value = post_json(
+        &app,
+        "/api/feeds",
+        json!({
+            "name": "Blog",
+            "source": url,
+            "schedule": "0 9 * * Mon",
+            "template_id": template_id,
+            "send_existing": true
+        }),
+    )
+    .await
+    .json()
+    .await
+    .expect("Failed to parse feed.");
+
+    // Act
+    let run = run_feed(&app, &feed).await;
+
+    // Assert
+    assert_eq!(json!(1), run["deliveries"]);
+    let queued = queued(&app).await;
+    assert_eq!(1, queued.len());
+    assert!(queued[0].contains("grace@example.org"));
+    assert!(!queued[0].contains("ada@example.org"));
+}
+
+#[sqlx::test]
+async fn feed_files_are_only_read_from_the_feed_directory(pool: PgPool) {
+    // Arrange
+    let outside = std::env::temp_dir().join(format!("minimail-feeds-{}", std::process::id()));
+    let directory = outside.join("feeds");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("blog.xml"), atom(&[("1", "First")])).unwrap();
+    std::fs::write(outside.join("secret.xml"), atom(&[("1", "First")])).unwrap();
+    let configured = directory.to_str().unwrap().to_string();
+    let app = spawn_app_with(pool.clone(), |settings| {
+        settings.feed.directory = Some(configured)
+    })
+    .await;
+    let without_directory = spawn_app_with(pool, |_| {}).await;
+    let template_id = prepare(&app).await;
+    let feed = |source: &str| {
+        json!({
+            "name": "Blog",
+            "source": source,
+            "schedule": "0 9 * * Mon",
+            "template_id": template_id
+        })
+    };
+
+    // Act
+    let inside = post_json(&app, "/api/feeds", feed("blog.xml")).await;
+    let escaping = post_json(&app, "/api/feeds", feed("../secret.xml")).await;
+    let absolute = post_json(
+        &app,
+        "/api/feeds",
+        feed(outside.join("secret.xml").to_str().unwrap()),
+    )
+    .await;
+    let unconfigured = post_json(&without_directory, "/api/feeds", feed("blog.xml")).await;
+
+    // Assert
+    std::fs::remove_dir_all(&outside).unwrap();
+    assert!(inside.status().is_success());
+    assert_eq!(422, escaping.status().as_u16());
+    assert_eq!(422, absolute.status().as_u16());
+    assert_eq!(422, unconfigured.status().as_u16());
+}
+
+#[sqlx::test]
+async fn feeds_over_the_size_limit_are_rejected(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |settings| settings.feed.max_size = 100).await;
+    let template_id = prepare(&app).await;
+    let url = serve_feed(Arc::new(Mutex::new(vec![("1", "First")]))).await;
+
+    // Act
+    let response = post_json(
+        &app,
+        "/api/feeds",
+        json!({"name": "Blog", "source": url, "schedule": "0 9 * * Mon", "template_id": template_id}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn due_feeds_are_run_on_schedule(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |settings| settings.feed.interval = 1).await;
+    let template_id = prepare(&app).await;
+    let entries: Entries = Arc::new(Mutex::new(vec![("1", "News")]));
+    let url = serve_feed(entries).await;
+    post_json(
+        &app,
+        "/api/feeds",
+        json!({
+            "name": "Blog",
+            "source": url,
+            "schedule": "0 9 * * Mon",
+            "template_id": template_id,
+            "send_existing": true
+        }),
+    )
+    .await;
+
+    // Act
+    sqlx::query!("UPDATE feeds SET next_run_at = now()")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to make feed due.");
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    // Assert
+    assert_eq!(1, queued(&app).await.len());
+    let feed = sqlx::query!("SELECT next_run_at, last_sent_at FROM feeds")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert!(feed.next_run_at > chrono::Utc::now());
+    assert!(feed.last_sent_at.is_some());
+}
+
+#[sqlx::test]
+async fn feeds_need_a_valid_schedule_and_source(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let template_id = prepare(&app).await;
+
+    // Act
+    let bad_schedule = post_json(
+        &app,
+        "/api/feeds",
+        json!({"name": "Blog", "source": "/nonexistent.xml", "schedule": "weekly", "template_id": template_id}),
+    )
+    .await;
+    let bad_source = post_json(
+        &app,
+        "/api/feeds",
+        json!({"name": "Blog", "source": "/nonexistent.xml", "schedule": "0 9 * * Mon", "template_id": template_id}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(422, bad_schedule.status().as_u16());
+    assert_eq!(422, bad_source.status().as_u16());
+}
@@ -0,0 +1,236 @@
+use minimail::config::FormSettings;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+async fn spawn_with_form(pool: PgPool) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.form = FormSettings {
+            fields: vec!["name".to_string()],
+            consent: Some("I agree to the privacy policy".to_string()),
+        };
+        settings.list.origins = vec!["https://www.example.com".to_string()];
+    })
+    .await
+}
+
+async fn post_json(app: &TestApp, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/subscriptions", app.address))
+        .header("origin", "https://www.example.com")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn subscriber_count(app: &TestApp) -> i64 {
+    sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[sqlx::test]
+async fn snippet_has_list_fields(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let unauthorized = client
+        .get(format!("{}/api/forms/snippet", app.address))
+        .bearer_auth("wrong")
+        .send()
+        .await
+        .unwrap();
+    let snippet = client
+        .get(format!("{}/api/forms/snippet?ajax=true", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, unauthorized.status().as_u16());
+    assert!(snippet.contains(&format!("action=\"{}/api/subscribe\"", app.address)));
+    assert!(snippet.contains("name=\"name\""));
+    assert!(snippet.contains("I agree to the privacy policy"));
+    assert!(snippet.contains("name=\"website\""));
+    assert!(snippet.contains(&format!(
+        "\"subscriptions\":\"{}/api/subscriptions\"",
+        app.address
+    )));
+}
+
+#[sqlx::test]
+async fn json_signup_subscribes_with_fields(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+
+    // Act
+    let response = post_json(
+        &app,
+        json!({"email": "ada@example.org", "name": "Ada", "consent": "true", "website": ""}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "https://www.example.com",
+        response.headers()["access-control-allow-origin"]
+    );
+    assert_eq!(
+        json!({"status": "subscribed"}),
+        response.json::<Value>().await.unwrap()
+    );
+    let saved = sqlx::query!("SELECT email, attributes FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!("ada@example.org", saved.email);
+    assert_eq!(json!({"name": "Ada"}), saved.attributes);
+}
+
+#[sqlx::test]
+async fn json_signup_reports_invalid_fields(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+
+    // Act
+    let response = post_json(&app, json!({"email": "ada"})).await;
+    let status = response.status().as_u16();
+    let body: Value = response.json().await.unwrap();
+    let malformed = reqwest::Client::new()
+        .post(format!("{}/api/subscriptions", app.address))
+        .body("email=ada")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(422, status);
+    assert_eq!(
+        json!({"errors": [
+            {"field": "email", "message": "Enter a valid email address."},
+            {"field": "consent", "message": "Tick the box to subscribe."}
+        ]}),
+        body
+    );
+    assert_eq!(400, malformed.status().as_u16());
+    assert_eq!(0, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn honeypot_signups_are_dropped(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+
+    // Act
+    let json = post_json(
+        &app,
+        json!({"email": "bot@example.org", "consent": true, "website": "https://spam.example"}),
+    )
+    .await;
+    let form = reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .header("origin", &app.address)
+        .form(&[
+            ("email", "bot@example.org"),
+            ("consent", "true"),
+            ("website", "https://spam.example"),
+        ])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(200, json.status().as_u16());
+    assert_eq!(200, form.status().as_u16());
+    assert_eq!(0, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn preflight_allows_configured_origins(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+    let preflight = |origin: &'static str| {
+        reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}/api/subscriptions", app.address),
+            )
+            .header("origin", origin)
+            .header("access-control-request-method", "POST")
+            .send()
+    };
+
+    // Act
+    let allowed = preflight("https://www.example.com").await.unwrap();
+    let other = preflight("https://evil.example").await.unwrap();
+
+    // Assert
+    assert_eq!(204, allowed.status().as_u16());
+    assert_eq!(
+        "https://www.example.com",
+        allowed.headers()["access-control-allow-origin"]
+    );
+    assert_eq!("POST", allowed.headers()["access-control-allow-methods"]);
+    assert!(other.headers().get("access-control-allow-origin").is_none());
+}
+
+#[sqlx::test]
+async fn json_signup_checks_addresses(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_form(pool).await;
+
+    // Act
+    let disposable = post_json(
+        &app,
+        json!({"email": "ada@mailinator.com", "consent": true}),
+    )
+    .await;
+    let disposable_status = disposable.status().as_u16();
+    let disposable: Value = disposable.json().await.unwrap();
+    let typo: Value = post_json(&app, json!({"email": "info@gmial.com", "consent": true}))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(422, disposable_status);
+    assert_eq!(
+        json!({"errors": [{"field": "email", "message": "Use an address that doesn't expire."}]}),
+        disposable
+    );
+    assert_eq!(
+        json!({
+            "status": "subscribed",
+            "warnings": [
+                {"field": "email", "message": "Use a personal address instead of a shared one."},
+                {
+                    "field": "email",
+                    "message": "Did you mean info@gmail.com?",
+                    "suggestion": "info@gmail.com"
+                }
+            ]
+        }),
+        typo
+    );
+    let tags = sqlx::query!(
+        "SELECT tag FROM subscriber_tags JOIN subscribers ON id = subscriber_id ORDER BY tag"
+    )
+    .fetch_all(&app.pool)
+    .await
+    .unwrap();
+    let tags: Vec<_> = tags.into_iter().map(|row| row.tag).collect();
+    assert_eq!(vec!["flagged:role", "flagged:typo"], tags);
+    assert_eq!(1, subscriber_count(&app).await);
+}
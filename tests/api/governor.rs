@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use minimail::{
+    config::{DomainLimits, SmtpSettings},
+    inbound::SmtpServer,
+};
+use serde_json::Value;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, TestApp};
+
+async fn enqueue(app: &TestApp, recipient: &str) {
+    sqlx::query!(
+        "INSERT INTO outbox(sender, recipient, raw) VALUES ('list@example.org', $1, $2)",
+        recipient,
+        b"Subject: Hi\r\n\r\nHello\r\n".as_slice(),
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to enqueue delivery.");
+}
+
+async fn report(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .get(format!("{}/api/outbound/governor", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+type Row = (String, i32, String);
+
+fn rows(rows: &[(&str, i32, &str)]) -> Vec<Row> {
+    rows.iter()
+        .map(|(recipient, attempts, status)| (recipient.to_string(), *attempts, status.to_string()))
+        .collect()
+}
+
+/// Waits until the outbox, as recipient, attempts and status by id, is as
+/// expected, and returns it.
+async fn outbox_becomes(app: &TestApp, expected: &[(&str, i32, &str)]) -> Vec<Row> {
+    let expected = rows(expected);
+    let mut outbox = Vec::new();
+    for _ in 0..50 {
+        outbox = sqlx::query!("SELECT recipient, attempts, status FROM outbox ORDER BY id")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.recipient, row.attempts, row.status))
+            .collect();
+        if outbox == expected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    outbox
+}
+
+#[sqlx::test]
+async fn hourly_caps_hold_further_mail_to_domain(pool: PgPool) {
+    // Arrange
+    let (port, mut relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        use_relay(settings, port);
+        settings.governor.domains.insert(
+            "example.org".to_string(),
+            DomainLimits {
+                concurrency: 1,
+                hourly: 2,
+            },
+        );
+    })
+    .await;
+
+    // Act
+    for recipient in ["ada@example.org", "grace@example.org", "hedy@example.org"] {
+        enqueue(&app, recipient).await;
+    }
+    relayed_message(&mut relayed).await;
+    relayed_message(&mut relayed).await;
+    let expected = [
+        ("ada@example.org", 0, "sent"),
+        ("grace@example.org", 0, "sent"),
+        ("hedy@example.org", 0, "pending"),
+    ];
+    let outbox = outbox_becomes(&app, &expected).await;
+    let report = report(&app).await;
+
+    // Assert
+    assert_eq!(rows(&expected), outbox);
+    assert!(relayed.try_recv().is_err());
+    assert_eq!(1, report["held"]);
+    assert_eq!(2, report["domains"]["example.org"]["sent_last_hour"]);
+    assert_eq!(2, report["domains"]["example.org"]["hourly_limit"]);
+}
+
+#[sqlx::test]
+async fn deferrals_pause_domain(pool: PgPool) {
+    // Arrange
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, mut relayed) = mpsc::unbounded_channel();
+    let relay = SmtpServer::from(SmtpSettings {
+        host: "127.0.0.1".to_string(),
+        port,
+        hostname: "relay".to_string(),
+        domains: vec!["busy.example".to_string(), "example.org".to_string()],
+        size: 1024 * 1024,
+    });
+    tokio::spawn(relay.serve(listener, move |envelope, _| {
+        let sender = sender.clone();
+        async move {
+            if envelope
+                .recipients
+                .iter()
+                .any(|to| to.ends_with("@busy.example"))
+            {
+                anyhow::bail!("Try again later");
+            }
+            sender.send((envelope.recipients, Vec::new())).ok();
+            Ok(())
+        }
+    }));
+    let app = spawn_app_with(pool, |settings| use_relay(settings, port)).await;
+
+    // Act
+    enqueue(&app, "ada@busy.example").await;
+    let deferred = outbox_becomes(&app, &[("ada@busy.example", 1, "pending")]).await;
+    enqueue(&app, "grace@busy.example").await;
+    enqueue(&app, "hedy@example.org").await;
+    relayed_message(&mut relayed).await;
+    let expected = [
+        ("ada@busy.example", 1, "pending"),
+        ("grace@busy.example", 0, "pending"),
+        ("hedy@example.org", 0, "sent"),
+    ];
+    let outbox = outbox_becomes(&app, &expected).await;
+    let report = report(&app).await;
+
+    // Assert
+    assert_eq!(rows(&[("ada@busy.example", 1, "pending")]), deferred);
+    assert_eq!(rows(&expected), outbox);
+    let busy = &report["domains"]["busy.example"];
+    assert_eq!(1, busy["deferrals"]);
+    assert_eq!(1, busy["held"]);
+    assert!(busy["paused_until"].is_string());
+    assert!(report["domains"]["example.org"]["paused_until"].is_null());
+}
@@ -0,0 +1,131 @@
+use std::{net::TcpListener, time::Duration};
+
+use serde_json::Value;
+use sqlx::{PgPool, Pool, Postgres};
+use tokio::sync::mpsc;
+
+use minimail::{
+    config::{get_configuration, AdminSettings, Settings, SmtpSettings, SubscribedSettings},
+    inbound::SmtpServer,
+    startup::run,
+};
+
+pub type Relayed = mpsc::UnboundedReceiver<(Vec<String>, Vec<u8>)>;
+
+pub struct TestApp {
+    pub address: String,
+    pub pool: Pool<Postgres>,
+    // Port of the SMTP listener, when the test configured one.
+    pub smtp_port: Option<u16>,
+}
+
+pub async fn spawn_app(
+    pool: PgPool,
+    admin: AdminSettings,
+    redirect: SubscribedSettings,
+) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.admin = admin;
+        settings.application.subscribed = redirect;
+    })
+    .await
+}
+
+/// Spawns the app with the base configuration, after letting `configure`
+/// adjust it for the test.
+pub async fn spawn_app_with(pool: PgPool, configure: impl FnOnce(&mut Settings)) -> TestApp {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    // We retrieve the port assigned to us by the OS
+    let port = listener.local_addr().unwrap().port();
+    let address = format!("http://127.0.0.1:{port}");
+
+    let mut settings = get_configuration().expect("Failed to read configuration.");
+    settings.admin = AdminSettings {
+        token: "admin".to_string(),
+    };
+    settings.application.subscribed = SubscribedSettings::default();
+    settings.tracking.url = address.clone();
+    settings.moderation.url = address.clone();
+    settings.pages.url = address.clone();
+    settings.tracking.secret = "secret".to_string();
+    settings.inbound.token = "inbound-token".to_string();
+    settings.list.secret = "list-secret".to_string();
+    configure(&mut settings);
+
+    let smtp_listener = settings.inbound.smtp.as_mut().map(|smtp| {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+        smtp.port = listener.local_addr().unwrap().port();
+        listener
+    });
+    let smtp_port = settings.inbound.smtp.as_ref().map(|smtp| smtp.port);
+
+    let server = run(listener, smtp_listener, pool.clone(), settings);
+    tokio::spawn(server);
+    TestApp {
+        address,
+        pool,
+        smtp_port,
+    }
+}
+
+/// Starts an SMTP server standing in for the relay, accepting mail for
+/// `domain`, and returns its port and the messages it receives.
+pub async fn start_relay(domain: &str) -> (u16, Relayed) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    let settings = SmtpSettings {
+        host: "127.0.0.1".to_string(),
+        port,
+        hostname: "relay".to_string(),
+        domains: vec![domain.to_string()],
+        size: 1024 * 1024,
+    };
+    tokio::spawn(
+        SmtpServer::from(settings).serve(listener, move |envelope, raw| {
+            let sender = sender.clone();
+            async move {
+                sender.send((envelope.recipients, raw)).ok();
+                Ok(())
+            }
+        }),
+    );
+
+    (port, receiver)
+}
+
+/// Sends the app's outgoing mail to a relay started with `start_relay`.
+pub fn use_relay(settings: &mut Settings, port: u16) {
+    settings.mailer.host = "127.0.0.1".to_string();
+    settings.mailer.port = port;
+    settings.mailer.tls = false;
+    settings.mailer.interval = 1;
+}
+
+pub async fn relayed_message(relayed: &mut Relayed) -> (Vec<String>, Vec<u8>) {
+    tokio::time::timeout(Duration::from_secs(5), relayed.recv())
+        .await
+        .expect("No message was relayed.")
+        .unwrap()
+}
+
+/// Reads a relayed message, returning its recipient and decoded text.
+pub async fn relayed_text(relayed: &mut Relayed) -> (String, String) {
+    let (recipients, raw) = relayed_message(relayed).await;
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    (recipients[0].clone(), mail.get_body().unwrap())
+}
+
+/// Posts JSON to an admin endpoint.
+pub async fn post_json(app: &TestApp, path: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}{path}", app.address))
+        .bearer_auth("admin")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
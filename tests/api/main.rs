@@ -1 +1,24 @@
+mod ab_tests;
+mod activity;
+mod admin;
+mod archive;
+mod automations;
+mod bounces;
+mod campaigns;
+mod complaints;
+mod deliverability;
+mod feeds;
+mod forms;
+mod governor;
+mod helpers;
+mod moderation;
+mod pages;
+mod posts;
+mod protection;
+mod requests;
+mod send;
+mod smtp;
 mod subscribers;
+mod tracking;
+mod warmup;
+mod webhooks;
@@ -0,0 +1,260 @@
+use minimail::config::{ListMode, Settings};
+use reqwest::redirect::Policy;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_text, spawn_app_with, start_relay, use_relay, Relayed, TestApp};
+
+fn post(from: &str) -> String {
+    format!(
+        "From: {from}\r\n\
+         To: news@localhost\r\n\
+         Subject: Hello\r\n\
+         \r\n\
+         Hi all\r\n"
+    )
+}
+
+fn moderated(settings: &mut Settings) {
+    settings.list.mode = ListMode::Discussion;
+    settings.moderation.moderators = vec!["moderator@example.org".to_string()];
+}
+
+async fn spawn_moderated(
+    pool: PgPool,
+    configure: impl FnOnce(&mut Settings),
+) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        use_relay(settings, port);
+        moderated(settings);
+        configure(settings);
+    })
+    .await;
+    (app, relayed)
+}
+
+async fn create_member(app: &TestApp, email: &str) {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ($1)", email)
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+}
+
+async fn post_post(app: &TestApp, from: &str) -> Value {
+    reqwest::Client::new()
+        .post(format!("{}/api/inbound/posts", app.address))
+        .bearer_auth("inbound-token")
+        .body(post(from))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn admin_post(app: &TestApp, path: &str, body: Option<Value>) -> reqwest::Response {
+    let request = reqwest::Client::new()
+        .post(format!("{}{path}", app.address))
+        .bearer_auth("admin");
+    let request = match body {
+        Some(body) => request.json(&body),
+        None => request,
+    };
+    request.send().await.expect("Failed to execute request.")
+}
+
+async fn queue(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .get(format!("{}/api/moderation", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+fn link(text: &str, label: &str) -> String {
+    text.lines()
+        .find_map(|line| line.strip_prefix(label))
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+#[sqlx::test]
+async fn held_post_is_approved_with_link_from_notification(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_moderated(pool, |_| {}).await;
+    create_member(&app, "member@example.org").await;
+    post_post(&app, "outsider@example.org").await;
+    let (moderator, notification) = relayed_text(&mut relayed).await;
+    let approve = link(&notification, "Approve:");
+    let client = reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let page = client.get(&approve).send().await.unwrap();
+    let page_status = page.status().as_u16();
+    let page = page.text().await.unwrap();
+    let done = client
+        .post(&approve)
+        .form(&[("reason", "")])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!("moderator@example.org", moderator);
+    assert!(notification.contains("outsider@example.org sent a post"));
+    assert_eq!(200, page_status);
+    assert!(page.contains("<form method=\"post\">"));
+    assert!(page.contains("“Hello” by outsider@example.org"));
+    assert_eq!(200, done.status().as_u16());
+    assert!(done.text().await.unwrap().contains("sent to the list"));
+    let (recipient, text) = relayed_text(&mut relayed).await;
+    assert_eq!("member@example.org", recipient);
+    assert_eq!("Hi all", text.trim_end());
+}
+
+#[sqlx::test]
+async fn forged_link_is_rejected(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_moderated(pool, |_| {}).await;
+
+    // Act
+    let response = reqwest::get(format!("{}/moderate/abc.def", app.address))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn rejected_post_tells_sender_the_reason(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_moderated(pool, |_| {}).await;
+    let post = post_post(&app, "outsider@example.org").await;
+    relayed_text(&mut relayed).await;
+    let held = queue(&app).await;
+
+    // Act
+    let response = admin_post(
+        &app,
+        &format!("/api/moderation/posts/{}/reject", post["id"]),
+        Some(serde_json::json!({ "reason": "Off topic" })),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(post["id"], held["posts"][0]["id"]);
+    let rejected: Value = response.json().await.unwrap();
+    assert_eq!("rejected", rejected["status"]);
+    assert_eq!("Off topic", rejected["reason"]);
+    let (recipient, text) = relayed_text(&mut relayed).await;
+    assert_eq!("outsider@example.org", recipient);
+    assert!(text.contains("Off topic"));
+    assert!(queue(&app).await["posts"].as_array().unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn approving_with_allow_lets_sender_post_freely(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_moderated(pool, |_| {}).await;
+    let first = post_post(&app, "guest@example.org").await;
+
+    // Act
+    let response = admin_post(
+        &app,
+        &format!("/api/moderation/posts/{}/approve?allow=true", first["id"]),
+        None,
+    )
+    .await;
+    let second = post_post(&app, "guest@example.org").await;
+    let again = admin_post(
+        &app,
+        &format!("/api/moderation/posts/{}/approve", first["id"]),
+        None,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("held", first["status"]);
+    assert_eq!("accepted", second["status"]);
+    assert_eq!(404, again.status().as_u16());
+    let allowlist: Value = reqwest::Client::new()
+        .get(format!("{}/api/moderation/allowlist", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(serde_json::json!(["guest@example.org"]), allowlist);
+}
+
+#[sqlx::test]
+async fn moderated_signups_wait_for_approval(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_moderated(pool, |settings| {
+        settings.moderation.signups = true;
+    })
+    .await;
+    let client = reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+    client
+        .post(format!("{}/api/subscribe", app.address))
+        .header("origin", &app.address)
+        .form(&[("email", "new@example.org")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let (moderator, notification) = relayed_text(&mut relayed).await;
+    let pending = queue(&app).await;
+
+    // Act
+    let response = admin_post(
+        &app,
+        &format!(
+            "/api/moderation/signups/{}/approve",
+            pending["signups"][0]["id"]
+        ),
+        None,
+    )
+    .await;
+
+    // Assert
+    assert_eq!("moderator@example.org", moderator);
+    assert!(notification.contains("new@example.org wants to subscribe"));
+    assert_eq!("pending", pending["signups"][0]["status"]);
+    let approved: Value = response.json().await.unwrap();
+    assert_eq!("active", approved["status"]);
+    assert!(queue(&app).await["signups"].as_array().unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn moderation_requires_admin_token(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_moderated(pool, |_| {}).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/moderation", app.address))
+        .bearer_auth("wrong")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+}
@@ -0,0 +1,345 @@
+use mailparse::MailHeaderMap;
+use minimail::config::Settings;
+use reqwest::header::{COOKIE, SET_COOKIE};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{
+    relayed_message, relayed_text, spawn_app_with, start_relay, use_relay, Relayed, TestApp,
+};
+
+async fn spawn_relayed(pool: PgPool, configure: impl FnOnce(&mut Settings)) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        use_relay(settings, port);
+        configure(settings);
+    })
+    .await;
+    (app, relayed)
+}
+
+/// A browser that keeps the CSRF cookie and the form token of the last page.
+struct Visitor {
+    client: reqwest::Client,
+    cookie: Option<String>,
+    csrf: String,
+}
+
+impl Visitor {
+    fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            cookie: None,
+            csrf: String::new(),
+        }
+    }
+
+    async fn get(&mut self, url: &str) -> (u16, String) {
+        let mut request = self.client.get(url);
+        if let Some(cookie) = &self.cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        let response = request.send().await.expect("Failed to execute request.");
+        if let Some(cookie) = response.headers().get(SET_COOKIE) {
+            let cookie = cookie.to_str().unwrap();
+            self.cookie = Some(cookie.split(';').next().unwrap().to_string());
+        }
+        let status = response.status().as_u16();
+        let page = response.text().await.unwrap();
+        if let Some((_, rest)) = page.split_once("name=\"csrf\" value=\"") {
+            self.csrf = rest.split('"').next().unwrap().to_string();
+        }
+        (status, page)
+    }
+
+    async fn post(&self, url: &str, form: &[(&str, &str)]) -> (u16, String) {
+        let mut form = form.to_vec();
+        form.push(("csrf", &self.csrf));
+        let mut request = self.client.post(url).form(&form);
+        if let Some(cookie) = &self.cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        let response = request.send().await.expect("Failed to execute request.");
+        (response.status().as_u16(), response.text().await.unwrap())
+    }
+}
+
+fn link(text: &str) -> String {
+    text.lines()
+        .find_map(|line| line.split_once("go to "))
+        .map(|(_, url)| url.trim().to_string())
+        .unwrap()
+}
+
+async fn create_subscriber(app: &TestApp, email: &str) {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ($1)", email)
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+}
+
+async fn subscribe(app: &TestApp, email: &str) -> (u16, String) {
+    let mut visitor = Visitor::new();
+    visitor.get(&format!("{}/subscribe", app.address)).await;
+    visitor
+        .post(
+            &format!("{}/subscribe", app.address),
+            &[("email", email), ("timezone", "Europe/Berlin")],
+        )
+        .await
+}
+
+/// Sends a campaign with the given text to every subscriber right away.
+async fn send_campaign(app: &TestApp, text: &str) {
+    let client = reqwest::Client::new();
+    let template: Value = client
+        .post(format!("{}/api/templates", app.address))
+        .bearer_auth("admin")
+        .json(&json!({
+            "name": "news",
+            "subject": "News",
+            "text": text
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let campaign: Value = client
+        .post(format!("{}/api/campaigns", app.address))
+        .bearer_auth("admin")
+        .json(&json!({ "name": "news" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    client
+        .post(format!(
+            "{}/api/campaigns/{}/schedule",
+            app.address, campaign["id"]
+        ))
+        .bearer_auth("admin")
+        .json(&json!({"template_id": template["id"], "send_at": "2020-01-01T09:00:00"}))
+        .send()
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn subscribing_by_page_waits_for_confirmation(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_relayed(pool, |_| {}).await;
+    let mut visitor = Visitor::new();
+
+    // Act
+    let (form_status, form) = visitor.get(&format!("{}/subscribe", app.address)).await;
+    let (status, page) = subscribe(&app, "ada@example.org").await;
+    let unconfirmed = sqlx::query!("SELECT email FROM subscribers")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    let (recipient, text) = relayed_text(&mut relayed).await;
+    let confirm = link(&text);
+    let (confirm_status, confirm_page) = visitor.get(&confirm).await;
+    let (done_status, done) = visitor.post(&confirm, &[]).await;
+
+    // Assert
+    assert_eq!(200, form_status);
+    assert!(form.contains("<title>Subscribe to Minimail · Minimail</title>"));
+    assert!(visitor.cookie.unwrap().starts_with("minimail_csrf="));
+    assert_eq!(200, status);
+    assert!(page.contains("We sent a link to ada@example.org"));
+    assert!(unconfirmed.is_empty());
+    assert_eq!("ada@example.org", recipient);
+    assert!(confirm.starts_with(&format!("{}/confirm/", app.address)));
+    assert_eq!(200, confirm_status);
+    assert!(confirm_page.contains("Confirm the subscription of ada@example.org"));
+    assert_eq!(200, done_status);
+    assert!(done.contains("ada@example.org is now subscribed to Minimail"));
+    let subscriber = sqlx::query!("SELECT status, timezone FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!("active", subscriber.status);
+    assert_eq!(Some("Europe/Berlin".to_string()), subscriber.timezone);
+}
+
+#[sqlx::test]
+async fn forms_without_csrf_token_are_rejected(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let mut visitor = Visitor::new();
+    visitor.get(&format!("{}/subscribe", app.address)).await;
+    let without_cookie = Visitor {
+        cookie: None,
+        ..Visitor::new()
+    };
+    let forged = Visitor {
+        csrf: "forged.token".to_string(),
+        cookie: visitor.cookie.clone(),
+        ..Visitor::new()
+    };
+
+    // Act
+    let responses = [
+        without_cookie
+            .post(
+                &format!("{}/subscribe", app.address),
+                &[("email", "ada@example.org")],
+            )
+            .await,
+        forged
+            .post(
+                &format!("{}/subscribe", app.address),
+                &[("email", "ada@example.org")],
+            )
+            .await,
+    ];
+
+    // Assert
+    for (status, page) in responses {
+        assert_eq!(403, status);
+        assert!(page.contains("This form has expired"));
+    }
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(0, queued.count);
+}
+
+#[sqlx::test]
+async fn preferences_pick_topics_and_edit_attributes(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_relayed(pool, |settings| {
+        settings.pages.topics = vec!["rust".to_string(), "go".to_string()];
+        settings.pages.attributes = vec!["name".to_string()];
+    })
+    .await;
+    create_subscriber(&app, "ada@example.org").await;
+    subscribe(&app, "ada@example.org").await;
+    let (_, text) = relayed_text(&mut relayed).await;
+    let preferences = link(&text);
+    let mut visitor = Visitor::new();
+
+    // Act
+    let (status, page) = visitor.get(&preferences).await;
+    let (saved_status, saved) = visitor
+        .post(
+            &preferences,
+            &[
+                ("subscribed", "true"),
+                ("topic", "rust"),
+                ("attribute.name", "Ada <Lovelace>"),
+            ],
+        )
+        .await;
+    let (_, reloaded) = visitor.get(&preferences).await;
+
+    // Assert
+    assert!(text.contains("ada@example.org is already subscribed"));
+    assert_eq!(200, status);
+    assert!(page.contains("<input type=\"checkbox\" name=\"topic\" value=\"go\"> go"));
+    assert!(page.contains("<input name=\"attribute.name\" value=\"\">"));
+    assert_eq!(200, saved_status);
+    assert!(saved.contains("Your preferences for Minimail have been saved"));
+    assert!(reloaded.contains("value=\"rust\" checked"));
+    assert!(reloaded.contains("value=\"Ada &lt;Lovelace&gt;\""));
+    let subscriber = sqlx::query!(
+        r#"SELECT attributes, ARRAY(SELECT tag FROM subscriber_tags t WHERE t.subscriber_id = s.id) AS "tags!" FROM subscribers s"#
+    )
+    .fetch_one(&app.pool)
+    .await
+    .unwrap();
+    assert_eq!(vec!["rust".to_string()], subscriber.tags);
+    assert_eq!(json!({"name": "Ada <Lovelace>"}), subscriber.attributes);
+}
+
+#[sqlx::test]
+async fn campaign_mail_links_to_unsubscribe_page(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_relayed(pool, |_| {}).await;
+    create_subscriber(&app, "ada@example.org").await;
+    send_campaign(&app, "Hi\n\nTo leave, go to {{ unsubscribe_url }}").await;
+    let (_, raw) = relayed_message(&mut relayed).await;
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    let unsubscribe = link(&mail.get_body().unwrap());
+    let mut visitor = Visitor::new();
+
+    // Act
+    let (status, page) = visitor.get(&unsubscribe).await;
+    let (done_status, done) = visitor.post(&unsubscribe, &[]).await;
+    let (gone_status, _) = visitor.get(&unsubscribe).await;
+
+    // Assert
+    assert_eq!(
+        Some(format!("<{unsubscribe}>")),
+        mail.headers.get_first_value("List-Unsubscribe")
+    );
+    assert_eq!(
+        Some("List-Unsubscribe=One-Click".to_string()),
+        mail.headers.get_first_value("List-Unsubscribe-Post")
+    );
+    assert_eq!(200, status);
+    assert!(page.contains("Unsubscribe ada@example.org from Minimail?"));
+    assert_eq!(200, done_status);
+    assert!(done.contains("ada@example.org has been unsubscribed"));
+    assert_eq!(404, gone_status);
+    let subscribers = sqlx::query!("SELECT email FROM subscribers")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert!(subscribers.is_empty());
+}
+
+#[sqlx::test]
+async fn mail_clients_unsubscribe_with_one_click(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_relayed(pool, |_| {}).await;
+    create_subscriber(&app, "ada@example.org").await;
+    send_campaign(&app, "Hi\n\nTo leave, go to {{ unsubscribe_url }}").await;
+    let (_, text) = relayed_text(&mut relayed).await;
+    let unsubscribe = link(&text);
+    let preferences = unsubscribe.replace("/unsubscribe/", "/preferences/");
+
+    // Act
+    let (preferences_status, _) = Visitor::new().get(&preferences).await;
+    let response = reqwest::Client::new()
+        .post(&unsubscribe)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(404, preferences_status);
+    assert_eq!(200, response.status().as_u16());
+    let subscribers = sqlx::query!("SELECT email FROM subscribers")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert!(subscribers.is_empty());
+}
+
+#[sqlx::test]
+async fn forged_links_show_invalid_page(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    let mut visitor = Visitor::new();
+
+    // Act
+    let (confirm_status, page) = visitor
+        .get(&format!("{}/confirm/forged.token", app.address))
+        .await;
+    let (preferences_status, _) = visitor
+        .get(&format!("{}/preferences/forged.token", app.address))
+        .await;
+
+    // Assert
+    assert_eq!(404, confirm_status);
+    assert!(page.contains("This link is invalid or has expired"));
+    assert_eq!(404, preferences_status);
+}
@@ -0,0 +1,178 @@
+use mailparse::MailHeaderMap;
+use minimail::config::{ListMode, Settings};
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, Relayed, TestApp};
+
+fn post(from: &str) -> String {
+    format!(
+        "From: {from}\r\n\
+         To: news@localhost\r\n\
+         Subject: Hello\r\n\
+         \r\n\
+         Hi all\r\n"
+    )
+}
+
+async fn post_post(app: &TestApp, body: String) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/inbound/posts", app.address))
+        .bearer_auth("inbound-token")
+        .header("Content-Type", "message/rfc822")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn discussion(settings: &mut Settings) {
+    settings.list.mode = ListMode::Discussion;
+    settings.list.prefix = Some("[news]".to_string());
+    settings.list.footer = Some("Leave with unsubscribe to news-request@localhost".to_string());
+}
+
+async fn spawn_discussion(pool: PgPool) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        use_relay(settings, port);
+        discussion(settings);
+    })
+    .await;
+    (app, relayed)
+}
+
+async fn create_member(app: &TestApp, email: &str, digest: bool) {
+    sqlx::query!(
+        "INSERT INTO subscribers(email, digest) VALUES ($1, $2)",
+        email,
+        digest
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+}
+
+async fn outbox_recipients(app: &TestApp) -> Vec<String> {
+    sqlx::query!("SELECT recipient FROM outbox ORDER BY recipient")
+        .fetch_all(&app.pool)
+        .await
+        .expect("Failed to fetch outbox.")
+        .into_iter()
+        .map(|row| row.recipient)
+        .collect()
+}
+
+#[sqlx::test]
+async fn member_posts_are_sent_to_members(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_discussion(pool).await;
+    create_member(&app, "alice@example.org", false).await;
+    create_member(&app, "bob@example.org", false).await;
+
+    // Act
+    let response = post_post(&app, post("Alice <alice@example.org>")).await;
+    let mut received = [
+        relayed_message(&mut relayed).await,
+        relayed_message(&mut relayed).await,
+    ];
+    received.sort();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let (recipients, raw) = &received[1];
+    assert_eq!(&vec!["bob@example.org".to_string()], recipients);
+    let mail = mailparse::parse_mail(raw).unwrap();
+    let header = |name| mail.headers.get_first_value(name).unwrap();
+    assert_eq!("[news] Hello", header("Subject"));
+    assert_eq!("\"Alice via Minimail\" <news@localhost>", header("From"));
+    assert_eq!("Alice <alice@example.org>", header("Reply-To"));
+    assert_eq!("\"Minimail\" <news.localhost>", header("List-Id"));
+    assert!(mail
+        .get_body()
+        .unwrap()
+        .contains("Leave with unsubscribe to news-request@localhost"));
+}
+
+#[sqlx::test]
+async fn posts_of_non_members_are_held(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_discussion(pool).await;
+    create_member(&app, "bob@example.org", false).await;
+
+    // Act
+    let response = post_post(&app, post("mallory@example.net")).await;
+
+    // Assert
+    let post: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("held", post["status"]);
+    assert!(outbox_recipients(&app).await.is_empty());
+}
+
+#[sqlx::test]
+async fn digest_readers_are_left_out(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_discussion(pool).await;
+    create_member(&app, "alice@example.org", false).await;
+    create_member(&app, "bob@example.org", true).await;
+
+    // Act
+    post_post(&app, post("alice@example.org")).await;
+
+    // Assert
+    assert_eq!(
+        vec!["alice@example.org".to_string()],
+        outbox_recipients(&app).await
+    );
+}
+
+#[sqlx::test]
+async fn suppressed_members_are_left_out(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_discussion(pool).await;
+    create_member(&app, "alice@example.org", false).await;
+    create_member(&app, "bob@example.org", false).await;
+    sqlx::query!("INSERT INTO suppressions(email, reason) VALUES ('bob@example.org', 'complaint')")
+        .execute(&app.pool)
+        .await
+        .expect("Failed to suppress address.");
+
+    // Act
+    post_post(&app, post("alice@example.org")).await;
+
+    // Assert
+    assert_eq!(
+        vec!["alice@example.org".to_string()],
+        outbox_recipients(&app).await
+    );
+}
+
+#[sqlx::test]
+async fn automatic_messages_are_not_posted(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_discussion(pool).await;
+    create_member(&app, "alice@example.org", false).await;
+    let vacation = format!(
+        "Auto-Submitted: auto-replied\r\n{}",
+        post("alice@example.org")
+    );
+
+    // Act
+    let response = post_post(&app, vacation).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    assert!(outbox_recipients(&app).await.is_empty());
+}
+
+#[sqlx::test]
+async fn newsletters_do_not_take_posts(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+    create_member(&app, "alice@example.org", false).await;
+
+    // Act
+    let response = post_post(&app, post("alice@example.org")).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
@@ -0,0 +1,184 @@
+use minimail::config::{ChallengeKind, ChallengeSettings, ProtectionSettings, RateLimitSettings};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+async fn spawn_protected(pool: PgPool, protection: ProtectionSettings) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.protection = protection;
+    })
+    .await
+}
+
+async fn post_json(app: &TestApp, body: Value) -> (u16, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/subscriptions", app.address))
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+async fn rejections(app: &TestApp) -> Value {
+    reqwest::Client::new()
+        .get(format!("{}/api/forms/rejections", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn subscriber_count(app: &TestApp) -> i64 {
+    sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[sqlx::test]
+async fn repeated_signups_are_rate_limited(pool: PgPool) {
+    // Arrange
+    let app = spawn_protected(
+        pool,
+        ProtectionSettings {
+            rate_limit: RateLimitSettings {
+                per_address: 2,
+                ..RateLimitSettings::default()
+            },
+            ..ProtectionSettings::default()
+        },
+    )
+    .await;
+
+    // Act
+    let first = post_json(&app, json!({"email": "ada@example.org"})).await;
+    let second = post_json(&app, json!({"email": "Ada@example.org"})).await;
+    let (status, body) = post_json(&app, json!({"email": "ada@example.org"})).await;
+    let other = post_json(&app, json!({"email": "grace@example.org"})).await;
+
+    // Assert
+    assert_eq!(200, first.0);
+    assert_eq!(200, second.0);
+    assert_eq!(429, status);
+    assert_eq!(
+        json!({"errors": [{"field": "", "message": "Too many signups. Please try again later."}]}),
+        body
+    );
+    assert_eq!(200, other.0);
+    assert_eq!(1, rejections(&app).await["address_rate_limited"]);
+}
+
+#[sqlx::test]
+async fn forms_sent_too_quickly_are_rejected(pool: PgPool) {
+    // Arrange
+    let app = spawn_protected(
+        pool,
+        ProtectionSettings {
+            min_fill_seconds: 2,
+            ..ProtectionSettings::default()
+        },
+    )
+    .await;
+    let stamp: Value = reqwest::get(format!("{}/api/forms/stamp", app.address))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let stamp = stamp["stamp"].as_str().unwrap();
+
+    // Act
+    let unstamped = post_json(&app, json!({"email": "ada@example.org"})).await;
+    let hasty = post_json(&app, json!({"email": "ada@example.org", "started": stamp})).await;
+    tokio::time::sleep(std::time::Duration::from_millis(3100)).await;
+    let patient = post_json(&app, json!({"email": "ada@example.org", "started": stamp})).await;
+
+    // Assert
+    assert_eq!(422, unstamped.0);
+    assert_eq!(422, hasty.0);
+    assert_eq!(200, patient.0);
+    assert_eq!(1, subscriber_count(&app).await);
+    assert_eq!(2, rejections(&app).await["too_fast"]);
+}
+
+#[sqlx::test]
+async fn signups_need_to_pass_challenge(pool: PgPool) {
+    // Arrange
+    let app = spawn_protected(
+        pool,
+        ProtectionSettings {
+            challenge: Some(ChallengeSettings {
+                kind: ChallengeKind::Stub,
+                verify_url: None,
+                secret: "solved".to_string(),
+                field: "challenge".to_string(),
+                widget: Some("<div class=\"challenge\"></div>".to_string()),
+            }),
+            ..ProtectionSettings::default()
+        },
+    )
+    .await;
+
+    // Act
+    let form = reqwest::get(format!("{}/subscribe", app.address))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let unsolved = reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .form(&[("email", "ada@example.org"), ("challenge", "guess")])
+        .send()
+        .await
+        .unwrap();
+    let unsolved_status = unsolved.status().as_u16();
+    let unsolved_page = unsolved.text().await.unwrap();
+    let solved = post_json(
+        &app,
+        json!({"email": "ada@example.org", "challenge": "solved"}),
+    )
+    .await;
+
+    // Assert
+    assert!(form.contains("<div class=\"challenge\"></div>"));
+    assert_eq!(422, unsolved_status);
+    assert!(unsolved_page.contains("Please complete the challenge."));
+    assert_eq!(200, solved.0);
+    assert_eq!(1, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn rejections_are_counted_for_admins(pool: PgPool) {
+    // Arrange
+    let app = spawn_protected(pool, ProtectionSettings::default()).await;
+
+    // Act
+    post_json(&app, json!({"email": "bot@example.org", "website": "spam"})).await;
+    let unauthorized = reqwest::Client::new()
+        .get(format!("{}/api/forms/rejections", app.address))
+        .bearer_auth("wrong")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, unauthorized.status().as_u16());
+    assert_eq!(
+        json!({
+            "honeypot": 1,
+            "too_fast": 0,
+            "ip_rate_limited": 0,
+            "address_rate_limited": 0,
+            "challenge_failed": 0
+        }),
+        rejections(&app).await
+    );
+    assert_eq!(0, subscriber_count(&app).await);
+}
@@ -0,0 +1,195 @@
+use mailparse::MailHeaderMap;
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, Relayed, TestApp};
+
+fn request(subject: &str, body: &str) -> String {
+    format!(
+        "From: User <user@example.org>\r\n\
+         To: news-request@localhost\r\n\
+         Message-ID: <1@example.org>\r\n\
+         Subject: {subject}\r\n\
+         \r\n\
+         {body}\r\n"
+    )
+}
+
+async fn post_request(app: &TestApp, token: &str, body: String) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/inbound/requests", app.address))
+        .bearer_auth(token)
+        .header("Content-Type", "message/rfc822")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn spawn_app_with_relay(pool: PgPool) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| use_relay(settings, port)).await;
+    (app, relayed)
+}
+
+async fn reply(relayed: &mut Relayed) -> String {
+    let (recipients, raw) = relayed_message(relayed).await;
+    assert_eq!(vec!["user@example.org".to_string()], recipients);
+    String::from_utf8(raw).unwrap()
+}
+
+async fn subscriber_count(app: &TestApp) -> i64 {
+    sqlx::query!(
+        "SELECT count(*) AS \"count!\" FROM subscribers WHERE email = $1",
+        "user@example.org"
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to count subscribers.")
+    .count
+}
+
+#[sqlx::test]
+async fn subscribe_is_confirmed_by_reply(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+
+    // Act
+    let response = post_request(&app, "inbound-token", request("subscribe", "")).await;
+    let confirmation = reply(&mut relayed).await;
+    let subject = mailparse::parse_mail(confirmation.as_bytes())
+        .unwrap()
+        .headers
+        .get_first_value("Subject")
+        .unwrap();
+    let subscribed_before_confirming = subscriber_count(&app).await;
+    post_request(
+        &app,
+        "inbound-token",
+        request(&format!("Re: {subject}"), "> Someone asked"),
+    )
+    .await;
+    let welcome = reply(&mut relayed).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert!(subject.starts_with("confirm "));
+    assert!(confirmation.contains("Auto-Submitted: auto-replied"));
+    assert!(confirmation.contains("In-Reply-To: <1@example.org>"));
+    assert_eq!(0, subscribed_before_confirming);
+    assert!(welcome.contains("Subject: Welcome to Minimail"));
+    assert_eq!(1, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn forged_confirmation_is_rejected(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+
+    // Act
+    post_request(&app, "inbound-token", request("confirm abc.def", "")).await;
+
+    // Assert
+    assert!(reply(&mut relayed)
+        .await
+        .contains("Subject: Confirmation failed"));
+    assert_eq!(0, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn unsubscribe_is_confirmed_by_reply(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+    sqlx::query!(
+        "INSERT INTO subscribers(email) VALUES ($1)",
+        "user@example.org"
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+
+    // Act
+    let response = post_request(&app, "inbound-token", request("Goodbye", "unsubscribe")).await;
+    let confirmation = reply(&mut relayed).await;
+    let subject = mailparse::parse_mail(confirmation.as_bytes())
+        .unwrap()
+        .headers
+        .get_first_value("Subject")
+        .unwrap();
+    let subscribed_before_confirming = subscriber_count(&app).await;
+    post_request(
+        &app,
+        "inbound-token",
+        request(&format!("Re: {subject}"), "> Someone asked"),
+    )
+    .await;
+    let goodbye = reply(&mut relayed).await;
+
+    // Assert
+    let commands: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!([{ "command": "unsubscribe" }]), commands);
+    assert!(subject.starts_with("confirm "));
+    assert_eq!(1, subscribed_before_confirming);
+    assert!(goodbye.contains("Subject: You have left Minimail"));
+    assert_eq!(0, subscriber_count(&app).await);
+}
+
+#[sqlx::test]
+async fn which_reports_subscription(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+
+    // Act
+    post_request(&app, "inbound-token", request("which", "")).await;
+
+    // Assert
+    assert!(reply(&mut relayed)
+        .await
+        .contains("user@example.org is not subscribed to Minimail."));
+}
+
+#[sqlx::test]
+async fn which_does_not_count_bounced_as_subscribed(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+    sqlx::query!(
+        "INSERT INTO subscribers(email, status) VALUES ($1, 'bounced')",
+        "user@example.org"
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+
+    // Act
+    post_request(&app, "inbound-token", request("which", "")).await;
+
+    // Assert
+    let reply = reply(&mut relayed).await;
+    assert!(reply.contains("user@example.org is no longer subscribed to Minimail"));
+}
+
+#[sqlx::test]
+async fn mail_without_commands_gets_help(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_app_with_relay(pool).await;
+
+    // Act
+    post_request(&app, "inbound-token", request("Hi", "How do I join?")).await;
+
+    // Assert
+    assert!(reply(&mut relayed)
+        .await
+        .contains("Subject: Help for Minimail"));
+}
+
+#[sqlx::test]
+async fn requests_require_inbound_token(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_app_with_relay(pool).await;
+
+    // Act
+    let response = post_request(&app, "wrong", request("subscribe", "")).await;
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(0, subscriber_count(&app).await);
+}
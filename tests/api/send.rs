@@ -0,0 +1,225 @@
+use mailparse::MailHeaderMap;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, Relayed, TestApp};
+
+async fn spawn_sending(pool: PgPool) -> (TestApp, Relayed) {
+    let (port, relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| use_relay(settings, port)).await;
+    (app, relayed)
+}
+
+async fn create_key(app: &TestApp, scopes: Value) -> String {
+    let created: Value = reqwest::Client::new()
+        .post(format!("{}/api/keys", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"name": "Shop", "scopes": scopes}))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .expect("Failed to read key.");
+    created["key"].as_str().unwrap().to_string()
+}
+
+async fn send(app: &TestApp, key: &str, idempotency_key: &str, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/send", app.address))
+        .bearer_auth(key)
+        .header("Idempotency-Key", idempotency_key)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn receipt() -> Value {
+    json!({
+        "to": "ada@example.org",
+        "subject": "Your receipt",
+        "text": "Thanks for your order."
+    })
+}
+
+#[sqlx::test]
+async fn send_delivers_message(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send", "status"])).await;
+
+    // Act
+    let response = send(&app, &key, "order-1", receipt()).await;
+    let status = response.status().as_u16();
+    let sent: Value = response.json().await.unwrap();
+    let (recipients, raw) = relayed_message(&mut relayed).await;
+
+    // Assert
+    assert_eq!(202, status);
+    assert_eq!(vec!["ada@example.org".to_string()], recipients);
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    assert_eq!(
+        Some("Your receipt".to_string()),
+        mail.headers.get_first_value("Subject")
+    );
+    let report: Value = reqwest::Client::new()
+        .get(format!("{}/api/messages/{}", app.address, sent["id"]))
+        .bearer_auth(&key)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(sent["id"], report["id"]);
+    assert_eq!(json!("ada@example.org"), report["recipient"]);
+}
+
+#[sqlx::test]
+async fn send_queues_bare_address_of_named_recipient(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send"])).await;
+    let mut request = receipt();
+    request["to"] = json!("Ada Lovelace <ada@example.org>");
+
+    // Act
+    let response = send(&app, &key, "order-1", request).await;
+    let (recipients, _) = relayed_message(&mut relayed).await;
+    let queued = sqlx::query!("SELECT recipient FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(202, response.status().as_u16());
+    assert_eq!(vec!["ada@example.org".to_string()], recipients);
+    assert_eq!("ada@example.org", queued.recipient);
+}
+
+#[sqlx::test]
+async fn send_with_same_idempotency_key_queues_once(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send"])).await;
+
+    // Act
+    let first = send(&app, &key, "order-1", receipt()).await;
+    let second = send(&app, &key, "order-1", receipt()).await;
+
+    // Assert
+    assert_eq!(202, first.status().as_u16());
+    assert_eq!(200, second.status().as_u16());
+    let first: Value = first.json().await.unwrap();
+    let second: Value = second.json().await.unwrap();
+    assert_eq!(first["id"], second["id"]);
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM outbox")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(1, queued.count);
+}
+
+#[sqlx::test]
+async fn send_fills_in_template(pool: PgPool) {
+    // Arrange
+    let (app, mut relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send"])).await;
+    let template: Value = reqwest::Client::new()
+        .post(format!("{}/api/templates", app.address))
+        .bearer_auth("admin")
+        .json(&json!({
+            "name": "reset",
+            "subject": "Reset your password",
+            "text": "Follow {{ link }}"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+
+    // Act
+    let response = send(
+        &app,
+        &key,
+        "reset-1",
+        json!({
+            "to": "ada@example.org",
+            "template_id": template["id"],
+            "variables": {"link": "https://example.com/reset"}
+        }),
+    )
+    .await;
+    let (_, raw) = relayed_message(&mut relayed).await;
+
+    // Assert
+    assert_eq!(202, response.status().as_u16());
+    let mail = mailparse::parse_mail(&raw).unwrap();
+    assert_eq!(
+        "Follow https://example.com/reset",
+        mail.get_body().unwrap().trim_end()
+    );
+}
+
+#[sqlx::test]
+async fn send_requires_scoped_key(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["status"])).await;
+
+    // Act
+    let unscoped = send(&app, &key, "order-1", receipt()).await;
+    let unknown = send(&app, "mm_unknown", "order-1", receipt()).await;
+    let admin = send(&app, "admin", "order-1", receipt()).await;
+
+    // Assert
+    assert_eq!(403, unscoped.status().as_u16());
+    assert_eq!(401, unknown.status().as_u16());
+    assert_eq!(401, admin.status().as_u16());
+}
+
+#[sqlx::test]
+async fn status_is_only_shown_to_sending_key(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send", "status"])).await;
+    let other = create_key(&app, json!(["status"])).await;
+    let sent: Value = send(&app, &key, "order-1", receipt())
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/messages/{}", app.address, sent["id"]))
+        .bearer_auth(&other)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[sqlx::test]
+async fn send_rejects_message_without_body(pool: PgPool) {
+    // Arrange
+    let (app, _relayed) = spawn_sending(pool).await;
+    let key = create_key(&app, json!(["send"])).await;
+
+    // Act
+    let response = send(
+        &app,
+        &key,
+        "order-1",
+        json!({"to": "ada@example.org", "subject": "Empty"}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}
@@ -0,0 +1,224 @@
+use lettre::{address::Envelope, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use minimail::config::{ListMode, Settings, SmtpSettings};
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, TestApp};
+
+fn smtp_settings() -> SmtpSettings {
+    SmtpSettings {
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        hostname: "localhost".to_string(),
+        domains: vec!["localhost".to_string()],
+        size: 1024 * 1024,
+    }
+}
+
+fn with_smtp(settings: &mut Settings) {
+    settings.inbound.smtp = Some(smtp_settings());
+}
+
+async fn send(port: u16, from: Option<&str>, to: &str, raw: &str) -> anyhow::Result<()> {
+    send_to_all(port, from, &[to], raw).await
+}
+
+async fn send_to_all(port: u16, from: Option<&str>, to: &[&str], raw: &str) -> anyhow::Result<()> {
+    let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+        .port(port)
+        .build();
+    let to = to.iter().map(|to| to.parse()).collect::<Result<_, _>>()?;
+    let envelope = Envelope::new(from.map(|from| from.parse().unwrap()), to)?;
+    transport.send_raw(&envelope, raw.as_bytes()).await?;
+    Ok(())
+}
+
+async fn create_subscriber(app: &TestApp, email: &str) {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ($1)", email)
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create subscriber.");
+}
+
+const DSN: &str = "From: MAILER-DAEMON@mx.example.org\r\n\
+    To: bounces+user=example.org@localhost\r\n\
+    Subject: Undelivered Mail Returned to Sender\r\n\
+    MIME-Version: 1.0\r\n\
+    Content-Type: multipart/report; report-type=delivery-status; boundary=\"b\"\r\n\
+    \r\n\
+    --b\r\n\
+    Content-Type: message/delivery-status\r\n\
+    \r\n\
+    Reporting-MTA: dns; mx.example.org\r\n\
+    \r\n\
+    Final-Recipient: rfc822; user@example.org\r\n\
+    Action: failed\r\n\
+    Status: 5.1.1\r\n\
+    --b--\r\n";
+
+const REPLY: &str = "From: reader@example.org\r\n\
+    To: news@localhost\r\n\
+    Subject: Re: Our newsletter\r\n\
+    \r\n\
+    Thanks!\r\n";
+
+#[sqlx::test]
+async fn bounce_to_return_path_marks_subscriber_bounced(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, with_smtp).await;
+    create_subscriber(&app, "user@example.org").await;
+
+    // Act
+    send(
+        app.smtp_port.unwrap(),
+        None,
+        "bounces+user=example.org@localhost",
+        DSN,
+    )
+    .await
+    .expect("Failed to send bounce.");
+
+    // Assert
+    let subscriber = sqlx::query!(
+        "SELECT status FROM subscribers WHERE email = $1",
+        "user@example.org"
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to fetch subscriber.");
+    assert_eq!("bounced", subscriber.status);
+}
+
+#[sqlx::test]
+async fn bounce_to_several_recipients_counts_once(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, with_smtp).await;
+    create_subscriber(&app, "user@example.org").await;
+
+    // Act
+    send_to_all(
+        app.smtp_port.unwrap(),
+        None,
+        &["bounces+user=example.org@localhost", "postmaster@localhost"],
+        DSN,
+    )
+    .await
+    .expect("Failed to send bounce.");
+
+    // Assert
+    let bounces = sqlx::query!("SELECT count(*) AS \"count!\" FROM events WHERE kind = 'bounce'")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count bounces.");
+    assert_eq!(1, bounces.count);
+}
+
+#[sqlx::test]
+async fn mail_for_other_domains_is_rejected(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, with_smtp).await;
+
+    // Act
+    let result = send(
+        app.smtp_port.unwrap(),
+        Some("reader@example.org"),
+        "someone@example.net",
+        REPLY,
+    )
+    .await;
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[sqlx::test]
+async fn replies_are_forwarded(pool: PgPool) {
+    // Arrange
+    let (relay_port, mut relayed) = start_relay("example.com").await;
+    let app = spawn_app_with(pool, |settings| {
+        with_smtp(settings);
+        use_relay(settings, relay_port);
+        settings.inbound.forward = Some("owner@example.com".to_string());
+    })
+    .await;
+
+    // Act
+    send(
+        app.smtp_port.unwrap(),
+        Some("reader@example.org"),
+        "news@localhost",
+        REPLY,
+    )
+    .await
+    .expect("Failed to send reply.");
+
+    // Assert
+    let (recipients, raw) = relayed_message(&mut relayed).await;
+    assert_eq!(vec!["owner@example.com".to_string()], recipients);
+    assert!(String::from_utf8(raw)
+        .unwrap()
+        .contains("Subject: Re: Our newsletter"));
+}
+
+#[sqlx::test]
+async fn list_requests_are_answered(pool: PgPool) {
+    // Arrange
+    let (relay_port, mut relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        with_smtp(settings);
+        use_relay(settings, relay_port);
+    })
+    .await;
+
+    // Act
+    send(
+        app.smtp_port.unwrap(),
+        Some("reader@example.org"),
+        "news-request@localhost",
+        "From: reader@example.org\r\nSubject: help\r\n\r\n",
+    )
+    .await
+    .expect("Failed to send request.");
+
+    // Assert
+    let (recipients, raw) = relayed_message(&mut relayed).await;
+    assert_eq!(vec!["reader@example.org".to_string()], recipients);
+    assert!(String::from_utf8(raw)
+        .unwrap()
+        .contains("Subject: Help for Minimail"));
+}
+
+#[sqlx::test]
+async fn posts_to_discussion_list_are_distributed(pool: PgPool) {
+    // Arrange
+    let (relay_port, mut relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| {
+        with_smtp(settings);
+        use_relay(settings, relay_port);
+        settings.list.mode = ListMode::Discussion;
+    })
+    .await;
+    sqlx::query!(
+        "INSERT INTO subscribers(email) VALUES ($1)",
+        "reader@example.org"
+    )
+    .execute(&app.pool)
+    .await
+    .expect("Failed to create subscriber.");
+
+    // Act
+    send(
+        app.smtp_port.unwrap(),
+        Some("reader@example.org"),
+        "news@localhost",
+        "From: reader@example.org\r\nSubject: Hello\r\n\r\nHi\r\n",
+    )
+    .await
+    .expect("Failed to send post.");
+
+    // Assert
+    let (recipients, raw) = relayed_message(&mut relayed).await;
+    assert_eq!(vec!["reader@example.org".to_string()], recipients);
+    assert!(String::from_utf8(raw)
+        .unwrap()
+        .contains("List-Post: <mailto:news@localhost>"));
+}
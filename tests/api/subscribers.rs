@@ -4,7 +4,7 @@ use reqwest::redirect::Policy;
 use sqlx::{PgPool, Pool, Postgres};
 
 use minimail::{
-    config::{AdminSettings, SubscribedSettings},
+    config::{get_configuration, AdminSettings, SubscribedSettings},
     startup::run,
 };
 
@@ -19,7 +19,14 @@ async fn spawn_app(pool: PgPool, admin: AdminSettings, redirect: SubscribedSetti
     let port = listener.local_addr().unwrap().port();
     let address = format!("http://127.0.0.1:{port}");
 
-    let server = run(listener, pool.clone(), admin, redirect);
+    let mut settings = get_configuration().expect("Failed to read configuration.");
+    settings.admin = admin;
+    settings.application.subscribed = redirect;
+    settings.pages.url = address.clone();
+    settings.tracking.secret = "secret".to_string();
+    settings.list.secret = "list-secret".to_string();
+    settings.inbound.token = "inbound-token".to_string();
+    let server = run(listener, None, pool.clone(), settings);
     tokio::spawn(server);
     TestApp { address, pool }
 }
@@ -43,7 +50,7 @@ async fn subscribe_redirects_to_origin(pool: PgPool) {
 
     // Act
     let response = client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body(body)
@@ -72,6 +79,7 @@ async fn subscribe_redirects_to_configured_redirect(pool: PgPool) {
         },
         SubscribedSettings {
             redirect: Some("http://example.com".to_string()),
+            ..SubscribedSettings::default()
         },
     )
     .await;
@@ -83,7 +91,7 @@ async fn subscribe_redirects_to_configured_redirect(pool: PgPool) {
 
     // Act
     let response = client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body(body)
@@ -118,7 +126,7 @@ async fn subscribe_persists_email(pool: PgPool) {
 
     // Act
     client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body(body)
@@ -152,7 +160,7 @@ async fn subscribe_lists_subscribers(pool: PgPool) {
 
     // Act
     client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body(first_subscriber)
@@ -160,7 +168,7 @@ async fn subscribe_lists_subscribers(pool: PgPool) {
         .await
         .expect("Failed to execute request.");
     client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body(second_subscriber)
@@ -168,7 +176,7 @@ async fn subscribe_lists_subscribers(pool: PgPool) {
         .await
         .expect("Failed to execute request.");
     let subscribers = client
-        .get(&format!("{}/api/subscribers", &app.address))
+        .get(format!("{}/api/subscribers", app.address))
         .bearer_auth("admin")
         .send()
         .await
@@ -196,7 +204,7 @@ async fn subscribe_without_auth_asks_for_it(pool: PgPool) {
 
     // Act
     let response = client
-        .get(&format!("{}/api/subscribers", &app.address))
+        .get(format!("{}/api/subscribers", app.address))
         .send()
         .await
         .expect("Failed to execute request.");
@@ -222,7 +230,7 @@ async fn subscribe_with_invalid_auth_fails(pool: PgPool) {
 
     // Act
     let response = client
-        .get(&format!("{}/api/subscribers", &app.address))
+        .get(format!("{}/api/subscribers", app.address))
         .bearer_auth("BAD_TOKEN")
         .send()
         .await
@@ -249,7 +257,7 @@ async fn delete_removes_subscriber(pool: PgPool) {
 
     // Act
     client
-        .post(&format!("{}/api/subscribe", &app.address))
+        .post(format!("{}/api/subscribe", app.address))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .header("origin", &app.address)
         .body("email=user%40email.com")
@@ -257,14 +265,14 @@ async fn delete_removes_subscriber(pool: PgPool) {
         .await
         .expect("Failed to execute request.");
     let delete_request = client
-        .delete(&format!("{}/api/subscribers", &app.address))
+        .delete(format!("{}/api/subscribers", app.address))
         .query(&[("email", "user@email.com")])
         .bearer_auth("admin")
         .send()
         .await
         .expect("Failed to execute request.");
     let subscribers = client
-        .get(&format!("{}/api/subscribers", &app.address))
+        .get(format!("{}/api/subscribers", app.address))
         .bearer_auth("admin")
         .send()
         .await
@@ -293,7 +301,7 @@ async fn delete_with_invalid_auth_fails(pool: PgPool) {
 
     // Act
     let response = client
-        .delete(&format!("{}/api/subscribers", &app.address))
+        .delete(format!("{}/api/subscribers", app.address))
         .query(&[("email", "user@email.com")])
         .bearer_auth("BAD_TOKEN")
         .send()
@@ -303,3 +311,161 @@ async fn delete_with_invalid_auth_fails(pool: PgPool) {
     // Assert
     assert_eq!(response.status().as_u16(), 401);
 }
+
+#[sqlx::test]
+async fn subscribe_keeps_known_timezone(pool: PgPool) {
+    // Arrange
+    let app = spawn_app(
+        pool,
+        AdminSettings {
+            token: "admin".to_string(),
+        },
+        SubscribedSettings::default(),
+    )
+    .await;
+    let client = reqwest::Client::new();
+
+    // Act
+    for body in [
+        "email=user%40email.com&timezone=Europe%2FBerlin",
+        "email=other%40email.com&timezone=Mars%2FOlympus",
+    ] {
+        client
+            .post(format!("{}/api/subscribe", app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("origin", &app.address)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+    }
+
+    // Assert
+    let saved = sqlx::query!("SELECT email, timezone FROM subscribers ORDER BY email")
+        .fetch_all(&app.pool)
+        .await
+        .expect("Failed to fetch saved subscribers.");
+    assert_eq!(None, saved[0].timezone);
+    assert_eq!(Some("Europe/Berlin".to_string()), saved[1].timezone);
+}
+
+async fn spawn_with_redirects(pool: PgPool) -> TestApp {
+    spawn_app(
+        pool,
+        AdminSettings {
+            token: "admin".to_string(),
+        },
+        SubscribedSettings {
+            error: Some("https://www.example.com/oops".to_string()),
+            origins: vec!["https://www.example.com".to_string()],
+            ..SubscribedSettings::default()
+        },
+    )
+    .await
+}
+
+async fn post_signup(app: &TestApp, origin: &str, form: &[(&str, &str)]) -> reqwest::Response {
+    reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap()
+        .post(format!("{}/api/subscribe", app.address))
+        .header("origin", origin)
+        .form(form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn location(response: &reqwest::Response) -> Option<&str> {
+    response
+        .headers()
+        .get("Location")
+        .map(|location| location.to_str().unwrap())
+}
+
+#[sqlx::test]
+async fn subscribe_follows_allowed_redirect_field(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_redirects(pool).await;
+
+    // Act
+    let allowed = post_signup(
+        &app,
+        "https://www.example.com",
+        &[
+            ("email", "ada@example.org"),
+            ("redirect", "https://www.example.com/thanks"),
+        ],
+    )
+    .await;
+    let foreign = post_signup(
+        &app,
+        "https://www.example.com",
+        &[
+            ("email", "grace@example.org"),
+            ("redirect", "https://evil.example/"),
+        ],
+    )
+    .await;
+
+    // Assert
+    assert_eq!(303, allowed.status().as_u16());
+    assert_eq!(Some("https://www.example.com/thanks"), location(&allowed));
+    assert_eq!(303, foreign.status().as_u16());
+    assert_eq!(Some("https://www.example.com"), location(&foreign));
+}
+
+#[sqlx::test]
+async fn subscribe_does_not_redirect_to_unknown_origin(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_redirects(pool).await;
+
+    // Act
+    let response = post_signup(
+        &app,
+        "https://evil.example",
+        &[("email", "ada@example.org")],
+    )
+    .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(None, location(&response));
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Thanks for subscribing to Minimail"));
+    let saved = sqlx::query!("SELECT email FROM subscribers")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch saved subscriber.");
+    assert_eq!("ada@example.org", saved.email);
+}
+
+#[sqlx::test]
+async fn subscribe_sends_invalid_signups_to_error_redirect(pool: PgPool) {
+    // Arrange
+    let app = spawn_with_redirects(pool).await;
+
+    // Act
+    let response = post_signup(
+        &app,
+        "https://www.example.com",
+        &[
+            ("email", "not an address"),
+            ("redirect", "https://www.example.com/thanks"),
+        ],
+    )
+    .await;
+
+    // Assert
+    assert_eq!(303, response.status().as_u16());
+    assert_eq!(Some("https://www.example.com/oops"), location(&response));
+    let saved = sqlx::query!("SELECT email FROM subscribers")
+        .fetch_all(&app.pool)
+        .await
+        .unwrap();
+    assert!(saved.is_empty());
+}
@@ -0,0 +1,175 @@
+use reqwest::redirect::Policy;
+use sqlx::PgPool;
+
+use minimail::{
+    config::{get_configuration, AdminSettings, SubscribedSettings, TrackingSettings},
+    startup::run,
+    tracking::Tracker,
+};
+
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+
+fn admin() -> AdminSettings {
+    AdminSettings {
+        token: "admin".to_string(),
+    }
+}
+
+fn tracker(app: &TestApp) -> Tracker {
+    Tracker::from(TrackingSettings {
+        enabled: true,
+        url: app.address.clone(),
+        secret: "secret".to_string(),
+    })
+}
+
+async fn create_subscriber(app: &TestApp) -> i32 {
+    sqlx::query!("INSERT INTO subscribers(email) VALUES ('user@email.com') RETURNING id")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to create subscriber.")
+        .id
+}
+
+#[sqlx::test]
+async fn open_returns_pixel_and_records_event(pool: PgPool) {
+    // Arrange
+    let app = spawn_app(pool, admin(), SubscribedSettings::default()).await;
+    let subscriber_id = create_subscriber(&app).await;
+    let url = tracker(&app).open_url(subscriber_id, Some(3));
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("User-Agent", "test-agent")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("image/gif", response.headers()["Content-Type"]);
+    let saved = sqlx::query!("SELECT * FROM events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch saved event.");
+    assert_eq!("open", saved.kind);
+    assert_eq!(subscriber_id, saved.subscriber_id);
+    assert_eq!(Some(3), saved.campaign_id);
+    assert_eq!(Some("test-agent".to_string()), saved.user_agent);
+}
+
+#[sqlx::test]
+async fn click_redirects_and_records_event(pool: PgPool) {
+    // Arrange
+    let app = spawn_app(pool, admin(), SubscribedSettings::default()).await;
+    let subscriber_id = create_subscriber(&app).await;
+    let url = tracker(&app).click_url(subscriber_id, None, "https://example.com/post");
+    let client = reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(302, response.status().as_u16());
+    assert_eq!("https://example.com/post", response.headers()["Location"]);
+    let saved = sqlx::query!("SELECT * FROM events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to fetch saved event.");
+    assert_eq!("click", saved.kind);
+    assert_eq!(Some("https://example.com/post".to_string()), saved.url);
+}
+
+#[sqlx::test]
+async fn click_with_tampered_token_is_not_found(pool: PgPool) {
+    // Arrange
+    let app = spawn_app(pool, admin(), SubscribedSettings::default()).await;
+    let forged = Tracker::from(TrackingSettings {
+        enabled: true,
+        url: app.address.clone(),
+        secret: "not the secret".to_string(),
+    })
+    .click_url(1, None, "https://evil.example.com");
+    let client = reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let response = client
+        .get(&forged)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+    assert!(response.headers().get("Location").is_none());
+}
+
+#[sqlx::test]
+async fn click_does_not_record_when_tracking_disabled(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |settings| settings.tracking.enabled = false).await;
+    let subscriber_id = create_subscriber(&app).await;
+    let url = tracker(&app).click_url(subscriber_id, None, "https://example.com/post");
+    let client = reqwest::ClientBuilder::new()
+        .redirect(Policy::none())
+        .build()
+        .unwrap();
+
+    // Act
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(302, response.status().as_u16());
+    let count = sqlx::query!("SELECT COUNT(*) AS count FROM events")
+        .fetch_one(&app.pool)
+        .await
+        .expect("Failed to count events.")
+        .count;
+    assert_eq!(Some(0), count);
+}
+
+#[sqlx::test]
+async fn app_does_not_start_without_secrets(pool: PgPool) {
+    // Arrange
+    let listener =
+        || std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let settings = get_configuration().expect("Failed to read configuration.");
+    let mut with_tracking = settings.clone();
+    with_tracking.tracking.secret = "secret".to_string();
+    let mut with_list = with_tracking.clone();
+    with_list.list.secret = "list-secret".to_string();
+
+    // Act
+    let without_tracking = run(listener(), None, pool.clone(), settings).await;
+    let without_list = run(listener(), None, pool.clone(), with_tracking).await;
+    let without_inbound = run(listener(), None, pool, with_list).await;
+
+    // Assert
+    assert!(without_tracking
+        .unwrap_err()
+        .to_string()
+        .contains("tracking.secret"));
+    assert!(without_list
+        .unwrap_err()
+        .to_string()
+        .contains("list.secret"));
+    assert!(without_inbound
+        .unwrap_err()
+        .to_string()
+        .contains("inbound.token"));
+}
@@ -0,0 +1,142 @@
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::helpers::{relayed_message, spawn_app_with, start_relay, use_relay, TestApp};
+
+async fn save_plan(app: &TestApp, body: Value) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!("{}/api/warmup", app.address))
+        .bearer_auth("admin")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn report(app: &TestApp) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/api/warmup", app.address))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn create_subscriber(app: &TestApp, email: &str, engagement: &[&str]) {
+    let id = sqlx::query!(
+        "INSERT INTO subscribers(email) VALUES ($1) RETURNING id",
+        email
+    )
+    .fetch_one(&app.pool)
+    .await
+    .expect("Failed to create subscriber.")
+    .id;
+    for kind in engagement {
+        sqlx::query!(
+            "INSERT INTO events(kind, subscriber_id) VALUES ($1, $2)",
+            kind,
+            id
+        )
+        .execute(&app.pool)
+        .await
+        .expect("Failed to create event.");
+    }
+}
+
+async fn send_campaign(app: &TestApp) {
+    let client = reqwest::Client::new();
+    let template: Value = client
+        .post(format!("{}/api/templates", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"name": "news", "subject": "News", "text": "Hello {{ email }}"}))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let campaign: Value = client
+        .post(format!("{}/api/campaigns", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"name": "Launch"}))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .post(format!(
+            "{}/api/campaigns/{}/schedule",
+            app.address, campaign["id"]
+        ))
+        .bearer_auth("admin")
+        .json(&json!({"template_id": template["id"], "send_at": "2020-01-01T09:00:00"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+}
+
+#[sqlx::test]
+async fn daily_cap_carries_least_engaged_over_to_next_day(pool: PgPool) {
+    // Arrange
+    let (port, mut relayed) = start_relay("example.org").await;
+    let app = spawn_app_with(pool, |settings| use_relay(settings, port)).await;
+    let today = Utc::now().date_naive();
+    let response = save_plan(
+        &app,
+        json!({"name": "New domain", "starts_on": today, "daily_caps": [2, 5, 10]}),
+    )
+    .await;
+    assert!(response.status().is_success());
+    create_subscriber(&app, "ada@example.org", &[]).await;
+    create_subscriber(&app, "grace@example.org", &["open"]).await;
+    create_subscriber(&app, "hedy@example.org", &["open", "click"]).await;
+
+    // Act
+    send_campaign(&app).await;
+    let mut recipients = relayed_message(&mut relayed).await.0;
+    recipients.extend(relayed_message(&mut relayed).await.0);
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    let carried_over =
+        sqlx::query!("SELECT recipient, next_attempt_at FROM outbox WHERE status = 'pending'")
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    let report: Value = report(&app).await.json().await.unwrap();
+
+    // Assert
+    recipients.sort();
+    assert_eq!(vec!["grace@example.org", "hedy@example.org"], recipients);
+    assert!(relayed.try_recv().is_err());
+    assert_eq!(1, carried_over.len());
+    assert_eq!("ada@example.org", carried_over[0].recipient);
+    assert_eq!(
+        (today + Duration::days(1)).and_time(Default::default()),
+        carried_over[0].next_attempt_at.naive_utc()
+    );
+    assert_eq!(1, report["day"]);
+    assert_eq!(2, report["cap"]);
+    assert_eq!(2, report["sent"]);
+    assert_eq!(1, report["waiting"]);
+}
+
+#[sqlx::test]
+async fn plan_needs_growing_caps(pool: PgPool) {
+    // Arrange
+    let app = spawn_app_with(pool, |_| {}).await;
+
+    // Act
+    let response = save_plan(
+        &app,
+        json!({"name": "New domain", "starts_on": "2030-01-01", "daily_caps": [100, 50]}),
+    )
+    .await;
+    let missing = report(&app).await;
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+    assert_eq!(404, missing.status().as_u16());
+}
@@ -0,0 +1,228 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+
+use crate::helpers::{spawn_app_with, TestApp};
+
+const SECRET: &str = "whsec_0123456789abcdef";
+
+type Received = mpsc::UnboundedReceiver<(HeaderMap, Vec<u8>)>;
+
+#[derive(Clone)]
+struct Receiver {
+    sender: mpsc::UnboundedSender<(HeaderMap, Vec<u8>)>,
+    // Calls that are answered with an error before the endpoint recovers.
+    failures: Arc<AtomicUsize>,
+}
+
+async fn receive(
+    State(receiver): State<Receiver>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> StatusCode {
+    receiver.sender.send((headers, body.to_vec())).ok();
+    let failing = receiver
+        .failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok();
+    if failing {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::NO_CONTENT
+    }
+}
+
+/// Starts an endpoint that fails the first `failures` calls, and returns its
+/// URL and the calls it receives.
+async fn start_receiver(failures: usize) -> (String, Received) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let receiver = Receiver {
+        sender,
+        failures: Arc::new(AtomicUsize::new(failures)),
+    };
+    let app = Router::new()
+        .route("/hooks", post(receive))
+        .with_state(receiver);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address: SocketAddr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+    (format!("http://{address}/hooks"), received)
+}
+
+async fn received_call(received: &mut Received) -> (HeaderMap, Value) {
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(10), received.recv())
+        .await
+        .expect("No webhook was called.")
+        .unwrap();
+    let signature = headers["Minimail-Signature"].to_str().unwrap();
+    let (timestamp, digest) = signature
+        .strip_prefix("t=")
+        .and_then(|rest| rest.split_once(",v1="))
+        .expect("Malformed signature");
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(&body);
+    let expected: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    assert_eq!(expected, digest, "Signature does not match");
+    (headers, serde_json::from_slice(&body).unwrap())
+}
+
+async fn create_webhook(app: &TestApp, url: &str, events: Value) -> Value {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/webhooks", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"url": url, "events": events, "secret": SECRET}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    response.json().await.unwrap()
+}
+
+async fn subscribe(app: &TestApp, email: &str) {
+    reqwest::Client::new()
+        .post(format!("{}/api/subscribe", app.address))
+        .form(&[("email", email)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+}
+
+async fn deliveries(app: &TestApp, webhook_id: &Value) -> Vec<Value> {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/api/webhooks/{webhook_id}/deliveries",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap()
+}
+
+async fn app(pool: PgPool) -> TestApp {
+    spawn_app_with(pool, |settings| {
+        settings.webhooks.interval = 1;
+        settings.webhooks.backoff = 1;
+    })
+    .await
+}
+
+#[sqlx::test]
+async fn subscriber_events_are_signed_and_sent(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let (url, mut received) = start_receiver(0).await;
+    create_webhook(
+        &app,
+        &url,
+        json!(["subscriber.subscribed", "subscriber.unsubscribed"]),
+    )
+    .await;
+
+    // Act
+    subscribe(&app, "ada@example.org").await;
+    let (headers, subscribed) = received_call(&mut received).await;
+    reqwest::Client::new()
+        .delete(format!(
+            "{}/api/subscribers?email=ada%40example.org",
+            app.address
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let (_, unsubscribed) = received_call(&mut received).await;
+
+    // Assert
+    assert!(headers.contains_key("Minimail-Delivery"));
+    assert_eq!("subscriber.subscribed", subscribed["type"]);
+    assert_eq!("ada@example.org", subscribed["data"]["email"]);
+    assert_eq!("subscriber.unsubscribed", unsubscribed["type"]);
+    assert_eq!(
+        subscribed["data"]["subscriber_id"],
+        unsubscribed["data"]["subscriber_id"]
+    );
+}
+
+#[sqlx::test]
+async fn failed_calls_are_retried_logged_and_replayable(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+    let (url, mut received) = start_receiver(1).await;
+    let webhook = create_webhook(&app, &url, json!(["subscriber.subscribed"])).await;
+
+    // Act
+    subscribe(&app, "grace@example.org").await;
+    let (first, _) = received_call(&mut received).await;
+    let (second, _) = received_call(&mut received).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let log = deliveries(&app, &webhook["id"]).await;
+    let replayed: Value = reqwest::Client::new()
+        .post(format!(
+            "{}/api/webhooks/{}/deliveries/{}/replay",
+            app.address, webhook["id"], log[0]["id"]
+        ))
+        .bearer_auth("admin")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let (third, replay) = received_call(&mut received).await;
+
+    // Assert
+    assert_eq!(first["Minimail-Delivery"], second["Minimail-Delivery"]);
+    assert_eq!(1, log.len());
+    assert_eq!("delivered", log[0]["status"]);
+    assert_eq!(2, log[0]["attempts"]);
+    assert_eq!(204, log[0]["response_status"]);
+    assert_eq!("pending", replayed["status"]);
+    assert_ne!(first["Minimail-Delivery"], third["Minimail-Delivery"]);
+    assert_eq!(log[0]["payload"], replay);
+}
+
+#[sqlx::test]
+async fn webhook_needs_valid_url(pool: PgPool) {
+    // Arrange
+    let app = app(pool).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/webhooks", app.address))
+        .bearer_auth("admin")
+        .json(&json!({"url": "not a url", "events": ["delivery.sent"]}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(422, response.status().as_u16());
+}